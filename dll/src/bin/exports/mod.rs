@@ -0,0 +1,126 @@
+//! Exports and error codes of the DLL, parsed from its sources for the
+//! generators.
+
+pub const LIB_RS: &str = include_str!("../../lib.rs");
+pub const ERROR_CODE_RS: &str = include_str!("../../error_code.rs");
+
+/// Exported `extern "C"` function
+#[derive(Debug, PartialEq)]
+pub struct Export {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+    pub ret: Option<String>,
+
+    /// Lines of the doc comment, without the `///`
+    pub doc: Vec<String>,
+}
+
+/// Variant of the `ErrorCode` enum
+#[derive(Debug, PartialEq)]
+pub struct ErrorCode {
+    pub name: String,
+    pub value: i32,
+    pub doc: Vec<String>,
+}
+
+impl ErrorCode {
+    /// Name of the constant, e.g. `VDA_ERROR_WINDOW_NOT_FOUND`
+    pub fn constant_name(&self) -> String {
+        let mut constant = String::from("VDA_ERROR");
+        for c in self.name.chars() {
+            if c.is_ascii_uppercase() {
+                constant.push('_');
+            }
+            constant.push(c.to_ascii_uppercase());
+        }
+        constant
+    }
+}
+
+/// Doc comment lines right above the line ending at `end`
+fn doc_before(source: &str, end: usize) -> Vec<String> {
+    let mut doc: Vec<String> = source[..end]
+        .trim_end_matches([' ', '\t'])
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("///"))
+        .map(|line| line.trim_start_matches("///").trim().to_string())
+        .collect();
+    doc.reverse();
+    doc
+}
+
+/// Parses `#[no_mangle] pub extern "C" fn` declarations from the source
+pub fn parse_exports(source: &str) -> Vec<Export> {
+    let mut exports = Vec::new();
+    let mut rest = source;
+    while let Some(pos) = rest.find("#[no_mangle]") {
+        rest = &rest[pos + "#[no_mangle]".len()..];
+
+        // Commented out exports start with `//`
+        let line_start = source.len() - rest.len() - "#[no_mangle]".len();
+        let line = source[..line_start].rsplit('\n').next().unwrap_or("");
+        if line.trim_start().starts_with("//") {
+            continue;
+        }
+
+        let decl = rest.trim_start();
+        let Some(decl) = decl.strip_prefix("pub extern \"C\" fn ") else {
+            continue;
+        };
+        let open = decl.find('(').expect("Export without arguments");
+        let close = decl.find(')').expect("Export without closing paren");
+        let body = decl.find('{').expect("Export without body");
+
+        let name = decl[..open].trim().to_string();
+        let args = decl[open + 1..close]
+            .split(',')
+            .map(str::trim)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| {
+                let (name, ty) = arg.split_once(':').expect("Argument without type");
+                (name.trim().to_string(), ty.trim().to_string())
+            })
+            .collect();
+        let ret = decl[close + 1..body]
+            .trim()
+            .strip_prefix("->")
+            .map(|ty| ty.trim().to_string());
+        let doc = doc_before(source, line_start);
+
+        exports.push(Export {
+            name,
+            args,
+            ret,
+            doc,
+        });
+    }
+    exports
+}
+
+/// Parses the `Name = value,` variants of the `ErrorCode` enum
+pub fn parse_error_codes(source: &str) -> Vec<ErrorCode> {
+    let start = source
+        .find("pub(crate) enum ErrorCode {")
+        .expect("ErrorCode enum not found");
+    let mut codes = Vec::new();
+    let mut offset = start;
+    for line in source[start..].lines() {
+        let line_start = offset;
+        offset += line.len() + 1;
+        let line = line.trim();
+        if line == "}" {
+            break;
+        }
+        let Some((name, value)) = line.trim_end_matches(',').split_once(" = ") else {
+            continue;
+        };
+        codes.push(ErrorCode {
+            name: name.to_string(),
+            value: value.parse().expect("Error code is not a number"),
+            doc: doc_before(source, line_start),
+        });
+    }
+    codes
+}
@@ -0,0 +1,191 @@
+//! Generates an AutoHotkey v2 include with wrappers for every exported function
+//! of the DLL.
+//!
+//! The exports are parsed from `src/lib.rs`, so the include is always in sync
+//! with the DLL it was built with:
+//!
+//! ```text
+//! cargo run -p dll --bin generate-ahk > VirtualDesktopAccessor.ah2
+//! ```
+//!
+//! Optional first argument is the path of the DLL relative to the script,
+//! defaults to `VirtualDesktopAccessor.dll`.
+//!
+//! Only 64-bit AutoHotkey is supported, on 32-bit a `GUID` passed by value does
+//! not fit in a single DllCall argument.
+
+mod exports;
+
+use exports::{parse_error_codes, parse_exports, ErrorCode, Export, ERROR_CODE_RS, LIB_RS};
+
+/// DllCall type of a Rust type
+fn dllcall_type(ty: &str) -> &'static str {
+    match ty {
+        "i32" => "Int",
+        "u32" => "UInt",
+        "i64" => "Int64",
+        "isize" => "Ptr",
+        "usize" => "UPtr",
+        "HWND" => "Ptr",
+        "Option<VdaLogCallback>" => "Ptr",
+        ty if ty.starts_with('*') => "Ptr",
+        _ => panic!("No DllCall type for `{}`, add it to generate-ahk", ty),
+    }
+}
+
+/// AutoHotkey variable name for the argument
+fn ahk_name(name: &str) -> String {
+    name.trim_start_matches('_').to_string()
+}
+
+fn write_function(out: &mut String, export: &Export) {
+    let params = export
+        .args
+        .iter()
+        .map(|(name, _)| ahk_name(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    out.push_str(&format!("{}({}) {{\n", export.name, params));
+    out.push_str(&format!("    global {}Proc\n", export.name));
+
+    let mut call_args = Vec::new();
+
+    // GUID is returned through a hidden pointer to the result
+    let returns_guid = export.ret.as_deref() == Some("GUID");
+    if returns_guid {
+        out.push_str("    guid := Buffer(16, 0)\n");
+        call_args.push("\"Ptr\", guid".to_string());
+    }
+
+    for (name, ty) in &export.args {
+        let name = ahk_name(name);
+        match ty.as_str() {
+            // GUID larger than 8 bytes is passed by pointer
+            "GUID" => call_args.push(format!("\"Ptr\", {}", name)),
+
+            // Strings are passed as null terminated UTF-8
+            "*const i8" => {
+                out.push_str(&format!(
+                    "    {0}_utf8 := Buffer(StrPut({0}, \"UTF-8\"), 0)\n",
+                    name
+                ));
+                out.push_str(&format!("    StrPut({0}, {0}_utf8, \"UTF-8\")\n", name));
+                call_args.push(format!("\"Ptr\", {}_utf8", name));
+            }
+            ty => call_args.push(format!("\"{}\", {}", dllcall_type(ty), name)),
+        }
+    }
+
+    match (returns_guid, export.ret.as_deref()) {
+        (true, _) => call_args.push("\"Ptr\"".to_string()),
+        (false, Some(ty)) => call_args.push(format!("\"{}\"", dllcall_type(ty))),
+        (false, None) => {}
+    }
+
+    let mut call = format!("DllCall({}Proc", export.name);
+    for arg in call_args {
+        call.push_str(", ");
+        call.push_str(&arg);
+    }
+    call.push(')');
+
+    if returns_guid {
+        out.push_str(&format!("    {}\n", call));
+        out.push_str("    return guid\n");
+    } else if export.ret.is_some() {
+        out.push_str(&format!("    return {}\n", call));
+    } else {
+        out.push_str(&format!("    {}\n", call));
+    }
+    out.push_str("}\n");
+}
+
+fn generate(exports: &[Export], codes: &[ErrorCode], dll_path: &str) -> String {
+    let mut out = String::new();
+    out.push_str("; AutoHotkey v2 include for VirtualDesktopAccessor.dll\n");
+    out.push_str("; Generated with `cargo run -p dll --bin generate-ahk`, do not edit\n");
+    out.push_str("; Requires 64-bit AutoHotkey\n\n");
+    out.push_str(&format!(
+        "VDA_PATH := A_LineFile . \"\\..\\{}\"\n",
+        dll_path.replace('/', "\\")
+    ));
+    out.push_str(
+        "hVirtualDesktopAccessor := DllCall(\"LoadLibrary\", \"Str\", VDA_PATH, \"Ptr\")\n\n",
+    );
+
+    for export in exports {
+        out.push_str(&format!(
+            "{0}Proc := DllCall(\"GetProcAddress\", \"Ptr\", hVirtualDesktopAccessor, \"AStr\", \"{0}\", \"Ptr\")\n",
+            export.name
+        ));
+    }
+
+    // Codes of GetVdaLastError
+    out.push('\n');
+    for code in codes {
+        out.push_str(&format!("{} := {}\n", code.constant_name(), code.value));
+    }
+
+    for export in exports {
+        out.push('\n');
+        for line in &export.doc {
+            out.push_str(&format!("; {}\n", line).replace("; \n", ";\n"));
+        }
+        write_function(&mut out, export);
+    }
+    out
+}
+
+fn main() {
+    let dll_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "VirtualDesktopAccessor.dll".to_string());
+    print!(
+        "{}",
+        generate(
+            &parse_exports(LIB_RS),
+            &parse_error_codes(ERROR_CODE_RS),
+            &dll_path
+        )
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_export_has_dllcall_types() {
+        let exports = parse_exports(LIB_RS);
+        assert!(exports.iter().any(|e| e.name == "GetDesktopCount"));
+        assert!(!exports.iter().any(|e| e.name == "GetDesktopNumber"));
+
+        // Panics on an unknown type
+        let codes = parse_error_codes(ERROR_CODE_RS);
+        let ahk = generate(&exports, &codes, "VirtualDesktopAccessor.dll");
+        assert!(ahk.contains("VDA_ERROR_WINDOW_NOT_FOUND := 1\n"));
+    }
+
+    #[test]
+    fn test_generate_function() {
+        let exports = parse_exports(
+            r#"
+#[no_mangle]
+pub extern "C" fn SetDesktopName(desktop_number: i32, in_name_ptr: *const i8) -> i32 {
+}
+"#,
+        );
+        let mut out = String::new();
+        write_function(&mut out, &exports[0]);
+        assert_eq!(
+            out,
+            r#"SetDesktopName(desktop_number, in_name_ptr) {
+    global SetDesktopNameProc
+    in_name_ptr_utf8 := Buffer(StrPut(in_name_ptr, "UTF-8"), 0)
+    StrPut(in_name_ptr, in_name_ptr_utf8, "UTF-8")
+    return DllCall(SetDesktopNameProc, "Int", desktop_number, "Ptr", in_name_ptr_utf8, "Int")
+}
+"#
+        );
+    }
+}
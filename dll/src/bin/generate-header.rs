@@ -0,0 +1,160 @@
+//! Generates the C header of the DLL, `virtualdesktopaccessor.h`.
+//!
+//! The exports and their doc comments are parsed from `src/lib.rs` and the
+//! error codes from `src/error_code.rs`:
+//!
+//! ```text
+//! cargo run -p dll --bin generate-header > dll/virtualdesktopaccessor.h
+//! ```
+//!
+//! The header in the repository is checked against the sources by the tests.
+
+mod exports;
+
+use exports::{parse_error_codes, parse_exports, ErrorCode, Export, ERROR_CODE_RS, LIB_RS};
+
+/// C type of a Rust type
+fn c_type(ty: &str) -> &'static str {
+    match ty {
+        "i32" => "int32_t",
+        "u32" => "uint32_t",
+        "i64" => "int64_t",
+        "isize" => "intptr_t",
+        "usize" => "size_t",
+        "HWND" => "HWND",
+        "GUID" => "GUID",
+        "*const i8" => "const char *",
+        "*mut u8" => "uint8_t *",
+        "Option<VdaLogCallback>" => "VdaLogCallback",
+        _ => panic!("No C type for `{}`, add it to generate-header", ty),
+    }
+}
+
+fn write_doc(out: &mut String, doc: &[String], indent: &str) {
+    match doc {
+        [] => return,
+        [line] => {
+            out.push_str(&format!("{}/** {} */\n", indent, line));
+            return;
+        }
+        _ => {}
+    }
+    out.push_str(&format!("{}/**\n", indent));
+    for line in doc {
+        let line = format!("{} * {}", indent, line);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!("{} */\n", indent));
+}
+
+fn write_function(out: &mut String, export: &Export) {
+    write_doc(out, &export.doc, "");
+    let ret = export.ret.as_deref().map_or("void", c_type);
+    let args = if export.args.is_empty() {
+        "void".to_string()
+    } else {
+        export
+            .args
+            .iter()
+            .map(|(name, ty)| {
+                let ty = c_type(ty);
+                if ty.ends_with('*') {
+                    format!("{}{}", ty, name)
+                } else {
+                    format!("{} {}", ty, name)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    out.push_str(&format!("VDA_API {} {}({});\n", ret, export.name, args));
+}
+
+fn generate(exports: &[Export], codes: &[ErrorCode]) -> String {
+    let mut out = String::new();
+    out.push_str("/* C header for VirtualDesktopAccessor.dll\n");
+    out.push_str(" * Generated with `cargo run -p dll --bin generate-header`, do not edit */\n\n");
+    out.push_str("#ifndef VIRTUALDESKTOPACCESSOR_H\n");
+    out.push_str("#define VIRTUALDESKTOPACCESSOR_H\n\n");
+    out.push_str("#include <stddef.h>\n");
+    out.push_str("#include <stdint.h>\n");
+    out.push_str("#include <windows.h>\n\n");
+    out.push_str("#ifndef VDA_API\n");
+    out.push_str("#define VDA_API __declspec(dllimport)\n");
+    out.push_str("#endif\n\n");
+    out.push_str("#ifdef __cplusplus\n");
+    out.push_str("extern \"C\" {\n");
+    out.push_str("#endif\n\n");
+
+    write_doc(
+        &mut out,
+        &["Codes returned by GetVdaLastError".to_string()],
+        "",
+    );
+    out.push_str("typedef enum VdaErrorCode {\n");
+    for code in codes {
+        write_doc(&mut out, &code.doc, "    ");
+        out.push_str(&format!("    {} = {},\n", code.constant_name(), code.value));
+    }
+    out.push_str("} VdaErrorCode;\n\n");
+
+    write_doc(
+        &mut out,
+        &["Receives the log messages, see SetLogCallback".to_string()],
+        "",
+    );
+    out.push_str("typedef void (*VdaLogCallback)(const uint8_t *message_utf8, int32_t level);\n");
+
+    for export in exports {
+        out.push('\n');
+        write_function(&mut out, export);
+    }
+
+    out.push_str("\n#ifdef __cplusplus\n");
+    out.push_str("}\n");
+    out.push_str("#endif\n\n");
+    out.push_str("#endif\n");
+    out
+}
+
+fn main() {
+    print!(
+        "{}",
+        generate(&parse_exports(LIB_RS), &parse_error_codes(ERROR_CODE_RS))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_is_up_to_date() {
+        let header = generate(&parse_exports(LIB_RS), &parse_error_codes(ERROR_CODE_RS));
+        assert!(
+            header == include_str!("../../virtualdesktopaccessor.h"),
+            "Header is out of date, run `cargo run -p dll --bin generate-header > dll/virtualdesktopaccessor.h`"
+        );
+    }
+
+    #[test]
+    fn test_generate_function() {
+        let exports = parse_exports(
+            r#"
+/// Set desktop name
+#[no_mangle]
+pub extern "C" fn SetDesktopName(desktop_number: i32, in_name_ptr: *const i8) -> i32 {
+}
+"#,
+        );
+        let mut out = String::new();
+        write_function(&mut out, &exports[0]);
+        assert_eq!(
+            out,
+            r#"/** Set desktop name */
+VDA_API int32_t SetDesktopName(int32_t desktop_number, const char *in_name_ptr);
+"#
+        );
+    }
+}
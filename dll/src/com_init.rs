@@ -0,0 +1,41 @@
+//! COM initialization of the threads calling the exports.
+//!
+//! Scripts call the exports from whatever thread they run on, often without
+//! initializing COM. Such threads are joined to the multithreaded apartment
+//! on their first call, threads that initialized COM themselves are left as
+//! they are.
+use std::cell::Cell;
+use std::sync::Once;
+use windows::Win32::System::Com::{
+    CoGetApartmentType, CoIncrementMTAUsage, APTTYPE, APTTYPEQUALIFIER,
+};
+
+thread_local! {
+    /// True once the apartment of the thread was checked
+    static CHECKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Keeps the multithreaded apartment alive until the process exits
+static MTA_USAGE: Once = Once::new();
+
+/// Makes sure the calling thread is in a COM apartment, call at the start of
+/// every export that calls COM
+pub(crate) fn ensure_com() {
+    if CHECKED.with(|checked| checked.replace(true)) {
+        return;
+    }
+    let mut apartment = APTTYPE::default();
+    let mut qualifier = APTTYPEQUALIFIER::default();
+    if unsafe { CoGetApartmentType(&mut apartment, &mut qualifier) }.is_ok() {
+        return;
+    }
+
+    // Threads without an apartment are implicitly in the multithreaded
+    // apartment while it is in use. The cookie is never released.
+    MTA_USAGE.call_once(|| {
+        if let Err(_er) = unsafe { CoIncrementMTAUsage() } {
+            #[cfg(debug_assertions)]
+            crate::log::log_output(&format!("CoIncrementMTAUsage failed: {:?}", _er));
+        }
+    });
+}
@@ -0,0 +1,99 @@
+//! Codes returned by `GetVdaLastError`.
+//!
+//! The values never change, new codes are added at the end of their range.
+//! `generate-header` reads this file for the C header.
+
+/// Cause of the last failed call, see `GetVdaLastError`
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorCode {
+    /// Last call succeeded
+    Ok = 0,
+
+    /// Window not found
+    WindowNotFound = 1,
+
+    /// Desktop not found
+    DesktopNotFound = 2,
+
+    /// Creating the desktop failed
+    CreateDesktopFailed = 3,
+
+    /// Removing the desktop failed
+    RemoveDesktopFailed = 4,
+
+    /// Virtual desktop service not registered, explorer.exe is not running
+    ClassNotRegistered = 5,
+
+    /// RPC server not available
+    RpcServerNotAvailable = 6,
+
+    /// COM not initialized
+    ComNotInitialized = 7,
+
+    /// COM object not connected
+    ComObjectNotConnected = 8,
+
+    /// COM element not found
+    ComElementNotFound = 9,
+
+    /// Interface not supported, Windows changed the interfaces
+    ComNoInterface = 10,
+
+    /// Not implemented on this Windows version
+    ComNotImplemented = 11,
+
+    /// Other COM error
+    ComError = 12,
+
+    /// COM call returned a null pointer
+    ComAllocatedNullPtr = 13,
+
+    /// Internal borrow error
+    InternalBorrowError = 14,
+
+    /// Listener thread failed to start
+    ListenerThreadFailed = 15,
+
+    /// Reading the registry failed
+    RegistryError = 16,
+
+    /// Alias not found
+    AliasNotFound = 17,
+
+    /// Reading or writing a file failed
+    FileError = 18,
+
+    /// COM method failed, the message names the method
+    ComCallFailed = 19,
+
+    /// Windows build not supported
+    UnsupportedWindowsBuild = 20,
+
+    /// Called on a thread the COM object doesn't belong to
+    NotOnCorrectThread = 21,
+
+    /// Connection to explorer.exe lost
+    Disconnected = 22,
+
+    /// Service stopped
+    ServiceStopped = 23,
+
+    /// The only desktop can't be removed
+    LastDesktop = 24,
+
+    /// Window can't be moved between desktops
+    WindowNotMovable = 25,
+
+    /// Rules config not valid
+    RulesConfigError = 26,
+
+    /// Listener events dropped
+    EventsDropped = 27,
+
+    /// Invalid argument, e.g. a negative number
+    InvalidArgument = 100,
+
+    /// Output buffer too small
+    BufferTooSmall = 101,
+}
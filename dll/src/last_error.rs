@@ -0,0 +1,107 @@
+//! Cause of the last failed call on the thread, read with `GetVdaLastError`
+//! and `GetVdaLastErrorMessage`.
+use std::cell::RefCell;
+use winvd::Error;
+
+use crate::error_code::ErrorCode;
+
+/// Failure of an exported function
+#[derive(Debug, Clone)]
+pub(crate) enum LastError {
+    Vd(Error),
+
+    /// Argument out of range, e.g. a negative desktop number
+    InvalidArgument,
+
+    /// Output buffer is too small for the string
+    BufferTooSmall,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Stores the error of the result, or clears the last error on success
+pub(crate) fn track<T>(result: winvd::Result<T>) -> winvd::Result<T> {
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = result.as_ref().err().cloned().map(LastError::Vd);
+    });
+    result
+}
+
+/// Stores the error and returns -1 for returning from an export
+pub(crate) fn fail(error: LastError) -> i32 {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+    -1
+}
+
+/// Code of the last error, `ErrorCode::Ok` if the last call succeeded
+pub(crate) fn code() -> ErrorCode {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ErrorCode::Ok, error_code))
+}
+
+/// Description of the last error, empty if the last call succeeded
+pub(crate) fn message() -> String {
+    LAST_ERROR.with(|last| match last.borrow().as_ref() {
+        None => String::new(),
+        Some(LastError::Vd(err)) => match err.hresult() {
+            Some(hresult) => format!("{:?} (HRESULT 0x{:08X})", err, hresult.0 as u32),
+            None => format!("{:?}", err),
+        },
+        Some(err) => format!("{:?}", err),
+    })
+}
+
+fn error_code(error: &LastError) -> ErrorCode {
+    let err = match error {
+        LastError::Vd(err) => err,
+        LastError::InvalidArgument => return ErrorCode::InvalidArgument,
+        LastError::BufferTooSmall => return ErrorCode::BufferTooSmall,
+    };
+    match err {
+        Error::WindowNotFound => ErrorCode::WindowNotFound,
+        Error::DesktopNotFound => ErrorCode::DesktopNotFound,
+        Error::CreateDesktopFailed => ErrorCode::CreateDesktopFailed,
+        Error::RemoveDesktopFailed => ErrorCode::RemoveDesktopFailed,
+        Error::ClassNotRegistered => ErrorCode::ClassNotRegistered,
+        Error::RpcServerNotAvailable => ErrorCode::RpcServerNotAvailable,
+        Error::ComNotInitialized => ErrorCode::ComNotInitialized,
+        Error::ComObjectNotConnected => ErrorCode::ComObjectNotConnected,
+        Error::ComElementNotFound => ErrorCode::ComElementNotFound,
+        Error::ComNoInterface => ErrorCode::ComNoInterface,
+        Error::ComNotImplemented => ErrorCode::ComNotImplemented,
+        Error::ComError(_) => ErrorCode::ComError,
+        Error::ComAllocatedNullPtr => ErrorCode::ComAllocatedNullPtr,
+        Error::InternalBorrowError => ErrorCode::InternalBorrowError,
+        Error::ListenerThreadFailed => ErrorCode::ListenerThreadFailed,
+        Error::RegistryError(_) => ErrorCode::RegistryError,
+        Error::AliasNotFound => ErrorCode::AliasNotFound,
+        Error::FileError(_) => ErrorCode::FileError,
+        Error::ComCallFailed { .. } => ErrorCode::ComCallFailed,
+        Error::UnsupportedWindowsBuild => ErrorCode::UnsupportedWindowsBuild,
+        Error::NotOnCorrectThread => ErrorCode::NotOnCorrectThread,
+        Error::Disconnected => ErrorCode::Disconnected,
+        Error::ServiceStopped => ErrorCode::ServiceStopped,
+        Error::LastDesktop => ErrorCode::LastDesktop,
+        Error::WindowNotMovable => ErrorCode::WindowNotMovable,
+        Error::RulesConfigError(_) => ErrorCode::RulesConfigError,
+        Error::EventsDropped => ErrorCode::EventsDropped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_error() {
+        let _ = track::<()>(Err(Error::LastDesktop));
+        assert_eq!(code(), ErrorCode::LastDesktop);
+        assert_eq!(message(), "LastDesktop");
+        let _ = track(Ok(()));
+        assert_eq!(code(), ErrorCode::Ok);
+        assert_eq!(message(), "");
+        assert_eq!(fail(LastError::InvalidArgument), -1);
+        assert_eq!(code() as i32, 100);
+    }
+}
@@ -2,7 +2,7 @@
 
 use once_cell::sync::Lazy;
 use std::{
-    collections::HashSet,
+    collections::HashMap,
     ffi::{CStr, CString},
     sync::{Arc, Mutex},
 };
@@ -15,9 +15,92 @@ use windows::{
 };
 use winvd::*;
 
+mod com_init;
+mod error_code;
+mod last_error;
+use com_init::ensure_com;
+use last_error::{fail, track, LastError};
+
+/// Desktop GUIDs in Task View order, kept up to date by the desktop events
+/// while a post message hook is registered. The numbers posted to the hooks
+/// and the numbers given to `GoToDesktopNumber` then refer to the same
+/// desktops, even while the desktops are reordered.
+static DESKTOP_NUMBERS: Lazy<Mutex<Option<Vec<GUID>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Number of the desktop from the map, or from explorer.exe if there is no map
+fn desktop_number(desktop: Desktop) -> Result<u32> {
+    if let Ok(id) = desktop.get_id() {
+        let numbers = DESKTOP_NUMBERS.lock().unwrap();
+        if let Some(index) = numbers
+            .as_ref()
+            .and_then(|n| n.iter().position(|g| *g == id))
+        {
+            return Ok(index as u32);
+        }
+    }
+    desktop.get_index()
+}
+
+/// Desktop of the number from the map, or by index if there is no map.
+/// Negative numbers are `LastError::InvalidArgument`.
+fn desktop_by_number(number: i32) -> std::result::Result<Desktop, LastError> {
+    if number < 0 {
+        return Err(LastError::InvalidArgument);
+    }
+    let numbers = DESKTOP_NUMBERS.lock().unwrap();
+    let desktop = match numbers.as_ref().and_then(|n| n.get(number as usize)) {
+        Some(id) => get_desktop(*id),
+        None => get_desktop(number),
+    };
+    Ok(desktop)
+}
+
+/// Applies the event to the desktop number map
+fn update_desktop_numbers(event: &DesktopEvent) {
+    let mut numbers = DESKTOP_NUMBERS.lock().unwrap();
+    match event {
+        DesktopEvent::InitialState { desktops, .. } => {
+            *numbers = Some(desktops.iter().map(|d| d.guid).collect());
+        }
+        DesktopEvent::ConnectionReset { desktops } | DesktopEvent::EventsDropped { desktops } => {
+            *numbers = desktops.iter().map(|d| d.get_id().ok()).collect();
+        }
+        DesktopEvent::DesktopCreated(desktop) => {
+            // New desktops are added last
+            if let (Some(numbers), Ok(id)) = (numbers.as_mut(), desktop.get_id()) {
+                numbers.push(id);
+            }
+        }
+        DesktopEvent::DesktopDestroyed { destroyed, .. } => {
+            if let (Some(numbers), Ok(id)) = (numbers.as_mut(), destroyed.get_id()) {
+                numbers.retain(|g| *g != id);
+            }
+        }
+        DesktopEvent::DesktopMoved {
+            desktop,
+            old_index,
+            new_index,
+        } => {
+            if let (Some(list), Ok(id)) = (numbers.as_mut(), desktop.get_id()) {
+                let (old, new) = (*old_index as usize, *new_index as usize);
+                if list.get(old) == Some(&id) && new < list.len() {
+                    let id = list.remove(old);
+                    list.insert(new, id);
+                } else {
+                    // Out of sync, fall back to explorer.exe until reset
+                    *numbers = None;
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Number of the current desktop
 #[no_mangle]
 pub extern "C" fn GetCurrentDesktopNumber() -> i32 {
-    get_current_desktop().map_or(-1, |x| x.get_index().map_or(-1, |x| x as i32))
+    ensure_com();
+    track(get_current_desktop().and_then(desktop_number)).map_or(-1, |x| x as i32)
 }
 
 // #[no_mangle]
@@ -25,209 +108,416 @@ pub extern "C" fn GetCurrentDesktopNumber() -> i32 {
 //     get_current_desktop_index_OLDD().map_or(-1, |x| x as i32)
 // }
 
+/// Number of desktops
 #[no_mangle]
 pub extern "C" fn GetDesktopCount() -> i32 {
-    get_desktop_count().map_or(-1, |x| x as i32)
+    ensure_com();
+    track(get_desktop_count()).map_or(-1, |x| x as i32)
 }
 
+/// GUID of the desktop, all zeros on error
 #[no_mangle]
 pub extern "C" fn GetDesktopIdByNumber(number: i32) -> GUID {
-    if number < 0 {
-        return GUID::default();
-    }
-    get_desktop(number).get_id().map_or(GUID::default(), |x| x)
+    ensure_com();
+    let desktop = match desktop_by_number(number) {
+        Ok(desktop) => desktop,
+        Err(err) => {
+            fail(err);
+            return GUID::default();
+        }
+    };
+    track(desktop.get_id()).unwrap_or_default()
 }
 
+/// Number of the desktop with the GUID
 #[no_mangle]
 pub extern "C" fn GetDesktopNumberById(desktop_id: GUID) -> i32 {
-    get_desktop(&desktop_id)
-        .get_index()
-        .map_or(-1, |x| x as i32)
+    ensure_com();
+    track(desktop_number(get_desktop(desktop_id))).map_or(-1, |x| x as i32)
 }
 
+/// GUID of the desktop the window is on, all zeros on error
 #[no_mangle]
 pub extern "C" fn GetWindowDesktopId(hwnd: HWND) -> GUID {
-    get_desktop_by_window(hwnd).map_or(GUID::default(), |x| {
-        x.get_id().map_or(GUID::default(), |y| y)
-    })
+    ensure_com();
+    track(get_desktop_by_window(hwnd).and_then(|x| x.get_id())).unwrap_or_default()
 }
 
+/// Number of the desktop the window is on
 #[no_mangle]
 pub extern "C" fn GetWindowDesktopNumber(hwnd: HWND) -> i32 {
-    get_desktop_by_window(hwnd).map_or(-1, |x| x.get_index().map_or(-1, |y| y as i32))
+    ensure_com();
+    track(get_desktop_by_window(hwnd).and_then(desktop_number)).map_or(-1, |y| y as i32)
 }
 
+/// 1 if the window is on the current desktop, 0 if not
 #[no_mangle]
 pub extern "C" fn IsWindowOnCurrentVirtualDesktop(hwnd: HWND) -> i32 {
-    is_window_on_current_desktop(hwnd).map_or(-1, |x| x as i32)
+    ensure_com();
+    track(is_window_on_current_desktop(hwnd)).map_or(-1, |x| x as i32)
 }
 
+/// Moves the window to the desktop
 #[no_mangle]
 pub extern "C" fn MoveWindowToDesktopNumber(hwnd: HWND, desktop_number: i32) -> i32 {
-    move_window_to_desktop(desktop_number as u32, &hwnd).map_or(-1, |_| 1)
+    ensure_com();
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(move_window_to_desktop(desktop, hwnd)).map_or(-1, |_| 1)
 }
 
+/// Switches to the desktop
 #[no_mangle]
 pub extern "C" fn GoToDesktopNumber(desktop_number: i32) -> i32 {
-    switch_desktop(desktop_number as u32).map_or(-1, |_| 1)
+    ensure_com();
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(switch_desktop(desktop)).map_or(-1, |_| 1)
 }
 
+/// Sets the name of the desktop, the name is null terminated UTF-8
 #[no_mangle]
 pub extern "C" fn SetDesktopName(desktop_number: i32, in_name_ptr: *const i8) -> i32 {
+    ensure_com();
+    if in_name_ptr.is_null() {
+        return fail(LastError::InvalidArgument);
+    }
     let name_str = unsafe { CStr::from_ptr(in_name_ptr).to_string_lossy() };
-    get_desktop(desktop_number)
-        .set_name(&name_str)
-        .map_or(-1, |_| 1)
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(desktop.set_name(&name_str)).map_or(-1, |_| 1)
 }
 
+/// Writes the name of the desktop to the buffer as null terminated UTF-8.
+/// Returns 0 if the name can't be read, -1 if it doesn't fit or the buffer
+/// is null.
 #[no_mangle]
 pub extern "C" fn GetDesktopName(
     desktop_number: i32,
     out_utf8_ptr: *mut u8,
     out_utf8_len: usize,
 ) -> i32 {
-    if let Ok(name) = get_desktop(desktop_number).get_name() {
-        let name_str = CString::new(name).unwrap();
-        let name_bytes = name_str.as_bytes_with_nul();
-        if name_bytes.len() > out_utf8_len {
-            return -1;
-        }
-        unsafe {
-            out_utf8_ptr.copy_from(name_bytes.as_ptr(), name_bytes.len());
+    ensure_com();
+    if out_utf8_ptr.is_null() {
+        return fail(LastError::InvalidArgument);
+    }
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    if let Ok(name) = track(desktop.get_name()) {
+        if write_utf8(&name, out_utf8_ptr, out_utf8_len) {
+            1
+        } else {
+            fail(LastError::BufferTooSmall)
         }
-        1
     } else {
         0
     }
 }
 
-static LISTENER_HWNDS: Lazy<Arc<Mutex<HashSet<isize>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+/// Copies the string with a null terminator to the buffer, false if it
+/// doesn't fit. The string is cut at a null character inside it.
+fn write_utf8(text: &str, out_utf8_ptr: *mut u8, out_utf8_len: usize) -> bool {
+    let text = text.split('\0').next().unwrap_or_default();
+    if out_utf8_ptr.is_null() || text.len() + 1 > out_utf8_len {
+        return false;
+    }
+    unsafe {
+        out_utf8_ptr.copy_from(text.as_ptr(), text.len());
+        out_utf8_ptr.add(text.len()).write(0);
+    }
+    true
+}
 
-static SENDER_THREAD: Lazy<Arc<Mutex<Option<(DesktopEventThread, std::thread::JoinHandle<()>)>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(None)));
+/// Sets the wallpaper of the desktop, the path is null terminated UTF-8
+#[no_mangle]
+pub extern "C" fn SetDesktopWallpaper(desktop_number: i32, in_path_ptr: *const i8) -> i32 {
+    ensure_com();
+    if in_path_ptr.is_null() {
+        return fail(LastError::InvalidArgument);
+    }
+    let path_str = unsafe { CStr::from_ptr(in_path_ptr).to_string_lossy() };
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(desktop.set_wallpaper(&path_str)).map_or(-1, |_| 1)
+}
 
+/// Moves the desktop to the index, the desktops in between shift by one
 #[no_mangle]
-pub extern "C" fn RegisterPostMessageHook(listener_hwnd: HWND, message_offset: u32) -> i32 {
-    {
-        let mut a = LISTENER_HWNDS.lock().unwrap();
-        a.insert(listener_hwnd.0);
+pub extern "C" fn MoveDesktopToIndex(desktop_number: i32, index: i32) -> i32 {
+    ensure_com();
+    if index < 0 {
+        return fail(LastError::InvalidArgument);
     }
-    {
-        let mut a = SENDER_THREAD.lock().unwrap();
-        let (tx, rx) = crossbeam_channel::unbounded::<DesktopEvent>();
-        if a.is_none() {
-            log::log_output("RegisterPostMessageHook: create new threads");
-            let listener_thread = std::thread::spawn(move || {
-                for item in rx {
-                    match item {
-                        DesktopEvent::DesktopChanged { new, old } => {
-                            let new_index = new.get_index().unwrap_or(0);
-                            let old_index = old.get_index().unwrap_or(0);
-                            let a = LISTENER_HWNDS.lock().unwrap();
-                            for hwnd in a.iter() {
-                                unsafe {
-                                    let _ = PostMessageW(
-                                        HWND(*hwnd as isize),
-                                        message_offset,
-                                        WPARAM(old_index as usize),
-                                        LPARAM(new_index as isize),
-                                    );
-                                }
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-            });
-            let create_sender_result = listen_desktop_events(tx);
-            match create_sender_result {
-                Ok(sender_thread) => {
-                    *a = Some((sender_thread, listener_thread));
-                    return 1;
-                }
-                Err(_er) => {
-                    #[cfg(debug_assertions)]
-                    log::log_output(&format!("RegisterPostMessageHook failed: {:?}", _er));
-                    return -1;
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(move_desktop(desktop, index as u32)).map_or(-1, |_| 1)
+}
+
+/// Windows registered with `RegisterPostMessageHook`, and the message posted
+/// to each
+static LISTENER_HWNDS: Lazy<Arc<Mutex<HashMap<isize, u32>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type SenderThread = (DesktopEventThread, std::thread::JoinHandle<()>);
+
+static SENDER_THREAD: Lazy<Arc<Mutex<Option<SenderThread>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// Posts the message of each registered window when the desktop changes,
+/// `WPARAM` is the old and `LPARAM` the new desktop number
+fn post_desktop_changes(rx: crossbeam_channel::Receiver<DesktopEvent>) {
+    for item in rx {
+        update_desktop_numbers(&item);
+        if let DesktopEvent::DesktopChanged { new, old } = item {
+            let new_index = desktop_number(new).unwrap_or(0);
+            let old_index = desktop_number(old).unwrap_or(0);
+            let hwnds = LISTENER_HWNDS.lock().unwrap();
+            for (hwnd, message) in hwnds.iter() {
+                unsafe {
+                    let _ = PostMessageW(
+                        HWND(*hwnd),
+                        *message,
+                        WPARAM(old_index as usize),
+                        LPARAM(new_index as isize),
+                    );
                 }
             }
         }
+    }
+}
+
+/// Posts `message_offset` to the window when the desktop changes, registering
+/// the window again changes its message. The listener is started with the
+/// first window.
+#[no_mangle]
+pub extern "C" fn RegisterPostMessageHook(listener_hwnd: HWND, message_offset: u32) -> i32 {
+    ensure_com();
+    // Locked first by both functions, so the windows and the listener change
+    // together
+    let mut sender = SENDER_THREAD.lock().unwrap();
+    LISTENER_HWNDS
+        .lock()
+        .unwrap()
+        .insert(listener_hwnd.0, message_offset);
+    if sender.is_some() {
         return 1;
     }
+    log::log_output("RegisterPostMessageHook: create new threads");
+    let (tx, rx) = crossbeam_channel::unbounded::<DesktopEvent>();
+    let listener_thread = std::thread::spawn(move || post_desktop_changes(rx));
+
+    // Initial state fills the desktop number map
+    let options = ListenerOptions {
+        initial_state: true,
+        ..ListenerOptions::default()
+    };
+    match track(listen_desktop_events_with_options(tx, options)) {
+        Ok(sender_thread) => {
+            *sender = Some((sender_thread, listener_thread));
+            1
+        }
+        Err(_er) => {
+            #[cfg(debug_assertions)]
+            log::log_output(&format!("RegisterPostMessageHook failed: {:?}", _er));
+
+            // The sender was dropped, so the listener thread finishes
+            let _ = listener_thread.join();
+            LISTENER_HWNDS.lock().unwrap().remove(&listener_hwnd.0);
+            -1
+        }
+    }
 }
 
+/// Stops posting to the window, the listener is stopped with the last window.
+/// Returns -1 if the window was not registered, or if the listener threads
+/// panicked.
 #[no_mangle]
-pub extern "C" fn UnregisterPostMessageHook(listener_hwnd: HWND) {
-    let mut a = LISTENER_HWNDS.lock().unwrap();
-    a.remove(&listener_hwnd.0);
-    if a.len() == 0 {
-        let mut a = SENDER_THREAD.lock().unwrap();
-        if let Some((mut sender_thread, listener_thread)) = a.take() {
-            // By joining sender thread first it ensures the listener thread finishes when joined
-            sender_thread.stop().unwrap();
-            listener_thread.join().unwrap();
+pub extern "C" fn UnregisterPostMessageHook(listener_hwnd: HWND) -> i32 {
+    let mut sender = SENDER_THREAD.lock().unwrap();
+    let mut hwnds = LISTENER_HWNDS.lock().unwrap();
+    if hwnds.remove(&listener_hwnd.0).is_none() {
+        return fail(LastError::InvalidArgument);
+    }
+    let last = hwnds.is_empty();
+
+    // The listener thread posts while holding the lock, it can't be joined
+    // before the lock is released
+    drop(hwnds);
+    if last {
+        return stop_listener(&mut sender);
+    }
+    1
+}
+
+/// Unregisters the desktop notifications of explorer.exe and joins the
+/// threads. Returns -1 if one of the threads panicked.
+fn stop_listener(sender: &mut Option<SenderThread>) -> i32 {
+    let mut result = 1;
+    if let Some((mut sender_thread, listener_thread)) = sender.take() {
+        // By joining sender thread first it ensures the listener thread finishes when joined
+        let stopped = sender_thread.stop();
+        if stopped.and(listener_thread.join()).is_err() {
+            result = fail(LastError::Vd(Error::ListenerThreadFailed));
         }
     }
+    // Poisoned if the listener thread panicked while updating the map
+    *DESKTOP_NUMBERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    result
+}
+
+/// Unregisters all post message hooks and stops listening to explorer.exe.
+/// Returns -1 if the listener threads panicked.
+///
+/// Call this before unloading the DLL, e.g. when reloading a script,
+/// otherwise explorer.exe keeps the notification registration of the unloaded
+/// DLL. It can't be done on `DLL_PROCESS_DETACH`, the listener threads can't
+/// be joined while the loader lock is held.
+#[no_mangle]
+pub extern "C" fn VdaShutdown() -> i32 {
+    let mut sender = SENDER_THREAD.lock().unwrap();
+    LISTENER_HWNDS.lock().unwrap().clear();
+    stop_listener(&mut sender)
 }
+
+/// 1 if the window is shown on all desktops, 0 if not
 #[no_mangle]
 pub extern "C" fn IsPinnedWindow(hwnd: HWND) -> i32 {
-    is_pinned_window(hwnd).map_or(-1, |x| x as i32)
+    ensure_com();
+    track(is_pinned_window(hwnd)).map_or(-1, |x| x as i32)
 }
+/// Shows the window on all desktops
 #[no_mangle]
 pub extern "C" fn PinWindow(hwnd: HWND) -> i32 {
-    pin_window(hwnd).map_or(-1, |_| 1)
+    ensure_com();
+    track(pin_window(hwnd)).map_or(-1, |_| 1)
 }
+/// Shows the window only on its desktop again
 #[no_mangle]
 pub extern "C" fn UnPinWindow(hwnd: HWND) -> i32 {
-    unpin_window(hwnd).map_or(-1, |_| 1)
+    ensure_com();
+    track(unpin_window(hwnd)).map_or(-1, |_| 1)
 }
+/// 1 if the windows of the window's app are shown on all desktops, 0 if
+/// not
 #[no_mangle]
 pub extern "C" fn IsPinnedApp(hwnd: HWND) -> i32 {
-    is_pinned_app(hwnd).map_or(-1, |x| x as i32)
+    ensure_com();
+    track(is_pinned_app(hwnd)).map_or(-1, |x| x as i32)
 }
+/// Shows the windows of the window's app on all desktops
 #[no_mangle]
 pub extern "C" fn PinApp(hwnd: HWND) -> i32 {
-    pin_app(hwnd).map_or(-1, |_| 1)
+    ensure_com();
+    track(pin_app(hwnd)).map_or(-1, |_| 1)
 }
+/// Shows the windows of the window's app only on their desktops again
 #[no_mangle]
 pub extern "C" fn UnPinApp(hwnd: HWND) -> i32 {
-    unpin_app(hwnd).map_or(-1, |_| 1)
+    ensure_com();
+    track(unpin_app(hwnd)).map_or(-1, |_| 1)
 }
+/// 1 if the window is on the desktop, 0 if not
 #[no_mangle]
 pub extern "C" fn IsWindowOnDesktopNumber(hwnd: HWND, desktop_number: i32) -> i32 {
-    is_window_on_desktop(desktop_number, hwnd).map_or(-1, |b| b as i32)
+    ensure_com();
+    let desktop = match desktop_by_number(desktop_number) {
+        Ok(desktop) => desktop,
+        Err(err) => return fail(err),
+    };
+    track(is_window_on_desktop(desktop, hwnd)).map_or(-1, |b| b as i32)
 }
 
+/// Creates a desktop, returns its number
 #[no_mangle]
 pub extern "C" fn CreateDesktop() -> i32 {
-    if let Ok(desk) = create_desktop() {
-        desk.get_index().map_or(-1, |x| x as i32)
-    } else {
-        -1
-    }
+    ensure_com();
+    track(create_desktop().and_then(|desk| desk.get_index())).map_or(-1, |x| x as i32)
 }
 
+/// Removes the desktop, its windows move to the fallback desktop
 #[no_mangle]
 pub extern "C" fn RemoveDesktop(remove_desktop_number: i32, fallback_desktop_number: i32) -> i32 {
+    ensure_com();
     if remove_desktop_number == fallback_desktop_number {
-        return -1;
+        return fail(LastError::InvalidArgument);
+    }
+    let (desktop, fallback) = match (
+        desktop_by_number(remove_desktop_number),
+        desktop_by_number(fallback_desktop_number),
+    ) {
+        (Ok(desktop), Ok(fallback)) => (desktop, fallback),
+        (Err(err), _) | (_, Err(err)) => return fail(err),
+    };
+    track(remove_desktop(desktop, fallback)).map_or(-1, |_| 1)
+}
+
+/// Code of the last error on the calling thread, `VDA_ERROR_OK` if the last
+/// call succeeded
+#[no_mangle]
+pub extern "C" fn GetVdaLastError() -> i32 {
+    last_error::code() as i32
+}
+
+/// Description of the last error on the calling thread as null terminated
+/// UTF-8, empty if the last call succeeded. Returns -1 if it doesn't fit.
+#[no_mangle]
+pub extern "C" fn GetVdaLastErrorMessage(out_utf8_ptr: *mut u8, out_utf8_len: usize) -> i32 {
+    // Reading the error must not replace it
+    if write_utf8(&last_error::message(), out_utf8_ptr, out_utf8_len) {
+        1
+    } else {
+        -1
     }
-    remove_desktop(remove_desktop_number, fallback_desktop_number).map_or(-1, |_| 1)
 }
 
+/// Log callback of the application, see `SetLogCallback`
+type VdaLogCallback = extern "C" fn(message_utf8: *const u8, level: i32);
+
+static LOG_CALLBACK: Mutex<Option<VdaLogCallback>> = Mutex::new(None);
+
+fn forward_log(message: &str, level: LogLevel) {
+    let callback = *LOG_CALLBACK.lock().unwrap();
+    if let (Some(callback), Ok(message)) = (callback, CString::new(message)) {
+        callback(message.as_ptr() as *const u8, level as i32);
+    }
+}
+
+/// Calls the callback with the log messages of the library as null terminated
+/// UTF-8, and their level: 1 error, 2 warning, 3 info, 4 debug. Null restores
+/// the default output. The callback may be called from other threads.
+#[no_mangle]
+pub extern "C" fn SetLogCallback(callback: Option<VdaLogCallback>) {
+    *LOG_CALLBACK.lock().unwrap() = callback;
+    set_log_callback(callback.map(|_| forward_log as LogCallback));
+}
+
+/// Does nothing, kept for compatibility
 #[no_mangle]
 pub extern "C" fn RestartVirtualDesktopAccessor() {
     // ?
 }
 
 mod log {
-    #[cfg(debug_assertions)]
+    #[cfg(all(debug_assertions, not(feature = "no-log")))]
     extern "system" {
         fn OutputDebugStringW(lpOutputString: windows::core::PCWSTR);
     }
 
-    #[cfg(debug_assertions)]
+    #[cfg(all(debug_assertions, not(feature = "no-log")))]
     pub(crate) fn log_output(s: &str) {
         unsafe {
             println!("{}", s);
@@ -237,7 +527,7 @@ mod log {
         }
     }
 
-    #[cfg(not(debug_assertions))]
+    #[cfg(any(not(debug_assertions), feature = "no-log"))]
     #[inline]
     pub(crate) fn log_output(_s: &str) {}
 }
@@ -246,6 +536,15 @@ mod log {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_write_utf8() {
+        let mut buffer = [0xFFu8; 8];
+        assert!(write_utf8("ab\0cd", buffer.as_mut_ptr(), buffer.len()));
+        assert_eq!(&buffer[..3], b"ab\0");
+        assert!(!write_utf8("too long", buffer.as_mut_ptr(), buffer.len()));
+        assert!(!write_utf8("", std::ptr::null_mut(), 0));
+    }
+
     #[test]
     fn test_dll_get_desktop_name() {
         // Allocate a buffer for the UTF-8 string
@@ -287,4 +586,22 @@ mod tests {
         let after_count = GetDesktopCount();
         assert_eq!(count, after_count);
     }
+
+    #[test]
+    fn test_move_desktop_to_index() {
+        let new_desk_index = CreateDesktop();
+        let new_desk_id = GetDesktopIdByNumber(new_desk_index);
+        assert_eq!(MoveDesktopToIndex(new_desk_index, 0), 1);
+        assert_eq!(GetDesktopNumberById(new_desk_id), 0);
+        assert_eq!(MoveDesktopToIndex(0, -1), -1);
+        assert_eq!(RemoveDesktop(0, 1), 1);
+    }
+
+    #[test]
+    fn test_negative_desktop_number() {
+        assert_eq!(GoToDesktopNumber(-1), -1);
+        assert_eq!(GetVdaLastError(), 100);
+        assert_eq!(IsWindowOnDesktopNumber(HWND(0), -1), -1);
+        assert_eq!(GetVdaLastError(), 100);
+    }
 }
@@ -0,0 +1,210 @@
+/* C header for VirtualDesktopAccessor.dll
+ * Generated with `cargo run -p dll --bin generate-header`, do not edit */
+
+#ifndef VIRTUALDESKTOPACCESSOR_H
+#define VIRTUALDESKTOPACCESSOR_H
+
+#include <stddef.h>
+#include <stdint.h>
+#include <windows.h>
+
+#ifndef VDA_API
+#define VDA_API __declspec(dllimport)
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/** Codes returned by GetVdaLastError */
+typedef enum VdaErrorCode {
+    /** Last call succeeded */
+    VDA_ERROR_OK = 0,
+    /** Window not found */
+    VDA_ERROR_WINDOW_NOT_FOUND = 1,
+    /** Desktop not found */
+    VDA_ERROR_DESKTOP_NOT_FOUND = 2,
+    /** Creating the desktop failed */
+    VDA_ERROR_CREATE_DESKTOP_FAILED = 3,
+    /** Removing the desktop failed */
+    VDA_ERROR_REMOVE_DESKTOP_FAILED = 4,
+    /** Virtual desktop service not registered, explorer.exe is not running */
+    VDA_ERROR_CLASS_NOT_REGISTERED = 5,
+    /** RPC server not available */
+    VDA_ERROR_RPC_SERVER_NOT_AVAILABLE = 6,
+    /** COM not initialized */
+    VDA_ERROR_COM_NOT_INITIALIZED = 7,
+    /** COM object not connected */
+    VDA_ERROR_COM_OBJECT_NOT_CONNECTED = 8,
+    /** COM element not found */
+    VDA_ERROR_COM_ELEMENT_NOT_FOUND = 9,
+    /** Interface not supported, Windows changed the interfaces */
+    VDA_ERROR_COM_NO_INTERFACE = 10,
+    /** Not implemented on this Windows version */
+    VDA_ERROR_COM_NOT_IMPLEMENTED = 11,
+    /** Other COM error */
+    VDA_ERROR_COM_ERROR = 12,
+    /** COM call returned a null pointer */
+    VDA_ERROR_COM_ALLOCATED_NULL_PTR = 13,
+    /** Internal borrow error */
+    VDA_ERROR_INTERNAL_BORROW_ERROR = 14,
+    /** Listener thread failed to start */
+    VDA_ERROR_LISTENER_THREAD_FAILED = 15,
+    /** Reading the registry failed */
+    VDA_ERROR_REGISTRY_ERROR = 16,
+    /** Alias not found */
+    VDA_ERROR_ALIAS_NOT_FOUND = 17,
+    /** Reading or writing a file failed */
+    VDA_ERROR_FILE_ERROR = 18,
+    /** COM method failed, the message names the method */
+    VDA_ERROR_COM_CALL_FAILED = 19,
+    /** Windows build not supported */
+    VDA_ERROR_UNSUPPORTED_WINDOWS_BUILD = 20,
+    /** Called on a thread the COM object doesn't belong to */
+    VDA_ERROR_NOT_ON_CORRECT_THREAD = 21,
+    /** Connection to explorer.exe lost */
+    VDA_ERROR_DISCONNECTED = 22,
+    /** Service stopped */
+    VDA_ERROR_SERVICE_STOPPED = 23,
+    /** The only desktop can't be removed */
+    VDA_ERROR_LAST_DESKTOP = 24,
+    /** Window can't be moved between desktops */
+    VDA_ERROR_WINDOW_NOT_MOVABLE = 25,
+    /** Rules config not valid */
+    VDA_ERROR_RULES_CONFIG_ERROR = 26,
+    /** Listener events dropped */
+    VDA_ERROR_EVENTS_DROPPED = 27,
+    /** Invalid argument, e.g. a negative number */
+    VDA_ERROR_INVALID_ARGUMENT = 100,
+    /** Output buffer too small */
+    VDA_ERROR_BUFFER_TOO_SMALL = 101,
+} VdaErrorCode;
+
+/** Receives the log messages, see SetLogCallback */
+typedef void (*VdaLogCallback)(const uint8_t *message_utf8, int32_t level);
+
+/** Number of the current desktop */
+VDA_API int32_t GetCurrentDesktopNumber(void);
+
+/** Number of desktops */
+VDA_API int32_t GetDesktopCount(void);
+
+/** GUID of the desktop, all zeros on error */
+VDA_API GUID GetDesktopIdByNumber(int32_t number);
+
+/** Number of the desktop with the GUID */
+VDA_API int32_t GetDesktopNumberById(GUID desktop_id);
+
+/** GUID of the desktop the window is on, all zeros on error */
+VDA_API GUID GetWindowDesktopId(HWND hwnd);
+
+/** Number of the desktop the window is on */
+VDA_API int32_t GetWindowDesktopNumber(HWND hwnd);
+
+/** 1 if the window is on the current desktop, 0 if not */
+VDA_API int32_t IsWindowOnCurrentVirtualDesktop(HWND hwnd);
+
+/** Moves the window to the desktop */
+VDA_API int32_t MoveWindowToDesktopNumber(HWND hwnd, int32_t desktop_number);
+
+/** Switches to the desktop */
+VDA_API int32_t GoToDesktopNumber(int32_t desktop_number);
+
+/** Sets the name of the desktop, the name is null terminated UTF-8 */
+VDA_API int32_t SetDesktopName(int32_t desktop_number, const char *in_name_ptr);
+
+/**
+ * Writes the name of the desktop to the buffer as null terminated UTF-8.
+ * Returns 0 if the name can't be read, -1 if it doesn't fit or the buffer
+ * is null.
+ */
+VDA_API int32_t GetDesktopName(int32_t desktop_number, uint8_t *out_utf8_ptr, size_t out_utf8_len);
+
+/** Sets the wallpaper of the desktop, the path is null terminated UTF-8 */
+VDA_API int32_t SetDesktopWallpaper(int32_t desktop_number, const char *in_path_ptr);
+
+/** Moves the desktop to the index, the desktops in between shift by one */
+VDA_API int32_t MoveDesktopToIndex(int32_t desktop_number, int32_t index);
+
+/**
+ * Posts `message_offset` to the window when the desktop changes, registering
+ * the window again changes its message. The listener is started with the
+ * first window.
+ */
+VDA_API int32_t RegisterPostMessageHook(HWND listener_hwnd, uint32_t message_offset);
+
+/**
+ * Stops posting to the window, the listener is stopped with the last window.
+ * Returns -1 if the window was not registered, or if the listener threads
+ * panicked.
+ */
+VDA_API int32_t UnregisterPostMessageHook(HWND listener_hwnd);
+
+/**
+ * Unregisters all post message hooks and stops listening to explorer.exe.
+ * Returns -1 if the listener threads panicked.
+ *
+ * Call this before unloading the DLL, e.g. when reloading a script,
+ * otherwise explorer.exe keeps the notification registration of the unloaded
+ * DLL. It can't be done on `DLL_PROCESS_DETACH`, the listener threads can't
+ * be joined while the loader lock is held.
+ */
+VDA_API int32_t VdaShutdown(void);
+
+/** 1 if the window is shown on all desktops, 0 if not */
+VDA_API int32_t IsPinnedWindow(HWND hwnd);
+
+/** Shows the window on all desktops */
+VDA_API int32_t PinWindow(HWND hwnd);
+
+/** Shows the window only on its desktop again */
+VDA_API int32_t UnPinWindow(HWND hwnd);
+
+/**
+ * 1 if the windows of the window's app are shown on all desktops, 0 if
+ * not
+ */
+VDA_API int32_t IsPinnedApp(HWND hwnd);
+
+/** Shows the windows of the window's app on all desktops */
+VDA_API int32_t PinApp(HWND hwnd);
+
+/** Shows the windows of the window's app only on their desktops again */
+VDA_API int32_t UnPinApp(HWND hwnd);
+
+/** 1 if the window is on the desktop, 0 if not */
+VDA_API int32_t IsWindowOnDesktopNumber(HWND hwnd, int32_t desktop_number);
+
+/** Creates a desktop, returns its number */
+VDA_API int32_t CreateDesktop(void);
+
+/** Removes the desktop, its windows move to the fallback desktop */
+VDA_API int32_t RemoveDesktop(int32_t remove_desktop_number, int32_t fallback_desktop_number);
+
+/**
+ * Code of the last error on the calling thread, `VDA_ERROR_OK` if the last
+ * call succeeded
+ */
+VDA_API int32_t GetVdaLastError(void);
+
+/**
+ * Description of the last error on the calling thread as null terminated
+ * UTF-8, empty if the last call succeeded. Returns -1 if it doesn't fit.
+ */
+VDA_API int32_t GetVdaLastErrorMessage(uint8_t *out_utf8_ptr, size_t out_utf8_len);
+
+/**
+ * Calls the callback with the log messages of the library as null terminated
+ * UTF-8, and their level: 1 error, 2 warning, 3 info, 4 debug. Null restores
+ * the default output. The callback may be called from other threads.
+ */
+VDA_API void SetLogCallback(VdaLogCallback callback);
+
+/** Does nothing, kept for compatibility */
+VDA_API void RestartVirtualDesktopAccessor(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
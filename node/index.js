@@ -0,0 +1,43 @@
+// Windows virtual desktops for Node.js. The calls of the native module return
+// promises, and `events` emits each desktop event by its kind, e.g.
+// `desktopChanged`, and as `event`.
+//
+//     const winvd = require("winvd");
+//     winvd.events.on("desktopChanged", (event) => console.log(event.desktop));
+//     await winvd.switchDesktop(1);
+//
+// Build `winvd.node` with the `node` feature of the crate, see `src/node.rs`.
+const { EventEmitter } = require("events");
+const native = require("./winvd.node");
+
+// Listens to the desktops only while someone listens to the emitter, so an
+// idle emitter doesn't keep the process alive
+class DesktopEvents extends EventEmitter {
+  constructor() {
+    super();
+    this.listener = null;
+    this.on("newListener", (name) => {
+      if (!this.listener && !isMeta(name)) {
+        this.listener = native.listen((event) => {
+          this.emit(event.kind, event);
+          this.emit("event", event);
+        });
+      }
+    });
+    this.on("removeListener", () => {
+      const count = this.eventNames()
+        .filter((name) => !isMeta(name))
+        .reduce((sum, name) => sum + this.listenerCount(name), 0);
+      if (this.listener && count === 0) {
+        this.listener.stop();
+        this.listener = null;
+      }
+    });
+  }
+}
+
+function isMeta(name) {
+  return name === "newListener" || name === "removeListener";
+}
+
+module.exports = { ...native, events: new DesktopEvents() };
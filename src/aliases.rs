@@ -0,0 +1,265 @@
+//! User defined aliases for desktops, e.g. "work" or "media".
+//!
+//! Aliases point to the desktop GUID, so they keep working when desktops are
+//! reordered or renamed. The name of the desktop is stored too, and if the GUID
+//! disappears, e.g. after an OS upgrade, the alias is bound again to a desktop
+//! with the same name.
+//!
+//! Aliases are stored in a text file, one alias per line:
+//!
+//! ```text
+//! work<TAB>XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX<TAB>Desktop name
+//! ```
+//!
+//! Tabs, line breaks and backslashes in the alias and the name are escaped as
+//! `\t`, `\n`, `\r` and `\\`.
+use std::path::{Path, PathBuf};
+
+use windows::core::GUID;
+
+use crate::backend::with_backend;
+use crate::comobjects::{parse_guid, DesktopInternal};
+use crate::{Desktop, Error, Result};
+
+/// Alias pointing to a desktop
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DesktopAlias {
+    pub alias: String,
+    pub guid: GUID,
+
+    /// Name of the desktop when the alias was last resolved, used for binding
+    /// the alias again if the GUID is gone
+    pub name: String,
+}
+
+/// Aliases stored in a file, see the module docs
+#[derive(Clone, Debug)]
+pub struct DesktopAliases {
+    path: PathBuf,
+    aliases: Vec<DesktopAlias>,
+}
+
+impl DesktopAliases {
+    /// Path of the default aliases file, `%APPDATA%\winvd\aliases.txt`
+    pub fn default_path() -> PathBuf {
+        let base = std::env::var_os("APPDATA").map_or_else(PathBuf::new, PathBuf::from);
+        base.join("winvd").join("aliases.txt")
+    }
+
+    /// Loads aliases from the default file
+    pub fn load_default() -> Result<Self> {
+        Self::load(Self::default_path())
+    }
+
+    /// Loads aliases from a file, missing file is treated as empty
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::FileError(err.kind())),
+        };
+        Ok(DesktopAliases {
+            path,
+            aliases: parse_aliases(&text),
+        })
+    }
+
+    /// Writes the aliases to the file they were loaded from
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| Error::FileError(err.kind()))?;
+        }
+        std::fs::write(&self.path, format_aliases(&self.aliases))
+            .map_err(|err| Error::FileError(err.kind()))
+    }
+
+    /// All aliases
+    pub fn aliases(&self) -> &[DesktopAlias] {
+        &self.aliases
+    }
+
+    /// Points the alias to the desktop, and saves the file
+    pub fn set<T>(&mut self, alias: &str, desktop: T) -> Result<()>
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into();
+        let entry = DesktopAlias {
+            alias: alias.to_string(),
+            guid: desktop.get_id()?,
+            // Raw name, a default name would match whichever desktop is at
+            // the same position
+            name: with_backend(move |b| b.get_desktop_name(desktop))?,
+        };
+        match self.aliases.iter_mut().find(|a| a.alias == alias) {
+            Some(existing) => *existing = entry,
+            None => self.aliases.push(entry),
+        }
+        self.save()
+    }
+
+    /// Removes the alias, and saves the file if it existed
+    pub fn remove(&mut self, alias: &str) -> Result<bool> {
+        let count = self.aliases.len();
+        self.aliases.retain(|a| a.alias != alias);
+        if self.aliases.len() == count {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Get the desktop of the alias.
+    ///
+    /// If the desktop is gone, the alias is bound to a desktop with the same
+    /// name and the file is saved.
+    pub fn get(&mut self, alias: &str) -> Result<Desktop> {
+        let desktops = with_backend(|b| b.get_desktop_infos())?;
+        let entry = self
+            .aliases
+            .iter_mut()
+            .find(|a| a.alias == alias)
+            .ok_or(Error::AliasNotFound)?;
+
+        let info = desktops
+            .iter()
+            .find(|info| info.guid == entry.guid)
+            .or_else(|| {
+                desktops
+                    .iter()
+                    .find(|info| !entry.name.is_empty() && info.name == entry.name)
+            })
+            .ok_or(Error::AliasNotFound)?;
+
+        let changed = entry.guid != info.guid || entry.name != info.name;
+        if changed {
+            log_format!(
+                "Alias {} bound to desktop {:?} {}",
+                alias,
+                info.guid,
+                info.name
+            );
+            entry.guid = info.guid;
+            entry.name = info.name.clone();
+            self.save()?;
+        }
+        Ok(DesktopInternal::IndexGuid(info.index, info.guid).into())
+    }
+
+    /// Switch to the desktop of the alias
+    pub fn switch_to_alias(&mut self, alias: &str) -> Result<()> {
+        crate::switch_desktop(self.get(alias)?)
+    }
+}
+
+/// Switch to the desktop of the alias, using the default aliases file
+pub fn switch_to_alias(alias: &str) -> Result<()> {
+    DesktopAliases::load_default()?.switch_to_alias(alias)
+}
+
+fn parse_aliases(text: &str) -> Vec<DesktopAlias> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let alias = parts.next()?.trim();
+            let guid = parse_guid(parts.next()?)?;
+            let name = parts.next().unwrap_or("");
+            if alias.is_empty() {
+                return None;
+            }
+            Some(DesktopAlias {
+                alias: unescape(alias),
+                guid,
+                name: unescape(name),
+            })
+        })
+        .collect()
+}
+
+fn format_aliases(aliases: &[DesktopAlias]) -> String {
+    aliases
+        .iter()
+        .map(|a| format!("{}\t{:?}\t{}\n", escape(&a.alias), a.guid, escape(&a.name)))
+        .collect()
+}
+
+/// Escapes the separators of the file, see the module docs
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape`, other backslashes are kept as they are
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_roundtrip() {
+        let aliases = vec![
+            DesktopAlias {
+                alias: "work".to_string(),
+                guid: GUID::from_u128(0x11223344_5566_7788_99aa_bbccddeeff00),
+                name: "Work stuff".to_string(),
+            },
+            DesktopAlias {
+                alias: "media".to_string(),
+                guid: GUID::from_u128(1),
+                name: String::new(),
+            },
+        ];
+        let text = format_aliases(&aliases);
+        assert_eq!(
+            text.lines().next(),
+            Some("work\t11223344-5566-7788-99AA-BBCCDDEEFF00\tWork stuff")
+        );
+        assert_eq!(parse_aliases(&text), aliases);
+        assert_eq!(parse_aliases("broken line\n\n"), vec![]);
+    }
+
+    #[test]
+    fn test_aliases_escaped() {
+        let aliases = vec![DesktopAlias {
+            alias: "a\tb".to_string(),
+            guid: GUID::from_u128(1),
+            name: "Line\r\nbreak\\tab\t".to_string(),
+        }];
+        let text = format_aliases(&aliases);
+        assert_eq!(text.lines().count(), 1);
+        assert_eq!(parse_aliases(&text), aliases);
+        assert_eq!(unescape("C:\\Users\\x"), "C:\\Users\\x");
+    }
+}
@@ -0,0 +1,389 @@
+//! Backend of the desktop functions, the shell or a mock for tests.
+//!
+//! The desktop functions, e.g. `switch_desktop`, `pin_window` and the
+//! `Desktop` methods, go through the backend, and so do `batch`, `Cache`,
+//! `DesktopAliases` and the self test. With the `test-hooks` feature a
+//! backend like `MockBackend` can be installed with `set_backend`, so tests
+//! of an application run without explorer.exe. The backend is installed for
+//! the whole process, so calls made on other threads, e.g. by the
+//! `VirtualDesktopService` worker, use it too. The listener always uses the
+//! shell.
+#[cfg(feature = "test-hooks")]
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::HWND;
+
+use crate::batch::BatchOp;
+use crate::comobjects::{with_com_objects, ComObjects, DesktopInternal};
+use crate::{BatchOps, Desktop, DesktopInfo, Direction, Error, Result, ViewInfo};
+
+/// Operations of the desktop functions, see the module docs.
+///
+/// Desktops may be given as index or GUID, returned desktops should have
+/// both, see `Desktop`. Operations only the shell has, e.g. the app ids of
+/// the windows, return `Error::ComNotImplemented` unless implemented.
+pub trait DesktopBackend {
+    fn get_desktops(&self) -> Result<Vec<Desktop>>;
+    fn get_desktop_count(&self) -> Result<u32>;
+    fn get_current_desktop(&self) -> Result<Desktop>;
+    fn get_desktop_id(&self, desktop: Desktop) -> Result<GUID>;
+    fn get_desktop_index(&self, desktop: Desktop) -> Result<u32>;
+
+    /// Name set by the user, empty for unnamed desktops
+    fn get_desktop_name(&self, desktop: Desktop) -> Result<String>;
+    fn set_desktop_name(&self, desktop: Desktop, name: &str) -> Result<()>;
+    fn switch_desktop(&self, desktop: Desktop) -> Result<()>;
+    fn create_desktop(&self) -> Result<Desktop>;
+
+    /// Windows of the removed desktop are moved to the fallback desktop
+    fn remove_desktop(&self, desktop: Desktop, fallback: Desktop) -> Result<()>;
+
+    /// Desktops in between shift by one
+    fn move_desktop(&self, desktop: Desktop, index: u32) -> Result<()>;
+    fn get_desktop_by_window(&self, hwnd: HWND) -> Result<Desktop>;
+    fn is_window_on_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<bool>;
+    fn move_window_to_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<()>;
+    fn can_move_window(&self, hwnd: HWND) -> Result<bool>;
+
+    /// Windows versions without per desktop wallpapers return
+    /// `Error::ComNotImplemented`
+    fn get_desktop_wallpaper(&self, desktop: Desktop) -> Result<String>;
+    fn set_desktop_wallpaper(&self, desktop: Desktop, path: &str) -> Result<()>;
+    fn is_pinned_window(&self, hwnd: HWND) -> Result<bool>;
+    fn pin_window(&self, hwnd: HWND) -> Result<()>;
+    fn unpin_window(&self, hwnd: HWND) -> Result<()>;
+    fn is_pinned_app(&self, hwnd: HWND) -> Result<bool>;
+    fn pin_app(&self, hwnd: HWND) -> Result<()>;
+    fn unpin_app(&self, hwnd: HWND) -> Result<()>;
+
+    /// Application views of all desktops, see `list_views`
+    fn get_view_infos(&self) -> Result<Vec<ViewInfo>>;
+
+    /// Desktop on the left or right of the desktop, `None` at the edge
+    fn get_adjacent_desktop(
+        &self,
+        desktop: Desktop,
+        direction: Direction,
+    ) -> Result<Option<Desktop>> {
+        let index = self.get_desktop_index(desktop)?;
+        let count = self.get_desktop_count()?;
+        let adjacent = match direction {
+            Direction::Left => index.checked_sub(1),
+            Direction::Right => Some(index + 1).filter(|next| *next < count),
+        };
+        adjacent.map(|index| desktop_at(self, index)).transpose()
+    }
+
+    /// Names of the infos are as set by the user, empty for unnamed desktops
+    fn get_desktop_infos(&self) -> Result<Vec<DesktopInfo>> {
+        self.get_desktops()?
+            .into_iter()
+            .map(|desktop| desktop_info(self, desktop))
+            .collect()
+    }
+
+    fn get_current_desktop_info(&self) -> Result<DesktopInfo> {
+        desktop_info(self, self.get_current_desktop()?)
+    }
+
+    fn is_window_on_current_desktop(&self, hwnd: HWND) -> Result<bool> {
+        self.is_window_on_desktop(hwnd, self.get_current_desktop()?)
+    }
+
+    /// Moves the windows one by one, `delay` apart. Fails only if the desktop
+    /// is not found.
+    fn move_windows_to_desktop(
+        &self,
+        windows: &[HWND],
+        desktop: Desktop,
+        delay: Duration,
+    ) -> Result<Vec<Result<()>>> {
+        let desktop = Desktop::from(self.get_desktop_id(desktop)?);
+        let mut results = Vec::with_capacity(windows.len());
+        for (i, hwnd) in windows.iter().enumerate() {
+            if i > 0 && !delay.is_zero() {
+                std::thread::sleep(delay);
+            }
+            results.push(self.move_window_to_desktop(*hwnd, desktop));
+        }
+        Ok(results)
+    }
+
+    /// Runs the operations of `batch` in order, and stops at the first
+    /// failing one. Switches are not animated.
+    fn run_batch(&self, ops: &BatchOps) -> Result<()> {
+        for op in &ops.ops {
+            match op {
+                BatchOp::Switch(desktop) => self.switch_desktop((*desktop).into())?,
+                BatchOp::MoveWindow(hwnd, desktop) => {
+                    self.move_window_to_desktop(*hwnd, (*desktop).into())?
+                }
+                BatchOp::PinWindow(hwnd) => self.pin_window(*hwnd)?,
+                BatchOp::UnpinWindow(hwnd) => self.unpin_window(*hwnd)?,
+                BatchOp::SetName(desktop, name) => {
+                    self.set_desktop_name((*desktop).into(), name)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Monitor of the desktop, `None` if it is shown on all monitors
+    fn get_desktop_monitor(&self, _desktop: Desktop) -> Result<Option<isize>> {
+        Ok(None)
+    }
+
+    fn get_desktops_with_urgent_windows(&self) -> Result<Vec<Desktop>> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn flash_window(&self, _hwnd: HWND) -> Result<()> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn is_shown_in_switchers(&self, _hwnd: HWND) -> Result<bool> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn set_shown_in_switchers(&self, _hwnd: HWND, _show: bool) -> Result<()> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn get_app_id(&self, _hwnd: HWND) -> Result<String> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn get_windows_by_app_id(&self, _app_id: &str) -> Result<Vec<HWND>> {
+        Err(Error::ComNotImplemented)
+    }
+}
+
+/// Desktop at the index with its GUID
+fn desktop_at<B: DesktopBackend + ?Sized>(b: &B, index: u32) -> Result<Desktop> {
+    let guid = b.get_desktop_id(Desktop::from(index))?;
+    Ok(DesktopInternal::IndexGuid(index, guid).into())
+}
+
+/// Info of the desktop, the wallpaper is empty if not supported
+fn desktop_info<B: DesktopBackend + ?Sized>(b: &B, desktop: Desktop) -> Result<DesktopInfo> {
+    Ok(DesktopInfo {
+        guid: b.get_desktop_id(desktop)?,
+        index: b.get_desktop_index(desktop)?,
+        name: b.get_desktop_name(desktop)?,
+        wallpaper: match b.get_desktop_wallpaper(desktop) {
+            Err(Error::ComNotImplemented) => String::new(),
+            result => result?,
+        },
+        is_remote: false,
+    })
+}
+
+impl DesktopBackend for ComObjects {
+    fn get_desktops(&self) -> Result<Vec<Desktop>> {
+        Ok(ComObjects::get_desktops(self)?
+            .into_iter()
+            .map(Desktop::from)
+            .collect())
+    }
+
+    fn get_desktop_count(&self) -> Result<u32> {
+        ComObjects::get_desktop_count(self)
+    }
+
+    fn get_current_desktop(&self) -> Result<Desktop> {
+        ComObjects::get_current_desktop(self).map(Desktop::from)
+    }
+
+    fn get_desktop_id(&self, desktop: Desktop) -> Result<GUID> {
+        ComObjects::get_desktop_id(self, &desktop.into())
+    }
+
+    fn get_desktop_index(&self, desktop: Desktop) -> Result<u32> {
+        ComObjects::get_desktop_index(self, &desktop.into())
+    }
+
+    fn get_desktop_name(&self, desktop: Desktop) -> Result<String> {
+        ComObjects::get_desktop_name(self, &desktop.into())
+    }
+
+    fn set_desktop_name(&self, desktop: Desktop, name: &str) -> Result<()> {
+        ComObjects::set_desktop_name(self, &desktop.into(), name)
+    }
+
+    fn switch_desktop(&self, desktop: Desktop) -> Result<()> {
+        ComObjects::switch_desktop(self, &desktop.into())
+    }
+
+    fn create_desktop(&self) -> Result<Desktop> {
+        ComObjects::create_desktop(self).map(Desktop::from)
+    }
+
+    fn remove_desktop(&self, desktop: Desktop, fallback: Desktop) -> Result<()> {
+        ComObjects::remove_desktop(self, &desktop.into(), &fallback.into())
+    }
+
+    fn get_desktop_by_window(&self, hwnd: HWND) -> Result<Desktop> {
+        ComObjects::get_desktop_by_window(self, &hwnd).map(Desktop::from)
+    }
+
+    fn is_window_on_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<bool> {
+        ComObjects::is_window_on_desktop(self, &hwnd, &desktop.into())
+    }
+
+    fn move_window_to_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<()> {
+        ComObjects::move_window_to_desktop(self, &hwnd, &desktop.into())
+    }
+
+    fn move_desktop(&self, desktop: Desktop, index: u32) -> Result<()> {
+        ComObjects::move_desktop(self, &desktop.into(), index)
+    }
+
+    fn can_move_window(&self, hwnd: HWND) -> Result<bool> {
+        ComObjects::can_move_window(self, &hwnd)
+    }
+
+    fn get_desktop_wallpaper(&self, desktop: Desktop) -> Result<String> {
+        ComObjects::get_desktop_wallpaper(self, &desktop.into())
+    }
+
+    fn set_desktop_wallpaper(&self, desktop: Desktop, path: &str) -> Result<()> {
+        ComObjects::set_desktop_wallpaper(self, &desktop.into(), path)
+    }
+
+    fn is_pinned_window(&self, hwnd: HWND) -> Result<bool> {
+        ComObjects::is_pinned_window(self, &hwnd)
+    }
+
+    fn pin_window(&self, hwnd: HWND) -> Result<()> {
+        ComObjects::pin_window(self, &hwnd)
+    }
+
+    fn unpin_window(&self, hwnd: HWND) -> Result<()> {
+        ComObjects::unpin_window(self, &hwnd)
+    }
+
+    fn is_pinned_app(&self, hwnd: HWND) -> Result<bool> {
+        ComObjects::is_pinned_app(self, &hwnd)
+    }
+
+    fn pin_app(&self, hwnd: HWND) -> Result<()> {
+        ComObjects::pin_app(self, &hwnd)
+    }
+
+    fn unpin_app(&self, hwnd: HWND) -> Result<()> {
+        ComObjects::unpin_app(self, &hwnd)
+    }
+
+    fn get_view_infos(&self) -> Result<Vec<ViewInfo>> {
+        ComObjects::get_view_infos(self)
+    }
+
+    fn get_adjacent_desktop(
+        &self,
+        desktop: Desktop,
+        direction: Direction,
+    ) -> Result<Option<Desktop>> {
+        Ok(ComObjects::get_adjacent_desktop(self, &desktop.into(), direction)?.map(Desktop::from))
+    }
+
+    fn get_desktop_infos(&self) -> Result<Vec<DesktopInfo>> {
+        ComObjects::get_desktop_infos(self)
+    }
+
+    fn get_current_desktop_info(&self) -> Result<DesktopInfo> {
+        ComObjects::get_current_desktop_info(self)
+    }
+
+    fn is_window_on_current_desktop(&self, hwnd: HWND) -> Result<bool> {
+        ComObjects::is_window_on_current_desktop(self, &hwnd)
+    }
+
+    fn move_windows_to_desktop(
+        &self,
+        windows: &[HWND],
+        desktop: Desktop,
+        delay: Duration,
+    ) -> Result<Vec<Result<()>>> {
+        ComObjects::move_windows_to_desktop(self, windows, &desktop.into(), &delay)
+    }
+
+    fn run_batch(&self, ops: &BatchOps) -> Result<()> {
+        ComObjects::run_batch(self, &ops.ops, ops.animate)
+    }
+
+    fn get_desktop_monitor(&self, desktop: Desktop) -> Result<Option<isize>> {
+        ComObjects::get_desktop_monitor(self, &desktop.into())
+    }
+
+    fn get_desktops_with_urgent_windows(&self) -> Result<Vec<Desktop>> {
+        Ok(ComObjects::get_desktops_with_urgent_windows(self)?
+            .into_iter()
+            .map(Desktop::from)
+            .collect())
+    }
+
+    fn flash_window(&self, hwnd: HWND) -> Result<()> {
+        ComObjects::flash_window(self, &hwnd)
+    }
+
+    fn is_shown_in_switchers(&self, hwnd: HWND) -> Result<bool> {
+        ComObjects::is_shown_in_switchers(self, &hwnd)
+    }
+
+    fn set_shown_in_switchers(&self, hwnd: HWND, show: bool) -> Result<()> {
+        ComObjects::set_shown_in_switchers(self, &hwnd, show)
+    }
+
+    fn get_app_id(&self, hwnd: HWND) -> Result<String> {
+        ComObjects::get_app_id(self, &hwnd)
+    }
+
+    fn get_windows_by_app_id(&self, app_id: &str) -> Result<Vec<HWND>> {
+        ComObjects::get_windows_by_app_id(self, app_id)
+    }
+}
+
+/// Backend installed with `set_backend`
+#[cfg(feature = "test-hooks")]
+static BACKEND: RwLock<Option<Arc<dyn DesktopBackend + Send + Sync>>> = RwLock::new(None);
+
+/// Installs a backend for all threads, `None` goes back to the shell.
+/// `MockBackend` needs the `listener` feature too.
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use winvd::{set_backend, MockBackend};
+///
+/// let mock = Arc::new(MockBackend::new(3));
+/// set_backend(Some(mock.clone()));
+/// winvd::switch_desktop(2).unwrap();
+/// assert_eq!(winvd::get_current_desktop().unwrap().get_index().unwrap(), 2);
+/// set_backend(None);
+/// ```
+#[cfg(feature = "test-hooks")]
+pub fn set_backend(backend: Option<Arc<dyn DesktopBackend + Send + Sync>>) {
+    *BACKEND
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = backend;
+}
+
+/// Calls the function with the installed backend, or with the shell
+pub(crate) fn with_backend<F, T>(f: F) -> Result<T>
+where
+    F: Fn(&dyn DesktopBackend) -> Result<T> + 'static,
+    T: 'static,
+{
+    #[cfg(feature = "test-hooks")]
+    {
+        let backend = BACKEND
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        if let Some(backend) = backend {
+            return f(&*backend);
+        }
+    }
+    with_com_objects(move |o| f(o))
+}
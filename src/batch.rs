@@ -0,0 +1,128 @@
+//! Runs several operations in one pass, e.g. for hotkey macros.
+//!
+//! ```rust,no_run
+//! use winvd::batch;
+//! # let hwnd = winvd::WindowId(0);
+//!
+//! batch(|ops| {
+//!     ops.move_window(hwnd, 2).switch(2).animate(true);
+//! })
+//! .unwrap();
+//! ```
+//!
+//! Desktops given by index are resolved from a single desktop list, which is
+//! read once per batch instead of once per operation. Only the last switch of
+//! the batch is animated, if animation is enabled, the other switches are
+//! never animated.
+use windows::Win32::Foundation::HWND;
+
+use crate::backend::with_backend;
+use crate::comobjects::DesktopInternal;
+use crate::{Desktop, Error, IntoHwnd, Result};
+
+#[derive(Clone, Debug)]
+pub(crate) enum BatchOp {
+    Switch(DesktopInternal),
+    MoveWindow(HWND, DesktopInternal),
+    PinWindow(HWND),
+    UnpinWindow(HWND),
+    SetName(DesktopInternal, String),
+}
+
+/// Operations queued in `batch`
+#[derive(Clone, Debug, Default)]
+pub struct BatchOps {
+    pub(crate) ops: Vec<BatchOp>,
+    pub(crate) animate: bool,
+
+    /// First window without a handle, fails the batch before it runs
+    error: Option<Error>,
+}
+
+impl BatchOps {
+    /// Switch to the desktop
+    pub fn switch<T>(&mut self, desktop: T) -> &mut Self
+    where
+        T: Into<Desktop>,
+    {
+        self.push(BatchOp::Switch(desktop.into().into()))
+    }
+
+    /// Move the window to the desktop
+    pub fn move_window<T, M>(&mut self, hwnd: impl IntoHwnd<M>, desktop: T) -> &mut Self
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into().into();
+        self.push_window(hwnd, |hwnd| BatchOp::MoveWindow(hwnd, desktop))
+    }
+
+    /// Pin the window to all desktops
+    pub fn pin_window<M>(&mut self, hwnd: impl IntoHwnd<M>) -> &mut Self {
+        self.push_window(hwnd, BatchOp::PinWindow)
+    }
+
+    /// Unpin the window
+    pub fn unpin_window<M>(&mut self, hwnd: impl IntoHwnd<M>) -> &mut Self {
+        self.push_window(hwnd, BatchOp::UnpinWindow)
+    }
+
+    /// Set the name of the desktop
+    pub fn set_name<T>(&mut self, desktop: T, name: &str) -> &mut Self
+    where
+        T: Into<Desktop>,
+    {
+        self.push(BatchOp::SetName(desktop.into().into(), name.to_string()))
+    }
+
+    /// Animate the last switch of the batch, if the Windows version supports
+    /// it. Disabled by default.
+    pub fn animate(&mut self, animate: bool) -> &mut Self {
+        self.animate = animate;
+        self
+    }
+
+    fn push(&mut self, op: BatchOp) -> &mut Self {
+        self.ops.push(op);
+        self
+    }
+
+    fn push_window<M>(
+        &mut self,
+        hwnd: impl IntoHwnd<M>,
+        op: impl FnOnce(HWND) -> BatchOp,
+    ) -> &mut Self {
+        match hwnd.into_hwnd() {
+            Ok(hwnd) => self.push(op(hwnd)),
+            Err(err) => {
+                self.error.get_or_insert(err);
+                self
+            }
+        }
+    }
+}
+
+/// Queue operations with the closure, and run them in one pass.
+///
+/// Operations run in the order they were queued, and the batch stops at the
+/// first failing operation. Nothing runs if a window has no handle. If the connection to explorer.exe is lost, the
+/// whole batch is run again, so the operations should be safe to repeat.
+pub fn batch<F>(f: F) -> Result<()>
+where
+    F: FnOnce(&mut BatchOps),
+{
+    let mut ops = BatchOps::default();
+    f(&mut ops);
+    if let Some(err) = ops.error {
+        return Err(err);
+    }
+    if ops.ops.is_empty() {
+        return Ok(());
+    }
+    let renames = ops.ops.iter().any(|op| matches!(op, BatchOp::SetName(..)));
+    let result = with_backend(move |b| b.run_batch(&ops));
+    if renames {
+        crate::desktop::invalidate_cache();
+    }
+    result
+}
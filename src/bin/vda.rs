@@ -0,0 +1,263 @@
+//! Command line tool for the virtual desktops
+//!
+//! Desktops are given by index, GUID or name, windows by handle (decimal or
+//! `0x` hex) or by their exact title:
+//!
+//! ```text
+//! vda list                          All desktops, the current one marked with *
+//! vda current                       Current desktop
+//! vda switch <desktop>
+//! vda create [--name <name>]        Prints the new desktop
+//! vda remove <desktop>              Windows move to the desktop on the left
+//! vda rename <desktop> <name>
+//! vda move-window <window> <desktop>
+//! vda pin <window>                  Shows the window on all desktops
+//! vda wallpaper <desktop> <path>
+//! ```
+//!
+//! `vda doctor` checks each capability and prints what works on this Windows
+//! build, include the output when reporting an issue.
+//!
+//! `vda self-test` also creates, renames, switches to and removes a scratch
+//! desktop, and moves and pins the foreground window, see `winvd::self_test`.
+//!
+//! `vda listen [--json]` prints the desktop events until stopped, one per line.
+//! With `--json` each line is an object with the `event` type and the
+//! `guid`, `index` and `name` of the desktop and the `hwnd` of the window,
+//! `null` where the event has none, for reading the events from other
+//! languages. The first event is `InitialState` with the current desktop.
+//!
+//! `vda server [pipe]` serves the JSON-RPC protocol on the named pipe,
+//! `\\.\pipe\winvd` by default, until stopped, requires the `server` feature.
+//!
+//! `vda rules <file>` moves new windows by the rules of the TOML file until
+//! stopped, requires the `rules-config` feature.
+#[cfg(feature = "windows-057")]
+extern crate windows_057 as windows;
+
+#[cfg(feature = "listener")]
+use std::io::Write;
+use std::process::ExitCode;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::FindWindowW;
+use winvd::{Desktop, Error, FallbackPolicy};
+
+const USAGE: &str = "Usage: vda list | current | switch <desktop> | create [--name <name>]
+       | remove <desktop> | rename <desktop> <name> | move-window <window> <desktop>
+       | pin <window> | wallpaper <desktop> <path> | listen [--json] | doctor | self-test";
+
+/// Failure of a command
+enum CliError {
+    /// Missing or invalid arguments, the message is printed with the usage
+    Usage(String),
+    Vd(Error),
+}
+
+impl From<Error> for CliError {
+    fn from(err: Error) -> Self {
+        CliError::Vd(err)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(CliError::Usage(message)) => {
+            if !message.is_empty() {
+                eprintln!("{}", message);
+            }
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+        Err(CliError::Vd(err)) => {
+            eprintln!("Failed: {:?}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[&str]) -> Result<ExitCode, CliError> {
+    match args {
+        ["list"] => {
+            let current = winvd::get_current_desktop()?.get_id()?;
+            for desktop in winvd::get_desktops()? {
+                let marker = if desktop.get_id()? == current {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}", marker, desktop);
+            }
+        }
+        ["current"] => println!("{}", winvd::get_current_desktop()?),
+        ["switch", desktop] => winvd::switch_desktop(parse_desktop(desktop)?)?,
+        ["create"] => println!("{}", winvd::create_desktop()?),
+        ["create", "--name", name] => println!("{}", winvd::create_desktop_with_name(name)?),
+        ["remove", desktop] => {
+            winvd::remove_desktop_with_policy(parse_desktop(desktop)?, FallbackPolicy::Left)?
+        }
+        ["rename", desktop, name] => parse_desktop(desktop)?.set_name(name)?,
+        ["move-window", window, desktop] => {
+            winvd::move_window_to_desktop(parse_desktop(desktop)?, parse_window(window)?)?
+        }
+        ["pin", window] => winvd::pin_window(parse_window(window)?)?,
+        ["wallpaper", desktop, path] => parse_desktop(desktop)?.set_wallpaper(path)?,
+        #[cfg(feature = "listener")]
+        ["listen"] => listen(false)?,
+        #[cfg(feature = "listener")]
+        ["listen", "--json"] => listen(true)?,
+        ["doctor"] => return Ok(print_report(winvd::run_compatibility_selftest())),
+        ["self-test"] => return Ok(print_report(winvd::self_test())),
+        #[cfg(feature = "server")]
+        ["server"] => serve(winvd::DEFAULT_PIPE_NAME)?,
+        #[cfg(feature = "server")]
+        ["server", pipe] => serve(pipe)?,
+        #[cfg(feature = "rules-config")]
+        ["rules", path] => {
+            let _rules = winvd::WindowRules::from_config(path)?;
+            loop {
+                std::thread::park();
+            }
+        }
+        [] => return Err(CliError::Usage(String::new())),
+        [command, ..] => {
+            return Err(CliError::Usage(format!(
+                "Unknown command or arguments for `{}`",
+                command
+            )))
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(feature = "server")]
+fn serve(pipe: &str) -> Result<(), CliError> {
+    let _server = winvd::serve_pipe(pipe)?;
+    eprintln!("Serving on {}", pipe);
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Desktop by index, GUID or name
+fn parse_desktop(text: &str) -> Result<Desktop, CliError> {
+    Ok(text.parse::<Desktop>()?)
+}
+
+/// Window by handle, or by its exact title
+fn parse_window(text: &str) -> Result<HWND, CliError> {
+    let handle = match text.strip_prefix("0x") {
+        Some(hex) => isize::from_str_radix(hex, 16).ok(),
+        None => text.parse::<isize>().ok(),
+    };
+    if let Some(handle) = handle {
+        return Ok(HWND(handle));
+    }
+    let hwnd = unsafe { FindWindowW(PCWSTR::null(), &HSTRING::from(text)) };
+    if hwnd == HWND::default() {
+        return Err(CliError::Vd(Error::WindowNotFound));
+    }
+    Ok(hwnd)
+}
+
+/// Prints the desktop events until stdout is closed
+#[cfg(feature = "listener")]
+fn listen(json: bool) -> Result<(), CliError> {
+    let (tx, rx) = std::sync::mpsc::channel::<winvd::DesktopEvent>();
+    let options = winvd::ListenerOptions {
+        initial_state: true,
+        ..Default::default()
+    };
+    let _thread = winvd::listen_desktop_events_with_options(tx, options)?;
+    let mut stdout = std::io::stdout().lock();
+    for event in rx {
+        let line = if json {
+            event_json(&event)
+        } else {
+            format!("{:?}", event)
+        };
+        // Reader of the pipe went away
+        if writeln!(stdout, "{}", line).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Event as a single line JSON object
+#[cfg(feature = "listener")]
+fn event_json(event: &winvd::DesktopEvent) -> String {
+    use winvd::DesktopEvent::*;
+
+    let (kind, desktop, hwnd) = match event {
+        DesktopCreated(desktop) => ("DesktopCreated", Some(*desktop), None),
+        DesktopDestroyed { destroyed, .. } => ("DesktopDestroyed", Some(*destroyed), None),
+        DesktopChanged { new, .. } => ("DesktopChanged", Some(*new), None),
+        DesktopNameChanged(desktop, _) => ("DesktopNameChanged", Some(*desktop), None),
+        DesktopWallpaperChanged(desktop, _) => ("DesktopWallpaperChanged", Some(*desktop), None),
+        DesktopMoved { desktop, .. } => ("DesktopMoved", Some(*desktop), None),
+        WindowChanged(hwnd) => (
+            "WindowChanged",
+            winvd::get_desktop_by_window(*hwnd).ok(),
+            Some(*hwnd),
+        ),
+        MonitorsChanged(_) => ("MonitorsChanged", None, None),
+        InitialState { current, .. } => ("InitialState", Some(*current), None),
+        ConnectionReset { .. } => ("ConnectionReset", None, None),
+        EventsDropped { .. } => ("EventsDropped", None, None),
+    };
+
+    // Destroyed desktops have no index or name anymore, and the new name is
+    // in the event before the desktop can be queried
+    let guid = desktop.and_then(|d| d.get_id().ok());
+    let index = desktop.and_then(|d| d.get_index().ok());
+    let name = match event {
+        DesktopNameChanged(_, name) => Some(name.clone()),
+        _ => desktop.and_then(|d| d.get_name().ok()),
+    };
+
+    format!(
+        "{{\"event\":\"{}\",\"guid\":{},\"index\":{},\"name\":{},\"hwnd\":{}}}",
+        kind,
+        json_or_null(guid.map(|guid| json_string(&format!("{{{:?}}}", guid)))),
+        json_or_null(index.map(|index| index.to_string())),
+        json_or_null(name.map(|name| json_string(&name))),
+        json_or_null(hwnd.map(|hwnd| hwnd.0.to_string())),
+    )
+}
+
+#[cfg(feature = "listener")]
+fn json_or_null(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
+
+#[cfg(feature = "listener")]
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn print_report(report: winvd::SelfTestReport) -> ExitCode {
+    print!("{}", report);
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
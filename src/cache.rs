@@ -0,0 +1,180 @@
+//! Opt-in cache of the desktop list, for lookups without COM calls.
+//!
+//! When enabled, `Desktop::get_index`, `Desktop::get_id` and
+//! `Desktop::get_name` are answered from a snapshot of the desktops. A
+//! listener owned by the cache drops the snapshot when desktops are created,
+//! destroyed, moved or renamed, and the next lookup reads a fresh one.
+//!
+//! ```rust,no_run
+//! winvd::Cache::enable().unwrap();
+//! let name = winvd::get_desktop(1).get_name().unwrap();
+//! ```
+//!
+//! While the listener is not registered, e.g. explorer.exe is restarting, the
+//! cache is bypassed.
+use std::sync::{Mutex, MutexGuard};
+
+use crate::backend::with_backend;
+use crate::comobjects::DesktopInternal;
+use crate::listener::DesktopEventHandler;
+use crate::{DesktopEvent, DesktopInfo, Result};
+
+struct CacheState {
+    listener: Option<DesktopEventHandler>,
+    desktops: Option<Vec<DesktopInfo>>,
+
+    /// Increased when the snapshot is dropped, a snapshot read meanwhile is
+    /// stale and not stored
+    generation: u64,
+}
+
+impl CacheState {
+    fn clear(&mut self) {
+        self.desktops = None;
+        self.generation += 1;
+    }
+}
+
+static CACHE: Mutex<CacheState> = Mutex::new(CacheState {
+    listener: None,
+    desktops: None,
+    generation: 0,
+});
+
+/// Controls the desktop cache, see the module docs
+pub struct Cache;
+
+impl Cache {
+    /// Starts the listener and enables the cache, disabled by default
+    pub fn enable() -> Result<()> {
+        let mut cache = lock();
+        if cache.listener.is_some() {
+            return Ok(());
+        }
+
+        cache.listener = Some(DesktopEventHandler::new(|event| {
+            if invalidates(&event) {
+                invalidate();
+            }
+        })?);
+        cache.clear();
+        Ok(())
+    }
+
+    /// Stops the listener and disables the cache, lookups go to COM again
+    pub fn disable() {
+        let listener = {
+            let mut cache = lock();
+            cache.clear();
+            cache.listener.take()
+        };
+        // Joining the listener thread takes a while, lookups are not blocked
+        drop(listener);
+    }
+
+    pub fn is_enabled() -> bool {
+        lock().listener.is_some()
+    }
+
+    /// Reads a fresh snapshot of the desktops right away
+    pub fn refresh() -> Result<()> {
+        let generation = {
+            let cache = lock();
+            if cache.listener.is_none() {
+                return Ok(());
+            }
+            cache.generation
+        };
+        store(generation, with_backend(|b| b.get_desktop_infos())?);
+        Ok(())
+    }
+}
+
+/// Cached info of the desktop, `None` if the cache is disabled or bypassed
+pub(crate) fn cached_info(desktop: DesktopInternal) -> Option<DesktopInfo> {
+    let generation = {
+        let cache = lock();
+        if !cache.listener.as_ref()?.is_registered() {
+            return None;
+        }
+        if let Some(desktops) = cache.desktops.as_ref() {
+            return find(desktops, desktop).cloned();
+        }
+        cache.generation
+    };
+
+    // Read without the lock, lookups of other threads are not blocked by COM
+    let desktops = with_backend(|b| b.get_desktop_infos()).ok()?;
+    let info = find(&desktops, desktop).cloned();
+    store(generation, desktops);
+    info
+}
+
+/// Drops the snapshot after a change made through the crate, so it is not
+/// read before the event arrives
+pub(crate) fn invalidate() {
+    lock().clear();
+}
+
+/// Stores the snapshot unless it was dropped while the snapshot was read
+fn store(generation: u64, desktops: Vec<DesktopInfo>) {
+    let mut cache = lock();
+    if cache.generation == generation && cache.listener.is_some() {
+        cache.desktops = Some(desktops);
+    }
+}
+
+fn find(desktops: &[DesktopInfo], desktop: DesktopInternal) -> Option<&DesktopInfo> {
+    match desktop {
+        DesktopInternal::Index(index) => desktops.get(index as usize),
+        DesktopInternal::Guid(guid) | DesktopInternal::IndexGuid(_, guid) => {
+            desktops.iter().find(|info| info.guid == guid)
+        }
+    }
+}
+
+fn invalidates(event: &DesktopEvent) -> bool {
+    matches!(
+        event,
+        DesktopEvent::DesktopCreated(_)
+            | DesktopEvent::DesktopDestroyed { .. }
+            | DesktopEvent::DesktopMoved { .. }
+            | DesktopEvent::DesktopNameChanged(..)
+            | DesktopEvent::ConnectionReset { .. }
+            | DesktopEvent::EventsDropped { .. }
+    )
+}
+
+fn lock() -> MutexGuard<'static, CacheState> {
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::GUID;
+
+    #[test]
+    fn test_find() {
+        let info = |index, guid| DesktopInfo {
+            guid: GUID::from_u128(guid),
+            index,
+            name: String::new(),
+            wallpaper: String::new(),
+            is_remote: false,
+        };
+        let desktops = vec![info(0, 10), info(1, 20)];
+
+        assert_eq!(
+            find(&desktops, DesktopInternal::Index(1)),
+            Some(&desktops[1])
+        );
+        assert_eq!(
+            find(&desktops, DesktopInternal::Guid(GUID::from_u128(10))),
+            Some(&desktops[0])
+        );
+        assert_eq!(find(&desktops, DesktopInternal::Index(2)), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Borrowed COM object parameters, shared by [`crate::interfaces`] and
+//! [`crate::interfaces_multi`].
+//!
+//! windows-rs 0.56 has no borrowed parameter type that works with
+//! `windows_interface::interface` traits, its `Param` only converts arguments
+//! of the generated Windows API wrappers. `ComIn` fills that gap.
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use windows::core::Interface;
+
+/// Type that can be cast into [`ComIn`]
+///
+/// # Safety
+///
+/// - Can cast from `*mut c_void` to `Self`. (`Self` is a transparent type over
+///   a raw pointer.)
+/// - The returned pointer is valid while the reference it was created from is
+///   valid.
+pub unsafe trait PointerRepr {
+    fn as_pointer_repr(&self) -> *mut c_void;
+}
+unsafe impl<T: Interface> PointerRepr for T {
+    fn as_pointer_repr(&self) -> *mut c_void {
+        Interface::as_raw(self)
+    }
+}
+
+/// ComIn is a wrapper for COM objects that are passed as input parameters. It
+/// allows to keep the life of the COM object for the duration of the function
+/// call.
+///
+/// Imagine following situation:
+///
+/// First you call an API function that gives COM object as out parameter. And
+/// you want to pass it to another function that takes the COM object as an
+/// input parameter. If you were to use ManuallyDrop then you'd have to call the
+/// drop manually after the second function call.
+///
+/// E.g.
+///
+/// ```rust,ignore
+/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
+/// fn switch_desktop(&self, desktop: ManuallyDrop<IVirtualDesktop>) -> HRESULT;
+///
+/// let mut desktop: Option<IVirtualDesktop> = None;
+/// get_current_desktop(&mut desktop);
+/// if let Some(desktop) = desktop {
+///     let input = ManuallyDrop::new(desktop);
+///     switch_desktop(input);
+///     ManuallyDrop::drop(input);
+/// }
+/// ```
+///
+/// To make things safer and easier to use, ComIn is used instead.
+///
+/// ```rust,ignore
+/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
+/// fn switch_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+///
+/// let mut desktop: Option<IVirtualDesktop> = None;
+/// if let Some(desktop) = desktop {
+///     get_current_desktop(&mut desktop);
+///     switch_desktop(ComIn::new(&input));
+/// }
+/// ```
+///
+/// A `ComIn` received from the shell, e.g. in a notification callback, may be
+/// null. Use `ComIn::get` there, dereferencing a null `ComIn` panics.
+#[repr(transparent)]
+pub struct ComIn<'a, T> {
+    data: *mut c_void,
+    _phantom: PhantomData<&'a T>,
+}
+impl<'a, T: PointerRepr> ComIn<'a, T> {
+    pub fn new(t: &'a T) -> Self {
+        Self {
+            // Copies the raw Inteface pointer
+            data: t.as_pointer_repr(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The object, or `None` if the caller passed null
+    pub fn get(this: &Self) -> Option<&'a T> {
+        if this.data.is_null() {
+            return None;
+        }
+        // Safety: `T` is a transparent type over a non-null raw pointer
+        Some(unsafe { &*(&this.data as *const *mut c_void as *const T) })
+    }
+
+    pub fn into_ref(this: &Self) -> &'a T {
+        Self::get(this).expect("ComIn is null")
+    }
+
+    /// Reinterprets the pointer as another interface type, null stays null
+    ///
+    /// # Safety
+    ///
+    /// The object must implement `U`.
+    pub unsafe fn cast_unchecked<U: PointerRepr>(this: Self) -> ComIn<'a, U> {
+        ComIn {
+            data: this.data,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<'a, T: PointerRepr> Deref for ComIn<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        Self::into_ref(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::IUnknown;
+
+    #[test]
+    fn test_null_com_in() {
+        let null = ComIn::<IUnknown> {
+            data: std::ptr::null_mut(),
+            _phantom: PhantomData,
+        };
+        assert!(ComIn::get(&null).is_none());
+        let null: ComIn<IUnknown> = unsafe { ComIn::cast_unchecked(null) };
+        assert!(ComIn::get(&null).is_none());
+    }
+}
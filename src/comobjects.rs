@@ -2,22 +2,25 @@
 #![allow(clippy::upper_case_acronyms)]
 
 use super::interfaces_multi::*;
-use super::Result;
+use super::{DesktopInfo, Direction, Result, ViewInfo};
+use crate::batch::BatchOp;
+use crate::journal;
+use crate::metrics;
+use crate::object_array::ObjectArrayExt;
 use std::convert::TryFrom;
 use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{cell::RefCell, ffi::c_void};
 use windows::core::HRESULT;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::System::Com::CoIncrementMTAUsage;
 use windows::Win32::System::Com::CLSCTX_LOCAL_SERVER;
 use windows::{
-    core::{Interface, GUID, HSTRING},
+    core::{IUnknown, Interface, GUID, HSTRING},
     Win32::{System::Com::CoCreateInstance, UI::Shell::Common::IObjectArray},
 };
 
-#[cfg(debug_assertions)]
-use crate::log::log_output;
-
 type WCHAR = u16;
 type APPIDPWSTR = *const WCHAR;
 
@@ -69,11 +72,89 @@ pub enum Error {
 
     /// Borrow error
     InternalBorrowError,
+
+    /// Listener thread exited before its message loop was started
+    ListenerThreadFailed,
+
+    /// Reading the virtual desktop state from the registry failed
+    RegistryError(HRESULT),
+
+    /// Alias is not defined, or the desktop it pointed to is gone
+    AliasNotFound,
+
+    /// Reading or writing a file failed, e.g. the aliases or IID overrides, or
+    /// a socket of `serve_ws`
+    FileError(std::io::ErrorKind),
+
+    /// COM method failed with an unhandled error
+    ComCallFailed {
+        interface: &'static str,
+        method: &'static str,
+        hresult: HRESULT,
+    },
+
+    /// Virtual desktop services of this Windows build are not supported, see
+    /// `is_current_windows_supported`
+    UnsupportedWindowsBuild,
+
+    /// COM object was called from a thread it doesn't belong to
+    NotOnCorrectThread,
+
+    /// Connection to explorer.exe was lost and could not be restored, e.g.
+    /// explorer.exe is restarting
+    Disconnected,
+
+    /// Worker thread of `VirtualDesktopService` has stopped
+    ServiceStopped,
+
+    /// The only desktop can't be removed
+    LastDesktop,
+
+    /// Window can't be moved between desktops, e.g. a pinned or a shell
+    /// window, see `can_move_window_between_desktops`
+    WindowNotMovable,
+
+    /// Rule or rules config file is not valid, see `WindowRule::new` and
+    /// `parse_rules`
+    RulesConfigError(String),
+
+    /// Listener events were dropped because the receiver fell too far behind,
+    /// `DesktopEvent::EventsDropped` follows
+    EventsDropped,
+}
+
+impl Error {
+    /// HRESULT of the failed COM call, if the error came from one
+    pub fn hresult(&self) -> Option<HRESULT> {
+        let code = match self {
+            Error::ComError(hresult)
+            | Error::RegistryError(hresult)
+            | Error::ComCallFailed { hresult, .. } => return Some(*hresult),
+            Error::ClassNotRegistered => 0x80040154_u32,
+            Error::RpcServerNotAvailable => 0x800706BA,
+            Error::ComObjectNotConnected => 0x800401FD,
+            Error::ComElementNotFound => 0x8002802B,
+            Error::ComNotInitialized => 0x800401F0,
+            Error::ComNoInterface | Error::UnsupportedWindowsBuild => 0x80004002,
+            Error::ComNotImplemented => 0x80004001,
+            Error::NotOnCorrectThread => 0x8001010E,
+            _ => return None,
+        };
+        Some(HRESULT(code as i32))
+    }
 }
 
 pub(crate) trait HRESULTHelpers {
     fn as_error(&self) -> Error;
     fn as_result(&self) -> Result<()>;
+
+    /// Like `as_result`, but unhandled errors are returned as
+    /// `Error::ComCallFailed` with the interface and method
+    fn as_call_result(&self, interface: &'static str, method: &'static str) -> Result<()>;
+
+    /// Like `as_result`, but for querying a service. Unknown interface means
+    /// the service has a different IID on this Windows build.
+    fn as_service_result(&self) -> Result<()>;
 }
 
 impl HRESULTHelpers for ::windows::core::HRESULT {
@@ -91,6 +172,10 @@ impl HRESULTHelpers for ::windows::core::HRESULT {
                 // 0x800401FD
                 Error::ComObjectNotConnected
             }
+            -2147417848 => {
+                // 0x80010108 RPC_E_DISCONNECTED, explorer.exe restarted
+                Error::ComObjectNotConnected
+            }
             -2147319765 => {
                 // 0x8002802B
                 Error::ComElementNotFound
@@ -107,6 +192,10 @@ impl HRESULTHelpers for ::windows::core::HRESULT {
                 // 0x80004001
                 Error::ComNotImplemented
             }
+            -2147417842 => {
+                // 0x8001010E
+                Error::NotOnCorrectThread
+            }
             _ => Error::ComError(*self),
         }
     }
@@ -117,6 +206,26 @@ impl HRESULTHelpers for ::windows::core::HRESULT {
         }
         Err(self.as_error())
     }
+
+    fn as_call_result(&self, interface: &'static str, method: &'static str) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(interface, method, hresult = ?self, "COM call");
+        self.as_result().map_err(|er| match er {
+            Error::ComError(hresult) => Error::ComCallFailed {
+                interface,
+                method,
+                hresult,
+            },
+            _ => er,
+        })
+    }
+
+    fn as_service_result(&self) -> Result<()> {
+        self.as_result().map_err(|er| match er {
+            Error::ComNoInterface => Error::UnsupportedWindowsBuild,
+            _ => er,
+        })
+    }
 }
 
 impl From<::windows::core::Error> for Error {
@@ -165,7 +274,11 @@ impl<'a> TryFrom<&'a IVirtualDesktop> for DesktopInternal {
 
     fn try_from(desktop: &'a IVirtualDesktop) -> Result<Self> {
         let mut guid = GUID::default();
-        unsafe { desktop.get_id(&mut guid).as_result()? }
+        unsafe {
+            desktop
+                .get_id(&mut guid)
+                .as_call_result("IVirtualDesktop", "get_id")?
+        }
         Ok(DesktopInternal::Guid(guid))
     }
 }
@@ -173,8 +286,13 @@ impl<'a> TryFrom<&'a ComIn<'a, IVirtualDesktop>> for DesktopInternal {
     type Error = Error;
 
     fn try_from(desktop: &'a ComIn<'a, IVirtualDesktop>) -> Result<Self> {
+        let desktop = ComIn::get(desktop).ok_or(Error::ComAllocatedNullPtr)?;
         let mut guid = GUID::default();
-        unsafe { desktop.get_id(&mut guid).as_result()? }
+        unsafe {
+            desktop
+                .get_id(&mut guid)
+                .as_call_result("IVirtualDesktop", "get_id")?
+        }
         Ok(DesktopInternal::Guid(guid))
     }
 }
@@ -186,26 +304,118 @@ pub struct ComObjects {
     notification_service: RefCell<Option<Rc<IVirtualDesktopNotificationService>>>,
     pinned_apps: RefCell<Option<Rc<IVirtualDesktopPinnedApps>>>,
     view_collection: RefCell<Option<Rc<IApplicationViewCollection>>>,
+
+    /// Last `SHELL_GENERATION` seen by the calls and by `is_connected`, each
+    /// of them notices a simulated restart on its own
+    #[cfg(feature = "test-hooks")]
+    calls_generation: std::cell::Cell<u64>,
+    #[cfg(all(feature = "test-hooks", feature = "listener"))]
+    connection_generation: std::cell::Cell<u64>,
+}
+
+/// Bumped by `simulate_shell_restart`, the services of `ComObjects` created
+/// before are treated as disconnected
+#[cfg(feature = "test-hooks")]
+static SHELL_GENERATION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Makes the COM connections behave as if explorer.exe was restarted.
+///
+/// The next call on each thread fails once as disconnected and is retried
+/// with new services, and listeners register again and send
+/// `DesktopEvent::ConnectionReset`.
+#[cfg(feature = "test-hooks")]
+pub fn simulate_shell_restart() {
+    SHELL_GENERATION.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Returns true if `simulate_shell_restart` was called after the observer
+/// last saw the generation, and updates it
+#[cfg(feature = "test-hooks")]
+fn simulated_restart_since(seen: &std::cell::Cell<u64>) -> bool {
+    let generation = SHELL_GENERATION.load(std::sync::atomic::Ordering::SeqCst);
+    seen.replace(generation) < generation
+}
+
+/// How calls are retried after the connection to explorer.exe is lost, see
+/// `set_reconnect_policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Retries after the first failed call, 0 disables retrying
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for each following retry
+    pub backoff: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Delay before the retry, starting from 0
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1 << retry.min(16))
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        DEFAULT_RECONNECT_POLICY
+    }
+}
+
+const DEFAULT_RECONNECT_POLICY: ReconnectPolicy = ReconnectPolicy {
+    retries: 3,
+    backoff: Duration::from_millis(50),
+};
+
+static RECONNECT_POLICY: Mutex<ReconnectPolicy> = Mutex::new(DEFAULT_RECONNECT_POLICY);
+
+/// Set how calls are retried when explorer.exe restarts.
+///
+/// Failed calls drop the cached COM objects, reconnect and retry, waiting
+/// between the retries so explorer.exe has time to start. If the connection
+/// is not restored, `Error::Disconnected` is returned.
+pub fn set_reconnect_policy(policy: ReconnectPolicy) {
+    *RECONNECT_POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
 }
 
-fn retry_function<F, R>(com_objects: &ComObjects, f: F, _fn_name: &str) -> Result<R>
+fn retry_function<F, R>(
+    com_objects: &ComObjects,
+    f: F,
+    fn_name: &'static str,
+    args: &[&dyn std::fmt::Debug],
+) -> Result<R>
 where
     F: Fn() -> Result<R>,
 {
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "com_call",
+        method = fn_name,
+        hresult = tracing::field::Empty
+    )
+    .entered();
+    let started = (journal::is_enabled() || metrics::is_enabled()).then(Instant::now);
+    let mut retries = 0;
+    let policy = *RECONNECT_POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    #[cfg(feature = "test-hooks")]
+    let mut value = if simulated_restart_since(&com_objects.calls_generation) {
+        Err(Error::ComObjectNotConnected)
+    } else {
+        f()
+    };
+    #[cfg(not(feature = "test-hooks"))]
     let mut value = f();
-    for _ in 0..3 {
+    for retry in 0..policy.retries {
         match &value {
-            Err(er)
-                if er == &Error::ClassNotRegistered
-                    || er == &Error::RpcServerNotAvailable
-                    || er == &Error::ComObjectNotConnected
-                    || er == &Error::ComAllocatedNullPtr
-                    || er == &Error::ComNotInitialized =>
-            {
-                log_format!("Retry the function \"{_fn_name}\" after {:?}", er);
+            Err(er) if is_retried(er) => {
+                log_format!("Retry the function \"{fn_name}\" after {:?}", er);
 
                 if er == &Error::ComNotInitialized {
                     let _ = unsafe { CoIncrementMTAUsage() };
+                } else {
+                    std::thread::sleep(policy.delay(retry));
                 }
 
                 drop(value);
@@ -215,6 +425,7 @@ where
                 com_objects.drop_services();
 
                 value = f();
+                retries += 1;
             }
             _ => {
                 break;
@@ -222,17 +433,47 @@ where
         }
     }
 
-    #[cfg(debug_assertions)]
+    // Still not connected after retrying
+    if let Err(Error::RpcServerNotAvailable | Error::ComObjectNotConnected) = &value {
+        value = Err(Error::Disconnected);
+    }
+
     if let Err(er) = &value {
         log_format!(
-            "Com_objects function \"{_fn_name}\" failed with {:?}",
+            Warn: "Com_objects function \"{fn_name}\" failed with {:?}",
             er
         );
     }
 
+    if let Some(started) = started {
+        if journal::is_enabled() {
+            journal::record(fn_name, args, &value, retries, started);
+        }
+        if metrics::is_enabled() {
+            metrics::record(fn_name, &value, retries, started.elapsed());
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    if let Some(hresult) = value.as_ref().err().and_then(Error::hresult) {
+        span.record("hresult", tracing::field::debug(hresult));
+    }
+
     value
 }
 
+/// Errors after which the connection is dropped and the call retried
+fn is_retried(er: &Error) -> bool {
+    matches!(
+        er,
+        Error::ClassNotRegistered
+            | Error::RpcServerNotAvailable
+            | Error::ComObjectNotConnected
+            | Error::ComAllocatedNullPtr
+            | Error::ComNotInitialized
+    )
+}
+
 /// Safely reruns the function if it returns one of the recoverable errors
 ///
 /// This should be applied to only public functions in ComObjects struct, having
@@ -255,7 +496,7 @@ macro_rules! retry_function {(
     {
         retry_function(&$self_, || -> $RetTy {
             $body
-        }, stringify!($fname))
+        }, stringify!($fname), &[$( &$arg_name ),*])
     }
 )}
 
@@ -268,6 +509,14 @@ impl ComObjects {
             notification_service: RefCell::new(None),
             pinned_apps: RefCell::new(None),
             view_collection: RefCell::new(None),
+            #[cfg(feature = "test-hooks")]
+            calls_generation: std::cell::Cell::new(
+                SHELL_GENERATION.load(std::sync::atomic::Ordering::SeqCst),
+            ),
+            #[cfg(all(feature = "test-hooks", feature = "listener"))]
+            connection_generation: std::cell::Cell::new(
+                SHELL_GENERATION.load(std::sync::atomic::Ordering::SeqCst),
+            ),
         }
     }
 
@@ -304,7 +553,7 @@ impl ComObjects {
                         &IVirtualDesktopManager::IID,
                         &mut obj,
                     )
-                    .as_result()?;
+                    .as_call_result("IServiceProvider", "query_service")?;
             }
             assert_eq!(obj.is_null(), false);
             *manager = Some(Rc::new(unsafe { IVirtualDesktopManager::from_raw(obj) }));
@@ -332,6 +581,28 @@ impl ComObjects {
             .ok_or(Error::ComAllocatedNullPtr)
     }
 
+    /// Service provider of the immersive shell, see `raw_service_provider`
+    #[apply(retry_function)]
+    pub fn raw_service_provider(&self) -> Result<windows::Win32::System::Com::IServiceProvider> {
+        Ok(self.get_provider()?.cast()?)
+    }
+
+    /// Build specific `IVirtualDesktopManagerInternal`, see
+    /// `raw_manager_internal`
+    #[apply(retry_function)]
+    pub fn raw_manager_internal(&self) -> Result<IUnknown> {
+        let manager_internal = self.get_manager_internal()?;
+        raw_unknown(manager_internal.as_raw())
+    }
+
+    /// Build specific `IVirtualDesktop`, see `Desktop::as_ivirtualdesktop`
+    #[apply(retry_function)]
+    pub fn raw_idesktop(&self, desktop: &DesktopInternal) -> Result<IUnknown> {
+        let desktop = self.get_idesktop(desktop)?;
+        raw_unknown(desktop.as_raw())
+    }
+
+    #[cfg(feature = "listener")]
     fn get_notification_service(&self) -> Result<Rc<IVirtualDesktopNotificationService>> {
         let mut notification_service = self
             .notification_service
@@ -381,7 +652,7 @@ impl ComObjects {
             .ok_or(Error::ComAllocatedNullPtr)
     }
 
-    fn drop_services(&self) {
+    pub(crate) fn drop_services(&self) {
         // Current implementation would be safe drop like this, but in case I
         // ever refactor I don't use this:
 
@@ -401,6 +672,7 @@ impl ComObjects {
         let _ = self.view_collection.try_borrow_mut().map(|mut v| v.take());
     }
 
+    #[cfg(feature = "listener")]
     pub(crate) fn is_connected(&self) -> bool {
         // TODO: What is a best way to check if service is connected?
 
@@ -408,18 +680,22 @@ impl ComObjects {
         //
         // I call get_count method, if it's well implemented it should be just
         // like returning a value, not allocating anything.
+        #[cfg(feature = "test-hooks")]
+        if simulated_restart_since(&self.connection_generation) {
+            self.drop_services();
+            return false;
+        }
         match self.get_manager_internal() {
             Ok(manager_internal) => {
                 let mut out_count = 0;
                 let res = unsafe {
                     manager_internal
                         .get_desktop_count(&mut out_count)
-                        .as_result()
+                        .as_call_result("IVirtualDesktopManagerInternal", "get_desktop_count")
                 };
 
-                #[cfg(debug_assertions)]
                 if let Err(er) = &res {
-                    log_output(&format!("is connected error: {:?} {}", er, out_count));
+                    log_format!("is connected error: {:?} {}", er, out_count);
                 }
 
                 if out_count == 0 || res.is_err() {
@@ -436,17 +712,15 @@ impl ComObjects {
         unsafe {
             self.get_manager_internal()?
                 .get_desktops(&mut desktops)
-                .as_result()?
+                .as_call_result("IVirtualDesktopManagerInternal", "get_desktops")?
         }
         desktops.ok_or(Error::ComAllocatedNullPtr)
     }
 
     fn get_desktop_index_by_guid(&self, id: &GUID) -> Result<u32> {
         let desktops = self.get_idesktops_array()?;
-        let count = unsafe { desktops.GetCount()? };
-        for i in 0..count {
-            let desktop_id: GUID = get_idesktop_guid(&unsafe { IObjectArrayGetAt(&desktops, i)? })?;
-            if desktop_id == *id {
+        for (i, desktop) in (0..).zip(desktops.iter::<IVirtualDesktop>()) {
+            if get_idesktop_guid(&desktop?)? == *id {
                 return Ok(i);
             }
         }
@@ -476,7 +750,9 @@ impl ComObjects {
                 let manager = self.get_manager_internal()?;
                 let mut desktop = None;
                 unsafe {
-                    manager.find_desktop(id, &mut desktop).as_result()?;
+                    manager
+                        .find_desktop(id, &mut desktop)
+                        .as_call_result("IVirtualDesktopManagerInternal", "find_desktop")?;
                 }
                 desktop.ok_or(Error::DesktopNotFound)
             }
@@ -484,23 +760,49 @@ impl ComObjects {
                 let manager = self.get_manager_internal()?;
                 let mut desktop = None;
                 unsafe {
-                    manager.find_desktop(id, &mut desktop).as_result()?;
+                    manager
+                        .find_desktop(id, &mut desktop)
+                        .as_call_result("IVirtualDesktopManagerInternal", "find_desktop")?;
                 }
                 desktop.ok_or(Error::DesktopNotFound)
             }
         }
     }
 
+    fn can_move_view(&self, view: &IApplicationView) -> Result<bool> {
+        let mut can_move = 0;
+        unsafe {
+            self.get_manager_internal()?
+                .can_move_view_between_desktops(ComIn::new(view), &mut can_move)
+                .as_call_result(
+                    "IVirtualDesktopManagerInternal",
+                    "can_move_view_between_desktops",
+                )?
+        }
+        Ok(can_move != 0)
+    }
+
     fn move_view_to_desktop(
         &self,
-        view: ComIn<IApplicationView>,
+        view: &IApplicationView,
         desktop: &DesktopInternal,
     ) -> Result<()> {
         let desktop = self.get_idesktop(desktop)?;
+        self.move_view_to_idesktop(view, &desktop)
+    }
+
+    fn move_view_to_idesktop(
+        &self,
+        view: &IApplicationView,
+        desktop: &IVirtualDesktop,
+    ) -> Result<()> {
+        if !self.can_move_view(view)? {
+            return Err(Error::WindowNotMovable);
+        }
         unsafe {
             self.get_manager_internal()?
-                .move_view_to_desktop(view, ComIn::new(&desktop))
-                .as_result()
+                .move_view_to_desktop(ComIn::new(view), ComIn::new(desktop))
+                .as_call_result("IVirtualDesktopManagerInternal", "move_view_to_desktop")
                 .map_err(|e| {
                     if e == Error::ComElementNotFound {
                         Error::DesktopNotFound
@@ -517,7 +819,7 @@ impl ComObjects {
         unsafe {
             self.get_view_collection()?
                 .get_view_for_hwnd(*hwnd, &mut view)
-                .as_result()
+                .as_call_result("IApplicationViewCollection", "get_view_for_hwnd")
                 .map_err(|er| {
                     if er == Error::ComElementNotFound {
                         Error::WindowNotFound
@@ -529,6 +831,49 @@ impl ComObjects {
         view.ok_or(Error::WindowNotFound)
     }
 
+    fn get_idesktop_info(&self, desktop: &IVirtualDesktop, index: u32) -> Result<DesktopInfo> {
+        let guid = get_idesktop_guid(desktop)?;
+        let mut name = HSTRING::default();
+        let mut wallpaper = HSTRING::default();
+        let mut is_remote = 0;
+        unsafe {
+            // Older Windows versions don't have names, wallpapers or remote desktops
+            ignore_not_implemented(
+                desktop
+                    .get_name(&mut name)
+                    .as_call_result("IVirtualDesktop", "get_name"),
+            )?;
+            ignore_not_implemented(
+                desktop
+                    .get_wallpaper(&mut wallpaper)
+                    .as_call_result("IVirtualDesktop", "get_wallpaper"),
+            )?;
+            ignore_not_implemented(
+                desktop
+                    .is_remote(&mut is_remote)
+                    .as_call_result("IVirtualDesktop", "is_remote"),
+            )?;
+        }
+        Ok(DesktopInfo {
+            guid,
+            index,
+            name: name.to_string(),
+            wallpaper: wallpaper.to_string(),
+            is_remote: is_remote != 0,
+        })
+    }
+
+    fn get_iapplication_views(&self) -> Result<Vec<IApplicationView>> {
+        let mut views = None;
+        unsafe {
+            self.get_view_collection()?
+                .get_views(&mut views)
+                .as_call_result("IApplicationViewCollection", "get_views")?
+        }
+        let views = views.ok_or(Error::ComAllocatedNullPtr)?;
+        Ok(views.iter().collect::<windows::core::Result<_>>()?)
+    }
+
     #[apply(retry_function)]
     pub fn get_desktop_index(&self, id: &DesktopInternal) -> Result<u32> {
         match id {
@@ -550,16 +895,25 @@ impl ComObjects {
     #[apply(retry_function)]
     pub fn get_desktops(&self) -> Result<Vec<DesktopInternal>> {
         let desktops = self.get_idesktops_array()?;
-        let count = unsafe { desktops.GetCount()? };
-        let mut result = Vec::with_capacity(count as usize);
-        for i in 0..count {
-            let desktop = unsafe { IObjectArrayGetAt(&desktops, i)? };
-            let id = get_idesktop_guid(&desktop)?;
+        let mut result = Vec::new();
+        for (i, desktop) in (0..).zip(desktops.iter::<IVirtualDesktop>()) {
+            let id = get_idesktop_guid(&desktop?)?;
             result.push(DesktopInternal::IndexGuid(i, id));
         }
         Ok(result)
     }
 
+    #[apply(retry_function)]
+    pub fn get_desktop_infos(&self) -> Result<Vec<DesktopInfo>> {
+        let desktops = self.get_idesktops_array()?;
+        let mut result = Vec::new();
+        for (i, desktop) in (0..).zip(desktops.iter()) {
+            result.push(self.get_idesktop_info(&desktop?, i)?);
+        }
+        Ok(result)
+    }
+
+    #[cfg(feature = "listener")]
     #[apply(retry_function)]
     pub fn register_for_notifications(
         &self,
@@ -572,15 +926,20 @@ impl ComObjects {
             let mut cookie = 0;
             notification_service
                 .register(notification, &mut cookie)
-                .as_result()
+                .as_call_result("IVirtualDesktopNotificationService", "register")
                 .map(|_| cookie)
         }
     }
 
+    #[cfg(feature = "listener")]
     #[apply(retry_function)]
     pub fn unregister_for_notifications(&self, cookie: u32) -> Result<()> {
         let notification_service = self.get_notification_service()?;
-        unsafe { notification_service.unregister(cookie).as_result() }
+        unsafe {
+            notification_service
+                .unregister(cookie)
+                .as_call_result("IVirtualDesktopNotificationService", "unregister")
+        }
     }
 
     #[apply(retry_function)]
@@ -589,18 +948,54 @@ impl ComObjects {
         unsafe {
             self.get_manager_internal()?
                 .switch_desktop(ComIn::new(&desktop))
-                .as_result()?
+                .as_call_result("IVirtualDesktopManagerInternal", "switch_desktop")?
         }
         Ok(())
     }
 
+    /// Desktop on the left or right of the desktop, `None` at the edge
+    #[apply(retry_function)]
+    pub fn get_adjacent_desktop(
+        &self,
+        desktop: &DesktopInternal,
+        direction: Direction,
+    ) -> Result<Option<DesktopInternal>> {
+        let desktop = self.get_idesktop(desktop)?;
+        let code = match direction {
+            Direction::Left => 3,
+            Direction::Right => 4,
+        };
+        let mut adjacent = None;
+        let res = unsafe {
+            self.get_manager_internal()?
+                .get_adjacent_desktop(ComIn::new(&desktop), code, &mut adjacent)
+                .as_call_result("IVirtualDesktopManagerInternal", "get_adjacent_desktop")
+        };
+        if let Err(er) = res {
+            // Fails at the edge, the error is not documented
+            let desktops = self.get_idesktops_array()?;
+            let count = unsafe { desktops.GetCount()? };
+            let edge = match direction {
+                Direction::Left => 0,
+                Direction::Right => count.saturating_sub(1),
+            };
+            let edge_desktop: IVirtualDesktop = unsafe { IObjectArrayGetAt(&desktops, edge)? };
+            if get_idesktop_guid(&edge_desktop)? == get_idesktop_guid(&desktop)? {
+                return Ok(None);
+            }
+            return Err(er);
+        }
+        let adjacent = adjacent.ok_or(Error::ComAllocatedNullPtr)?;
+        Ok(Some(DesktopInternal::Guid(get_idesktop_guid(&adjacent)?)))
+    }
+
     #[apply(retry_function)]
     pub fn create_desktop(&self) -> Result<DesktopInternal> {
         let mut desktop = None;
         unsafe {
             self.get_manager_internal()?
                 .create_desktop(&mut desktop)
-                .as_result()?
+                .as_call_result("IVirtualDesktopManagerInternal", "create_desktop")?
         }
         let desktop = desktop.ok_or(Error::ComAllocatedNullPtr)?;
         let id = get_idesktop_guid(&desktop)?;
@@ -619,7 +1014,112 @@ impl ComObjects {
         unsafe {
             self.get_manager_internal()?
                 .remove_desktop(ComIn::new(&desktop), ComIn::new(&fb_desktop))
-                .as_result()?
+                .as_call_result("IVirtualDesktopManagerInternal", "remove_desktop")?
+        }
+        Ok(())
+    }
+
+    #[apply(retry_function)]
+    pub fn move_desktop(&self, desktop: &DesktopInternal, index: u32) -> Result<()> {
+        let desktop = self.get_idesktop(desktop)?;
+        unsafe {
+            self.get_manager_internal()?
+                .move_desktop(ComIn::new(&desktop), index)
+                .as_call_result("IVirtualDesktopManagerInternal", "move_desktop")?
+        }
+        Ok(())
+    }
+
+    #[apply(retry_function)]
+    pub fn run_batch(&self, ops: &[BatchOp], animate: bool) -> Result<()> {
+        // Batches don't create or remove desktops, so the array stays valid
+        let desktops = self.get_idesktops_array()?;
+        let manager = self.get_manager_internal()?;
+        let resolve = |desktop: &DesktopInternal| match desktop {
+            DesktopInternal::Index(id) => {
+                let count = unsafe { desktops.GetCount()? };
+                if *id >= count {
+                    return Err(Error::DesktopNotFound);
+                }
+                Ok(unsafe { IObjectArrayGetAt(&desktops, *id)? })
+            }
+            _ => self.get_idesktop(desktop),
+        };
+
+        let last_switch = ops.iter().rposition(|op| matches!(op, BatchOp::Switch(_)));
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                BatchOp::Switch(desktop) => {
+                    let desktop = resolve(desktop)?;
+                    let mut res = Err(Error::ComNotImplemented);
+                    if animate && Some(i) == last_switch {
+                        res = unsafe {
+                            manager
+                                .switch_desktop_with_animation(ComIn::new(&desktop))
+                                .as_call_result(
+                                    "IVirtualDesktopManagerInternal",
+                                    "switch_desktop_with_animation",
+                                )
+                        };
+                    }
+
+                    // Older versions can't animate the switch
+                    if res == Err(Error::ComNotImplemented) {
+                        res = unsafe {
+                            manager
+                                .switch_desktop(ComIn::new(&desktop))
+                                .as_call_result("IVirtualDesktopManagerInternal", "switch_desktop")
+                        };
+                    }
+                    res?
+                }
+                BatchOp::MoveWindow(window, desktop) => {
+                    let view = self.get_iapplication_view_for_hwnd(window)?;
+                    if !self.can_move_view(&view)? {
+                        return Err(Error::WindowNotMovable);
+                    }
+                    let desktop = resolve(desktop)?;
+                    unsafe {
+                        manager
+                            .move_view_to_desktop(ComIn::new(&view), ComIn::new(&desktop))
+                            .as_call_result(
+                                "IVirtualDesktopManagerInternal",
+                                "move_view_to_desktop",
+                            )
+                            .map_err(|e| {
+                                if e == Error::ComElementNotFound {
+                                    Error::DesktopNotFound
+                                } else {
+                                    e
+                                }
+                            })?
+                    }
+                }
+                BatchOp::PinWindow(window) => {
+                    let view = self.get_iapplication_view_for_hwnd(window)?;
+                    unsafe {
+                        self.get_pinned_apps()?
+                            .pin_view(ComIn::new(&view))
+                            .as_call_result("IVirtualDesktopPinnedApps", "pin_view")?
+                    }
+                }
+                BatchOp::UnpinWindow(window) => {
+                    let view = self.get_iapplication_view_for_hwnd(window)?;
+                    unsafe {
+                        self.get_pinned_apps()?
+                            .unpin_view(ComIn::new(&view))
+                            .as_call_result("IVirtualDesktopPinnedApps", "unpin_view")?
+                    }
+                }
+                BatchOp::SetName(desktop, name) => {
+                    let desktop = resolve(desktop)?;
+                    unsafe {
+                        manager
+                            .set_name(ComIn::new(&desktop), HSTRING::from(name.as_str()))
+                            .as_call_result("IVirtualDesktopManagerInternal", "set_name")?
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -636,7 +1136,7 @@ impl ComObjects {
             let mut value = false;
             self.get_manager()?
                 .is_window_on_current_desktop(*window, &mut value)
-                .as_result()
+                .as_call_result("IVirtualDesktopManager", "is_window_on_current_desktop")
                 .map_err(|er| match er {
                     // Window does not exist
                     Error::ComElementNotFound => Error::WindowNotFound,
@@ -649,7 +1149,41 @@ impl ComObjects {
     #[apply(retry_function)]
     pub fn move_window_to_desktop(&self, window: &HWND, desktop: &DesktopInternal) -> Result<()> {
         let view = self.get_iapplication_view_for_hwnd(window)?;
-        self.move_view_to_desktop(ComIn::new(&view), desktop)
+        self.move_view_to_desktop(&view, desktop)
+    }
+
+    /// Moves the windows one by one, `delay` apart. Fails only if the desktop
+    /// is not found.
+    ///
+    /// Not retried as a whole: each move is retried on its own, so a
+    /// reconnect doesn't move the windows already moved and sleep again.
+    pub fn move_windows_to_desktop(
+        &self,
+        windows: &[HWND],
+        desktop: &DesktopInternal,
+        delay: &Duration,
+    ) -> Result<Vec<Result<()>>> {
+        let desktop = DesktopInternal::Guid(self.get_existing_desktop_id(desktop)?);
+        let mut results = Vec::with_capacity(windows.len());
+        for (i, window) in windows.iter().enumerate() {
+            if i > 0 && !delay.is_zero() {
+                std::thread::sleep(*delay);
+            }
+            results.push(self.move_window_to_desktop(window, &desktop));
+        }
+        Ok(results)
+    }
+
+    /// Id of the desktop, fails if it does not exist
+    #[apply(retry_function)]
+    fn get_existing_desktop_id(&self, desktop: &DesktopInternal) -> Result<GUID> {
+        get_idesktop_guid(&self.get_idesktop(desktop)?)
+    }
+
+    #[apply(retry_function)]
+    pub fn can_move_window(&self, window: &HWND) -> Result<bool> {
+        let view = self.get_iapplication_view_for_hwnd(window)?;
+        self.can_move_view(&view)
     }
 
     #[apply(retry_function)]
@@ -657,7 +1191,9 @@ impl ComObjects {
         let manager = self.get_manager_internal()?;
         let mut count = 0;
         unsafe {
-            manager.get_desktop_count(&mut count).as_result()?;
+            manager
+                .get_desktop_count(&mut count)
+                .as_call_result("IVirtualDesktopManagerInternal", "get_desktop_count")?;
         };
         Ok(count)
     }
@@ -668,7 +1204,7 @@ impl ComObjects {
         unsafe {
             self.get_manager()?
                 .get_desktop_by_window(*window, &mut desktop)
-                .as_result()
+                .as_call_result("IVirtualDesktopManager", "get_desktop_by_window")
                 .map_err(|er| match er {
                     // Window does not exist
                     Error::ComElementNotFound => Error::WindowNotFound,
@@ -687,13 +1223,103 @@ impl ComObjects {
         unsafe {
             self.get_manager_internal()?
                 .get_current_desktop(&mut desktop)
-                .as_result()?
+                .as_call_result("IVirtualDesktopManagerInternal", "get_current_desktop")?
         }
         let desktop = desktop.ok_or(Error::ComAllocatedNullPtr)?;
         let id = get_idesktop_guid(&desktop)?;
         Ok(DesktopInternal::Guid(id))
     }
 
+    #[apply(retry_function)]
+    pub fn get_current_desktop_info(&self) -> Result<DesktopInfo> {
+        let mut desktop = None;
+        unsafe {
+            self.get_manager_internal()?
+                .get_current_desktop(&mut desktop)
+                .as_call_result("IVirtualDesktopManagerInternal", "get_current_desktop")?
+        }
+        let desktop = desktop.ok_or(Error::ComAllocatedNullPtr)?;
+        let id = get_idesktop_guid(&desktop)?;
+        let index = self.get_desktop_index_by_guid(&id)?;
+        self.get_idesktop_info(&desktop, index)
+    }
+
+    #[apply(retry_function)]
+    pub fn get_desktops_with_urgent_windows(&self) -> Result<Vec<DesktopInternal>> {
+        let mut urgent = Vec::new();
+        for view in self.get_iapplication_views()? {
+            let mut neediness = 0;
+            let mut desktop_id = GUID::default();
+            unsafe {
+                // Some views (e.g. shell windows) don't answer these, skip them
+                if view.get_neediness(&mut neediness).is_err()
+                    || view.get_virtual_desktop_id(&mut desktop_id).is_err()
+                {
+                    continue;
+                }
+            }
+            if neediness != 0 && desktop_id != GUID::default() && !urgent.contains(&desktop_id) {
+                urgent.push(desktop_id);
+            }
+        }
+
+        // Return in the same order as the desktops are
+        Ok(self
+            .get_desktops()?
+            .into_iter()
+            .filter(|desktop| match desktop {
+                DesktopInternal::IndexGuid(_, id) => urgent.contains(id),
+                _ => false,
+            })
+            .collect())
+    }
+
+    #[apply(retry_function)]
+    pub fn get_view_infos(&self) -> Result<Vec<ViewInfo>> {
+        let pinned_apps = self.get_pinned_apps()?;
+        let mut infos = Vec::new();
+        for view in self.get_iapplication_views()? {
+            let mut hwnd = HWND::default();
+            let mut desktop = GUID::default();
+            let mut show_in_switchers = 0;
+            let mut last_activation_timestamp = 0;
+            let mut app_id: APPIDPWSTR = std::ptr::null();
+            let mut view_pinned = false;
+            let mut app_pinned = false;
+            unsafe {
+                // Views without a window can't be used for anything, skip them
+                if view.get_thumbnail_window(&mut hwnd).is_err() || hwnd == HWND::default() {
+                    continue;
+                }
+
+                // Some views (e.g. shell windows) don't answer these, the
+                // defaults are kept
+                let _ = view.get_virtual_desktop_id(&mut desktop);
+                let _ = view.get_show_in_switchers(&mut show_in_switchers);
+                let _ = view.get_last_activation_timestamp(&mut last_activation_timestamp);
+                let _ = pinned_apps.is_view_pinned(ComIn::new(&view), &mut view_pinned);
+                if view
+                    .get_app_user_model_id(&mut app_id as *mut _ as *mut _)
+                    .is_err()
+                {
+                    app_id = std::ptr::null();
+                }
+                if !app_id.is_null() {
+                    let _ = pinned_apps.is_app_pinned(app_id, &mut app_pinned);
+                }
+            }
+            infos.push(ViewInfo {
+                hwnd: hwnd.into(),
+                app_user_model_id: app_id_to_string(app_id),
+                desktop,
+                show_in_switchers: show_in_switchers != 0,
+                is_pinned: view_pinned || app_pinned,
+                last_activation_timestamp,
+            });
+        }
+        Ok(infos)
+    }
+
     #[apply(retry_function)]
     pub fn is_pinned_window(&self, window: &HWND) -> Result<bool> {
         let view = self.get_iapplication_view_for_hwnd(window)?;
@@ -701,7 +1327,7 @@ impl ComObjects {
             let mut value = false;
             self.get_pinned_apps()?
                 .is_view_pinned(ComIn::new(&view), &mut value)
-                .as_result()?;
+                .as_call_result("IVirtualDesktopPinnedApps", "is_view_pinned")?;
             Ok(value)
         }
     }
@@ -712,7 +1338,7 @@ impl ComObjects {
         unsafe {
             self.get_pinned_apps()?
                 .pin_view(ComIn::new(&view))
-                .as_result()?;
+                .as_call_result("IVirtualDesktopPinnedApps", "pin_view")?;
         }
         Ok(())
     }
@@ -723,7 +1349,7 @@ impl ComObjects {
         unsafe {
             self.get_pinned_apps()?
                 .unpin_view(ComIn::new(&view))
-                .as_result()?;
+                .as_call_result("IVirtualDesktopPinnedApps", "unpin_view")?;
         }
         Ok(())
     }
@@ -733,11 +1359,72 @@ impl ComObjects {
         let mut app_id: APPIDPWSTR = std::ptr::null_mut();
         unsafe {
             view.get_app_user_model_id(&mut app_id as *mut _ as *mut _)
-                .as_result()?
+                .as_call_result("IApplicationView", "get_app_user_model_id")?
         }
         Ok(app_id)
     }
 
+    #[apply(retry_function)]
+    pub fn flash_window(&self, window: &HWND) -> Result<()> {
+        let view = self.get_iapplication_view_for_hwnd(window)?;
+        unsafe {
+            view.flash().as_call_result("IApplicationView", "flash")?;
+        }
+        Ok(())
+    }
+
+    #[apply(retry_function)]
+    pub fn is_shown_in_switchers(&self, window: &HWND) -> Result<bool> {
+        let view = self.get_iapplication_view_for_hwnd(window)?;
+        let mut show = 0;
+        unsafe {
+            view.get_show_in_switchers(&mut show)
+                .as_call_result("IApplicationView", "get_show_in_switchers")?;
+        }
+        Ok(show != 0)
+    }
+
+    #[apply(retry_function)]
+    pub fn set_shown_in_switchers(&self, window: &HWND, show: bool) -> Result<()> {
+        let view = self.get_iapplication_view_for_hwnd(window)?;
+        unsafe {
+            view.set_show_in_switchers(show as i32)
+                .as_call_result("IApplicationView", "set_show_in_switchers")?;
+        }
+        Ok(())
+    }
+
+    #[apply(retry_function)]
+    pub fn get_app_id(&self, window: &HWND) -> Result<String> {
+        let view = self.get_iapplication_view_for_hwnd(window)?;
+        Ok(app_id_to_string(self.get_iapplication_id_for_view(&view)?))
+    }
+
+    #[apply(retry_function)]
+    pub fn get_windows_by_app_id(&self, app_id: &str) -> Result<Vec<HWND>> {
+        let app_id = HSTRING::from(app_id);
+        let mut views: Option<IObjectArray> = None;
+        unsafe {
+            self.get_view_collection()?
+                .get_views_by_app_user_model_id(app_id.as_ptr(), &mut views)
+                .as_call_result(
+                    "IApplicationViewCollection",
+                    "get_views_by_app_user_model_id",
+                )?
+        }
+        let views = views.ok_or(Error::ComAllocatedNullPtr)?;
+        let mut windows = Vec::new();
+        for view in views.iter::<IApplicationView>() {
+            let view = view?;
+            let mut hwnd = HWND::default();
+            let res = unsafe { view.get_thumbnail_window(&mut hwnd) };
+            if res.is_ok() && hwnd != HWND::default() {
+                windows.push(hwnd);
+            }
+        }
+        Ok(windows)
+    }
+
     #[apply(retry_function)]
     pub fn is_pinned_app(&self, window: &HWND) -> Result<bool> {
         let view = self.get_iapplication_view_for_hwnd(window)?;
@@ -746,7 +1433,7 @@ impl ComObjects {
             let mut value = false;
             self.get_pinned_apps()?
                 .is_app_pinned(app_id, &mut value)
-                .as_result()?;
+                .as_call_result("IVirtualDesktopPinnedApps", "is_app_pinned")?;
             Ok(value)
         }
     }
@@ -756,7 +1443,9 @@ impl ComObjects {
         let view = self.get_iapplication_view_for_hwnd(window)?;
         let app_id = self.get_iapplication_id_for_view(&view)?;
         unsafe {
-            self.get_pinned_apps()?.pin_app(app_id).as_result()?;
+            self.get_pinned_apps()?
+                .pin_app(app_id)
+                .as_call_result("IVirtualDesktopPinnedApps", "pin_app")?;
         }
         Ok(())
     }
@@ -766,7 +1455,9 @@ impl ComObjects {
         let view = self.get_iapplication_view_for_hwnd(window)?;
         let app_id = self.get_iapplication_id_for_view(&view)?;
         unsafe {
-            self.get_pinned_apps()?.unpin_app(app_id).as_result()?;
+            self.get_pinned_apps()?
+                .unpin_app(app_id)
+                .as_call_result("IVirtualDesktopPinnedApps", "unpin_app")?;
         }
         Ok(())
     }
@@ -775,10 +1466,38 @@ impl ComObjects {
     pub fn get_desktop_name(&self, desktop: &DesktopInternal) -> Result<String> {
         let desktop = self.get_idesktop(desktop)?;
         let mut name = HSTRING::default();
-        unsafe {
-            desktop.get_name(&mut name).as_result()?;
+        let res = unsafe {
+            desktop
+                .get_name(&mut name)
+                .as_call_result("IVirtualDesktop", "get_name")
+        };
+        match res {
+            // Older Windows 10 builds have no names, they are kept in the registry
+            #[cfg(feature = "registry")]
+            Err(Error::ComNotImplemented) => {
+                crate::registry::read_desktop_name(&get_idesktop_guid(&desktop)?)
+            }
+            res => res.map(|()| name.to_string()),
+        }
+    }
+
+    /// Raw HMONITOR of the desktop, `None` if the desktop is shown on all
+    /// monitors
+    #[apply(retry_function)]
+    pub fn get_desktop_monitor(&self, desktop: &DesktopInternal) -> Result<Option<isize>> {
+        let desktop = self.get_idesktop(desktop)?;
+        let mut monitor = 0;
+        let res = unsafe {
+            desktop
+                .get_monitor(&mut monitor)
+                .as_call_result("IVirtualDesktop", "get_monitor")
+        };
+        match res {
+            Ok(()) if monitor != 0 => Ok(Some(monitor)),
+            // Desktops are per-monitor only on the 22000 build
+            Ok(()) | Err(Error::ComNotImplemented) => Ok(None),
+            Err(err) => Err(err),
         }
-        Ok(name.to_string())
     }
 
     #[apply(retry_function)]
@@ -786,10 +1505,18 @@ impl ComObjects {
         let desktop = self.get_idesktop(desktop)?;
         let manager_internal = self.get_manager_internal()?;
 
-        unsafe {
+        let res = unsafe {
             manager_internal
                 .set_name(ComIn::new(&desktop), HSTRING::from(name))
-                .as_result()
+                .as_call_result("IVirtualDesktopManagerInternal", "set_name")
+        };
+        match res {
+            // See `get_desktop_name`
+            #[cfg(feature = "registry")]
+            Err(Error::ComNotImplemented) => {
+                crate::registry::write_desktop_name(&get_idesktop_guid(&desktop)?, name)
+            }
+            res => res,
         }
     }
 
@@ -798,7 +1525,9 @@ impl ComObjects {
         let desktop = self.get_idesktop(desktop)?;
         let mut path = HSTRING::default();
         unsafe {
-            desktop.get_wallpaper(&mut path).as_result()?;
+            desktop
+                .get_wallpaper(&mut path)
+                .as_call_result("IVirtualDesktop", "get_wallpaper")?;
         }
         Ok(path.to_string())
     }
@@ -810,17 +1539,57 @@ impl ComObjects {
         unsafe {
             manager_internal
                 .set_wallpaper(ComIn::new(&desktop), HSTRING::from(path))
-                .as_result()
+                .as_call_result("IVirtualDesktopManagerInternal", "set_wallpaper")
         }
     }
 }
 
 fn get_idesktop_guid(desktop: &IVirtualDesktop) -> Result<GUID> {
     let mut guid = GUID::default();
-    unsafe { desktop.get_id(&mut guid).as_result()? }
+    unsafe {
+        desktop
+            .get_id(&mut guid)
+            .as_call_result("IVirtualDesktop", "get_id")?
+    }
     Ok(guid)
 }
 
+/// Parses a GUID in `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX` form, braces are
+/// allowed
+pub(crate) fn parse_guid(text: &str) -> Option<GUID> {
+    let hex: String = text
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split('-')
+        .collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok().map(GUID::from_u128)
+}
+
+/// Adds a reference to the COM object, for handing it out
+fn raw_unknown(raw: *mut c_void) -> Result<IUnknown> {
+    unsafe { IUnknown::from_raw_borrowed(&raw) }
+        .cloned()
+        .ok_or(Error::ComAllocatedNullPtr)
+}
+
+fn ignore_not_implemented(result: Result<()>) -> Result<()> {
+    match result {
+        Err(Error::ComNotImplemented) => Ok(()),
+        _ => result,
+    }
+}
+
+fn app_id_to_string(app_id: APPIDPWSTR) -> String {
+    if app_id.is_null() {
+        return String::new();
+    }
+    unsafe { windows::core::PCWSTR::from_raw(app_id).to_string() }.unwrap_or_default()
+}
+
 thread_local! {
     static COM_OBJECTS: ComObjects = ComObjects::new();
 }
@@ -844,3 +1613,66 @@ where
     // return COM_OBJECTS.with(|c| run_function_and_retry(&f, &c));
     COM_OBJECTS.with(|c| f(c))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay() {
+        let policy = ReconnectPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(50),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(50));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(50 << 16));
+    }
+
+    #[test]
+    fn test_error_hresult() {
+        let hresult = HRESULT(0x800706BA_u32 as i32);
+        assert_eq!(hresult.as_error().hresult(), Some(hresult));
+        assert_eq!(Error::WindowNotFound.hresult(), None);
+    }
+
+    #[test]
+    fn test_error_categories() {
+        let e_fail = HRESULT(0x80004005_u32 as i32);
+        assert_eq!(
+            e_fail.as_call_result("IVirtualDesktop", "get_id"),
+            Err(Error::ComCallFailed {
+                interface: "IVirtualDesktop",
+                method: "get_id",
+                hresult: e_fail,
+            })
+        );
+        assert_eq!(
+            HRESULT(0x8001010E_u32 as i32).as_call_result("IVirtualDesktop", "get_id"),
+            Err(Error::NotOnCorrectThread)
+        );
+        assert_eq!(
+            HRESULT(0x80004002_u32 as i32).as_service_result(),
+            Err(Error::UnsupportedWindowsBuild)
+        );
+        assert_eq!(
+            HRESULT(0).as_call_result("IVirtualDesktop", "get_id"),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "test-hooks")]
+    #[test]
+    fn test_simulated_restart_observers() {
+        let calls =
+            std::cell::Cell::new(SHELL_GENERATION.load(std::sync::atomic::Ordering::SeqCst));
+        let connection = calls.clone();
+        simulate_shell_restart();
+
+        // Both observers notice the restart, each only once
+        assert!(simulated_restart_since(&calls));
+        assert!(!simulated_restart_since(&calls));
+        assert!(simulated_restart_since(&connection));
+        assert!(!simulated_restart_since(&connection));
+    }
+}
@@ -1,7 +1,16 @@
+use super::backend::with_backend;
 use super::comobjects::*;
 use super::interfaces_multi::{ComIn, IVirtualDesktop};
 use super::*;
-use std::{convert::TryFrom, fmt::Debug};
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{convert::TryFrom, str::FromStr};
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SPI_SETDESKWALLPAPER,
+};
 use windows::{core::GUID, Win32::Foundation::HWND};
 
 /// You can construct Desktop instance with `get_desktop(5)` by index or GUID.
@@ -24,8 +33,10 @@ impl PartialEq for Desktop {
             (DesktopInternal::IndexGuid(a, _), DesktopInternal::Index(b)) => a == b,
             (DesktopInternal::Guid(a), DesktopInternal::IndexGuid(_, b)) => a == b,
             (DesktopInternal::IndexGuid(_, a), DesktopInternal::Guid(b)) => a == b,
-            _ => with_com_objects(move |f| Ok(f.get_desktop_id(&a)? == f.get_desktop_id(&b)?))
-                .unwrap_or(false),
+            _ => with_backend(move |f| {
+                Ok(f.get_desktop_id(Desktop(a))? == f.get_desktop_id(Desktop(b))?)
+            })
+            .unwrap_or(false),
         }
     }
 }
@@ -91,45 +102,296 @@ impl<'a> TryFrom<ComIn<'a, IVirtualDesktop>> for Desktop {
         Ok(Desktop(DesktopInternal::try_from(&desktop)?))
     }
 }
+/// Formats as `index: name {GUID}`. If the desktop can't be read, e.g. it was
+/// removed, only the index or the GUID it was created with is shown.
+impl Display for Desktop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let info = || Ok::<_, Error>((self.get_index()?, self.get_name()?, self.get_id()?));
+        match (info(), self.0) {
+            (Ok((index, name, guid)), _) => write!(f, "{}: {} {{{:?}}}", index, name, guid),
+            (Err(_), DesktopInternal::Index(index)) => write!(f, "{}", index),
+            (Err(_), DesktopInternal::Guid(guid) | DesktopInternal::IndexGuid(_, guid)) => {
+                write!(f, "{{{:?}}}", guid)
+            }
+        }
+    }
+}
+
+/// Parses a GUID, an index or a desktop name, see `get_desktop_by_name`. The
+/// `Display` format is parsed by its GUID. Names are resolved when parsing.
+impl FromStr for Desktop {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match parse_desktop_id(text) {
+            Some(desktop) => Ok(desktop),
+            None => get_desktop_by_name(text, NameMatch::default()),
+        }
+    }
+}
+
+/// GUID, index or `Display` formatted desktop
+fn parse_desktop_id(text: &str) -> Option<Desktop> {
+    let text = text.trim();
+    if let Ok(index) = text.parse::<u32>() {
+        return Some(Desktop::from(index));
+    }
+    let guid = match text.rfind('{') {
+        Some(start) if text.ends_with('}') => &text[start..],
+        _ => text,
+    };
+    parse_guid(guid).map(Desktop::from)
+}
+
 impl Desktop {
     /// Get the GUID of the desktop
     pub fn get_id(&self) -> Result<GUID> {
         let internal = self.0;
-        with_com_objects(move |o| o.get_desktop_id(&internal))
+        if let DesktopInternal::Index(_) = internal {
+            if let Some(info) = cached_info(internal) {
+                return Ok(info.guid);
+            }
+        }
+        with_backend(move |b| b.get_desktop_id(Desktop(internal)))
     }
 
     pub fn get_index(&self) -> Result<u32> {
         let internal = self.0;
-        with_com_objects(move |o| o.get_desktop_index(&internal))
+        if let DesktopInternal::Guid(_) = internal {
+            if let Some(info) = cached_info(internal) {
+                return Ok(info.index);
+            }
+        }
+        with_backend(move |b| b.get_desktop_index(Desktop(internal)))
     }
 
-    /// Get desktop name
+    /// Get desktop name, unnamed desktops are named "Desktop N" unless
+    /// disabled with `set_unnamed_desktop_names`
     pub fn get_name(&self) -> Result<String> {
         let internal = self.0;
-        with_com_objects(move |o| o.get_desktop_name(&internal))
+        if let Some(info) = cached_info(internal) {
+            return Ok(with_default_name(info).name);
+        }
+        with_backend(move |b| {
+            let name = b.get_desktop_name(Desktop(internal))?;
+            if name.is_empty() && UNNAMED_DESKTOP_NAMES.load(Ordering::Relaxed) {
+                return Ok(default_desktop_name(
+                    b.get_desktop_index(Desktop(internal))?,
+                ));
+            }
+            Ok(name)
+        })
     }
 
     /// Set desktop name
     pub fn set_name(&self, name: &str) -> Result<()> {
         let internal = self.0;
         let name_ = name.to_owned();
-        with_com_objects(move |o| o.set_desktop_name(&internal, &name_))
+        invalidating(with_backend(move |b| {
+            b.set_desktop_name(Desktop(internal), &name_)
+        }))
+    }
+
+    /// Is the window shown on this desktop. Unlike `is_window_on_desktop`,
+    /// pinned windows and windows of pinned apps are on every desktop.
+    pub fn has_window<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<bool> {
+        let hwnd = hwnd.into_hwnd()?;
+        let internal = self.0;
+        with_backend(move |b| {
+            Ok(b.is_window_on_desktop(hwnd, Desktop(internal))?
+                || b.is_pinned_window(hwnd)?
+                || b.is_pinned_app(hwnd)?)
+        })
+    }
+
+    /// Get the windows shown on this desktop, most recently used first, with
+    /// their last activation timestamps. Windows hidden from the switchers are
+    /// left out, pinned windows are included.
+    pub fn windows_by_recent_use(&self) -> Result<Vec<(WindowId, u64)>> {
+        let internal = self.0;
+        with_backend(move |b| {
+            let id = b.get_desktop_id(Desktop(internal))?;
+            let mut windows: Vec<_> = b
+                .get_view_infos()?
+                .into_iter()
+                .filter(|view| view.show_in_switchers && (view.desktop == id || view.is_pinned))
+                .map(|view| (view.hwnd, view.last_activation_timestamp))
+                .collect();
+            windows.sort_by_key(|(_, timestamp)| std::cmp::Reverse(*timestamp));
+            Ok(windows)
+        })
+    }
+
+    /// Monitor the desktop is on, `None` if the desktop is shown on all
+    /// monitors. Only the 22000 build has per-monitor desktops.
+    pub fn monitor(&self) -> Result<Option<HMONITOR>> {
+        let internal = self.0;
+        with_backend(move |b| Ok(b.get_desktop_monitor(Desktop(internal))?.map(HMONITOR)))
     }
 
     /// Get desktop wallpaper path
+    ///
+    /// Windows versions without per desktop wallpapers return
+    /// `Error::ComNotImplemented`, or the wallpaper set while a
+    /// `WallpaperEmulation` is running.
     pub fn get_wallpaper(&self) -> Result<String> {
         let internal = self.0;
-        with_com_objects(move |o| o.get_desktop_wallpaper(&internal))
+        with_backend(move |b| match b.get_desktop_wallpaper(Desktop(internal)) {
+            #[cfg(feature = "listener")]
+            Err(Error::ComNotImplemented) if crate::wallpapers::is_active() => Ok(
+                crate::wallpapers::get(&b.get_desktop_id(Desktop(internal))?),
+            ),
+            result => result,
+        })
     }
 
-    /// Set desktop wallpaper path
+    /// Set desktop wallpaper path.
+    ///
+    /// Windows versions without per desktop wallpapers have one wallpaper for
+    /// all desktops. It is set instead if the desktop is the current desktop,
+    /// otherwise `Error::ComNotImplemented` is returned, see
+    /// `wallpaper_support`. While a `WallpaperEmulation` is running, the
+    /// wallpaper is shown whenever the desktop is switched to.
     pub fn set_wallpaper(&self, path: &str) -> Result<()> {
         let internal = self.0;
         let path_ = path.to_owned();
-        with_com_objects(move |o| o.set_desktop_wallpaper(&internal, &path_))
+        with_backend(
+            move |b| match b.set_desktop_wallpaper(Desktop(internal), &path_) {
+                Err(Error::ComNotImplemented) => {
+                    let guid = b.get_desktop_id(Desktop(internal))?;
+                    let emulated = emulate_wallpaper(guid, &path_);
+                    if guid != b.get_desktop_id(b.get_current_desktop()?)? {
+                        return if emulated {
+                            Ok(())
+                        } else {
+                            Err(Error::ComNotImplemented)
+                        };
+                    }
+                    set_shared_wallpaper(&path_)
+                }
+                result => result,
+            },
+        )
     }
 }
 
+/// Snapshot of a desktop's properties, gathered in one pass
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesktopInfo {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::guid"))]
+    pub guid: GUID,
+    pub index: u32,
+
+    /// Name of the desktop, "Desktop N" or empty if the desktop is not named,
+    /// see `set_unnamed_desktop_names`
+    pub name: String,
+
+    /// Wallpaper path, empty if the Windows version has no per desktop wallpapers
+    pub wallpaper: String,
+
+    pub is_remote: bool,
+}
+
+/// Snapshot of an application view, i.e. a window Task View knows about, see
+/// `list_views`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ViewInfo {
+    pub hwnd: WindowId,
+
+    /// AppUserModelID of the app, empty if the view doesn't have one
+    pub app_user_model_id: String,
+
+    /// GUID of the desktop the view is on, zero if it is not on a desktop
+    pub desktop: GUID,
+
+    /// Is the view shown in Alt+Tab and the taskbar
+    pub show_in_switchers: bool,
+
+    /// Is the window or its app pinned to all desktops
+    pub is_pinned: bool,
+
+    /// Increases each time a view is activated, for ordering the views by
+    /// recent use
+    pub last_activation_timestamp: u64,
+}
+
+/// Name unnamed desktops by their position in Task View, see
+/// `set_unnamed_desktop_names`
+static UNNAMED_DESKTOP_NAMES: AtomicBool = AtomicBool::new(true);
+
+/// Name desktops the user has not renamed "Desktop N" like Task View does,
+/// where N is the index plus one. Enabled by default, when disabled names of
+/// unnamed desktops are empty.
+///
+/// Applies to `Desktop::get_name`, `current_desktop_info`,
+/// `list_desktops_detailed` and the desktops of `DesktopEvent::InitialState`.
+/// Task View uses a localized name, this is always the English one.
+pub fn set_unnamed_desktop_names(enabled: bool) {
+    UNNAMED_DESKTOP_NAMES.store(enabled, Ordering::Relaxed);
+}
+
+fn default_desktop_name(index: u32) -> String {
+    format!("Desktop {}", index + 1)
+}
+
+/// Names the desktop if it is unnamed and the fallback is enabled
+pub(crate) fn with_default_name(mut info: DesktopInfo) -> DesktopInfo {
+    if info.name.is_empty() && UNNAMED_DESKTOP_NAMES.load(Ordering::Relaxed) {
+        info.name = default_desktop_name(info.index);
+    }
+    info
+}
+
+/// Remembers the wallpaper of the desktop if a `WallpaperEmulation` is running
+fn emulate_wallpaper(guid: GUID, path: &str) -> bool {
+    #[cfg(feature = "listener")]
+    if crate::wallpapers::is_active() {
+        crate::wallpapers::set(guid, path);
+        return true;
+    }
+    let _ = (guid, path);
+    false
+}
+
+/// Sets the wallpaper shown on all desktops
+pub(crate) fn set_shared_wallpaper(path: &str) -> Result<()> {
+    let mut path: Vec<u16> = path.encode_utf16().chain(Some(0)).collect();
+    unsafe {
+        SystemParametersInfoW(
+            SPI_SETDESKWALLPAPER,
+            0,
+            Some(path.as_mut_ptr().cast()),
+            SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+        )?;
+    }
+    Ok(())
+}
+
+/// Cached info of the desktop, see `Cache`
+#[cfg(feature = "listener")]
+fn cached_info(desktop: DesktopInternal) -> Option<DesktopInfo> {
+    crate::cache::cached_info(desktop)
+}
+
+/// Desktops are only cached with the listener
+#[cfg(not(feature = "listener"))]
+fn cached_info(_desktop: DesktopInternal) -> Option<DesktopInfo> {
+    None
+}
+
+/// Drops the cached desktops after a change, see `Cache`
+pub(crate) fn invalidate_cache() {
+    #[cfg(feature = "listener")]
+    crate::cache::invalidate();
+}
+
+/// Drops the cached desktops after the change, also if it failed halfway
+fn invalidating<T>(result: Result<T>) -> Result<T> {
+    invalidate_cache();
+    result
+}
+
 /// Get desktop by index or GUID
 ///
 /// # Examples
@@ -151,7 +413,7 @@ where
     T: Into<Desktop>,
     T: Send + 'static + Copy,
 {
-    with_com_objects(move |o| o.switch_desktop(&desktop.into().into()))
+    with_backend(move |b| b.switch_desktop(desktop.into()))
 }
 
 /// Remove desktop by index or GUID
@@ -160,85 +422,557 @@ where
     T: Into<Desktop>,
     T: Send + 'static + Copy,
 {
-    with_com_objects(move |o| {
-        o.remove_desktop(&desktop.into().into(), &fallback_desktop.into().into())
-    })
+    invalidating(with_backend(move |b| {
+        b.remove_desktop(desktop.into(), fallback_desktop.into())
+    }))
+}
+
+/// Move desktop by index or GUID to the index, the desktops in between shift
+/// by one. Windows versions that can't reorder desktops return
+/// `Error::ComNotImplemented`.
+pub fn move_desktop<T>(desktop: T, index: u32) -> Result<()>
+where
+    T: Into<Desktop>,
+    T: Send + 'static + Copy,
+{
+    invalidating(with_backend(move |b| b.move_desktop(desktop.into(), index)))
+}
+
+/// Which desktop gets the windows of a removed desktop, see
+/// `remove_desktop_with_policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackPolicy {
+    /// Desktop on the left, or on the right if the removed desktop is first
+    Left,
+
+    /// Desktop on the right, or on the left if the removed desktop is last
+    Right,
+
+    /// Current desktop, or the one on the left if the current desktop is
+    /// removed
+    Current,
+
+    /// Desktop by index, it can't be the removed desktop
+    Index(u32),
+}
+
+/// Remove desktop by index or GUID, the fallback desktop is chosen by the
+/// policy.
+///
+/// Removing the current desktop switches to the fallback desktop. Removing
+/// the only desktop fails with `Error::LastDesktop`.
+pub fn remove_desktop_with_policy<T>(desktop: T, policy: FallbackPolicy) -> Result<()>
+where
+    T: Into<Desktop>,
+    T: Send + 'static + Copy,
+{
+    invalidating(with_backend(move |b| {
+        let desktop = desktop.into();
+        let index = b.get_desktop_index(desktop)?;
+        let current = b.get_desktop_index(b.get_current_desktop()?)?;
+        let count = b.get_desktop_count()?;
+        let fallback = resolve_fallback(policy, index, current, count)?;
+        b.remove_desktop(desktop, Desktop::from(fallback))
+    }))
+}
+
+/// Index of the fallback desktop when removing the desktop at `index`
+fn resolve_fallback(policy: FallbackPolicy, index: u32, current: u32, count: u32) -> Result<u32> {
+    if count <= 1 {
+        return Err(Error::LastDesktop);
+    }
+    let left = if index == 0 { 1 } else { index - 1 };
+    let right = if index + 1 == count {
+        index - 1
+    } else {
+        index + 1
+    };
+    match policy {
+        FallbackPolicy::Left => Ok(left),
+        FallbackPolicy::Right => Ok(right),
+        FallbackPolicy::Current if current == index => Ok(left),
+        FallbackPolicy::Current => Ok(current),
+        FallbackPolicy::Index(fallback) if fallback == index => Err(Error::RemoveDesktopFailed),
+        FallbackPolicy::Index(fallback) if fallback >= count => Err(Error::DesktopNotFound),
+        FallbackPolicy::Index(fallback) => Ok(fallback),
+    }
+}
+
+/// Create or remove desktops until there are exactly `count` desktops.
+///
+/// Desktops are created and removed at the end, windows of the removed
+/// desktops are moved to `fallback_desktop`. If the fallback would be removed
+/// too, or `count` is zero, `Error::RemoveDesktopFailed` is returned before
+/// anything is changed.
+pub fn ensure_desktop_count<T>(count: u32, fallback_desktop: T) -> Result<()>
+where
+    T: Into<Desktop>,
+    T: Send + 'static + Copy,
+{
+    invalidating(with_backend(move |b| {
+        let desktops = b.get_desktops()?;
+        if desktops.len() > count as usize {
+            let fallback = fallback_desktop.into();
+            if count == 0 || b.get_desktop_index(fallback)? >= count {
+                return Err(Error::RemoveDesktopFailed);
+            }
+            for desktop in desktops[count as usize..].iter().rev() {
+                b.remove_desktop(*desktop, fallback)?;
+            }
+        }
+        for _ in desktops.len()..count as usize {
+            b.create_desktop()?;
+        }
+        Ok(())
+    }))
 }
 
 /// Is window on desktop by index or GUID
-pub fn is_window_on_desktop<T>(desktop: T, hwnd: HWND) -> Result<bool>
+pub fn is_window_on_desktop<T, M>(desktop: T, hwnd: impl IntoHwnd<M>) -> Result<bool>
 where
     T: Into<Desktop>,
     T: Send + 'static + Copy,
 {
-    with_com_objects(move |o| o.is_window_on_desktop(&hwnd, &desktop.into().into()))
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.is_window_on_desktop(hwnd, desktop.into()))
+}
+
+/// Can the window be moved to another desktop, false e.g. for pinned and
+/// shell windows
+pub fn can_move_window_between_desktops<M>(hwnd: impl IntoHwnd<M>) -> Result<bool> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.can_move_window(hwnd))
 }
 
-/// Move window to desktop by index or GUID
-pub fn move_window_to_desktop<T>(desktop: T, hwnd: &HWND) -> Result<()>
+/// Move window to desktop by index or GUID, windows that can't be moved return
+/// `Error::WindowNotMovable`
+pub fn move_window_to_desktop<T, M>(desktop: T, hwnd: impl IntoHwnd<M>) -> Result<()>
 where
     T: Into<Desktop>,
     T: Send + 'static + Copy,
 {
-    let hwnd = *hwnd;
-    with_com_objects(move |o| o.move_window_to_desktop(&hwnd, &desktop.into().into()))
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.move_window_to_desktop(hwnd, desktop.into()))
+}
+
+/// Delay between the moves of `move_windows_to_desktop`, see
+/// `set_bulk_move_delay`
+static BULK_MOVE_DELAY: Mutex<Duration> = Mutex::new(Duration::ZERO);
+
+/// Set the delay between the moves of `move_windows_to_desktop`, so moving
+/// many windows doesn't flood explorer.exe with calls. No delay by default.
+pub fn set_bulk_move_delay(delay: Duration) {
+    *BULK_MOVE_DELAY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = delay;
+}
+
+/// Move the windows to the desktop by index or GUID, returns the result of
+/// each window in the order of `windows`.
+///
+/// The moves are spaced by the delay of `set_bulk_move_delay`. Windows that
+/// can't be moved return `Error::WindowNotMovable`, closed windows
+/// `Error::WindowNotFound`. The call fails only if the desktop is not found
+/// or explorer.exe is not reachable.
+///
+/// ```rust,no_run
+/// # let windows = [winvd::WindowId(0)];
+/// for result in winvd::move_windows_to_desktop(1, &windows).unwrap() {
+///     if let Err(err) = result {
+///         println!("Not moved: {:?}", err);
+///     }
+/// }
+/// ```
+pub fn move_windows_to_desktop<T, W, M>(desktop: T, windows: &[W]) -> Result<Vec<Result<()>>>
+where
+    T: Into<Desktop>,
+    W: IntoHwnd<M> + Copy,
+{
+    let handles: Vec<Result<HWND>> = windows
+        .iter()
+        .map(|window| IntoHwnd::<M>::into_hwnd(*window))
+        .collect();
+    let hwnds: Vec<HWND> = handles
+        .iter()
+        .filter_map(|handle| handle.as_ref().ok().copied())
+        .collect();
+    let desktop = desktop.into();
+    let delay = *BULK_MOVE_DELAY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut moved =
+        with_backend(move |b| b.move_windows_to_desktop(&hwnds, desktop, delay))?.into_iter();
+
+    // Windows without a handle keep their error in place
+    Ok(handles
+        .into_iter()
+        .map(|handle| handle.and_then(|_| moved.next().unwrap_or(Err(Error::WindowNotFound))))
+        .collect())
+}
+
+/// Direction of the adjacent desktop, see `get_adjacent_desktop`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// Get the desktop on the left or right of the desktop, in Task View order.
+///
+/// At the edge the desktop on the other end is returned if `wrap` is true,
+/// otherwise `Error::DesktopNotFound`.
+pub fn get_adjacent_desktop<T>(desktop: T, direction: Direction, wrap: bool) -> Result<Desktop>
+where
+    T: Into<Desktop>,
+    T: Send + 'static + Copy,
+{
+    with_backend(
+        move |b| match b.get_adjacent_desktop(desktop.into(), direction)? {
+            Some(adjacent) => Ok(adjacent),
+            None if wrap => {
+                let index = match direction {
+                    Direction::Left => b.get_desktop_count()? - 1,
+                    Direction::Right => 0,
+                };
+                Ok(Desktop::from(b.get_desktop_id(Desktop::from(index))?))
+            }
+            None => Err(Error::DesktopNotFound),
+        },
+    )
+}
+
+/// Get the desktop on the right of the current desktop, see
+/// `get_adjacent_desktop`
+pub fn next_desktop(wrap: bool) -> Result<Desktop> {
+    get_adjacent_desktop(get_current_desktop()?, Direction::Right, wrap)
+}
+
+/// Get the desktop on the left of the current desktop, see
+/// `get_adjacent_desktop`
+pub fn previous_desktop(wrap: bool) -> Result<Desktop> {
+    get_adjacent_desktop(get_current_desktop()?, Direction::Left, wrap)
+}
+
+/// Move the window to the desktop on the left or right of its desktop, and
+/// switch to that desktop if `follow` is true. Returns the desktop.
+///
+/// Pinned windows are shown on all desktops, they are not moved, and the
+/// desktop next to the current desktop is used. At the edge
+/// `Error::DesktopNotFound` is returned, and windows that can't be moved
+/// return `Error::WindowNotMovable`.
+pub fn move_window_to_adjacent_desktop<M>(
+    hwnd: impl IntoHwnd<M>,
+    direction: Direction,
+    follow: bool,
+) -> Result<Desktop> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| {
+        let pinned = b.is_pinned_window(hwnd)? || b.is_pinned_app(hwnd)?;
+        let from = if pinned {
+            b.get_current_desktop()?
+        } else {
+            b.get_desktop_by_window(hwnd)?
+        };
+        let target = b
+            .get_adjacent_desktop(from, direction)?
+            .ok_or(Error::DesktopNotFound)?;
+        if !pinned {
+            b.move_window_to_desktop(hwnd, target)?;
+        }
+        if follow {
+            b.switch_desktop(target)?;
+        }
+        Ok(target)
+    })
 }
 
 /// Create desktop
 pub fn create_desktop() -> Result<Desktop> {
-    with_com_objects(|o| o.create_desktop().map(Desktop))
+    invalidating(with_backend(|b| b.create_desktop()))
+}
+
+/// Create desktop and set its name.
+///
+/// If the name can't be set, the new desktop is removed again and the error
+/// is returned, so an unnamed desktop is not left behind.
+pub fn create_desktop_with_name(name: &str) -> Result<Desktop> {
+    let name = name.to_owned();
+    invalidating(with_backend(move |b| {
+        let desktop = b.create_desktop()?;
+        if let Err(err) = b.set_desktop_name(desktop, &name) {
+            // The new desktop is never the current one, nothing moves
+            let _ = b.remove_desktop(desktop, b.get_current_desktop()?);
+            return Err(err);
+        }
+        Ok(desktop)
+    }))
 }
 
 /// Get current desktop
 pub fn get_current_desktop() -> Result<Desktop> {
-    with_com_objects(|o| o.get_current_desktop().map(Desktop))
+    with_backend(|b| b.get_current_desktop())
+}
+
+/// Get index, GUID, name and wallpaper of the current desktop in one call
+pub fn current_desktop_info() -> Result<DesktopInfo> {
+    with_backend(|b| b.get_current_desktop_info().map(with_default_name))
 }
 
 /// Get all desktops
 pub fn get_desktops() -> Result<Vec<Desktop>> {
-    with_com_objects(|o| Ok(o.get_desktops()?.into_iter().map(Desktop).collect()))
+    with_backend(|b| b.get_desktops())
+}
+
+/// Get index, GUID, name, wallpaper and remote flag of all desktops, gathered
+/// in one pass over the desktop list
+pub fn list_desktops_detailed() -> Result<Vec<DesktopInfo>> {
+    with_backend(|b| {
+        Ok(b.get_desktop_infos()?
+            .into_iter()
+            .map(with_default_name)
+            .collect())
+    })
+}
+
+/// How `get_desktop_by_name` compares the names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameMatch {
+    /// Compare the names case sensitively, by default "work" matches "Work"
+    pub case_sensitive: bool,
+
+    /// Match desktops whose name starts with the given name, an exact match is
+    /// still preferred
+    pub prefix: bool,
+}
+
+/// Get the first desktop with the name.
+///
+/// Unnamed desktops match their default name, e.g. "Desktop 2", see
+/// `set_unnamed_desktop_names`.
+pub fn get_desktop_by_name(name: &str, options: NameMatch) -> Result<Desktop> {
+    let desktops = list_desktops_detailed()?;
+    find_by_name(&desktops, name, options)
+        .map(|info| DesktopInternal::IndexGuid(info.index, info.guid).into())
+        .ok_or(Error::DesktopNotFound)
+}
+
+/// Switch to the first desktop with the name, see `get_desktop_by_name`
+pub fn switch_desktop_by_name(name: &str, options: NameMatch) -> Result<()> {
+    switch_desktop(get_desktop_by_name(name, options)?)
+}
+
+fn find_by_name<'a>(
+    desktops: &'a [DesktopInfo],
+    name: &str,
+    options: NameMatch,
+) -> Option<&'a DesktopInfo> {
+    let normalize = |text: &str| {
+        if options.case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    };
+    let name = normalize(name);
+    desktops
+        .iter()
+        .find(|info| normalize(&info.name) == name)
+        .or_else(|| {
+            desktops
+                .iter()
+                .find(|info| options.prefix && normalize(&info.name).starts_with(&name))
+        })
 }
 
 /// Get desktop by window
-pub fn get_desktop_by_window(hwnd: HWND) -> Result<Desktop> {
-    with_com_objects(move |o| o.get_desktop_by_window(&hwnd).map(Desktop))
+pub fn get_desktop_by_window<M>(hwnd: impl IntoHwnd<M>) -> Result<Desktop> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.get_desktop_by_window(hwnd))
+}
+
+/// Get the application views of all desktops, in no particular order
+pub fn list_views() -> Result<Vec<ViewInfo>> {
+    with_backend(|b| b.get_view_infos())
+}
+
+/// Flash the taskbar button of the window to request attention, also when the
+/// window is on another desktop
+pub fn flash_window_on_other_desktop<M>(hwnd: impl IntoHwnd<M>) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.flash_window(hwnd))
+}
+
+/// Is the window shown in Alt+Tab, Task View and the taskbar
+pub fn is_window_visible_in_switchers<M>(hwnd: impl IntoHwnd<M>) -> Result<bool> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.is_shown_in_switchers(hwnd))
+}
+
+/// Show or hide the window in Alt+Tab, Task View and the taskbar, e.g. for
+/// helper windows. The window itself stays visible.
+pub fn set_window_visible_in_switchers<M>(hwnd: impl IntoHwnd<M>, visible: bool) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.set_shown_in_switchers(hwnd, visible))
+}
+
+/// Get the AppUserModelID of the window's app, empty if it doesn't have one
+pub fn app_id_of_window<M>(hwnd: impl IntoHwnd<M>) -> Result<String> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.get_app_id(hwnd))
+}
+
+/// Get the windows of an app by its AppUserModelID, see `app_id_of_window`
+pub fn windows_with_app_id(app_id: &str) -> Result<Vec<WindowId>> {
+    let app_id = app_id.to_owned();
+    with_backend(move |b| b.get_windows_by_app_id(&app_id))
+        .map(|hwnds| hwnds.into_iter().map(WindowId::from).collect())
+}
+
+/// Move all windows of an app to the desktop, by the AppUserModelID of the
+/// app. Returns the result of each window, a failed window doesn't stop the
+/// rest from moving.
+pub fn move_app_to_desktop<T>(desktop: T, app_id: &str) -> Result<Vec<(WindowId, Result<()>)>>
+where
+    T: Into<Desktop>,
+    T: Send + 'static + Copy,
+{
+    let app_id = app_id.to_owned();
+    with_backend(move |b| {
+        let desktop = desktop.into();
+        Ok(b.get_windows_by_app_id(&app_id)?
+            .into_iter()
+            .map(|hwnd| (hwnd.into(), b.move_window_to_desktop(hwnd, desktop)))
+            .collect())
+    })
+}
+
+/// Get desktops having windows that request attention, e.g. flashing in the
+/// taskbar
+pub fn desktops_with_urgent_windows() -> Result<Vec<Desktop>> {
+    with_backend(|b| b.get_desktops_with_urgent_windows())
 }
 
 /// Get desktop count
 pub fn get_desktop_count() -> Result<u32> {
-    with_com_objects(|o| o.get_desktop_count())
+    with_backend(|b| b.get_desktop_count())
 }
 
-pub fn is_window_on_current_desktop(hwnd: HWND) -> Result<bool> {
-    with_com_objects(move |o| o.is_window_on_current_desktop(&hwnd))
+pub fn is_window_on_current_desktop<M>(hwnd: impl IntoHwnd<M>) -> Result<bool> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.is_window_on_current_desktop(hwnd))
 }
 
 /// Is window pinned?
-pub fn is_pinned_window(hwnd: HWND) -> Result<bool> {
-    with_com_objects(move |o| o.is_pinned_window(&hwnd))
+pub fn is_pinned_window<M>(hwnd: impl IntoHwnd<M>) -> Result<bool> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.is_pinned_window(hwnd))
 }
 
 /// Pin window
-pub fn pin_window(hwnd: HWND) -> Result<()> {
-    with_com_objects(move |o| o.pin_window(&hwnd))
+pub fn pin_window<M>(hwnd: impl IntoHwnd<M>) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.pin_window(hwnd))
 }
 
 /// Unpin window
-pub fn unpin_window(hwnd: HWND) -> Result<()> {
-    with_com_objects(move |o| o.unpin_window(&hwnd))
+pub fn unpin_window<M>(hwnd: impl IntoHwnd<M>) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.unpin_window(hwnd))
 }
 
 /// Is pinned app
-pub fn is_pinned_app(hwnd: HWND) -> Result<bool> {
-    with_com_objects(move |o| o.is_pinned_app(&hwnd))
+pub fn is_pinned_app<M>(hwnd: impl IntoHwnd<M>) -> Result<bool> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.is_pinned_app(hwnd))
 }
 
 /// Pin app
-pub fn pin_app(hwnd: HWND) -> Result<()> {
-    with_com_objects(move |o| o.pin_app(&hwnd))
+pub fn pin_app<M>(hwnd: impl IntoHwnd<M>) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.pin_app(hwnd))
 }
 
 /// Unpin app
-pub fn unpin_app(hwnd: HWND) -> Result<()> {
-    with_com_objects(move |o| o.unpin_app(&hwnd))
+pub fn unpin_app<M>(hwnd: impl IntoHwnd<M>) -> Result<()> {
+    let hwnd = hwnd.into_hwnd()?;
+    with_backend(move |b| b.unpin_app(hwnd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_by_name() {
+        let desktops: Vec<DesktopInfo> = ["Work mail", "Work", "Games"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| DesktopInfo {
+                guid: GUID::from_u128(index as u128),
+                index: index as u32,
+                name: name.to_string(),
+                wallpaper: String::new(),
+                is_remote: false,
+            })
+            .collect();
+        let find = |name, case_sensitive, prefix| {
+            let options = NameMatch {
+                case_sensitive,
+                prefix,
+            };
+            find_by_name(&desktops, name, options).map(|info| info.index)
+        };
+
+        assert_eq!(find("work", false, false), Some(1));
+        assert_eq!(find("work", true, false), None);
+        assert_eq!(find("gam", false, false), None);
+        assert_eq!(find("gam", false, true), Some(2));
+
+        // Exact match wins over an earlier prefix match
+        assert_eq!(find("Work", true, true), Some(1));
+        assert_eq!(find("work m", false, true), Some(0));
+    }
+
+    #[test]
+    fn test_resolve_fallback() {
+        use FallbackPolicy::*;
+
+        assert_eq!(resolve_fallback(Left, 2, 0, 4), Ok(1));
+        assert_eq!(resolve_fallback(Left, 0, 0, 4), Ok(1));
+        assert_eq!(resolve_fallback(Right, 2, 0, 4), Ok(3));
+        assert_eq!(resolve_fallback(Right, 3, 0, 4), Ok(2));
+        assert_eq!(resolve_fallback(Current, 2, 0, 4), Ok(0));
+        assert_eq!(resolve_fallback(Current, 2, 2, 4), Ok(1));
+        assert_eq!(resolve_fallback(Index(3), 2, 0, 4), Ok(3));
+        assert_eq!(
+            resolve_fallback(Index(2), 2, 0, 4),
+            Err(Error::RemoveDesktopFailed)
+        );
+        assert_eq!(
+            resolve_fallback(Index(4), 2, 0, 4),
+            Err(Error::DesktopNotFound)
+        );
+        assert_eq!(resolve_fallback(Left, 0, 0, 1), Err(Error::LastDesktop));
+    }
+
+    #[test]
+    fn test_parse_desktop_id() {
+        let guid = GUID::from_u128(0x1e1b0d56_0b3a_4d1c_8c8e_5a2f3b4c6d7e);
+        let parse = |text| parse_desktop_id(text).map(DesktopInternal::from);
+
+        assert!(matches!(parse(" 2 "), Some(DesktopInternal::Index(2))));
+        for text in [
+            "1E1B0D56-0B3A-4D1C-8C8E-5A2F3B4C6D7E",
+            "{1e1b0d56-0b3a-4d1c-8c8e-5a2f3b4c6d7e}",
+            "1: Work {1E1B0D56-0B3A-4D1C-8C8E-5A2F3B4C6D7E}",
+        ] {
+            assert!(
+                matches!(parse(text), Some(DesktopInternal::Guid(g)) if g == guid),
+                "{}",
+                text
+            );
+        }
+        assert!(parse("Work").is_none());
+        assert!(parse("-1").is_none());
+    }
 }
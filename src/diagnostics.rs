@@ -0,0 +1,174 @@
+//! Which COM interfaces are used, and does the current Windows build support
+//! them.
+//!
+//! Useful for showing a meaningful message to the user, e.g. "Windows build
+//! 26100 is newer than the latest supported build 22631", instead of an opaque
+//! COM error.
+use crate::backend::with_backend;
+#[cfg(feature = "registry")]
+use crate::registry::read_windows_version;
+use crate::{Error, Result};
+
+/// The single version `interfaces` module matches this version
+#[cfg(not(feature = "multiple-windows-versions"))]
+const SINGLE_VERSION: (&str, (u32, u32)) = ("build_22631_3155", (22631, 3155));
+
+/// Oldest and latest `(build, patch)` using the IIDs of the single version
+/// `interfaces` module. 22621 and 22631 share the patches, and 22621.3155 got
+/// the same IIDs as 22631.3155. 22631 patches before 3155 used other IIDs,
+/// but only the oldest patch is compared.
+#[cfg(not(feature = "multiple-windows-versions"))]
+const SINGLE_VERSION_RANGE: ((u32, u32), (u32, u32)) = ((22621, 3155), SINGLE_VERSION.1);
+
+/// Does the current Windows build have supported COM interfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupportStatus {
+    /// Interfaces for this build are known
+    Supported,
+
+    /// Windows build is newer than the latest supported interfaces. They may
+    /// work if Windows didn't change them.
+    NewerThanSupported {
+        windows: (u32, u32),
+        latest_supported: (u32, u32),
+    },
+
+    /// Windows build is older than the oldest supported interfaces
+    OlderThanSupported {
+        windows: (u32, u32),
+        oldest_supported: (u32, u32),
+    },
+
+    /// Windows version could not be read
+    Unknown,
+}
+
+/// How wallpapers work on the current Windows version, see `wallpaper_support`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallpaperSupport {
+    /// Each desktop has its own wallpaper
+    PerDesktop,
+
+    /// All desktops share one wallpaper. `Desktop::set_wallpaper` only works
+    /// for the current desktop, and `Desktop::get_wallpaper` fails.
+    Shared,
+}
+
+/// Module name and `(build, patch)` of the COM interfaces in use, e.g.
+/// `("build_22631_3155", (22631, 3155))`
+pub fn interface_version_in_use() -> (&'static str, (u32, u32)) {
+    #[cfg(feature = "multiple-windows-versions")]
+    return crate::interfaces_multi::version_in_use();
+
+    #[cfg(not(feature = "multiple-windows-versions"))]
+    return SINGLE_VERSION;
+}
+
+/// Check if the desktops have their own wallpapers, by reading the wallpaper
+/// of the current desktop
+pub fn wallpaper_support() -> Result<WallpaperSupport> {
+    with_backend(
+        |b| match b.get_desktop_wallpaper(b.get_current_desktop()?) {
+            Ok(_) => Ok(WallpaperSupport::PerDesktop),
+            Err(Error::ComNotImplemented) => Ok(WallpaperSupport::Shared),
+            Err(err) => Err(err),
+        },
+    )
+}
+
+/// Compare the Windows build against the supported interfaces
+pub fn is_current_windows_supported() -> SupportStatus {
+    #[cfg(feature = "multiple-windows-versions")]
+    let (oldest, latest) = crate::interfaces_multi::supported_range();
+
+    #[cfg(not(feature = "multiple-windows-versions"))]
+    let (oldest, latest) = SINGLE_VERSION_RANGE;
+
+    match windows_version() {
+        Some(windows) => support_status(windows, oldest, latest),
+        None => SupportStatus::Unknown,
+    }
+}
+
+/// Windows `(build, patch)` from the registry
+#[cfg(feature = "registry")]
+pub(crate) fn windows_version() -> Option<(u32, u32)> {
+    read_windows_version().ok()
+}
+
+/// Windows version is only read from the registry
+#[cfg(not(feature = "registry"))]
+pub(crate) fn windows_version() -> Option<(u32, u32)> {
+    None
+}
+
+/// Patch versions are compared only against the oldest version, newer patches
+/// rarely change the interfaces
+fn support_status(windows: (u32, u32), oldest: (u32, u32), latest: (u32, u32)) -> SupportStatus {
+    if windows < oldest {
+        SupportStatus::OlderThanSupported {
+            windows,
+            oldest_supported: oldest,
+        }
+    } else if windows.0 > latest.0 {
+        SupportStatus::NewerThanSupported {
+            windows,
+            latest_supported: latest,
+        }
+    } else {
+        SupportStatus::Supported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_support_status() {
+        let (oldest, latest) = ((10240, 0), (22631, 3155));
+        assert_eq!(
+            support_status((22631, 4000), oldest, latest),
+            SupportStatus::Supported
+        );
+        assert_eq!(
+            support_status((26100, 1), oldest, latest),
+            SupportStatus::NewerThanSupported {
+                windows: (26100, 1),
+                latest_supported: latest,
+            }
+        );
+        assert_eq!(
+            support_status((9600, 0), oldest, latest),
+            SupportStatus::OlderThanSupported {
+                windows: (9600, 0),
+                oldest_supported: oldest,
+            }
+        );
+    }
+
+    #[cfg(not(feature = "multiple-windows-versions"))]
+    #[test]
+    fn test_single_version_range() {
+        let (oldest, latest) = SINGLE_VERSION_RANGE;
+        assert_eq!(
+            support_status((22621, 3155), oldest, latest),
+            SupportStatus::Supported
+        );
+        assert_eq!(
+            support_status((22621, 4317), oldest, latest),
+            SupportStatus::Supported
+        );
+        assert_eq!(
+            support_status((22631, 3155), oldest, latest),
+            SupportStatus::Supported
+        );
+        assert_eq!(
+            support_status((22621, 2215), oldest, latest),
+            SupportStatus::OlderThanSupported {
+                windows: (22621, 2215),
+                oldest_supported: (22621, 3155),
+            }
+        );
+    }
+}
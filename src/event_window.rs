@@ -0,0 +1,181 @@
+//! Desktop events delivered through a message-only window.
+//!
+//! Applications with their own Win32 message loop, e.g. Win32, WinUI or WPF
+//! hosts, can receive the events on their UI thread without a thread draining
+//! a channel. The window is created on the calling thread, and the callback is
+//! called when that thread's message loop dispatches the event messages.
+//!
+//! ```rust,no_run
+//! let _events = winvd::DesktopEventWindow::new(|event| {
+//!     println!("{:?}", event);
+//! })
+//! .unwrap();
+//! // Run the message loop of the thread as usual
+//! ```
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetWindowLongPtrW, PeekMessageW,
+    RegisterClassW, SetWindowLongPtrW, GWLP_USERDATA, HWND_MESSAGE, MSG, PM_REMOVE,
+    WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WNDCLASSW,
+};
+
+use crate::{
+    DesktopEvent, DesktopEventSender, DesktopEventThread, Error, ListenerOptions, Result,
+    WindowTarget,
+};
+
+/// Message posted to the window for each event, see
+/// `WindowTarget`
+pub const WM_DESKTOP_EVENT: u32 = WM_APP + 3;
+
+const EVENT_WINDOW_CLASS: PCWSTR = w!("winvd_event_window");
+
+struct WindowState {
+    callback: RefCell<Box<dyn FnMut(DesktopEvent)>>,
+
+    /// Events received while the callback runs a nested message loop
+    pending: RefCell<VecDeque<DesktopEvent>>,
+}
+
+/// Message-only window receiving desktop events, see the module docs.
+///
+/// Dropping the value stops the listener and destroys the window, it must be
+/// dropped on the thread that created it.
+pub struct DesktopEventWindow {
+    hwnd: HWND,
+    listener: Option<DesktopEventThread>,
+    state: *mut WindowState,
+}
+
+impl DesktopEventWindow {
+    /// Creates the window on the calling thread, and starts listening
+    pub fn new<F>(callback: F) -> Result<Self>
+    where
+        F: FnMut(DesktopEvent) + 'static,
+    {
+        Self::with_options(callback, ListenerOptions::default())
+    }
+
+    /// Creates the window with listener options, see `new`
+    pub fn with_options<F>(callback: F, options: ListenerOptions) -> Result<Self>
+    where
+        F: FnMut(DesktopEvent) + 'static,
+    {
+        let hwnd = create_event_window()?;
+        let state = Box::into_raw(Box::new(WindowState {
+            callback: RefCell::new(Box::new(callback)),
+            pending: RefCell::new(VecDeque::new()),
+        }));
+        unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, state as isize) };
+
+        let mut window = DesktopEventWindow {
+            hwnd,
+            listener: None,
+            state,
+        };
+        // The window procedure frees the events posted with WM_DESKTOP_EVENT
+        let target = unsafe { WindowTarget::new(hwnd, WM_DESKTOP_EVENT) };
+        let sender = DesktopEventSender::<DesktopEvent>::Window(target);
+        window.listener = Some(DesktopEventThread::new(sender, options)?);
+        Ok(window)
+    }
+
+    /// Handle of the message-only window
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+}
+
+impl Drop for DesktopEventWindow {
+    fn drop(&mut self) {
+        // Stop sending before the window is destroyed
+        drop(self.listener.take());
+        unsafe {
+            SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, 0);
+
+            // Free the events nobody is going to receive
+            let mut msg = MSG::default();
+            while PeekMessageW(
+                &mut msg,
+                self.hwnd,
+                WM_DESKTOP_EVENT,
+                WM_DESKTOP_EVENT,
+                PM_REMOVE,
+            )
+            .as_bool()
+            {
+                drop(Box::from_raw(msg.lParam.0 as *mut DesktopEvent));
+            }
+            let _ = DestroyWindow(self.hwnd);
+            drop(Box::from_raw(self.state));
+        }
+    }
+}
+
+fn create_event_window() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(event_window_proc),
+            hInstance: instance.into(),
+            lpszClassName: EVENT_WINDOW_CLASS,
+            ..Default::default()
+        };
+
+        // Fails if the class is already registered by another window, which is fine
+        RegisterClassW(&class);
+
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            EVENT_WINDOW_CLASS,
+            PCWSTR::null(),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            instance,
+            None,
+        );
+        if hwnd == HWND::default() {
+            return Err(Error::from(windows::core::Error::from_win32()));
+        }
+        Ok(hwnd)
+    }
+}
+
+unsafe extern "system" fn event_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_DESKTOP_EVENT {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    let event = Box::from_raw(lparam.0 as *mut DesktopEvent);
+    let state = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowState;
+    let Some(state) = state.as_ref() else {
+        return LRESULT(0);
+    };
+    state.pending.borrow_mut().push_back(*event);
+
+    // If the callback is already running, the outer call delivers the event
+    if let Ok(mut callback) = state.callback.try_borrow_mut() {
+        loop {
+            let Some(event) = state.pending.borrow_mut().pop_front() else {
+                break;
+            };
+            callback(event);
+        }
+    }
+    LRESULT(0)
+}
@@ -0,0 +1,99 @@
+//! Windows Event Log output, enabled with the `eventlog` feature.
+//!
+//! Writes the diagnostics of the crate, e.g. the listener registrations, the
+//! COM interface version chosen and failed COM calls, to the Application log,
+//! so deployments can be troubleshot from the Event Viewer without attaching a
+//! debugger. This is independent of `set_log_callback`, both receive the
+//! messages.
+//!
+//! The event ID is the level of the message: 1 error, 2 warning, 3 info and
+//! 4 debug. Without a message file registered for the source the Event Viewer
+//! notes that the description of the event is missing, and shows the message
+//! below it.
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HANDLE, PSID};
+use windows::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+};
+
+use crate::log::LogLevel;
+use crate::Result;
+
+/// Event source, `None` when not enabled
+static SOURCE: Mutex<Option<HANDLE>> = Mutex::new(None);
+
+/// Most verbose level written, 0 when not enabled
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Writes the messages up to `max_level` to the Application log with the event
+/// source name, e.g. `enable_event_log("MyApp", LogLevel::Info)` leaves out
+/// the debug messages. Enabling again replaces the source.
+///
+/// ```rust,no_run
+/// winvd::enable_event_log("MyApp", winvd::LogLevel::Warn).unwrap();
+/// ```
+pub fn enable_event_log(source: &str, max_level: LogLevel) -> Result<()> {
+    let handle = unsafe { RegisterEventSourceW(PCWSTR::null(), &HSTRING::from(source))? };
+    let mut current = lock();
+    if let Some(old) = current.replace(handle) {
+        let _ = unsafe { DeregisterEventSource(old) };
+    }
+    MAX_LEVEL.store(max_level as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Stops writing to the Event Log
+pub fn disable_event_log() {
+    MAX_LEVEL.store(0, Ordering::Relaxed);
+    if let Some(old) = lock().take() {
+        let _ = unsafe { DeregisterEventSource(old) };
+    }
+}
+
+pub(crate) fn is_enabled(level: LogLevel) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+pub(crate) fn report(level: LogLevel, s: &str) {
+    if !is_enabled(level) {
+        return;
+    }
+    // Held while reporting, so the source isn't deregistered meanwhile
+    let source = lock();
+    let Some(handle) = *source else {
+        return;
+    };
+    let message = HSTRING::from(s);
+    let strings = [PCWSTR::from_raw(message.as_ptr())];
+    // Nothing to do if the log is full or gone
+    let _ = unsafe {
+        ReportEventW(
+            handle,
+            event_type(level),
+            0,
+            level as u32,
+            PSID::default(),
+            0,
+            Some(&strings),
+            None,
+        )
+    };
+}
+
+fn event_type(level: LogLevel) -> REPORT_EVENT_TYPE {
+    match level {
+        LogLevel::Error => EVENTLOG_ERROR_TYPE,
+        LogLevel::Warn => EVENTLOG_WARNING_TYPE,
+        LogLevel::Info | LogLevel::Debug => EVENTLOG_INFORMATION_TYPE,
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<HANDLE>> {
+    SOURCE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
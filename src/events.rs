@@ -1,7 +1,13 @@
 use crate::Desktop;
 use crate::DesktopEventThread;
+use crate::DesktopInfo;
 use crate::Error;
-use windows::Win32::Foundation::HWND;
+use crate::WindowId;
+use std::sync::Arc;
+use std::time::Duration;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
 
 #[derive(Clone)]
 pub enum DesktopEventSender<T>
@@ -13,8 +19,36 @@ where
     #[cfg(feature = "crossbeam-channel")]
     Crossbeam(crossbeam_channel::Sender<T>),
 
+    /// Sends the events as user events to the winit event loop, so they
+    /// arrive in `ApplicationHandler::user_event` without a bridging thread.
+    /// Create the loop with `EventLoop::<T>::with_user_event()`.
     #[cfg(feature = "winit")]
     Winit(winit::event_loop::EventLoopProxy<T>),
+
+    /// Posts the events to a window, see `WindowTarget`. See
+    /// `DesktopEventWindow` for a ready window.
+    Window(WindowTarget),
+}
+
+/// Window receiving the events of `DesktopEventSender::Window`
+#[derive(Debug, Clone, Copy)]
+pub struct WindowTarget {
+    hwnd: HWND,
+    message: u32,
+}
+
+impl WindowTarget {
+    /// Posts `message` to `hwnd` for each event, `lParam` is a pointer from
+    /// `Box::<T>::into_raw`
+    ///
+    /// # Safety
+    ///
+    /// The window procedure of `hwnd` must take each `message` as the
+    /// `Box<T>` of the sender, and free it with `Box::from_raw`. The
+    /// `message` must not be used for anything else while events are sent.
+    pub unsafe fn new(hwnd: HWND, message: u32) -> Self {
+        WindowTarget { hwnd, message }
+    }
 }
 
 // From STD Sender
@@ -65,11 +99,28 @@ impl<T> DesktopEventSender<T> {
             DesktopEventSender::Winit(sender) => {
                 let _ = sender.send_event(event);
             }
+
+            DesktopEventSender::Window(target) => {
+                let event = Box::into_raw(Box::new(event));
+                let res = unsafe {
+                    PostMessageW(
+                        target.hwnd,
+                        target.message,
+                        WPARAM(0),
+                        LPARAM(event as isize),
+                    )
+                };
+                if res.is_err() {
+                    // Window is gone, nobody else frees the event
+                    drop(unsafe { Box::from_raw(event) });
+                }
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DesktopEvent {
     DesktopCreated(Desktop),
     DesktopDestroyed {
@@ -87,7 +138,94 @@ pub enum DesktopEvent {
         old_index: i64,
         new_index: i64,
     },
-    WindowChanged(HWND),
+    WindowChanged(WindowId),
+
+    /// Monitors were added or removed, contains the current monitors. Monitor
+    /// handles cached before this event may not be valid anymore.
+    ///
+    /// Which monitors were added or removed is not reported, compare with the
+    /// previous list, and read `Desktop::monitor` again for the desktops.
+    MonitorsChanged(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::hmonitors"))]
+        Vec<HMONITOR>,
+    ),
+
+    /// State of the desktops right after the listener was registered, sent
+    /// only if `ListenerOptions::initial_state` is set
+    InitialState {
+        current: Desktop,
+        desktops: Vec<DesktopInfo>,
+    },
+
+    /// Listener had to register again, most likely because explorer.exe was
+    /// restarted. Contains a fresh list of the desktops.
+    ConnectionReset {
+        desktops: Vec<Desktop>,
+    },
+
+    /// Events were dropped because the receiver fell too far behind, see
+    /// `ListenerOptions::on_error`. Contains a fresh list of the desktops,
+    /// state kept from the earlier events should be read again. Unlike after
+    /// `ConnectionReset`, explorer.exe was not restarted.
+    EventsDropped {
+        desktops: Vec<Desktop>,
+    },
+}
+
+/// Called with errors of the listener thread, see `ListenerOptions::on_error`
+pub type ListenerErrorHook = Arc<dyn Fn(Error) + Send + Sync>;
+
+/// Options for `listen_desktop_events_with_options`
+#[derive(Clone, Default)]
+pub struct ListenerOptions {
+    /// Send `DesktopEvent::InitialState` right after the listener is
+    /// registered, before any other events.
+    ///
+    /// The snapshot is taken after registering, so no change is missed
+    /// between the snapshot and the live events, but a change happening at the
+    /// same time may be seen in both.
+    pub initial_state: bool,
+
+    /// Called from the listener thread in two cases:
+    ///
+    /// - Registering the listener fails. Listener tries to register again
+    ///   every few seconds, and this is called for each failed attempt.
+    /// - The receiver falls too far behind and events are dropped, with
+    ///   `Error::EventsDropped`, once until there is room again.
+    ///   `DesktopEvent::EventsDropped` is sent within a few seconds after that.
+    ///
+    /// In the second case the hook is called inside the COM notification
+    /// callback, explorer.exe waits for it. The hook must return quickly and
+    /// not block, e.g. send the error to a channel instead of handling it
+    /// there.
+    pub on_error: Option<ListenerErrorHook>,
+
+    /// While registering the listener fails, e.g. on an unsupported Windows
+    /// build, poll the virtual desktops from the registry at this interval and
+    /// send `DesktopCreated`, `DesktopDestroyed` and `DesktopChanged` events
+    /// for the changes.
+    ///
+    /// Defaults to `None`, no fallback, e.g. `Some(Duration::from_secs(1))`
+    /// enables it.
+    pub registry_poll_interval: Option<Duration>,
+}
+
+impl ListenerOptions {
+    /// Sets the `on_error` hook
+    pub fn on_error(mut self, hook: impl Fn(Error) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl std::fmt::Debug for ListenerOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListenerOptions")
+            .field("initial_state", &self.initial_state)
+            .field("on_error", &self.on_error.is_some())
+            .field("registry_poll_interval", &self.registry_poll_interval)
+            .finish()
+    }
 }
 
 /// Create event sending thread, give this `crossbeam_channel::Sender<T>`,
@@ -98,6 +236,10 @@ pub enum DesktopEvent {
 /// This function returns `DesktopEventThread`, which must be kept alive. When
 /// the value is dropped the listener is closed and thread joined.
 ///
+/// Events are sent from a worker thread, not from the notification callbacks.
+/// If the receiver falls behind by a few hundred events, further events are
+/// dropped until it catches up.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -118,5 +260,32 @@ where
     T: From<DesktopEvent> + Clone + Send + 'static,
     S: Into<DesktopEventSender<T>> + Clone,
 {
-    DesktopEventThread::new(sender.into())
+    DesktopEventThread::new(sender.into(), ListenerOptions::default())
+}
+
+/// Create event sending thread with options, see `listen_desktop_events`
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let (tx, rx) = std::sync::mpsc::channel::<winvd::DesktopEvent>();
+/// let options = winvd::ListenerOptions {
+///     initial_state: true,
+///     ..Default::default()
+/// };
+/// let _notifications_thread = winvd::listen_desktop_events_with_options(tx, options);
+/// // First item is `DesktopEvent::InitialState`
+/// for item in rx {
+///    println!("{:?}", item);
+/// }
+/// ```
+pub fn listen_desktop_events_with_options<T, S>(
+    sender: S,
+    options: ListenerOptions,
+) -> Result<DesktopEventThread, Error>
+where
+    T: From<DesktopEvent> + Clone + Send + 'static,
+    S: Into<DesktopEventSender<T>> + Clone,
+{
+    DesktopEventThread::new(sender.into(), options)
 }
@@ -0,0 +1,144 @@
+//! History of the visited desktops, for switching back like alt-tab.
+//!
+//! ```rust,no_run
+//! let history = winvd::DesktopHistory::new(10).unwrap();
+//! // ... the user switches desktops ...
+//! history.switch_back().unwrap();
+//! ```
+//!
+//! `toggle_desktops` binds a single hotkey to going back and forth between
+//! two desktops.
+//!
+//! Only the switches while the history lives are recorded. Removed desktops
+//! are dropped from the history.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::listener::DesktopEventHandler;
+use crate::{Desktop, DesktopEvent, Error, Result};
+
+/// Records the desktops switched from, see the module docs
+pub struct DesktopHistory {
+    desktops: Arc<Mutex<VecDeque<Desktop>>>,
+    capacity: usize,
+    _events: DesktopEventHandler,
+}
+
+impl DesktopHistory {
+    /// Starts the listener, keeping the `capacity` most recently visited
+    /// desktops
+    pub fn new(capacity: usize) -> Result<Self> {
+        let desktops = Arc::new(Mutex::new(VecDeque::new()));
+        let thread_desktops = desktops.clone();
+        let events = DesktopEventHandler::new(move |event| match event {
+            DesktopEvent::DesktopChanged { new, old } => {
+                visit(&mut lock(&thread_desktops), capacity, old, new)
+            }
+            DesktopEvent::DesktopDestroyed { destroyed, .. } => {
+                lock(&thread_desktops).retain(|desktop| *desktop != destroyed)
+            }
+            // Desktops may have been removed meanwhile
+            DesktopEvent::ConnectionReset { desktops }
+            | DesktopEvent::EventsDropped { desktops } => {
+                lock(&thread_desktops).retain(|desktop| desktops.contains(desktop))
+            }
+            _ => {}
+        })?;
+
+        Ok(DesktopHistory {
+            desktops,
+            capacity,
+            _events: events,
+        })
+    }
+
+    /// Desktops switched from, most recent first. The current desktop is
+    /// left out.
+    pub fn history(&self) -> Vec<Desktop> {
+        lock(&self.desktops).iter().copied().collect()
+    }
+
+    /// Switches to the most recently visited desktop, and returns it. Fails
+    /// with `Error::DesktopNotFound` if there is no desktop to go back to.
+    pub fn switch_back(&self) -> Result<Desktop> {
+        let current = crate::get_current_desktop()?;
+        let mut desktops = lock(&self.desktops);
+        while let Some(desktop) = desktops.pop_front() {
+            match crate::switch_desktop(desktop) {
+                Ok(()) => {
+                    // Recorded right away, in case of switching back again
+                    // before the event arrives
+                    visit(&mut desktops, self.capacity, current, desktop);
+                    return Ok(desktop);
+                }
+                // Removed while the event was on its way
+                Err(Error::DesktopNotFound) => continue,
+                Err(err) => {
+                    desktops.push_front(desktop);
+                    return Err(err);
+                }
+            }
+        }
+        Err(Error::DesktopNotFound)
+    }
+
+    /// Switches to `b` if `a` is the current desktop, otherwise to `a`, and
+    /// returns the desktop switched to. Without `b`, toggles between `a` and
+    /// the most recently visited desktop, see `switch_back`.
+    ///
+    /// The switch is recorded right away, so a hotkey bound to this can be
+    /// pressed repeatedly without waiting for the events.
+    pub fn toggle_desktops<T>(&self, a: T, b: Option<T>) -> Result<Desktop>
+    where
+        T: Into<Desktop>,
+    {
+        let a = a.into();
+        let current = crate::get_current_desktop()?;
+        if current != a {
+            crate::switch_desktop(a)?;
+            visit(&mut lock(&self.desktops), self.capacity, current, a);
+            return Ok(a);
+        }
+        match b {
+            Some(b) => {
+                let b = b.into();
+                crate::switch_desktop(b)?;
+                visit(&mut lock(&self.desktops), self.capacity, current, b);
+                Ok(b)
+            }
+            None => self.switch_back(),
+        }
+    }
+}
+
+/// Records a switch from `old` to `new`, generic for testing without COM
+fn visit<T: PartialEq>(desktops: &mut VecDeque<T>, capacity: usize, old: T, new: T) {
+    desktops.retain(|desktop| *desktop != old && *desktop != new);
+    desktops.push_front(old);
+    desktops.truncate(capacity);
+}
+
+fn lock(desktops: &Mutex<VecDeque<Desktop>>) -> MutexGuard<'_, VecDeque<Desktop>> {
+    desktops
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit() {
+        let mut desktops = VecDeque::new();
+        visit(&mut desktops, 2, 1, 2);
+        visit(&mut desktops, 2, 2, 3);
+        assert_eq!(desktops, [2, 1]);
+
+        // Going back moves the desktop to the front, the oldest is dropped
+        visit(&mut desktops, 2, 3, 1);
+        assert_eq!(desktops, [3, 2]);
+        visit(&mut desktops, 2, 1, 4);
+        assert_eq!(desktops, [1, 3]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Runtime overrides for the IIDs of the virtual desktop services.
+//!
+//! Windows patch releases often change only the IID of an interface. Instead of
+//! waiting for a new release, the IID can be overridden with environment
+//! variables, e.g.
+//!
+//! ```text
+//! VDA_IID_IVirtualDesktopManagerInternal=53F5CA0B-158F-4124-900C-057158060B27
+//! ```
+//!
+//! or with a file given in `VDA_IID_FILE`, or loaded with
+//! `load_iid_overrides`. The file has one `Name = GUID` line per interface,
+//! the GUID may be quoted, so the file can also be read as flat TOML:
+//!
+//! ```text
+//! # Windows 11 23H2
+//! IVirtualDesktopManagerInternal = "53F5CA0B-158F-4124-900C-057158060B27"
+//! IApplicationViewCollection = 1841C6D7-4F9D-42C0-AF41-8747538F10E5
+//! ```
+//!
+//! Empty lines and lines starting with `#` are skipped, any other line is an
+//! error, e.g. tables or JSON.
+//!
+//! Overrides apply to the services queried from explorer.exe:
+//! `IVirtualDesktopManagerInternal`, `IVirtualDesktopNotificationService`,
+//! `IVirtualDesktopPinnedApps` and `IApplicationViewCollection`. The method
+//! layout of the interface is not changed, so this helps only when the methods
+//! stayed the same.
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use windows::core::GUID;
+
+use crate::comobjects::parse_guid;
+use crate::{Error, Result};
+
+const ENV_PREFIX: &str = "VDA_IID_";
+const ENV_FILE: &str = "VDA_IID_FILE";
+
+static OVERRIDES: Mutex<Option<HashMap<String, GUID>>> = Mutex::new(None);
+
+/// Override the IID of the interface, e.g. `"IVirtualDesktopManagerInternal"`.
+///
+/// Services already created are not affected, the override is used when the
+/// services are created again, e.g. in a new thread or after explorer.exe
+/// restarts.
+pub fn set_iid_override(interface: &str, iid: GUID) {
+    with_overrides(|overrides| {
+        overrides.insert(interface.to_string(), iid);
+    });
+}
+
+/// Load IID overrides from a file of `Name = GUID` lines, see the module docs
+/// and `set_iid_override`. Invalid files return
+/// `Error::FileError(ErrorKind::InvalidData)`, and no overrides are loaded.
+pub fn load_iid_overrides(path: impl AsRef<Path>) -> Result<()> {
+    let text = std::fs::read_to_string(path).map_err(|err| Error::FileError(err.kind()))?;
+    let parsed = parse_overrides(&text).map_err(|_line| {
+        log_format!(Warn: "Invalid IID override on line {}", _line);
+        Error::FileError(std::io::ErrorKind::InvalidData)
+    })?;
+    with_overrides(|overrides| overrides.extend(parsed));
+    Ok(())
+}
+
+/// Returns the overridden IID of the interface, or the default
+pub(crate) fn iid_or(interface: &str, default: GUID) -> GUID {
+    match with_overrides(|overrides| overrides.get(interface).copied()) {
+        Some(iid) => {
+            log_format!("Using IID override {:?} for {}", iid, interface);
+            iid
+        }
+        None => default,
+    }
+}
+
+fn with_overrides<T>(f: impl FnOnce(&mut HashMap<String, GUID>) -> T) -> T {
+    let mut overrides = OVERRIDES.lock().unwrap_or_else(|err| err.into_inner());
+    f(overrides.get_or_insert_with(overrides_from_env))
+}
+
+/// Overrides from the `VDA_IID_FILE` file, and from the environment variables
+/// which take precedence
+fn overrides_from_env() -> HashMap<String, GUID> {
+    let mut overrides = HashMap::new();
+    if let Some(path) = std::env::var_os(ENV_FILE) {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => match parse_overrides(&text) {
+                Ok(parsed) => overrides.extend(parsed),
+                Err(_line) => {
+                    log_format!(Warn: "Invalid IID override on line {} of {:?}", _line, path);
+                }
+            },
+            Err(_err) => {
+                log_format!("Could not read {:?}: {:?}", path, _err);
+            }
+        }
+    }
+    // Variables that are not Unicode can't be overrides, and would make
+    // `std::env::vars` panic
+    let vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    for (name, value) in vars {
+        if name == ENV_FILE {
+            continue;
+        }
+        if let Some(interface) = name.strip_prefix(ENV_PREFIX) {
+            match parse_guid(&value) {
+                Some(iid) => {
+                    overrides.insert(interface.to_string(), iid);
+                }
+                None => {
+                    log_format!("Invalid IID in {}: {}", name, value);
+                }
+            }
+        }
+    }
+    overrides
+}
+
+/// Parses the `Name = GUID` lines of the file, see the module docs. Returns
+/// the number of the first invalid line on errors.
+fn parse_overrides(text: &str) -> std::result::Result<Vec<(String, GUID)>, usize> {
+    let mut overrides = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = line.split_once('=').and_then(|(name, value)| {
+            let name = name.trim();
+            let value = value.trim();
+            let value = match value.strip_prefix('"') {
+                Some(quoted) => quoted.strip_suffix('"')?,
+                None => value,
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return None;
+            }
+            Some((name.to_string(), parse_guid(value)?))
+        });
+        match entry {
+            Some(entry) => overrides.push(entry),
+            None => return Err(number + 1),
+        }
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides() {
+        let iid = GUID::from_u128(0x53F5CA0B_158F_4124_900C_057158060B27);
+        let name = "IVirtualDesktopManagerInternal".to_string();
+        let text = "# comment\n\nIVirtualDesktopManagerInternal = \"53F5CA0B-158F-4124-900C-057158060B27\"\n";
+        assert_eq!(parse_overrides(text), Ok(vec![(name.clone(), iid)]));
+        let text = "IVirtualDesktopManagerInternal={53F5CA0B-158F-4124-900C-057158060B27}";
+        assert_eq!(parse_overrides(text), Ok(vec![(name, iid)]));
+
+        let invalid = Err(1);
+        assert_eq!(parse_overrides("# comment\n[iids]"), Err(2));
+        assert_eq!(parse_overrides("Broken = 1"), invalid);
+        assert_eq!(
+            parse_overrides("= 53F5CA0B-158F-4124-900C-057158060B27"),
+            invalid
+        );
+        assert_eq!(
+            parse_overrides("Name = \"53F5CA0B-158F-4124-900C-057158060B27"),
+            invalid
+        );
+        assert_eq!(
+            parse_overrides("{\"Name\": \"53F5CA0B-158F-4124-900C-057158060B27\"}"),
+            invalid
+        );
+    }
+}
@@ -1,8 +1,10 @@
 //! Interface definitions for the Virtual Desktop API
 //!
 //! Most of the functions are not tested or used, beware if you try to use these
-//! for something else. Notably I know that most out parameters defined as `*mut
-//! IMyObject` are incorrect, they probably should be *mut Option<IMyObject>.
+//! for something else. Out parameters returning COM objects are all `*mut
+//! Option<IMyObject>`, the callee writes an owned pointer or null into them.
+//! Types declared as `UINT` placeholders are not COM objects yet, see
+//! [Unbound interfaces](#unbound-interfaces).
 //!
 //! Generally these are the rules:
 //! 1. InOpt = `Option<ComIn<IMyObject>>` or `Option<ManuallyDrop<IMyObject>>`
@@ -34,76 +36,105 @@
 //!
 //! If you read the rules carefully, ComIn is most common usecase in Rust
 //! API definitions as most parameters are `In` parameters.
+//!
+//! ## Unbound interfaces
+//!
+//! The symbol dump of `twinui.pcshell.dll` lists more virtual desktop classes
+//! than the crate binds. The vtable symbol only tells that the class exists,
+//! not its IID, nor the order and the signatures of the methods. Guessing them
+//! is not safe, calling a wrong vtable slot crashes explorer.exe or the caller.
+//!
+//! Binding one of these needs, for each supported build in
+//! `src/interfaces_multi/`:
+//!
+//! - The IID, e.g. from the `QueryInterface` implementation of the class, or a
+//!   registry or service provider lookup.
+//! - The method order and signatures from the disassembly of the vtable.
+//! - The service id, if it's queried from the immersive shell service provider
+//!   like `IVirtualDesktopManagerInternal`.
+//!
+//! ### IVirtualDesktopHotkeyHandler
+//!
+//! Symbol: `??_7CVirtualDesktopHotkeyHandler@@6B@`
+//!
+//! Handles the built-in Win+Ctrl+Left/Right/D/F4 hotkeys. Binding it would
+//! allow intercepting or emulating the built-in switching, e.g. with
+//! wrap-around.
+//!
+//! No public binding exists, and the class may not be exposed through
+//! `QueryService` at all, in which case it can't be reached from another
+//! process. Wrap-around switching is available without it, see `next_desktop`
+//! and `previous_desktop` with `wrap = true`.
+//!
+//! ### VirtualDesktopsApi
+//!
+//! Symbol: `??_7VirtualDesktopsApi@@6B@`
+//!
+//! Higher level object that seems to expose capabilities missing from
+//! `IVirtualDesktopManagerInternal`, e.g. animation control and switching
+//! policies. The class name has no `I` or `C` prefix like the COM classes, so
+//! it may be a WinRT runtime class or an internal C++ class rather than a COM
+//! interface. A module querying it would need the activatable class name or
+//! the service id in addition to the vtable layout.
+//!
+//! Some animation control is available without it:
+//! `IVirtualDesktopManagerInternal::switch_desktop_with_animation` is used by
+//! `batch` on the builds that have it.
+//!
+//! ### IApplicationViewChangeListener
+//!
+//! Parameter of
+//! `IApplicationViewCollection::register_for_application_view_changes`,
+//! declared as a `UINT` placeholder here and in `interfaces_multi.rs`.
+//!
+//! Unlike the classes above this one is called by the shell, so the crate
+//! would implement it like `IVirtualDesktopNotification` with an adaptor. That
+//! needs the IID the shell queries for and the exact method order, a wrong
+//! order sends the shell's calls to the wrong methods. Neither is in the
+//! references listed in `interfaces_multi.rs`, so `ViewCreated`,
+//! `ViewDestroyed` and `ViewMoved` events are not available yet.
+//!
+//! Meanwhile:
+//!
+//! - New windows and windows moved between desktops are seen through
+//!   `DesktopEvent::WindowChanged`, which `WindowRules` relies on.
+//! - `list_views` gives a snapshot of the views to compare against.
+//!
+//! ### IImmersiveMonitor
+//!
+//! Returned by `IApplicationView::get_monitor`, and by
+//! `IVirtualDesktop::get_monitor` before the 20348 build. Declared as a `UINT`
+//! placeholder.
+//!
+//! Only the COM object is not bound, the monitor metadata is available without
+//! it: `Desktop::monitor` uses the `HMONITOR` returned by the 22000 build's
+//! `get_monitor`, and `monitor_of_window` and `list_monitors` read the monitors
+//! through Win32. The 20348 build's `get_monitor` takes an extra `HMONITOR`
+//! argument with unknown meaning, it is not called.
+//!
+//! ### IApplicationViewOperation and IApplicationViewPosition
+//!
+//! Parameters of `IApplicationView::apply_operation` and `set_position`,
+//! declared as `UINT` placeholders, so neither method can be called.
+//!
+//! No factory creating them is known. They may only be created inside the
+//! shell, in which case opaque wrappers would have nothing to wrap. Windows can
+//! be positioned with `SetWindowPos` on the `HWND` meanwhile, the view follows
+//! the window.
 #![allow(non_upper_case_globals)]
 
 use crate::comobjects::HRESULTHelpers;
+use crate::iid_overrides::iid_or;
 use std::ffi::c_void;
-use std::ops::Deref;
 use windows::{
     core::{IUnknown, IUnknown_Vtbl, Interface, GUID, HRESULT, HSTRING},
-    Win32::{Foundation::HWND, UI::Shell::Common::IObjectArray},
+    Win32::{
+        Foundation::{E_NOTIMPL, HWND},
+        UI::Shell::Common::IObjectArray,
+    },
 };
 
-/// ComIn is a wrapper for COM objects that are passed as input parameters. It
-/// allows to keep the life of the COM object for the duration of the function
-/// call.
-///
-/// Imagine following situation:
-///
-/// First you call an API function that gives COM object as out parameter. And
-/// you want to pass it to another function that takes the COM object as an
-/// input parameter. If you were to use ManuallyDrop then you'd have to call the
-/// drop manually after the second function call.
-///
-/// E.g.
-///
-/// ```rust,ignore
-/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
-/// fn switch_desktop(&self, desktop: ManuallyDrop<IVirtualDesktop>) -> HRESULT;
-///
-/// let mut desktop: Option<IVirtualDesktop> = None;
-/// get_current_desktop(&mut desktop);
-/// if let Some(desktop) = desktop {
-///     let input = ManuallyDrop::new(desktop);
-///     switch_desktop(input);
-///     ManuallyDrop::drop(input);
-/// }
-/// ```
-///
-/// To make things safer and easier to use, ComIn is used instead.
-///
-/// ```rust,ignore
-/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
-/// fn switch_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
-///
-/// let mut desktop: Option<IVirtualDesktop> = None;
-/// if let Some(desktop) = desktop {
-///     get_current_desktop(&mut desktop);
-///     switch_desktop(ComIn::new(&input));
-/// }
-/// ```
-#[repr(transparent)]
-pub struct ComIn<'a, T: Interface> {
-    data: *mut c_void,
-    _phantom: std::marker::PhantomData<&'a T>,
-}
-
-impl<'a, T: Interface> ComIn<'a, T> {
-    pub fn new(t: &'a T) -> Self {
-        Self {
-            // Copies the raw Inteface pointer
-            data: t.as_raw(),
-            _phantom: std::marker::PhantomData,
-        }
-    }
-}
-
-impl<'a, T: Interface> Deref for ComIn<'a, T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        unsafe { std::mem::transmute(&self.data) }
-    }
-}
+pub use crate::com_in::ComIn;
 
 #[allow(non_upper_case_globals)]
 pub const CLSID_ImmersiveShell: GUID = GUID {
@@ -154,6 +185,8 @@ type IImmersiveMonitor = UINT;
 type IApplicationViewOperation = UINT;
 type IApplicationViewPosition = UINT;
 type IImmersiveApplication = UINT;
+// Placeholder, `register_for_application_view_changes` can't be called until
+// the listener is bound, see the module docs
 type IApplicationViewChangeListener = UINT;
 #[allow(non_camel_case_types)]
 type APPLICATION_VIEW_COMPATIBILITY_POLICY = UINT;
@@ -292,8 +325,11 @@ pub unsafe trait IApplicationView: IUnknown {
     pub unsafe fn is_in_high_zorder_band(&self, out_is: *mut BOOL) -> HRESULT;
     pub unsafe fn is_splash_screen_presented(&self, out_is: *mut BOOL) -> HRESULT;
     pub unsafe fn flash(&self) -> HRESULT;
-    pub unsafe fn get_root_switchable_owner(&self, app_view: *mut IApplicationView) -> HRESULT; // proc45
-    pub unsafe fn enumerate_ownership_tree(&self, objects: *mut IObjectArray) -> HRESULT; // proc46
+    pub unsafe fn get_root_switchable_owner(
+        &self,
+        app_view: *mut Option<IApplicationView>,
+    ) -> HRESULT; // proc45
+    pub unsafe fn enumerate_ownership_tree(&self, objects: *mut Option<IObjectArray>) -> HRESULT; // proc46
 
     pub unsafe fn get_enterprise_id(&self, out_id: *mut PWSTR) -> HRESULT; // proc47
     pub unsafe fn is_mirrored(&self, out_is: *mut BOOL) -> HRESULT; //
@@ -322,18 +358,26 @@ pub unsafe trait IVirtualDesktop: IUnknown {
     pub unsafe fn get_id(&self, out_guid: *mut GUID) -> HRESULT;
     pub unsafe fn get_name(&self, out_string: *mut HSTRING) -> HRESULT;
     pub unsafe fn get_wallpaper(&self, out_string: *mut HSTRING) -> HRESULT;
+    pub unsafe fn is_remote(&self, out_is_remote: *mut i32) -> HRESULT;
+}
+impl IVirtualDesktop {
+    /// Desktops of this build are shared by all monitors, only the 22000
+    /// build has per-monitor desktops
+    pub unsafe fn get_monitor(&self, _out_monitor: *mut isize) -> HRESULT {
+        E_NOTIMPL
+    }
 }
 
 #[windows_interface::interface("1841c6d7-4f9d-42c0-af41-8747538f10e5")]
 pub unsafe trait IApplicationViewCollection: IUnknown {
-    pub unsafe fn get_views(&self, out_views: *mut IObjectArray) -> HRESULT;
+    pub unsafe fn get_views(&self, out_views: *mut Option<IObjectArray>) -> HRESULT;
 
-    pub unsafe fn get_views_by_zorder(&self, out_views: *mut IObjectArray) -> HRESULT;
+    pub unsafe fn get_views_by_zorder(&self, out_views: *mut Option<IObjectArray>) -> HRESULT;
 
     pub unsafe fn get_views_by_app_user_model_id(
         &self,
         id: PCWSTR,
-        out_views: *mut IObjectArray,
+        out_views: *mut Option<IObjectArray>,
     ) -> HRESULT;
 
     pub unsafe fn get_view_for_hwnd(
@@ -345,20 +389,20 @@ pub unsafe trait IApplicationViewCollection: IUnknown {
     pub unsafe fn get_view_for_application(
         &self,
         app: IImmersiveApplication,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
     pub unsafe fn get_view_for_app_user_model_id(
         &self,
         id: PCWSTR,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
-    pub unsafe fn get_view_in_focus(&self, out_view: *mut IApplicationView) -> HRESULT;
+    pub unsafe fn get_view_in_focus(&self, out_view: *mut Option<IApplicationView>) -> HRESULT;
 
     pub unsafe fn try_get_last_active_visible_view(
         &self,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
     pub unsafe fn refresh_collection(&self) -> HRESULT;
@@ -373,15 +417,15 @@ pub unsafe trait IApplicationViewCollection: IUnknown {
 }
 impl IApplicationViewCollection {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IApplicationViewCollection",
+            IApplicationViewCollection::IID,
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &IApplicationViewCollection::IID,
-                    &IApplicationViewCollection::IID,
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&iid, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IApplicationViewCollection::from_raw(obj)) }
@@ -457,15 +501,15 @@ pub unsafe trait IVirtualDesktopNotificationService: IUnknown {
 }
 impl IVirtualDesktopNotificationService {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IVirtualDesktopNotificationService",
+            IVirtualDesktopNotificationService::IID,
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_IVirtualNotificationService,
-                    &IVirtualDesktopNotificationService::IID,
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_IVirtualNotificationService, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopNotificationService::from_raw(obj)) }
@@ -525,25 +569,48 @@ pub unsafe trait IVirtualDesktopManagerInternal: IUnknown {
     pub unsafe fn get_desktop_switch_include_exclude_views(
         &self,
         desktop: ComIn<IVirtualDesktop>,
-        out_pp_desktops1: *mut IObjectArray,
-        out_pp_desktops2: *mut IObjectArray,
+        out_pp_desktops1: *mut Option<IObjectArray>,
+        out_pp_desktops2: *mut Option<IObjectArray>,
     ) -> HRESULT;
 
     pub unsafe fn set_name(&self, desktop: ComIn<IVirtualDesktop>, name: HSTRING) -> HRESULT;
     pub unsafe fn set_wallpaper(&self, desktop: ComIn<IVirtualDesktop>, name: HSTRING) -> HRESULT;
     pub unsafe fn update_wallpaper_for_all(&self, name: HSTRING) -> HRESULT;
+
+    pub unsafe fn copy_desktop_state(
+        &self,
+        view0: ComIn<IApplicationView>,
+        view1: ComIn<IApplicationView>,
+    ) -> HRESULT;
+
+    pub unsafe fn create_remote_desktop(
+        &self,
+        name: HSTRING,
+        out_desktop: *mut Option<IVirtualDesktop>,
+    ) -> HRESULT;
+
+    pub unsafe fn switch_remote_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+
+    pub unsafe fn switch_desktop_with_animation(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+
+    pub unsafe fn get_last_active_desktop(
+        &self,
+        out_desktop: *mut Option<IVirtualDesktop>,
+    ) -> HRESULT;
+
+    pub unsafe fn wait_for_animation_to_complete(&self) -> HRESULT;
 }
 impl IVirtualDesktopManagerInternal {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IVirtualDesktopManagerInternal",
+            IVirtualDesktopManagerInternal::IID,
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_VirtualDesktopManagerInternal,
-                    &IVirtualDesktopManagerInternal::IID,
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_VirtualDesktopManagerInternal, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopManagerInternal::from_raw(obj)) }
@@ -566,15 +633,12 @@ pub unsafe trait IVirtualDesktopPinnedApps: IUnknown {
 }
 impl IVirtualDesktopPinnedApps {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or("IVirtualDesktopPinnedApps", IVirtualDesktopPinnedApps::IID);
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_VirtualDesktopPinnedApps,
-                    &IVirtualDesktopPinnedApps::IID,
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_VirtualDesktopPinnedApps, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopPinnedApps::from_raw(obj)) }
@@ -590,3 +654,13 @@ pub unsafe fn IObjectArrayGetAt<T: Interface>(
 ) -> windows::core::Result<T> {
     array.GetAt(index)
 }
+
+/// Element type of an `IObjectArray`, see [`crate::object_array::ObjectArrayIter`].
+pub trait ObjectArrayItem<'a>: Sized {
+    unsafe fn get_at(array: &'a IObjectArray, index: UINT) -> windows::core::Result<Self>;
+}
+impl<'a, T: Interface> ObjectArrayItem<'a> for T {
+    unsafe fn get_at(array: &'a IObjectArray, index: UINT) -> windows::core::Result<Self> {
+        IObjectArrayGetAt(array, index)
+    }
+}
@@ -18,9 +18,8 @@
 #![allow(non_upper_case_globals, clippy::upper_case_acronyms)]
 
 use std::ffi::c_void;
-use std::ops::Deref;
 use windows::{
-    core::{Interface, IUnknown, IUnknown_Vtbl, GUID, HRESULT, HSTRING},
+    core::{IUnknown, IUnknown_Vtbl, GUID, HRESULT, HSTRING},
     Win32::{Foundation::HWND, UI::Shell::Common::IObjectArray},
 };
 
@@ -133,87 +132,7 @@ macro_rules! _reusable_com_interface {
 // Allow normal imports to work for macro:
 use _reusable_com_interface as reusable_com_interface;
 
-/// Type that can be cast into [`ComIn`]
-///
-/// # Safety
-///
-/// - Can cast from `*mut c_void` to `Self`. (`Self` is a transparent type over
-///   a raw pointer.)
-/// - The returned pointer is valid while the reference it was created from is
-///   valid.
-pub unsafe trait PointerRepr {
-    fn as_pointer_repr(&self) -> *mut c_void;
-}
-unsafe impl<T: Interface> PointerRepr for T {
-    fn as_pointer_repr(&self) -> *mut c_void {
-        windows::core::Interface::as_raw(self)
-    }
-}
-
-/// ComIn is a wrapper for COM objects that are passed as input parameters. It
-/// allows to keep the life of the COM object for the duration of the function
-/// call.
-///
-/// Imagine following situation:
-///
-/// First you call an API function that gives COM object as out parameter. And
-/// you want to pass it to another function that takes the COM object as an
-/// input parameter. If you were to use ManuallyDrop then you'd have to call the
-/// drop manually after the second function call.
-///
-/// E.g.
-///
-/// ```rust,ignore
-/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
-/// fn switch_desktop(&self, desktop: ManuallyDrop<IVirtualDesktop>) -> HRESULT;
-///
-/// let mut desktop: Option<IVirtualDesktop> = None;
-/// get_current_desktop(&mut desktop);
-/// if let Some(desktop) = desktop {
-///     let input = ManuallyDrop::new(desktop);
-///     switch_desktop(input);
-///     ManuallyDrop::drop(input);
-/// }
-/// ```
-///
-/// To make things safer and easier to use, ComIn is used instead.
-///
-/// ```rust,ignore
-/// fn get_current_desktop(&mut self, desktop: &mut Option<IVirtualDesktop>) -> HRESULT;
-/// fn switch_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
-///
-/// let mut desktop: Option<IVirtualDesktop> = None;
-/// if let Some(desktop) = desktop {
-///     get_current_desktop(&mut desktop);
-///     switch_desktop(ComIn::new(&input));
-/// }
-/// ```
-#[repr(transparent)]
-pub struct ComIn<'a, T> {
-    data: *mut c_void,
-    _phantom: std::marker::PhantomData<&'a T>,
-}
-impl<'a, T: PointerRepr> ComIn<'a, T> {
-    pub fn new(t: &'a T) -> Self {
-        Self {
-            // Copies the raw Inteface pointer
-            data: t.as_pointer_repr(),
-            _phantom: std::marker::PhantomData,
-        }
-    }
-}
-impl<'a, T> ComIn<'a, T> {
-    pub fn into_ref(this: &Self) -> &'a T {
-        // Safety: A ComInterface type `T` is just a transparent type over a raw pointer
-        unsafe { &*(&this.data as *const *mut c_void as *const T) }
-    }
-}
-impl<'a, T> Deref for ComIn<'a, T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        Self::into_ref(self)
-    }
-}
+pub use crate::com_in::{ComIn, PointerRepr};
 
 #[allow(non_upper_case_globals)]
 pub const CLSID_ImmersiveShell: GUID = GUID::from_u128(0xC2F03A33_21F5_47FA_B4BB_156362A2F239);
@@ -250,6 +169,8 @@ type IApplicationViewOperation = UINT;
 type IApplicationViewPosition = UINT;
 type IShellPositionerPriority = *mut c_void;
 type IImmersiveApplication = UINT;
+// Placeholder, `register_for_application_view_changes` can't be called until
+// the listener is bound, see the module docs of `interfaces`
 type IApplicationViewChangeListener = UINT;
 #[allow(non_camel_case_types)]
 type APPLICATION_VIEW_COMPATIBILITY_POLICY = UINT;
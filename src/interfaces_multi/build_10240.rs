@@ -118,9 +118,12 @@ reusable_com_interface!(
             pub unsafe fn flash(&self) -> HRESULT;
             pub unsafe fn get_root_switchable_owner(
                 &self,
-                app_view: *mut IApplicationView,
+                app_view: *mut Option<IApplicationView>,
             ) -> HRESULT; // proc45
-            pub unsafe fn enumerate_ownership_tree(&self, objects: *mut IObjectArray) -> HRESULT; // proc46
+            pub unsafe fn enumerate_ownership_tree(
+                &self,
+                objects: *mut Option<IObjectArray>,
+            ) -> HRESULT; // proc46
 
             pub unsafe fn get_enterprise_id(&self, out_id: *mut PWSTR) -> HRESULT; // proc47
             pub unsafe fn is_mirrored(&self, out_is: *mut BOOL) -> HRESULT; //
@@ -148,14 +151,17 @@ reusable_com_interface!(
     },
     {
         pub unsafe trait IApplicationViewCollection: IUnknown {
-            pub unsafe fn get_views(&self, out_views: *mut IObjectArray) -> HRESULT;
+            pub unsafe fn get_views(&self, out_views: *mut Option<IObjectArray>) -> HRESULT;
 
-            pub unsafe fn get_views_by_zorder(&self, out_views: *mut IObjectArray) -> HRESULT;
+            pub unsafe fn get_views_by_zorder(
+                &self,
+                out_views: *mut Option<IObjectArray>,
+            ) -> HRESULT;
 
             pub unsafe fn get_views_by_app_user_model_id(
                 &self,
                 id: PCWSTR,
-                out_views: *mut IObjectArray,
+                out_views: *mut Option<IObjectArray>,
             ) -> HRESULT;
 
             pub unsafe fn get_view_for_hwnd(
@@ -167,16 +173,19 @@ reusable_com_interface!(
             pub unsafe fn get_view_for_application(
                 &self,
                 app: IImmersiveApplication,
-                out_view: *mut IApplicationView,
+                out_view: *mut Option<IApplicationView>,
             ) -> HRESULT;
 
             pub unsafe fn get_view_for_app_user_model_id(
                 &self,
                 id: PCWSTR,
-                out_view: *mut IApplicationView,
+                out_view: *mut Option<IApplicationView>,
             ) -> HRESULT;
 
-            pub unsafe fn get_view_in_focus(&self, out_view: *mut IApplicationView) -> HRESULT;
+            pub unsafe fn get_view_in_focus(
+                &self,
+                out_view: *mut Option<IApplicationView>,
+            ) -> HRESULT;
 
             pub unsafe fn refresh_collection(&self) -> HRESULT;
 
@@ -323,13 +332,14 @@ reusable_com_interface!(
         #[windows::core::implement(IVirtualDesktopNotification)]
         pub struct VirtualDesktopNotificationAdaptor<T>
         where
-            T: build_dyn::IVirtualDesktopNotification_Impl,
+            // `implement` of windows 0.57 needs 'static
+            T: build_dyn::IVirtualDesktopNotification_Impl + 'static,
         {
             pub inner: T,
         }
         impl<T> IVirtualDesktopNotification_Impl for VirtualDesktopNotificationAdaptor<T>
         where
-            T: build_dyn::IVirtualDesktopNotification_Impl,
+            T: build_dyn::IVirtualDesktopNotification_Impl + 'static,
         {
             unsafe fn current_virtual_desktop_changed(
                 &self,
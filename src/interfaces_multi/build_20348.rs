@@ -116,8 +116,8 @@ reusable_com_interface!(
             pub unsafe fn get_desktop_switch_include_exclude_views(
                 &self,
                 desktop: ComIn<IVirtualDesktop>,
-                out_pp_desktops1: *mut IObjectArray,
-                out_pp_desktops2: *mut IObjectArray,
+                out_pp_desktops1: *mut Option<IObjectArray>,
+                out_pp_desktops2: *mut Option<IObjectArray>,
             ) -> HRESULT;
 
             pub unsafe fn set_name(
@@ -225,13 +225,13 @@ reusable_com_interface!(
         #[windows::core::implement(IVirtualDesktopNotification)]
         pub struct VirtualDesktopNotificationAdaptor<T>
         where
-            T: build_dyn::IVirtualDesktopNotification_Impl,
+            T: build_dyn::IVirtualDesktopNotification_Impl + 'static,
         {
             pub inner: T,
         }
         impl<T> IVirtualDesktopNotification_Impl for VirtualDesktopNotificationAdaptor<T>
         where
-            T: build_dyn::IVirtualDesktopNotification_Impl,
+            T: build_dyn::IVirtualDesktopNotification_Impl + 'static,
         {
             unsafe fn virtual_desktop_created(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT {
                 self.inner.virtual_desktop_created(desktop.into())
@@ -8,6 +8,7 @@
 use super::*;
 
 use crate::comobjects::HRESULTHelpers;
+use crate::iid_overrides::iid_or;
 use core::{ffi::c_void, marker::PhantomData};
 use windows::{
     core::{Interface, GUID, HRESULT, HSTRING},
@@ -176,16 +177,50 @@ impl WindowsVersion {
     /// - [RtlGetVersion in windows::Wdk::System::SystemServices -
     ///   Rust](https://microsoft.github.io/windows-docs-rs/doc/windows/Wdk/System/SystemServices/fn.RtlGetVersion.html)
     ///   - Always returns the correct version.
+    ///
+    /// # Forcing a version
+    ///
+    /// The detected version can be overridden with `set_interface_version`, or
+    /// with the `VDA_FORCE_BUILD` environment variable, e.g.
+    /// `VDA_FORCE_BUILD=build_22631_3155`.
+    ///
+    /// # Probing
+    ///
+    /// Microsoft sometimes backports interface changes, so the build number is
+    /// not always enough. When probing is enabled with `set_interface_probing`
+    /// or the `VDA_PROBE_INTERFACES` environment variable, the version found
+    /// from the build number is verified by querying the IIDs of its core
+    /// interfaces from explorer.exe. If they don't respond, the other versions
+    /// are tried from the newest to the oldest.
     pub fn get() -> Self {
+        let forced = FORCED_VERSION.load(core::sync::atomic::Ordering::Relaxed);
+        if let Some(forced) = forced.checked_sub(1).and_then(|ix| Self::ALL.get(ix)) {
+            return *forced;
+        }
+
         static INIT: std::sync::OnceLock<WindowsVersion> = std::sync::OnceLock::new();
         *INIT.get_or_init(|| {
+            if let Ok(name) = std::env::var("VDA_FORCE_BUILD") {
+                match BuildVersion::from_name(&name) {
+                    Some(BuildVersion(forced)) => {
+                        log_format!(
+                            Info: "Using COM interfaces forced with VDA_FORCE_BUILD: {forced:?}"
+                        );
+                        return forced;
+                    }
+                    None => {
+                        log_format!(Warn: "Unknown version in VDA_FORCE_BUILD: {name}");
+                    }
+                }
+            }
+
             let mut version: windows::Win32::System::SystemInformation::OSVERSIONINFOW =
                 Default::default();
             version.dwOSVersionInfoSize = core::mem::size_of_val(&version) as u32;
             let res = unsafe { windows::Wdk::System::SystemServices::RtlGetVersion(&mut version) };
             if res.is_err() {
                 log_format!(
-                    "Failed to get Windows version with error {res:?} using \
+                    Warn: "Failed to get Windows version with error {res:?} using \
                     COM interfaces for version latest supported version: {:?}",
                     Self::default()
                 );
@@ -205,7 +240,7 @@ impl WindowsVersion {
                 .map(|(v, _)| v)
                 .unwrap_or_default();
             log_format!(
-                "Using COM interfaces for Windows version: {latest_supported:?} \
+                Info: "Using COM interfaces for Windows version: {latest_supported:?} \
                 (Detected Windows version was: {}.{}.{}.{})",
                 version.dwMajorVersion,
                 version.dwMinorVersion,
@@ -215,9 +250,157 @@ impl WindowsVersion {
                     None => "N/A".to_owned(),
                 }
             );
+            let probe = PROBE_INTERFACES.load(core::sync::atomic::Ordering::Relaxed)
+                || std::env::var_os("VDA_PROBE_INTERFACES").is_some();
+            if probe {
+                if let Some(probed) = Self::probe(latest_supported) {
+                    return probed;
+                }
+            }
             latest_supported
         })
     }
+
+    /// Returns the first version whose core interfaces respond, trying the
+    /// preferred version first. `None` if none respond, or explorer.exe could
+    /// not be reached.
+    fn probe(preferred: Self) -> Option<Self> {
+        let provider: IServiceProvider = match unsafe {
+            windows::Win32::System::Com::CoCreateInstance(
+                &CLSID_ImmersiveShell,
+                None,
+                windows::Win32::System::Com::CLSCTX_LOCAL_SERVER,
+            )
+        } {
+            Ok(provider) => provider,
+            Err(err) => {
+                log_format!(Warn: "Could not probe COM interfaces: {err:?}");
+                return None;
+            }
+        };
+        let responds = |(sid, iid): &(GUID, GUID)| {
+            let mut obj = std::ptr::null_mut::<c_void>();
+            let res = unsafe { provider.query_service(sid, iid, &mut obj) };
+            if res.is_err() || obj.is_null() {
+                return false;
+            }
+            // Release the queried service
+            drop(unsafe { windows::core::IUnknown::from_raw(obj) });
+            true
+        };
+
+        let candidates = core::iter::once(preferred).chain(
+            Self::ALL
+                .iter()
+                .rev()
+                .copied()
+                .filter(move |v| *v != preferred),
+        );
+        for candidate in candidates {
+            if candidate.core_services().iter().all(responds) {
+                log_format!(Info: "Probed COM interfaces for Windows version: {candidate:?}");
+                return Some(candidate);
+            }
+        }
+        log_format!(Warn: "No COM interfaces responded when probing");
+        None
+    }
+}
+
+macro_rules! declare_core_services {
+    (versions = {$($version:ident,)*},) => {
+        impl WindowsVersion {
+            /// Service SIDs and IIDs that must respond for the version to be
+            /// used when probing, queried like the `query_service` functions
+            /// below do
+            fn core_services(&self) -> [(GUID, GUID); 2] {
+                match self {
+                    $(Self::$version => [
+                        (
+                            CLSID_VirtualDesktopManagerInternal,
+                            <$version::IVirtualDesktopManagerInternal as Interface>::IID,
+                        ),
+                        (
+                            <$version::IApplicationViewCollection as Interface>::IID,
+                            <$version::IApplicationViewCollection as Interface>::IID,
+                        ),
+                    ],)*
+                }
+            }
+        }
+    };
+}
+with_versions!(declare_core_services);
+
+/// Set when probing was enabled with `set_interface_probing`
+static PROBE_INTERFACES: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+/// Verify the version detected from the build number by querying its COM
+/// interfaces from explorer.exe, and try the other versions if they don't
+/// respond.
+///
+/// The version is selected once, so call this before using the other
+/// functions. The `VDA_PROBE_INTERFACES` environment variable enables probing
+/// as well.
+pub fn set_interface_probing(enabled: bool) {
+    PROBE_INTERFACES.store(enabled, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// Index of the forced version in `WindowsVersion::ALL` plus one, zero if not
+/// forced
+static FORCED_VERSION: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+/// Windows build whose virtual desktop interfaces are used, named after the
+/// module declaring them, e.g. `build_22631_3155`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct BuildVersion(WindowsVersion);
+
+impl BuildVersion {
+    /// All supported versions, from the oldest to the newest
+    pub fn all() -> impl Iterator<Item = BuildVersion> {
+        WindowsVersion::ALL.iter().copied().map(BuildVersion)
+    }
+
+    /// Version by its module name, e.g. `build_22631_3155`
+    pub fn from_name(name: &str) -> Option<BuildVersion> {
+        Self::all().find(|version| version.name() == name.trim())
+    }
+
+    /// Module name of the version, e.g. `build_22631_3155`
+    pub fn name(&self) -> &'static str {
+        self.0.as_str()
+    }
+}
+
+/// Module name and `(build, patch)` of the version in use, see
+/// `crate::interface_version_in_use`
+pub(crate) fn version_in_use() -> (&'static str, (u32, u32)) {
+    let version = WindowsVersion::get();
+    (version.as_str(), version.windows_version())
+}
+
+/// `(build, patch)` of the oldest and the newest supported versions
+pub(crate) fn supported_range() -> ((u32, u32), (u32, u32)) {
+    let oldest = WindowsVersion::ALL
+        .first()
+        .expect("No Windows version is supported");
+    let newest = WindowsVersion::default();
+    (oldest.windows_version(), newest.windows_version())
+}
+
+/// Use the COM interfaces of the given version instead of the detected one.
+///
+/// This is meant for finding out which version works on e.g. an insider build.
+/// COM objects already created keep using the version they were created with,
+/// so call this before using the other functions.
+pub fn set_interface_version(version: BuildVersion) {
+    let ix = WindowsVersion::ALL
+        .iter()
+        .position(|v| *v == version.0)
+        .expect("Version is always in ALL");
+    FORCED_VERSION.store(ix + 1, core::sync::atomic::Ordering::Relaxed);
+    log_format!(Info: "Forced COM interfaces for version: {:?}", version.0);
 }
 
 /// Do an action with the type of the actual COM Interface on this Windows
@@ -384,7 +567,15 @@ macro_rules! support_interface {
             /// ComIn<Versioned> -> ComIn<Abstract>
             impl<'a> From<ComIn<'a, self::$version::$name>> for ComIn<'a, $name> {
                 fn from(v: ComIn<'a, self::$version::$name>) -> Self {
-                    ComIn::new(<&$name as From<_>>::from(ComIn::into_ref(&v)))
+                    debug_assert_eq!(
+                        WindowsVersion::get(),
+                        WindowsVersion::$version,
+                        "if we have an COM interface for a specific Windows version then we must already have ensured that it is actually the Windows version the user has"
+                    );
+                    // Safety: both types are transparent wrappers over the
+                    // same raw pointer. The shell may pass null, which is
+                    // kept as is.
+                    unsafe { ComIn::cast_unchecked(v) }
                 }
             }
             /// Abstract -> Versioned (fallible)
@@ -778,8 +969,11 @@ impl IApplicationView {
     pub unsafe fn is_in_high_zorder_band(&self, out_is: *mut BOOL) -> HRESULT;
     pub unsafe fn is_splash_screen_presented(&self, out_is: *mut BOOL) -> HRESULT;
     pub unsafe fn flash(&self) -> HRESULT;
-    pub unsafe fn get_root_switchable_owner(&self, app_view: *mut IApplicationView) -> HRESULT; // proc45
-    pub unsafe fn enumerate_ownership_tree(&self, objects: *mut IObjectArray) -> HRESULT; // proc46
+    pub unsafe fn get_root_switchable_owner(
+        &self,
+        app_view: *mut Option<IApplicationView>,
+    ) -> HRESULT; // proc45
+    pub unsafe fn enumerate_ownership_tree(&self, objects: *mut Option<IObjectArray>) -> HRESULT; // proc46
 
     pub unsafe fn get_enterprise_id(&self, out_id: *mut PWSTR) -> HRESULT; // proc47
     pub unsafe fn is_mirrored(&self, out_is: *mut BOOL) -> HRESULT; //
@@ -819,6 +1013,16 @@ impl IVirtualDesktop {
     pub unsafe fn get_name(&self, out_string: *mut HSTRING) -> HRESULT;
     #[optional_method]
     pub unsafe fn get_wallpaper(&self, out_string: *mut HSTRING) -> HRESULT;
+    #[optional_method]
+    pub unsafe fn is_remote(&self, out_is_remote: *mut i32) -> HRESULT;
+
+    /// Only the 22000 build has per-monitor desktops
+    pub unsafe fn get_monitor(&self, out_monitor: *mut HMONITOR) -> HRESULT {
+        match IVirtualDesktopInner::from_typed(self) {
+            IVirtualDesktopInner::build_22000(desktop) => (*desktop).get_monitor(out_monitor),
+            _ => E_NOTIMPL,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -832,14 +1036,14 @@ support_interface!(MacroOptions {
 
 #[apply(forward_call)]
 impl IApplicationViewCollection {
-    pub unsafe fn get_views(&self, out_views: *mut IObjectArray) -> HRESULT;
+    pub unsafe fn get_views(&self, out_views: *mut Option<IObjectArray>) -> HRESULT;
 
-    pub unsafe fn get_views_by_zorder(&self, out_views: *mut IObjectArray) -> HRESULT;
+    pub unsafe fn get_views_by_zorder(&self, out_views: *mut Option<IObjectArray>) -> HRESULT;
 
     pub unsafe fn get_views_by_app_user_model_id(
         &self,
         id: PCWSTR,
-        out_views: *mut IObjectArray,
+        out_views: *mut Option<IObjectArray>,
     ) -> HRESULT;
 
     pub unsafe fn get_view_for_hwnd(
@@ -851,21 +1055,21 @@ impl IApplicationViewCollection {
     pub unsafe fn get_view_for_application(
         &self,
         app: IImmersiveApplication,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
     pub unsafe fn get_view_for_app_user_model_id(
         &self,
         id: PCWSTR,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
-    pub unsafe fn get_view_in_focus(&self, out_view: *mut IApplicationView) -> HRESULT;
+    pub unsafe fn get_view_in_focus(&self, out_view: *mut Option<IApplicationView>) -> HRESULT;
 
     #[optional_method]
     pub unsafe fn try_get_last_active_visible_view(
         &self,
-        out_view: *mut IApplicationView,
+        out_view: *mut Option<IApplicationView>,
     ) -> HRESULT;
 
     pub unsafe fn refresh_collection(&self) -> HRESULT;
@@ -880,15 +1084,15 @@ impl IApplicationViewCollection {
 }
 impl IApplicationViewCollection {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IApplicationViewCollection",
+            IApplicationViewCollection::IID(),
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &IApplicationViewCollection::IID(),
-                    &IApplicationViewCollection::IID(),
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&iid, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IApplicationViewCollection::from_raw(obj)) }
@@ -907,7 +1111,7 @@ support_interface!(MacroOptions {
 /// interface using [`IVirtualDesktopNotification_Impl`].
 impl<T> From<T> for IVirtualDesktopNotification
 where
-    T: IVirtualDesktopNotification_Impl,
+    T: IVirtualDesktopNotification_Impl + 'static,
 {
     fn from(value: T) -> Self {
         // Each Windows version has a unique "adaptor" type that implements its
@@ -1002,15 +1206,15 @@ impl IVirtualDesktopNotificationService {
 }
 impl IVirtualDesktopNotificationService {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IVirtualDesktopNotificationService",
+            IVirtualDesktopNotificationService::IID(),
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_IVirtualNotificationService,
-                    &IVirtualDesktopNotificationService::IID(),
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_IVirtualNotificationService, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopNotificationService::from_raw(obj)) }
@@ -1081,8 +1285,8 @@ impl IVirtualDesktopManagerInternal {
     pub unsafe fn get_desktop_switch_include_exclude_views(
         &self,
         desktop: ComIn<IVirtualDesktop>,
-        out_pp_desktops1: *mut IObjectArray,
-        out_pp_desktops2: *mut IObjectArray,
+        out_pp_desktops1: *mut Option<IObjectArray>,
+        out_pp_desktops2: *mut Option<IObjectArray>,
     ) -> HRESULT;
 
     #[optional_method]
@@ -1091,18 +1295,22 @@ impl IVirtualDesktopManagerInternal {
     pub unsafe fn set_wallpaper(&self, desktop: ComIn<IVirtualDesktop>, name: HSTRING) -> HRESULT;
     #[optional_method]
     pub unsafe fn update_wallpaper_for_all(&self, name: HSTRING) -> HRESULT;
+    #[optional_method]
+    pub unsafe fn switch_desktop_with_animation(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+    #[optional_method]
+    pub unsafe fn wait_for_animation_to_complete(&self) -> HRESULT;
 }
 impl IVirtualDesktopManagerInternal {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IVirtualDesktopManagerInternal",
+            IVirtualDesktopManagerInternal::IID(),
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_VirtualDesktopManagerInternal,
-                    &IVirtualDesktopManagerInternal::IID(),
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_VirtualDesktopManagerInternal, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopManagerInternal::from_raw(obj)) }
@@ -1134,15 +1342,15 @@ impl IVirtualDesktopPinnedApps {
 }
 impl IVirtualDesktopPinnedApps {
     pub unsafe fn query_service(provider: &IServiceProvider) -> crate::Result<Self> {
+        let iid = iid_or(
+            "IVirtualDesktopPinnedApps",
+            IVirtualDesktopPinnedApps::IID(),
+        );
         let mut obj = std::ptr::null_mut::<c_void>();
         unsafe {
             provider
-                .query_service(
-                    &CLSID_VirtualDesktopPinnedApps,
-                    &IVirtualDesktopPinnedApps::IID(),
-                    &mut obj,
-                )
-                .as_result()?;
+                .query_service(&CLSID_VirtualDesktopPinnedApps, &iid, &mut obj)
+                .as_service_result()?;
         }
         assert_eq!(obj.is_null(), false);
         unsafe { Ok(IVirtualDesktopPinnedApps::from_raw(obj)) }
@@ -1182,3 +1390,49 @@ where
     T::with_versioned_type(IObjectArrayGetAtCallback(object_array, index, PhantomData))
         .ok_or_else(|| windows::core::Error::from(E_NOTIMPL))?
 }
+
+/// Element type of an `IObjectArray`, see [`crate::object_array::ObjectArrayIter`].
+pub trait ObjectArrayItem<'a>: Sized {
+    unsafe fn get_at(array: &'a IObjectArray, index: UINT) -> Result<Self, windows::core::Error>;
+}
+#[allow(private_bounds)]
+impl<'a, T> ObjectArrayItem<'a> for T
+where
+    T: WithVersionedType<IObjectArrayGetAtCallback<'a, T>, Result<T, windows::core::Error>>,
+{
+    unsafe fn get_at(array: &'a IObjectArray, index: UINT) -> Result<Self, windows::core::Error> {
+        IObjectArrayGetAt(array, index)
+    }
+}
+
+#[cfg(test)]
+mod coverage;
+#[cfg(test)]
+mod layout;
+#[cfg(test)]
+mod macro_tests;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probed_services() {
+        for version in WindowsVersion::ALL {
+            let [manager_internal, view_collection] = version.core_services();
+            assert_eq!(manager_internal.0, CLSID_VirtualDesktopManagerInternal);
+            assert_ne!(manager_internal.1, CLSID_VirtualDesktopManagerInternal);
+            assert_eq!(view_collection.0, view_collection.1);
+        }
+
+        let [manager_internal, view_collection] = WindowsVersion::build_22631_3155.core_services();
+        assert_eq!(
+            manager_internal.1,
+            <build_22631_3155::IVirtualDesktopManagerInternal as Interface>::IID
+        );
+        assert_eq!(
+            view_collection.1,
+            <build_22631_3155::IApplicationViewCollection as Interface>::IID
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Coverage report of the COM interface methods.
+//!
+//! Cross-references the methods declared in the `build_*` modules against the
+//! methods forwarded by `build_dyn` and the methods used by the rest of the
+//! crate. Print the table with:
+//!
+//! ```text
+//! cargo test --features multiple-windows-versions interface_coverage -- --nocapture
+//! ```
+//!
+//! The sources are only scanned as text, so nothing here needs COM or Windows.
+
+use std::collections::BTreeMap;
+
+/// Sources of the version specific modules
+const VERSIONS: &[(&str, &str)] = &[
+    ("10240", include_str!("../build_10240.rs")),
+    ("16299", include_str!("../build_16299.rs")),
+    ("17134", include_str!("../build_17134.rs")),
+    ("19045", include_str!("../build_19045.rs")),
+    ("20348", include_str!("../build_20348.rs")),
+    ("22000", include_str!("../build_22000.rs")),
+    ("22621_2215", include_str!("../build_22621_2215.rs")),
+    ("22621_3155", include_str!("../build_22621_3155.rs")),
+    ("22631_2428", include_str!("../build_22631_2428.rs")),
+    ("22631_3155", include_str!("../build_22631_3155.rs")),
+];
+
+const BUILD_DYN: &str = include_str!("../build_dyn.rs");
+
+/// Sources using the COM interfaces
+const HIGH_LEVEL: &[&str] = &[
+    include_str!("../../comobjects.rs"),
+    include_str!("../../listener.rs"),
+];
+
+#[derive(Debug, Default)]
+struct MethodCoverage {
+    /// Versions where the method is declared, later versions re-using the
+    /// declaration are not listed
+    declared_in: Vec<&'static str>,
+    forwarded: bool,
+    used: bool,
+}
+
+/// Returns `(interface, method)` pairs of the methods declared in blocks
+/// starting with the keyword, e.g. `"trait "`. With an attribute only blocks
+/// right after the attribute are included.
+fn block_methods(source: &str, keyword: &str, attribute: Option<&str>) -> Vec<(String, String)> {
+    let mut methods = Vec::new();
+    let mut current: Option<String> = None;
+    let mut after_attribute = attribute.is_none();
+    let mut depth = 0;
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap_or("");
+        if current.is_none() {
+            if let Some((_, rest)) = line.split_once(keyword).filter(|_| after_attribute) {
+                let name = identifier(rest);
+                if !name.is_empty() {
+                    current = Some(name);
+                    depth = 0;
+                }
+            }
+            if let Some(attribute) = attribute {
+                after_attribute = line.trim() == attribute;
+            }
+        }
+        let Some(interface) = current.as_ref() else {
+            continue;
+        };
+
+        if depth == 1 {
+            if let Some((_, rest)) = line.split_once("fn ") {
+                methods.push((interface.clone(), identifier(rest)));
+            }
+        }
+        depth += line.matches('{').count() as i32;
+        depth -= line.matches('}').count() as i32;
+        if depth <= 0 && line.contains('}') {
+            current = None;
+        }
+    }
+    methods
+}
+
+/// Methods forwarded by `build_dyn`, and the methods of the notification
+/// interface implemented by the listener
+fn forwarded_methods(source: &str) -> Vec<(String, String)> {
+    let mut methods = block_methods(source, "impl ", Some("#[apply(forward_call)]"));
+    for (interface, method) in block_methods(source, "trait ", None) {
+        if let Some(interface) = interface.strip_suffix("_Impl") {
+            methods.push((interface.to_string(), method));
+        }
+    }
+    methods
+}
+
+fn identifier(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// Returns true if `name(` is found as a whole identifier
+fn is_used(name: &str, sources: &[&str]) -> bool {
+    let pattern = format!("{}(", name);
+    sources.iter().any(|source| {
+        source.match_indices(&pattern).any(|(index, _)| {
+            !source[..index]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        })
+    })
+}
+
+fn coverage() -> BTreeMap<(String, String), MethodCoverage> {
+    let mut coverage = BTreeMap::<(String, String), MethodCoverage>::new();
+    for (version, source) in VERSIONS {
+        for key in block_methods(source, "trait ", None) {
+            coverage.entry(key).or_default().declared_in.push(version);
+        }
+    }
+    for key in forwarded_methods(BUILD_DYN) {
+        coverage.entry(key).or_default().forwarded = true;
+    }
+    for ((_, method), entry) in coverage.iter_mut() {
+        entry.used = is_used(method, HIGH_LEVEL);
+    }
+    coverage
+}
+
+#[test]
+fn interface_coverage() {
+    let coverage = coverage();
+    let mark = |b: bool| if b { "yes" } else { "" };
+
+    println!("| Interface | Method | Declared in | Forwarded | Used |");
+    println!("|---|---|---|---|---|");
+    for ((interface, method), entry) in &coverage {
+        println!(
+            "| {} | {} | {} | {} | {} |",
+            interface,
+            method,
+            entry.declared_in.join(", "),
+            mark(entry.forwarded),
+            mark(entry.used)
+        );
+    }
+    let forwarded = coverage.values().filter(|e| e.forwarded).count();
+    let used = coverage.values().filter(|e| e.used).count();
+    println!(
+        "\n{} methods, {} forwarded, {} used",
+        coverage.len(),
+        forwarded,
+        used
+    );
+
+    // Sanity check that the sources were parsed at all. Forwarded methods may
+    // be missing from all versions, see `#[optional_method]` in `build_dyn`.
+    let switch_desktop = &coverage[&(
+        "IVirtualDesktopManagerInternal".to_string(),
+        "switch_desktop".to_string(),
+    )];
+    assert!(switch_desktop.forwarded && switch_desktop.used);
+    assert!(switch_desktop.declared_in.contains(&"10240"));
+}
+
+#[test]
+fn test_block_methods() {
+    let source = "
+        pub unsafe trait IFoo: IUnknown {
+            pub unsafe fn get_count(&self, out: *mut UINT) -> HRESULT;
+            // pub unsafe fn commented(&self) -> HRESULT;
+            pub unsafe fn query(provider: &IServiceProvider) -> Result<Self> {
+                let fn_inside = 1;
+            }
+        }
+        fn outside() {}
+    ";
+    assert_eq!(
+        block_methods(source, "trait ", None),
+        vec![
+            ("IFoo".to_string(), "get_count".to_string()),
+            ("IFoo".to_string(), "query".to_string()),
+        ]
+    );
+    assert!(block_methods(source, "trait ", Some("#[apply(forward_call)]")).is_empty());
+    assert!(is_used("get_count", &["o.get_count()"]));
+    assert!(!is_used("count", &["o.get_count()"]));
+}
@@ -0,0 +1,342 @@
+//! Vtable layouts of the COM interfaces, checked at compile time.
+//!
+//! The tables below list the vtable slots of each interface after the
+//! `IUnknown` methods, in order, as recorded from the `build_*` modules. When
+//! adding a build, record its table from the references listed in
+//! `interfaces_multi.rs`, not from the new module. If a method is moved, added
+//! or removed in a `build_*` module without updating the table, the crate
+//! fails to compile with
+//!
+//! ```text
+//! cargo test --features multiple-windows-versions
+//! ```
+//!
+//! instead of calling the wrong slot and crashing explorer.exe at runtime.
+//! Interfaces re-declared with a new IID share the table of the declaration.
+
+use std::mem::{offset_of, size_of};
+use windows::core::{IUnknown_Vtbl, Interface};
+
+use crate::interfaces_multi::*;
+
+/// Asserts that the methods are the vtable slots of the interface, in order,
+/// and that the vtable has no other slots.
+macro_rules! assert_vtable {
+    ($build:ident, $name:ident, { $($method:ident),* $(,)? }) => {
+        const _: () = {
+            type Vtable = <$build::$name as Interface>::Vtable;
+            let mut slot = size_of::<IUnknown_Vtbl>();
+            $(
+                assert!(
+                    offset_of!(Vtable, $method) == slot,
+                    concat!(
+                        "vtable slot of ",
+                        stringify!($build::$name::$method),
+                        " doesn't match the table"
+                    )
+                );
+                slot += size_of::<usize>();
+            )*
+            assert!(
+                size_of::<Vtable>() == slot,
+                concat!(
+                    "vtable of ",
+                    stringify!($build::$name),
+                    " has slots missing from the table"
+                )
+            );
+        };
+    };
+}
+
+/// `assert_vtable` for each build sharing the table
+macro_rules! assert_vtables {
+    ($($name:ident in [$($build:ident),* $(,)?] $methods:tt)*) => {
+        $($(
+            assert_vtable!($build, $name, $methods);
+        )*)*
+    };
+}
+
+assert_vtables! {
+    IApplicationView in [
+        build_10240,
+        build_16299,
+        build_17134,
+        build_19045,
+        build_20348,
+        build_22000,
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        get_iids,
+        get_runtime_class_name,
+        get_trust_level,
+        set_focus,
+        switch_to,
+        try_invoke_back,
+        get_thumbnail_window,
+        get_monitor,
+        get_visibility,
+        set_cloak,
+        get_position,
+        set_position,
+        insert_after_window,
+        get_extended_frame_position,
+        get_app_user_model_id,
+        set_app_user_model_id,
+        is_equal_by_app_user_model_id,
+        get_view_state,
+        set_view_state,
+        get_neediness,
+        get_last_activation_timestamp,
+        set_last_activation_timestamp,
+        get_virtual_desktop_id,
+        set_virtual_desktop_id,
+        get_show_in_switchers,
+        set_show_in_switchers,
+        get_scale_factor,
+        can_receive_input,
+        get_compatibility_policy_type,
+        set_compatibility_policy_type,
+        get_position_priority,
+        set_position_priority,
+        get_size_constraints,
+        get_size_constraints_for_dpi,
+        set_size_constraints_for_dpi,
+        query_size_constraints_from_app,
+        on_min_size_preferences_updated,
+        apply_operation,
+        is_tray,
+        is_in_high_zorder_band,
+        is_splash_screen_presented,
+        flash,
+        get_root_switchable_owner,
+        enumerate_ownership_tree,
+        get_enterprise_id,
+        is_mirrored,
+        unknown1,
+        unknown2,
+        unknown3,
+        unknown4,
+        unknown5,
+        unknown6,
+        unknown7,
+        unknown8,
+        unknown9,
+        unknown10,
+        unknown11,
+        unknown12,
+    }
+    IApplicationViewCollection in [
+        build_10240,
+        build_16299,
+        build_17134,
+        build_19045,
+        build_20348,
+        build_22000,
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        get_views,
+        get_views_by_zorder,
+        get_views_by_app_user_model_id,
+        get_view_for_hwnd,
+        get_view_for_application,
+        get_view_for_app_user_model_id,
+        get_view_in_focus,
+        refresh_collection,
+        register_for_application_view_changes,
+        register_for_application_view_position_changes,
+        unregister_for_application_view_changes,
+    }
+    IVirtualDesktop in [build_10240, build_16299, build_17134, build_19045] {
+        is_view_visible,
+        get_id,
+    }
+    IVirtualDesktop in [build_20348] {
+        is_view_visible,
+        get_id,
+        get_monitor,
+        get_name,
+    }
+    IVirtualDesktop in [build_22000] {
+        is_view_visible,
+        get_id,
+        get_monitor,
+        get_name,
+        get_wallpaper,
+    }
+    IVirtualDesktop in [build_22621_2215, build_22621_3155, build_22631_2428, build_22631_3155] {
+        is_view_visible,
+        get_id,
+        get_name,
+        get_wallpaper,
+        is_remote,
+    }
+    IVirtualDesktopManagerInternal in [build_10240, build_16299, build_17134, build_19045] {
+        get_desktop_count,
+        move_view_to_desktop,
+        can_move_view_between_desktops,
+        get_current_desktop,
+        get_desktops,
+        get_adjacent_desktop,
+        switch_desktop,
+        create_desktop,
+        remove_desktop,
+        find_desktop,
+    }
+    IVirtualDesktopManagerInternal in [build_20348] {
+        get_desktop_count_m,
+        move_view_to_desktop,
+        can_move_view_between_desktops,
+        get_current_desktop_m,
+        get_desktops_m,
+        get_adjacent_desktop,
+        switch_desktop_m,
+        create_desktop_m,
+        remove_desktop,
+        find_desktop,
+        get_desktop_switch_include_exclude_views,
+        set_name,
+        copy_desktop_state,
+        get_desktop_is_per_monitor,
+    }
+    IVirtualDesktopManagerInternal in [build_22000] {
+        get_desktop_count_m,
+        move_view_to_desktop,
+        can_move_view_between_desktops,
+        get_current_desktop_m,
+        get_all_current_desktops,
+        get_desktops_m,
+        get_adjacent_desktop,
+        switch_desktop_m,
+        create_desktop_m,
+        move_desktop_m,
+        remove_desktop,
+        find_desktop,
+        get_desktop_switch_include_exclude_views,
+        set_name,
+        set_wallpaper,
+        update_wallpaper_for_all,
+        copy_desktop_state,
+        get_desktop_is_per_monitor,
+        set_desktop_is_per_monitor,
+    }
+    IVirtualDesktopManagerInternal in [
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        get_desktop_count,
+        move_view_to_desktop,
+        can_move_view_between_desktops,
+        get_current_desktop,
+        get_desktops,
+        get_adjacent_desktop,
+        switch_desktop,
+        create_desktop,
+        move_desktop,
+        remove_desktop,
+        find_desktop,
+        get_desktop_switch_include_exclude_views,
+        set_name,
+        set_wallpaper,
+        update_wallpaper_for_all,
+        copy_desktop_state,
+        create_remote_desktop,
+        switch_remote_desktop,
+        switch_desktop_with_animation,
+        get_last_active_desktop,
+        wait_for_animation_to_complete,
+    }
+    IVirtualDesktopNotification in [build_10240, build_16299, build_17134, build_19045] {
+        virtual_desktop_created,
+        virtual_desktop_destroy_begin,
+        virtual_desktop_destroy_failed,
+        virtual_desktop_destroyed,
+        view_virtual_desktop_changed,
+        current_virtual_desktop_changed,
+    }
+    IVirtualDesktopNotification in [build_20348] {
+        virtual_desktop_created,
+        virtual_desktop_destroy_begin,
+        virtual_desktop_destroy_failed,
+        virtual_desktop_destroyed,
+        virtual_desktop_is_per_monitor_changed,
+        virtual_desktop_name_changed,
+        view_virtual_desktop_changed,
+        current_virtual_desktop_changed,
+    }
+    IVirtualDesktopNotification in [build_22000] {
+        virtual_desktop_created,
+        virtual_desktop_destroy_begin,
+        virtual_desktop_destroy_failed,
+        virtual_desktop_destroyed,
+        virtual_desktop_is_per_monitor_changed,
+        virtual_desktop_moved,
+        virtual_desktop_name_changed,
+        view_virtual_desktop_changed,
+        current_virtual_desktop_changed,
+        virtual_desktop_wallpaper_changed,
+        virtual_desktop_switched,
+        remote_virtual_desktop_connected,
+    }
+    IVirtualDesktopNotification in [
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        virtual_desktop_created,
+        virtual_desktop_destroy_begin,
+        virtual_desktop_destroy_failed,
+        virtual_desktop_destroyed,
+        virtual_desktop_moved,
+        virtual_desktop_name_changed,
+        view_virtual_desktop_changed,
+        current_virtual_desktop_changed,
+        virtual_desktop_wallpaper_changed,
+        virtual_desktop_switched,
+        remote_virtual_desktop_connected,
+    }
+    IVirtualDesktopNotificationService in [
+        build_10240,
+        build_16299,
+        build_17134,
+        build_19045,
+        build_20348,
+        build_22000,
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        register,
+        unregister,
+    }
+    IVirtualDesktopPinnedApps in [
+        build_10240,
+        build_16299,
+        build_17134,
+        build_19045,
+        build_20348,
+        build_22000,
+        build_22621_2215,
+        build_22621_3155,
+        build_22631_2428,
+        build_22631_3155,
+    ] {
+        is_app_pinned,
+        pin_app,
+        unpin_app,
+        is_view_pinned,
+        pin_view,
+        unpin_view,
+    }
+}
@@ -0,0 +1,177 @@
+//! Tests for the macros that declare and forward the versioned COM interfaces.
+//!
+//! A regression in `reusable_com_interface!`, `support_interface!` or
+//! `forward_call!` normally only shows up as a confusing compile error inside
+//! a new `build_*` module. Here the macros are exercised with a small fake
+//! interface instead, so most of these tests fail at compile time already.
+//!
+//! Nothing here calls into a COM object or `WindowsVersion::get`, so the tests
+//! run without explorer.exe (or Windows) being available.
+
+use super::*;
+
+/// Base declaration of the fake interface.
+mod build_10240 {
+    use super::*;
+    use crate::interfaces_multi::build_10240::IVirtualDesktop;
+
+    reusable_com_interface!(
+        MacroOptions {
+            temp_macro_name: _ITestInterface,
+            iid: "0F1C5A0E-5E0B-4B47-9C6A-3D0C9D7F1A01",
+        },
+        {
+            pub unsafe trait ITestInterface: IUnknown {
+                pub unsafe fn get_count(&self, out_count: *mut UINT) -> HRESULT;
+                pub unsafe fn take_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+                pub unsafe fn get_desktop(
+                    &self,
+                    out_desktop: *mut Option<IVirtualDesktop>,
+                ) -> HRESULT;
+            }
+        }
+    );
+}
+
+/// IID change only, the trait is re-declared from the previous build.
+mod build_16299 {
+    use super::*;
+    use crate::interfaces_multi::build_16299::IVirtualDesktop;
+
+    super::build_10240::ITestInterface!("0F1C5A0E-5E0B-4B47-9C6A-3D0C9D7F1A02");
+}
+
+/// Interface change, adds a method that older builds don't have.
+mod build_22000 {
+    use super::*;
+    use crate::interfaces_multi::build_22000::IVirtualDesktop;
+
+    reusable_com_interface!(
+        MacroOptions {
+            temp_macro_name: _ITestInterface,
+            iid: "0F1C5A0E-5E0B-4B47-9C6A-3D0C9D7F1A03",
+        },
+        {
+            pub unsafe trait ITestInterface: IUnknown {
+                pub unsafe fn get_count(&self, out_count: *mut UINT) -> HRESULT;
+                pub unsafe fn take_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+                pub unsafe fn get_desktop(
+                    &self,
+                    out_desktop: *mut Option<IVirtualDesktop>,
+                ) -> HRESULT;
+                pub unsafe fn get_name(&self, out_name: *mut HSTRING) -> HRESULT;
+            }
+        }
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ITestInterface(IUnknown);
+support_interface!(MacroOptions {
+    interface_name: ITestInterface,
+    enum_name: ITestInterfaceInner,
+    all_versions: false,
+    versions: [build_10240, build_16299, build_22000],
+});
+
+#[apply(forward_call)]
+impl ITestInterface {
+    pub unsafe fn get_count(&self, out_count: *mut UINT) -> HRESULT;
+
+    pub unsafe fn take_desktop(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT;
+
+    pub unsafe fn get_desktop(&self, out_desktop: *mut Option<IVirtualDesktop>) -> HRESULT;
+
+    #[optional_method]
+    pub unsafe fn get_name(&self, out_name: *mut HSTRING) -> HRESULT;
+
+    /// Manual bodies are left as they are.
+    pub unsafe fn get_count_or_zero(&self) -> UINT {
+        let mut count = 0;
+        if self.get_count(&mut count).is_err() {
+            return 0;
+        }
+        count
+    }
+}
+
+fn assert_forward_arg<From: ForwardArg<To>, To>() {}
+
+// Generated methods keep the declared signatures:
+const _: fn() = || {
+    let _: unsafe fn(&ITestInterface, *mut UINT) -> HRESULT = ITestInterface::get_count;
+    let _: unsafe fn(&ITestInterface, ComIn<IVirtualDesktop>) -> HRESULT =
+        ITestInterface::take_desktop;
+    let _: unsafe fn(&ITestInterface, *mut Option<IVirtualDesktop>) -> HRESULT =
+        ITestInterface::get_desktop;
+    let _: unsafe fn(&ITestInterface, *mut HSTRING) -> HRESULT = ITestInterface::get_name;
+    let _: unsafe fn(&ITestInterface) -> UINT = ITestInterface::get_count_or_zero;
+    let _: fn() -> GUID = ITestInterface::IID;
+};
+
+// Arguments can be forwarded to every versioned interface:
+const _: fn() = || {
+    use crate::interfaces_multi::{build_10240, build_16299, build_22000};
+
+    assert_forward_arg::<ComIn<IVirtualDesktop>, ComIn<build_10240::IVirtualDesktop>>();
+    assert_forward_arg::<ComIn<IVirtualDesktop>, ComIn<build_16299::IVirtualDesktop>>();
+    assert_forward_arg::<ComIn<IVirtualDesktop>, ComIn<build_22000::IVirtualDesktop>>();
+    assert_forward_arg::<*mut Option<IVirtualDesktop>, *mut Option<build_10240::IVirtualDesktop>>();
+    assert_forward_arg::<*mut Option<IVirtualDesktop>, *mut Option<build_22000::IVirtualDesktop>>();
+    assert_forward_arg::<*mut UINT, *mut UINT>();
+};
+
+// The abstract type converts from and to the versioned types:
+const _: fn() = || {
+    fn assert_from<From, To: core::convert::From<From>>() {}
+
+    assert_from::<build_10240::ITestInterface, ITestInterface>();
+    assert_from::<build_16299::ITestInterface, ITestInterface>();
+    assert_from::<build_22000::ITestInterface, ITestInterface>();
+    assert_from::<ITestInterface, build_22000::ITestInterface>();
+    assert_from::<ComIn<build_16299::ITestInterface>, ComIn<ITestInterface>>();
+    assert_from::<ComIn<ITestInterface>, ComIn<build_16299::ITestInterface>>();
+};
+
+#[test]
+fn reused_interface_gets_new_iid() {
+    assert_eq!(
+        build_10240::ITestInterface::IID,
+        GUID::from_u128(0x0F1C5A0E_5E0B_4B47_9C6A_3D0C9D7F1A01)
+    );
+    assert_eq!(
+        build_16299::ITestInterface::IID,
+        GUID::from_u128(0x0F1C5A0E_5E0B_4B47_9C6A_3D0C9D7F1A02)
+    );
+    assert_eq!(
+        build_22000::ITestInterface::IID,
+        GUID::from_u128(0x0F1C5A0E_5E0B_4B47_9C6A_3D0C9D7F1A03)
+    );
+}
+
+#[test]
+fn reused_interface_keeps_vtable_layout() {
+    use core::mem::size_of;
+
+    assert_eq!(
+        size_of::<build_10240::ITestInterface_Vtbl>(),
+        size_of::<build_16299::ITestInterface_Vtbl>()
+    );
+    assert_eq!(
+        size_of::<build_10240::ITestInterface_Vtbl>() + size_of::<usize>(),
+        size_of::<build_22000::ITestInterface_Vtbl>()
+    );
+}
+
+#[test]
+fn abstract_interface_is_pointer_sized() {
+    use core::mem::size_of;
+
+    assert_eq!(size_of::<ITestInterface>(), size_of::<*mut c_void>());
+    assert_eq!(size_of::<ComIn<ITestInterface>>(), size_of::<*mut c_void>());
+    assert_eq!(
+        size_of::<Option<ITestInterface>>(),
+        size_of::<Option<build_22000::ITestInterface>>()
+    );
+}
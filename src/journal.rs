@@ -0,0 +1,190 @@
+//! Opt-in journal of the COM calls, for debugging user issues.
+//!
+//! When enabled, each call to the virtual desktop services is recorded with
+//! its arguments, result, duration and the number of retries, in the order
+//! the calls finished. Only the most recent calls are kept.
+//!
+//! ```rust,no_run
+//! winvd::enable_journal(100);
+//! let _ = winvd::switch_desktop(1);
+//! for operation in winvd::recent_operations() {
+//!     println!("{}", operation);
+//! }
+//! ```
+//!
+//! Calls made by other calls, e.g. `get_desktop_name` while listing the
+//! desktops, are recorded too.
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::Result;
+
+/// Recorded call, see `recent_operations`
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// Position in the journal, increases by one for each call
+    pub sequence: u64,
+
+    /// Name of the `ComObjects` method, e.g. "switch_desktop"
+    pub name: &'static str,
+
+    /// Debug formatted arguments, separated by commas
+    pub arguments: String,
+
+    /// Result after the retries, the value of successful calls is left out
+    pub result: Result<()>,
+
+    /// How many times the call was retried after a connection error
+    pub retries: u32,
+
+    pub finished_at: SystemTime,
+    pub duration: Duration,
+    pub thread: ThreadId,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {}({}) -> ",
+            self.sequence, self.name, self.arguments
+        )?;
+        match &self.result {
+            Ok(()) => write!(f, "Ok")?,
+            Err(err) => match err.hresult() {
+                Some(hresult) => write!(f, "{:?} (HRESULT 0x{:08X})", err, hresult.0)?,
+                None => write!(f, "{:?}", err)?,
+            },
+        }
+        write!(f, " in {:?}", self.duration)?;
+        if self.retries > 0 {
+            write!(f, " after {} retries", self.retries)?;
+        }
+        Ok(())
+    }
+}
+
+struct Journal {
+    capacity: usize,
+    next_sequence: u64,
+    operations: VecDeque<Operation>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal {
+    capacity: 0,
+    next_sequence: 0,
+    operations: VecDeque::new(),
+});
+
+/// Start recording the calls, keeping the `capacity` most recent ones.
+/// Disabled by default.
+pub fn enable_journal(capacity: usize) {
+    let mut journal = lock();
+    journal.capacity = capacity;
+    while journal.operations.len() > capacity {
+        journal.operations.pop_front();
+    }
+    ENABLED.store(capacity > 0, Ordering::SeqCst);
+}
+
+/// Stop recording the calls and clear the journal
+pub fn disable_journal() {
+    ENABLED.store(false, Ordering::SeqCst);
+    let mut journal = lock();
+    journal.capacity = 0;
+    journal.operations.clear();
+}
+
+/// Most recent calls, oldest first
+pub fn recent_operations() -> Vec<Operation> {
+    lock().operations.iter().cloned().collect()
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn record<R>(
+    name: &'static str,
+    arguments: &[&dyn Debug],
+    result: &Result<R>,
+    retries: u32,
+    started: Instant,
+) {
+    let duration = started.elapsed();
+    let arguments = arguments
+        .iter()
+        .map(|argument| format!("{:?}", argument))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut journal = lock();
+    if journal.capacity == 0 {
+        return;
+    }
+    if journal.operations.len() == journal.capacity {
+        journal.operations.pop_front();
+    }
+    let sequence = journal.next_sequence;
+    journal.next_sequence += 1;
+    journal.operations.push_back(Operation {
+        sequence,
+        name,
+        arguments,
+        result: result.as_ref().map(|_| ()).map_err(Clone::clone),
+        retries,
+        finished_at: SystemTime::now(),
+        duration,
+        thread: std::thread::current().id(),
+    });
+}
+
+fn lock() -> MutexGuard<'static, Journal> {
+    JOURNAL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_journal() {
+        enable_journal(2);
+        record("get_desktop_count", &[], &Ok(3), 0, Instant::now());
+        record::<()>(
+            "switch_desktop",
+            &[&5u32],
+            &Err(Error::DesktopNotFound),
+            1,
+            Instant::now(),
+        );
+        record("pin_window", &[&7u32, &"a"], &Ok(()), 0, Instant::now());
+
+        let operations = recent_operations();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].name, "switch_desktop");
+        assert_eq!(operations[0].arguments, "5");
+        assert_eq!(operations[0].result, Err(Error::DesktopNotFound));
+        assert_eq!(operations[1].arguments, "7, \"a\"");
+        assert_eq!(operations[1].sequence, operations[0].sequence + 1);
+        let line = operations[0].to_string();
+        let prefix = format!(
+            "#{} switch_desktop(5) -> DesktopNotFound in ",
+            operations[0].sequence
+        );
+        assert!(line.starts_with(&prefix), "{}", line);
+        assert!(line.ends_with(" after 1 retries"), "{}", line);
+
+        disable_journal();
+        assert!(recent_operations().is_empty());
+    }
+}
@@ -10,36 +10,190 @@
 //! * Get third desktop name `get_desktop(2).get_name()`
 #![allow(clippy::bool_assert_comparison)]
 
-// Log format macro
+// With `windows-057` the 0.57 crates are renamed to the usual names, so the
+// code and the code generated by their macros finds them
+#[cfg(not(any(feature = "windows-056", feature = "windows-057")))]
+compile_error!("Enable the windows-056 or the windows-057 feature");
+#[cfg(all(feature = "windows-056", feature = "windows-057"))]
+compile_error!(
+    "Enable only one of the windows-056 and windows-057 features, windows-057 needs default-features = false"
+);
+#[cfg(feature = "windows-057")]
+extern crate windows_057 as windows;
+#[cfg(feature = "windows-057")]
+extern crate windows_core_057 as windows_core;
+#[cfg(feature = "windows-057")]
+extern crate windows_implement_057 as windows_implement;
+#[cfg(feature = "windows-057")]
+extern crate windows_interface_057 as windows_interface;
+
+// Log format macro, formats only if the message goes somewhere. Debug level
+// unless the level is given first, e.g. `log_format!(Warn: "...")`. With the
+// `no-log` feature the branch is dead, and the messages are left out.
 macro_rules! log_format {
+    ($level:ident: $($arg:tt)*) => {
+        if !cfg!(feature = "no-log") && $crate::log::is_enabled($crate::log::LogLevel::$level) {
+            $crate::log::log_at($crate::log::LogLevel::$level, &format!($($arg)*));
+        }
+    };
     ($($arg:tt)*) => {
-        #[cfg(debug_assertions)]
-        $crate::log::log_output(&format!($($arg)*));
+        log_format!(Debug: $($arg)*)
     };
 }
 
+mod aliases;
+mod backend;
+mod batch;
+#[cfg(feature = "listener")]
+mod cache;
+mod com_in;
 mod comobjects;
 mod desktop;
+mod diagnostics;
+#[cfg(feature = "listener")]
+mod event_window;
+#[cfg(feature = "eventlog")]
+mod eventlog;
+#[cfg(feature = "listener")]
 mod events;
+#[cfg(feature = "listener")]
+mod history;
+mod iid_overrides;
 #[cfg_attr(feature = "multiple-windows-versions", allow(dead_code))]
 mod interfaces;
 #[cfg(feature = "multiple-windows-versions")]
 mod interfaces_multi;
+mod journal;
+#[cfg(feature = "listener")]
 mod listener;
 mod log;
+mod metrics;
+#[cfg(all(feature = "test-hooks", feature = "listener"))]
+mod mock;
+mod monitors;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "listener")]
+mod naming;
+// napi-rs registers the exports only outside of tests
+#[cfg(all(feature = "node", not(test)))]
+mod node;
+mod object_array;
+#[cfg(feature = "server")]
+mod pipe_server;
+#[cfg(feature = "listener")]
+mod placement;
+pub mod prelude;
+#[cfg(feature = "python")]
+mod python;
+mod raw;
+#[cfg(feature = "registry")]
+mod registry;
+#[cfg(any(feature = "server", feature = "websocket", feature = "mqtt"))]
+mod rpc;
+#[cfg(feature = "rules")]
+mod rules;
+#[cfg(feature = "rules-config")]
+mod rules_config;
+mod runtime;
+mod selftest;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod service;
+#[cfg(feature = "async")]
+mod service_async;
+#[cfg(feature = "listener")]
+mod sticky;
+#[cfg(feature = "test-hooks")]
+pub mod testing;
 
 #[cfg(feature = "integration-tests")]
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "listener")]
+mod tracked;
+#[cfg(feature = "listener")]
+mod wallpapers;
+mod window;
+#[cfg(feature = "websocket")]
+mod ws_server;
 
 // Allow importing the single version COM interfaces.
 #[cfg(not(feature = "multiple-windows-versions"))]
 use interfaces as interfaces_multi;
 
-pub use comobjects::Error;
+pub use aliases::{switch_to_alias, DesktopAlias, DesktopAliases};
+#[cfg(feature = "test-hooks")]
+pub use backend::set_backend;
+pub use backend::DesktopBackend;
+pub use batch::{batch, BatchOps};
+#[cfg(feature = "listener")]
+pub use cache::Cache;
+#[cfg(feature = "test-hooks")]
+pub use comobjects::simulate_shell_restart;
+pub use comobjects::{set_reconnect_policy, Error, ReconnectPolicy};
 pub use desktop::*;
+pub use diagnostics::{
+    interface_version_in_use, is_current_windows_supported, wallpaper_support, SupportStatus,
+    WallpaperSupport,
+};
+#[cfg(feature = "listener")]
+pub use event_window::{DesktopEventWindow, WM_DESKTOP_EVENT};
+#[cfg(feature = "eventlog")]
+pub use eventlog::{disable_event_log, enable_event_log};
+#[cfg(feature = "listener")]
 pub use events::*;
+#[cfg(feature = "listener")]
+pub use history::DesktopHistory;
+pub use iid_overrides::{load_iid_overrides, set_iid_override};
+#[cfg(feature = "multiple-windows-versions")]
+pub use interfaces_multi::{set_interface_probing, set_interface_version, BuildVersion};
+pub use journal::{disable_journal, enable_journal, recent_operations, Operation};
+#[cfg(feature = "listener")]
 pub use listener::DesktopEventThread;
+pub use log::{set_log_callback, LogCallback, LogLevel};
+pub use metrics::{
+    disable_metrics, enable_metrics, metrics_snapshot, reset_metrics, MethodMetrics,
+    MetricsSnapshot,
+};
+#[cfg(all(feature = "test-hooks", feature = "listener"))]
+pub use mock::MockBackend;
+pub use monitors::{
+    list_monitors, monitor_at_point, monitor_of_window, switch_desktop_on_monitor, MonitorInfo,
+};
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttBridge, MqttConfig};
+#[cfg(feature = "listener")]
+pub use naming::{DesktopNaming, NamingPolicy};
+#[cfg(feature = "server")]
+pub use pipe_server::{serve_pipe, PipeServer, DEFAULT_PIPE_NAME};
+#[cfg(feature = "listener")]
+pub use placement::PlacementGuard;
+pub use raw::{raw_manager_internal, raw_service_provider};
+#[cfg(feature = "registry")]
+pub use registry::{current_desktop_from_registry, desktops_from_registry};
+#[cfg(feature = "rules")]
+pub use rules::{RuleId, RuleTarget, WindowMatch, WindowRule, WindowRules};
+#[cfg(feature = "rules-config")]
+pub use rules_config::{load_rules, parse_rules};
+pub use runtime::{ChildReport, ChildStatus, RestartPolicy, Runtime, RuntimeChild};
+pub use selftest::{
+    run_compatibility_selftest, self_test, SelfTestCheck, SelfTestReport, SelfTestResult,
+};
+pub use service::VirtualDesktopService;
+#[cfg(feature = "async")]
+pub use service_async::ServiceFuture;
+#[cfg(feature = "listener")]
+pub use sticky::{StickyMode, StickyWindows};
+#[cfg(feature = "listener")]
+pub use tracked::TrackedDesktop;
+#[cfg(feature = "listener")]
+pub use wallpapers::WallpaperEmulation;
+#[cfg(feature = "raw-window-handle")]
+pub use window::RawWindowHandleMarker;
+pub use window::{IntoHwnd, WindowId};
+#[cfg(feature = "websocket")]
+pub use ws_server::{serve_ws, WsServer};
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[macro_use]
@@ -1,71 +1,156 @@
 use std::convert::TryInto;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::comobjects::ComObjects;
+use crate::comobjects::{ComObjects, DesktopInternal};
 use crate::interfaces_multi::{
     ComIn, IApplicationView, IVirtualDesktop, IVirtualDesktopNotification,
     IVirtualDesktopNotification_Impl,
 };
-use crate::log::log_output;
+use crate::log::{log_at, LogLevel};
+use crate::monitors::monitor_handles;
+use crate::registry::RegistryPoller;
 use crate::DesktopEventSender;
-use crate::{DesktopEvent, Result};
+use crate::{Desktop, DesktopEvent, Error, ListenerErrorHook, ListenerOptions, Result};
 
+use windows::core::{w, PCWSTR};
 #[allow(unused_imports)]
 use windows::core::{Interface, HRESULT, HSTRING};
-use windows::Win32::Foundation::HWND;
-use windows::Win32::System::Threading::{
-    GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_TIME_CRITICAL,
+use windows::Win32::Foundation::{
+    GetLastError, ERROR_CLASS_ALREADY_EXISTS, E_FAIL, HWND, LPARAM, LRESULT, WPARAM,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW, KillTimer,
+    PeekMessageW, PostThreadMessageW, RegisterClassW, SetTimer, TranslateMessage, UnregisterClassW,
+    MSG, PM_NOREMOVE, WINDOW_EX_STYLE, WM_APP, WM_DISPLAYCHANGE, WM_TIMER, WM_USER, WNDCLASSW,
+    WS_OVERLAPPED,
 };
 
-enum DekstopEventThreadMsg {
-    Quit,
-}
+/// Posted to the listener thread's message queue to stop the message loop.
+const WM_LISTENER_QUIT: u32 = WM_APP + 1;
+
+/// Posted to the listener thread's message queue when the displays changed.
+const WM_LISTENER_DISPLAY_CHANGED: u32 = WM_APP + 2;
+
+/// Window class of the hidden window receiving `WM_DISPLAYCHANGE`.
+const DISPLAY_WINDOW_CLASS: PCWSTR = w!("winvd_listener_display_window");
+
+/// Display windows of the running listeners, the window class is registered
+/// while there are any, and unregistered with the last one
+static DISPLAY_WINDOWS: Mutex<usize> = Mutex::new(0);
+
+/// Interval for checking that the listener is still registered.
+const RECONNECT_TIMER_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Events waiting for the worker thread, further events are dropped and
+/// `DesktopEvent::EventsDropped` is sent once there is room again.
+const EVENT_QUEUE_CAPACITY: usize = 256;
 
 /// Event listener thread, create with `listen_desktop_events(sender)`,
 /// value must be held in the state of the program, the thread is joined when
 /// the value is dropped.
 #[derive(Debug)]
 pub struct DesktopEventThread {
-    thread_control_sender: Option<std::sync::mpsc::Sender<DekstopEventThreadMsg>>,
+    thread_id: Option<u32>,
     thread: Option<std::thread::JoinHandle<()>>,
+
+    /// Passes the queued events to the sender, so a slow receiver doesn't
+    /// hold up the notification callbacks
+    worker: Option<std::thread::JoinHandle<()>>,
+    status: Arc<ListenerStatus>,
+}
+
+/// State of the listener, shared with the listener thread
+#[derive(Debug, Default)]
+struct ListenerStatus {
+    registered: AtomicBool,
+    last_event_at: Mutex<Option<Instant>>,
 }
 
 impl DesktopEventThread {
-    pub(crate) fn new<T>(sender: DesktopEventSender<T>) -> Result<Self>
+    pub(crate) fn new<T>(sender: DesktopEventSender<T>, options: ListenerOptions) -> Result<Self>
     where
         T: From<DesktopEvent> + Clone + Send + 'static,
     {
-        // Channel for quitting
-        let (tx, rx) = std::sync::mpsc::channel::<DekstopEventThreadMsg>();
+        // Channel for receiving the thread id of the listener thread
+        let (tx, rx) = std::sync::mpsc::channel::<u32>();
+
+        let status = Arc::new(ListenerStatus::default());
+        let thread_status = status.clone();
+
+        // Stops once the listener thread and its callbacks drop the queue
+        let (queue, events) = std::sync::mpsc::sync_channel::<DesktopEvent>(EVENT_QUEUE_CAPACITY);
+        let queue = EventQueue {
+            sender: queue,
+            overflowed: Arc::new(AtomicBool::new(false)),
+            on_error: options.on_error.clone(),
+        };
+        let worker = std::thread::spawn(move || {
+            for event in events {
+                sender.try_send(event.into());
+            }
+        });
 
-        // Main notification thread, with STA message loop
+        // Main notification thread, with a message loop
         let notification_thread = std::thread::spawn(move || {
             let com_objects = ComObjects::new();
             log_format!("Listener thread started {:?}", std::thread::current().id());
 
-            // Set thread priority to time critical, explorer.exe really hates if your listener thread is slow
-            let _ = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) };
+            // Ensure the thread has a message queue before anyone posts to it
+            let mut msg = MSG::default();
+            unsafe {
+                let _ = PeekMessageW(&mut msg, None, WM_USER, WM_USER, PM_NOREMOVE);
+            }
+            let _ = tx.send(unsafe { GetCurrentThreadId() });
 
             // Create listener
-            let sender_new = sender.clone();
-            let mut listener = VirtualDesktopNotificationWrapper::new(
-                &com_objects,
-                Box::new(move |event| {
-                    sender_new.try_send(event.into());
-                }),
+            let mut listener = register_listener(&com_objects, &queue, &thread_status);
+
+            if options.initial_state && listener.is_ok() {
+                if let Some(event) = initial_state_event(&com_objects) {
+                    queue.push(event);
+                }
+            }
+
+            // WM_DISPLAYCHANGE is only sent to top-level windows
+            let display_window = create_display_window();
+            let mut monitors = monitor_handles();
+
+            let timer =
+                unsafe { SetTimer(None, 0, RECONNECT_TIMER_INTERVAL.as_millis() as u32, None) };
+
+            // Registry is polled while the listener is not registered
+            let mut poller = None;
+            update_registry_poller(
+                &mut poller,
+                listener.is_ok(),
+                options.registry_poll_interval,
             );
 
             loop {
-                let item = rx.recv_timeout(Duration::from_secs(3));
-                match item {
-                    Ok(DekstopEventThreadMsg::Quit) => {
-                        log_output("Listener thread received quit message");
+                let res = unsafe { GetMessageW(&mut msg, None, 0, 0) };
+
+                // WM_QUIT or an error
+                if res.0 == 0 || res.0 == -1 {
+                    break;
+                }
+
+                match msg.message {
+                    WM_LISTENER_QUIT => {
+                        log_at(LogLevel::Debug, "Listener thread received quit message");
                         break;
                     }
-                    Err(_) => {
+                    WM_TIMER if msg.hwnd == HWND::default() && msg.wParam.0 == timer => {
+                        queue.resync(&com_objects);
                         if !com_objects.is_connected() || listener.is_err() {
-                            log_output(
+                            log_at(
+                                LogLevel::Warn,
                                 "Listener is not connected, or failed to register, trying again",
                             );
 
@@ -73,38 +158,113 @@ impl DesktopEventThread {
                             // new one is created, this is required, read more
                             // from note-IVirtualDesktopNotification.md
                             drop(listener);
-                            let sender_new = sender.clone();
-                            listener = VirtualDesktopNotificationWrapper::new(
-                                &com_objects,
-                                Box::new(move |event| {
-                                    sender_new.try_send(event.into());
-                                }),
+                            listener = register_listener(&com_objects, &queue, &thread_status);
+
+                            // Explorer was most likely restarted, any state the
+                            // consumer has cached is stale now
+                            if listener.is_ok() {
+                                if let Some(event) = connection_reset_event(&com_objects) {
+                                    queue.push(event);
+                                }
+                            }
+                            update_registry_poller(
+                                &mut poller,
+                                listener.is_ok(),
+                                options.registry_poll_interval,
                             );
                         }
                     }
+                    WM_TIMER if msg.hwnd == HWND::default() => {
+                        if let Some((poll_timer, poller)) = &mut poller {
+                            if msg.wParam.0 == *poll_timer {
+                                for event in poller.poll() {
+                                    queue.push(event);
+                                }
+                            }
+                        }
+                    }
+                    WM_LISTENER_DISPLAY_CHANGED => {
+                        // Resolution changes also send WM_DISPLAYCHANGE,
+                        // those are not interesting
+                        let new_monitors = monitor_handles();
+                        if new_monitors != monitors {
+                            monitors = new_monitors;
+                            queue.push(DesktopEvent::MonitorsChanged(monitors.clone()));
+                        }
+                    }
+                    _ => unsafe {
+                        let _ = TranslateMessage(&msg);
+                        DispatchMessageW(&msg);
+                    },
                 }
             }
 
+            if timer != 0 {
+                let _ = unsafe { KillTimer(None, timer) };
+            }
+            update_registry_poller(&mut poller, true, None);
+            if let Some(hwnd) = display_window {
+                destroy_display_window(hwnd);
+            }
+            drop(listener);
+            thread_status.registered.store(false, Ordering::SeqCst);
+
             log_format!("Listener thread finished {:?}", std::thread::current().id());
         });
 
+        // Wait until the message queue of the thread exists
+        let thread_id = rx.recv().map_err(|_| Error::ListenerThreadFailed)?;
+
         // Store the new thread
         Ok(DesktopEventThread {
-            thread_control_sender: Some(tx),
+            thread_id: Some(thread_id),
             thread: Some(notification_thread),
+            worker: Some(worker),
+            status,
         })
     }
 
+    /// Returns false once the listener thread has exited, e.g. after a panic
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Returns true if the listener is currently registered for notifications.
+    ///
+    /// If this stays false, the listener thread is not able to register, e.g.
+    /// when explorer.exe is not running or the Windows version is not
+    /// supported.
+    pub fn is_registered(&self) -> bool {
+        self.status.registered.load(Ordering::SeqCst)
+    }
+
+    /// Time of the last notification received from explorer.exe, or `None` if
+    /// nothing has been received yet
+    pub fn last_event_at(&self) -> Option<Instant> {
+        self.status
+            .last_event_at
+            .lock()
+            .map_or(None, |last_event_at| *last_event_at)
+    }
+
     /// Stops the listener, and join the thread if it is still running, normally
     /// you don't need to call this as drop calls this automatically
     pub fn stop(&mut self) -> std::thread::Result<()> {
-        if let Some(thread_control_sender) = self.thread_control_sender.take() {
-            let _ = thread_control_sender.send(DekstopEventThreadMsg::Quit);
+        if let Some(thread_id) = self.thread_id.take() {
+            let _ =
+                unsafe { PostThreadMessageW(thread_id, WM_LISTENER_QUIT, WPARAM(0), LPARAM(0)) };
         }
 
         if let Some(thread) = self.thread.take() {
             thread.join()?;
         }
+
+        // Sends the remaining queued events before stopping
+        if let Some(worker) = self.worker.take() {
+            worker.join()?;
+        }
         Ok(())
     }
 }
@@ -120,6 +280,243 @@ impl Drop for DesktopEventThread {
     }
 }
 
+/// Listener with a thread handling its events, owned by the managers, e.g.
+/// `StickyWindows`. Both threads are joined when stopped, so the handler
+/// doesn't run afterwards.
+pub(crate) struct DesktopEventHandler {
+    listener: Option<DesktopEventThread>,
+    handler: Option<std::thread::JoinHandle<()>>,
+}
+
+impl DesktopEventHandler {
+    /// Starts the listener, `handler` is called on the handler thread
+    pub(crate) fn new<F>(mut handler: F) -> Result<Self>
+    where
+        F: FnMut(DesktopEvent) + Send + 'static,
+    {
+        let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+        let listener = DesktopEventThread::new(tx.into(), ListenerOptions::default())?;
+
+        // Stops when the listener is stopped and drops the sender
+        let handler = std::thread::spawn(move || {
+            for event in rx {
+                handler(event);
+            }
+        });
+        Ok(DesktopEventHandler {
+            listener: Some(listener),
+            handler: Some(handler),
+        })
+    }
+
+    pub(crate) fn is_registered(&self) -> bool {
+        self.listener
+            .as_ref()
+            .is_some_and(|listener| listener.is_registered())
+    }
+
+    /// Stops the listener, and joins the handler once it handled the
+    /// remaining events
+    pub(crate) fn stop(&mut self) {
+        drop(self.listener.take());
+        if let Some(handler) = self.handler.take() {
+            let _ = handler.join();
+        }
+    }
+}
+
+impl Drop for DesktopEventHandler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Registers a new listener queueing the events, and updates the status
+fn register_listener<'a>(
+    com_objects: &'a ComObjects,
+    queue: &EventQueue,
+    status: &Arc<ListenerStatus>,
+) -> Result<Pin<Box<VirtualDesktopNotificationWrapper<'a>>>> {
+    let event_queue = queue.clone();
+    let event_status = status.clone();
+    let listener = VirtualDesktopNotificationWrapper::new(
+        com_objects,
+        Box::new(move |event| {
+            if let Ok(mut last_event_at) = event_status.last_event_at.lock() {
+                *last_event_at = Some(Instant::now());
+            }
+            event_queue.push(event);
+        }),
+    );
+
+    status.registered.store(listener.is_ok(), Ordering::SeqCst);
+    if let (Err(err), Some(on_error)) = (&listener, &queue.on_error) {
+        on_error(err.clone());
+    }
+    listener
+}
+
+/// Events waiting for the worker thread
+#[derive(Clone)]
+struct EventQueue {
+    sender: SyncSender<DesktopEvent>,
+
+    /// Set when events were dropped, until `DesktopEvent::EventsDropped` is
+    /// queued
+    overflowed: Arc<AtomicBool>,
+    on_error: Option<ListenerErrorHook>,
+}
+
+impl EventQueue {
+    /// Queues the event without blocking, so explorer.exe is not held up by a
+    /// slow receiver. If the receiver has fallen too far behind the event is
+    /// dropped, and `Error::EventsDropped` is passed to `on_error` once.
+    fn push(&self, event: DesktopEvent) {
+        if let Err(TrySendError::Full(_event)) = self.sender.try_send(event) {
+            if !self.overflowed.swap(true, Ordering::SeqCst) {
+                log_format!(Warn: "Listener event queue is full, dropping {:?}", _event);
+                if let Some(on_error) = &self.on_error {
+                    on_error(Error::EventsDropped);
+                }
+            }
+        }
+    }
+
+    /// Queues `DesktopEvent::EventsDropped` with the current desktops if
+    /// events were dropped and there is room again
+    fn resync(&self, com_objects: &ComObjects) {
+        if !self.overflowed.load(Ordering::SeqCst) {
+            return;
+        }
+        let Some(desktops) = current_desktops(com_objects) else {
+            return;
+        };
+        if self
+            .sender
+            .try_send(DesktopEvent::EventsDropped { desktops })
+            .is_ok()
+        {
+            self.overflowed.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Starts polling the registry if the listener is not registered, and stops
+/// it once it is
+fn update_registry_poller(
+    poller: &mut Option<(usize, RegistryPoller)>,
+    registered: bool,
+    interval: Option<Duration>,
+) {
+    match (&poller, registered, interval) {
+        (None, false, Some(interval)) => {
+            let timer = unsafe { SetTimer(None, 0, interval.as_millis() as u32, None) };
+            if timer != 0 {
+                log_at(
+                    LogLevel::Warn,
+                    "Listener is not registered, polling the registry instead",
+                );
+                *poller = Some((timer, RegistryPoller::new()));
+            }
+        }
+        (Some((timer, _)), true, _) => {
+            let _ = unsafe { KillTimer(None, *timer) };
+            *poller = None;
+        }
+        _ => {}
+    }
+}
+
+/// Creates a hidden top-level window for receiving `WM_DISPLAYCHANGE`, it
+/// forwards the message to the thread's message queue
+fn create_display_window() -> Option<HWND> {
+    let mut count = DISPLAY_WINDOWS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    unsafe {
+        let instance = GetModuleHandleW(None).ok()?;
+        if *count == 0 {
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(display_window_proc),
+                hInstance: instance.into(),
+                lpszClassName: DISPLAY_WINDOW_CLASS,
+                ..Default::default()
+            };
+
+            // Already existing class is left over from a listener that wasn't
+            // stopped, e.g. of an unloaded DLL, and works the same
+            if RegisterClassW(&class) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+                log_at(
+                    LogLevel::Warn,
+                    "Could not register window class for display changes",
+                );
+                return None;
+            }
+        }
+
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            DISPLAY_WINDOW_CLASS,
+            PCWSTR::null(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            instance,
+            None,
+        );
+        if hwnd == HWND::default() {
+            log_at(
+                LogLevel::Warn,
+                "Could not create window for display changes",
+            );
+            if *count == 0 {
+                let _ = UnregisterClassW(DISPLAY_WINDOW_CLASS, instance);
+            }
+            return None;
+        }
+        *count += 1;
+        Some(hwnd)
+    }
+}
+
+/// Destroys the window of `create_display_window`, the last one also
+/// unregisters the window class
+fn destroy_display_window(hwnd: HWND) {
+    let mut count = DISPLAY_WINDOWS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    unsafe {
+        let _ = DestroyWindow(hwnd);
+        *count -= 1;
+        if *count == 0 {
+            if let Ok(instance) = GetModuleHandleW(None) {
+                let _ = UnregisterClassW(DISPLAY_WINDOW_CLASS, instance);
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn display_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DISPLAYCHANGE {
+        let _ = PostThreadMessageW(
+            GetCurrentThreadId(),
+            WM_LISTENER_DISPLAY_CHANGED,
+            WPARAM(0),
+            LPARAM(0),
+        );
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
 /// Wrapper registers the actual IVirtualDesktopNotification and on drop unregisters the notification
 struct VirtualDesktopNotificationWrapper<'a> {
     #[allow(dead_code)]
@@ -143,7 +540,7 @@ impl<'a> VirtualDesktopNotificationWrapper<'a> {
             ptr,
         }));
         log_format!(
-            "Registered notification {:?} {} {:?}",
+            Info: "Registered notification {:?} {} {:?}",
             raw_ptr,
             notification.cookie,
             std::thread::current().id()
@@ -156,7 +553,7 @@ impl<'a> VirtualDesktopNotificationWrapper<'a> {
 impl<'a> Drop for VirtualDesktopNotificationWrapper<'a> {
     fn drop(&mut self) {
         log_format!(
-            "Unregistering notification {} {:?}",
+            Info: "Unregistering notification {} {:?}",
             self.cookie,
             std::thread::current().id()
         );
@@ -176,12 +573,64 @@ fn eat_error<T>(func: impl FnOnce() -> Result<T>) -> Option<T> {
     match res {
         Ok(v) => Some(v),
         Err(_er) => {
-            log_format!("Error in listener: {:?}", _er);
+            log_format!(Warn: "Error in listener: {:?}", _er);
             None
         }
     }
 }
 
+/// Event with a fresh list of desktops, sent after the listener re-registered
+fn connection_reset_event(com_objects: &ComObjects) -> Option<DesktopEvent> {
+    let desktops = current_desktops(com_objects)?;
+    Some(DesktopEvent::ConnectionReset { desktops })
+}
+
+fn current_desktops(com_objects: &ComObjects) -> Option<Vec<Desktop>> {
+    let desktops = eat_error(|| com_objects.get_desktops())?;
+    Some(desktops.into_iter().map(Desktop::from).collect())
+}
+
+/// Event describing the current state, sent right after the listener registered
+fn initial_state_event(com_objects: &ComObjects) -> Option<DesktopEvent> {
+    eat_error(|| {
+        let desktops: Vec<_> = com_objects
+            .get_desktop_infos()?
+            .into_iter()
+            .map(crate::desktop::with_default_name)
+            .collect();
+        let current_id = com_objects.get_desktop_id(&com_objects.get_current_desktop()?)?;
+        let current = desktops
+            .iter()
+            .find(|info| info.guid == current_id)
+            .map_or(DesktopInternal::Guid(current_id), |info| {
+                DesktopInternal::IndexGuid(info.index, info.guid)
+            });
+        Ok(DesktopEvent::InitialState {
+            current: current.into(),
+            desktops,
+        })
+    })
+}
+
+/// Runs the body of a notification callback. A panic must not unwind into
+/// explorer.exe, it's logged and the call fails with `E_FAIL` instead.
+///
+/// The adaptors of every build in `interfaces_multi` only forward to the
+/// methods below, so guarding these covers all builds.
+fn guard_callback(_callback: &str, body: impl FnOnce()) -> HRESULT {
+    match std::panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(()) => HRESULT(0),
+        Err(_panic) => {
+            log_format!(
+                "Panic in listener callback {}: {}",
+                _callback,
+                crate::runtime::panic_message(&_panic)
+            );
+            E_FAIL
+        }
+    }
+}
+
 // Allow unused variable warnings
 #[allow(unused_variables)]
 impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
@@ -190,13 +639,14 @@ impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
         desktop_old: ComIn<IVirtualDesktop>,
         desktop_new: ComIn<IVirtualDesktop>,
     ) -> HRESULT {
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopChanged {
-                old: desktop_old.try_into()?,
-                new: desktop_new.try_into()?,
-            }))
-        });
-        HRESULT(0)
+        guard_callback("current_virtual_desktop_changed", || {
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopChanged {
+                    old: desktop_old.try_into()?,
+                    new: desktop_new.try_into()?,
+                }))
+            });
+        })
     }
 
     unsafe fn virtual_desktop_wallpaper_changed(
@@ -204,22 +654,24 @@ impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
         desktop: ComIn<IVirtualDesktop>,
         name: HSTRING,
     ) -> HRESULT {
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopWallpaperChanged(
-                desktop.try_into()?,
-                name.to_string(),
-            )))
-        });
-        HRESULT(0)
+        guard_callback("virtual_desktop_wallpaper_changed", || {
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopWallpaperChanged(
+                    desktop.try_into()?,
+                    name.to_string(),
+                )))
+            });
+        })
     }
 
     unsafe fn virtual_desktop_created(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT {
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopCreated(
-                desktop.try_into()?,
-            )))
-        });
-        HRESULT(0)
+        guard_callback("virtual_desktop_created", || {
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopCreated(
+                    desktop.try_into()?,
+                )))
+            });
+        })
     }
 
     unsafe fn virtual_desktop_destroy_begin(
@@ -243,14 +695,15 @@ impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
         desktop_destroyed: ComIn<IVirtualDesktop>,
         desktop_fallback: ComIn<IVirtualDesktop>,
     ) -> HRESULT {
-        // Desktop destroyed is not anymore in the stack
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopDestroyed {
-                destroyed: desktop_destroyed.try_into()?,
-                fallback: desktop_fallback.try_into()?,
-            }))
-        });
-        HRESULT(0)
+        guard_callback("virtual_desktop_destroyed", || {
+            // Desktop destroyed is not anymore in the stack
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopDestroyed {
+                    destroyed: desktop_destroyed.try_into()?,
+                    fallback: desktop_fallback.try_into()?,
+                }))
+            });
+        })
     }
 
     unsafe fn virtual_desktop_moved(
@@ -259,14 +712,15 @@ impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
         old_index: i64,
         new_index: i64,
     ) -> HRESULT {
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopMoved {
-                desktop: desktop.try_into()?,
-                old_index,
-                new_index,
-            }))
-        });
-        HRESULT(0)
+        guard_callback("virtual_desktop_moved", || {
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopMoved {
+                    desktop: desktop.try_into()?,
+                    old_index,
+                    new_index,
+                }))
+            });
+        })
     }
 
     unsafe fn virtual_desktop_name_changed(
@@ -274,20 +728,24 @@ impl IVirtualDesktopNotification_Impl for VirtualDesktopNotification {
         desktop: ComIn<IVirtualDesktop>,
         name: HSTRING,
     ) -> HRESULT {
-        eat_error(|| {
-            Ok((self.sender)(DesktopEvent::DesktopNameChanged(
-                desktop.try_into()?,
-                name.to_string(),
-            )))
-        });
-        HRESULT(0)
+        guard_callback("virtual_desktop_name_changed", || {
+            eat_error(|| {
+                Ok((self.sender)(DesktopEvent::DesktopNameChanged(
+                    desktop.try_into()?,
+                    name.to_string(),
+                )))
+            });
+        })
     }
 
     unsafe fn view_virtual_desktop_changed(&self, view: ComIn<IApplicationView>) -> HRESULT {
-        let mut hwnd = HWND::default();
-        let _ = view.get_thumbnail_window(&mut hwnd);
-        (self.sender)(DesktopEvent::WindowChanged(hwnd));
-        HRESULT(0)
+        guard_callback("view_virtual_desktop_changed", || {
+            let mut hwnd = HWND::default();
+            if let Some(view) = ComIn::get(&view) {
+                let _ = view.get_thumbnail_window(&mut hwnd);
+            }
+            (self.sender)(DesktopEvent::WindowChanged(hwnd.into()));
+        })
     }
 
     unsafe fn virtual_desktop_switched(&self, desktop: ComIn<IVirtualDesktop>) -> HRESULT {
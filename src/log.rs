@@ -1,10 +1,80 @@
-#[cfg(debug_assertions)]
+use std::sync::RwLock;
+
+/// Level of a log message passed to the log callback
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+/// Receiver of the log messages, see `set_log_callback`
+pub type LogCallback = fn(&str, LogLevel);
+
+static CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+
+/// Sends the log messages of the crate to the callback instead of the default
+/// output, e.g. to the console or the log file of the application. `None`
+/// restores the default output.
+///
+/// By default the messages are written to the debug output in debug builds
+/// only, and with the `tracing` feature they are `tracing` events. The
+/// callback receives them in release builds too.
+///
+/// The callback is called from the thread logging, e.g. the listener thread,
+/// and must not block. With the `no-log` feature it is never called.
+///
+/// ```rust
+/// winvd::set_log_callback(Some(|message, level| eprintln!("{:?}: {}", level, message)));
+/// ```
+pub fn set_log_callback(callback: Option<LogCallback>) {
+    *CALLBACK.write().unwrap_or_else(|p| p.into_inner()) = callback;
+}
+
+fn callback() -> Option<LogCallback> {
+    *CALLBACK.read().unwrap_or_else(|p| p.into_inner())
+}
+
+/// False if the messages of the level would be dropped, so they don't need
+/// formatting
+pub(crate) fn is_enabled(level: LogLevel) -> bool {
+    if cfg!(feature = "no-log") {
+        return false;
+    }
+    #[cfg(feature = "eventlog")]
+    if crate::eventlog::is_enabled(level) {
+        return true;
+    }
+    callback().is_some() || default_enabled(level)
+}
+
+pub(crate) fn log_at(level: LogLevel, s: &str) {
+    if cfg!(feature = "no-log") {
+        return;
+    }
+    #[cfg(feature = "eventlog")]
+    crate::eventlog::report(level, s);
+
+    // Copied out, so the callback can replace itself
+    match callback() {
+        Some(callback) => callback(s, level),
+        None => default_output(level, s),
+    }
+}
+
+#[cfg(all(debug_assertions, not(feature = "tracing")))]
 extern "system" {
     fn OutputDebugStringW(lpOutputString: windows::core::PCWSTR);
 }
 
-#[cfg(debug_assertions)]
-pub(crate) fn log_output(s: &str) {
+#[cfg(all(debug_assertions, not(feature = "tracing")))]
+fn default_enabled(_level: LogLevel) -> bool {
+    true
+}
+
+#[cfg(all(debug_assertions, not(feature = "tracing")))]
+fn default_output(_level: LogLevel, s: &str) {
     unsafe {
         println!("{}", s);
         let notepad = format!("{}\0", s).encode_utf16().collect::<Vec<_>>();
@@ -13,6 +83,33 @@ pub(crate) fn log_output(s: &str) {
     }
 }
 
-#[cfg(not(debug_assertions))]
+/// Logs go to the subscriber of the application instead
+#[cfg(feature = "tracing")]
+fn default_enabled(level: LogLevel) -> bool {
+    match level {
+        LogLevel::Error => tracing::enabled!(tracing::Level::ERROR),
+        LogLevel::Warn => tracing::enabled!(tracing::Level::WARN),
+        LogLevel::Info => tracing::enabled!(tracing::Level::INFO),
+        LogLevel::Debug => tracing::enabled!(tracing::Level::DEBUG),
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn default_output(level: LogLevel, s: &str) {
+    match level {
+        LogLevel::Error => tracing::error!("{}", s),
+        LogLevel::Warn => tracing::warn!("{}", s),
+        LogLevel::Info => tracing::info!("{}", s),
+        LogLevel::Debug => tracing::debug!("{}", s),
+    }
+}
+
+#[cfg(all(not(debug_assertions), not(feature = "tracing")))]
+#[inline]
+fn default_enabled(_level: LogLevel) -> bool {
+    false
+}
+
+#[cfg(all(not(debug_assertions), not(feature = "tracing")))]
 #[inline]
-pub(crate) fn log_output(_s: &str) {}
+fn default_output(_level: LogLevel, _s: &str) {}
@@ -0,0 +1,247 @@
+//! Opt-in statistics of the COM calls, for finding slow calls and measuring
+//! how often explorer.exe disconnects.
+//!
+//! When enabled, the calls to the virtual desktop services are counted by the
+//! `ComObjects` method, with their failures by HRESULT, the retries after
+//! connection errors and the latencies:
+//!
+//! ```rust,no_run
+//! winvd::enable_metrics();
+//! let _ = winvd::switch_desktop(1);
+//! for method in winvd::metrics_snapshot().methods {
+//!     println!("{}", method);
+//! }
+//! ```
+//!
+//! Unlike the journal, the arguments of the calls are not kept, so the
+//! metrics can stay enabled for the life of the application.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::{Error, Result};
+
+/// Statistics of one `ComObjects` method, see `metrics_snapshot`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// Name of the method, e.g. "switch_desktop"
+    pub name: &'static str,
+    pub calls: u64,
+
+    /// Calls that failed after the retries
+    pub failures: u64,
+
+    /// Failures by the HRESULT of the error, failures without an HRESULT, e.g.
+    /// `DesktopNotFound`, are only in `failures`
+    pub failures_by_hresult: BTreeMap<i32, u64>,
+
+    /// Calls that failed with `Error::Disconnected`, explorer.exe was not
+    /// reachable even after the retries
+    pub disconnects: u64,
+
+    /// Retries after connection errors, e.g. when explorer.exe restarted
+    pub retries: u64,
+
+    /// Total time of the calls, including the retries
+    pub total_duration: Duration,
+    pub max_duration: Duration,
+}
+
+impl MethodMetrics {
+    fn new(name: &'static str) -> Self {
+        MethodMetrics {
+            name,
+            calls: 0,
+            failures: 0,
+            failures_by_hresult: BTreeMap::new(),
+            disconnects: 0,
+            retries: 0,
+            total_duration: Duration::ZERO,
+            max_duration: Duration::ZERO,
+        }
+    }
+
+    /// Average time of the calls
+    pub fn mean_duration(&self) -> Duration {
+        let calls = u32::try_from(self.calls).unwrap_or(u32::MAX);
+        self.total_duration.checked_div(calls).unwrap_or_default()
+    }
+}
+
+impl Display for MethodMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} calls, {} failed, {} retries, mean {:?}, max {:?}",
+            self.name,
+            self.calls,
+            self.failures,
+            self.retries,
+            self.mean_duration(),
+            self.max_duration
+        )?;
+        for (hresult, count) in &self.failures_by_hresult {
+            write!(f, ", 0x{:08X} x{}", hresult, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of the calls since the metrics were enabled or reset
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub since: SystemTime,
+
+    /// Methods called at least once, by name
+    pub methods: Vec<MethodMetrics>,
+}
+
+impl MetricsSnapshot {
+    /// Statistics of the method, `None` if it wasn't called
+    pub fn method(&self, name: &str) -> Option<&MethodMetrics> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+
+    /// Calls of all methods
+    pub fn calls(&self) -> u64 {
+        self.methods.iter().map(|method| method.calls).sum()
+    }
+
+    /// Failures of all methods
+    pub fn failures(&self) -> u64 {
+        self.methods.iter().map(|method| method.failures).sum()
+    }
+
+    /// Calls of all methods that found explorer.exe disconnected
+    pub fn disconnects(&self) -> u64 {
+        self.methods.iter().map(|method| method.disconnects).sum()
+    }
+}
+
+struct Metrics {
+    since: SystemTime,
+    methods: HashMap<&'static str, MethodMetrics>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static METRICS: Mutex<Option<Metrics>> = Mutex::new(None);
+
+/// Start counting the calls. Disabled by default.
+pub fn enable_metrics() {
+    let mut metrics = lock();
+    if metrics.is_none() {
+        *metrics = Some(Metrics {
+            since: SystemTime::now(),
+            methods: HashMap::new(),
+        });
+    }
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Stop counting the calls and clear the statistics
+pub fn disable_metrics() {
+    ENABLED.store(false, Ordering::SeqCst);
+    *lock() = None;
+}
+
+/// Clear the statistics, keeps counting if enabled
+pub fn reset_metrics() {
+    if let Some(metrics) = lock().as_mut() {
+        metrics.since = SystemTime::now();
+        metrics.methods.clear();
+    }
+}
+
+/// Statistics of the calls so far, empty if the metrics are not enabled
+pub fn metrics_snapshot() -> MetricsSnapshot {
+    let metrics = lock();
+    let Some(metrics) = metrics.as_ref() else {
+        return MetricsSnapshot {
+            since: SystemTime::now(),
+            methods: Vec::new(),
+        };
+    };
+    let mut methods = metrics.methods.values().cloned().collect::<Vec<_>>();
+    methods.sort_by_key(|method| method.name);
+    MetricsSnapshot {
+        since: metrics.since,
+        methods,
+    }
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn record<R>(name: &'static str, result: &Result<R>, retries: u32, duration: Duration) {
+    let mut metrics = lock();
+    let Some(metrics) = metrics.as_mut() else {
+        return;
+    };
+    let method = metrics
+        .methods
+        .entry(name)
+        .or_insert_with(|| MethodMetrics::new(name));
+    method.calls += 1;
+    method.retries += u64::from(retries);
+    method.total_duration += duration;
+    method.max_duration = method.max_duration.max(duration);
+    if let Err(err) = result {
+        method.failures += 1;
+        if let Some(hresult) = err.hresult() {
+            *method.failures_by_hresult.entry(hresult.0).or_default() += 1;
+        }
+        if *err == Error::Disconnected {
+            method.disconnects += 1;
+        }
+    }
+}
+
+fn lock() -> MutexGuard<'static, Option<Metrics>> {
+    METRICS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        enable_metrics();
+        let millis = Duration::from_millis;
+        record("test_count", &Ok(3), 0, millis(2));
+        record("test_count", &Ok(3), 0, millis(4));
+        record::<()>("test_switch", &Err(Error::Disconnected), 3, millis(30));
+        record::<()>("test_switch", &Err(Error::DesktopNotFound), 0, millis(1));
+        record::<()>("test_switch", &Err(Error::ComNotImplemented), 0, millis(1));
+
+        let snapshot = metrics_snapshot();
+        let count = snapshot.method("test_count").unwrap();
+        assert_eq!(count.calls, 2);
+        assert_eq!(count.failures, 0);
+        assert_eq!(count.mean_duration(), millis(3));
+        assert_eq!(count.max_duration, millis(4));
+        let switch = snapshot.method("test_switch").unwrap();
+        assert_eq!(switch.failures, 3);
+        assert_eq!(switch.disconnects, 1);
+        assert_eq!(switch.retries, 3);
+        assert_eq!(
+            switch.failures_by_hresult,
+            BTreeMap::from([(0x80004001_u32 as i32, 1)])
+        );
+        assert!(switch.to_string().ends_with(", 0x80004001 x1"));
+        assert_eq!(snapshot.failures(), 3);
+        assert_eq!(snapshot.disconnects(), 1);
+
+        reset_metrics();
+        assert!(metrics_snapshot().methods.is_empty());
+        disable_metrics();
+        record("test_count", &Ok(3), 0, millis(2));
+        assert!(metrics_snapshot().methods.is_empty());
+    }
+}
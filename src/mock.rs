@@ -0,0 +1,378 @@
+//! In-memory backend for tests, enabled with the `test-hooks` feature.
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard};
+use windows::core::GUID;
+use windows::Win32::Foundation::HWND;
+
+use crate::comobjects::DesktopInternal;
+use crate::{Desktop, DesktopBackend, DesktopEvent, Error, IntoHwnd, Result, ViewInfo};
+
+/// Desktops and windows kept in memory, install with `set_backend`.
+///
+/// Desktops get the GUIDs 1, 2, 3... in order of creation, and are unnamed
+/// until renamed. Windows are only known after `add_window`, and apps can't
+/// be pinned.
+#[derive(Debug)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// GUIDs, names and wallpapers of the desktops, in order
+    desktops: Vec<(GUID, String, String)>,
+    current: GUID,
+    windows: Vec<(HWND, GUID)>,
+    pinned: Vec<HWND>,
+    created: u128,
+    subscribers: Vec<Sender<DesktopEvent>>,
+}
+
+impl MockBackend {
+    /// Mock with the given number of desktops, at least one, the first
+    /// desktop is the current one
+    pub fn new(count: u32) -> Self {
+        let mut state = MockState::default();
+        for _ in 0..count.max(1) {
+            state.add_desktop();
+        }
+        state.current = state.desktops[0].0;
+        MockBackend {
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Puts a window on the desktop, or moves it there if it's known already
+    pub fn add_window<M>(&self, hwnd: impl IntoHwnd<M>, desktop: Desktop) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        let mut state = self.state();
+        let guid = state.desktops[state.position(desktop)?].0;
+        state.windows.retain(|(window, _)| *window != hwnd);
+        state.windows.push((hwnd, guid));
+        Ok(())
+    }
+
+    /// Receives the events the shell would send for the changes made through
+    /// the backend, desktops are given by GUID like in the listener's events
+    pub fn subscribe(&self) -> Receiver<DesktopEvent> {
+        let (tx, rx) = channel();
+        self.state().subscribers.push(tx);
+        rx
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl MockState {
+    fn add_desktop(&mut self) -> GUID {
+        self.created += 1;
+        let guid = GUID::from_u128(self.created);
+        self.desktops.push((guid, String::new(), String::new()));
+        guid
+    }
+
+    fn position(&self, desktop: Desktop) -> Result<usize> {
+        let position = match DesktopInternal::from(desktop) {
+            DesktopInternal::Index(index) => Some(index as usize),
+            DesktopInternal::Guid(guid) | DesktopInternal::IndexGuid(_, guid) => {
+                self.desktops.iter().position(|(id, ..)| *id == guid)
+            }
+        };
+        position
+            .filter(|position| *position < self.desktops.len())
+            .ok_or(Error::DesktopNotFound)
+    }
+
+    fn desktop_at(&self, position: usize) -> Desktop {
+        DesktopInternal::IndexGuid(position as u32, self.desktops[position].0).into()
+    }
+
+    fn window(&self, hwnd: HWND) -> Result<usize> {
+        self.windows
+            .iter()
+            .position(|(window, _)| *window == hwnd)
+            .ok_or(Error::WindowNotFound)
+    }
+
+    fn send(&mut self, event: DesktopEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+impl DesktopBackend for MockBackend {
+    fn get_desktops(&self) -> Result<Vec<Desktop>> {
+        let state = self.state();
+        Ok((0..state.desktops.len())
+            .map(|position| state.desktop_at(position))
+            .collect())
+    }
+
+    fn get_desktop_count(&self) -> Result<u32> {
+        Ok(self.state().desktops.len() as u32)
+    }
+
+    fn get_current_desktop(&self) -> Result<Desktop> {
+        let state = self.state();
+        Ok(state.desktop_at(state.position(Desktop::from(state.current))?))
+    }
+
+    fn get_desktop_id(&self, desktop: Desktop) -> Result<GUID> {
+        let state = self.state();
+        Ok(state.desktops[state.position(desktop)?].0)
+    }
+
+    fn get_desktop_index(&self, desktop: Desktop) -> Result<u32> {
+        Ok(self.state().position(desktop)? as u32)
+    }
+
+    fn get_desktop_name(&self, desktop: Desktop) -> Result<String> {
+        let state = self.state();
+        Ok(state.desktops[state.position(desktop)?].1.clone())
+    }
+
+    fn set_desktop_name(&self, desktop: Desktop, name: &str) -> Result<()> {
+        let mut state = self.state();
+        let position = state.position(desktop)?;
+        state.desktops[position].1 = name.to_string();
+        let guid = state.desktops[position].0;
+        state.send(DesktopEvent::DesktopNameChanged(
+            guid.into(),
+            name.to_string(),
+        ));
+        Ok(())
+    }
+
+    fn switch_desktop(&self, desktop: Desktop) -> Result<()> {
+        let mut state = self.state();
+        let new = state.desktops[state.position(desktop)?].0;
+        let old = std::mem::replace(&mut state.current, new);
+        if old != new {
+            state.send(DesktopEvent::DesktopChanged {
+                new: new.into(),
+                old: old.into(),
+            });
+        }
+        Ok(())
+    }
+
+    fn create_desktop(&self) -> Result<Desktop> {
+        let mut state = self.state();
+        let guid = state.add_desktop();
+        state.send(DesktopEvent::DesktopCreated(guid.into()));
+        Ok(state.desktop_at(state.desktops.len() - 1))
+    }
+
+    fn remove_desktop(&self, desktop: Desktop, fallback: Desktop) -> Result<()> {
+        let mut state = self.state();
+        let position = state.position(desktop)?;
+        let fallback_position = state.position(fallback)?;
+        if state.desktops.len() == 1 {
+            return Err(Error::LastDesktop);
+        }
+        if position == fallback_position {
+            return Err(Error::RemoveDesktopFailed);
+        }
+        let fallback = state.desktops[fallback_position].0;
+        let (removed, ..) = state.desktops.remove(position);
+        for (_, window_desktop) in state.windows.iter_mut() {
+            if *window_desktop == removed {
+                *window_desktop = fallback;
+            }
+        }
+        if state.current == removed {
+            state.current = fallback;
+        }
+        state.send(DesktopEvent::DesktopDestroyed {
+            destroyed: removed.into(),
+            fallback: fallback.into(),
+        });
+        Ok(())
+    }
+
+    fn get_desktop_by_window(&self, hwnd: HWND) -> Result<Desktop> {
+        let state = self.state();
+        let guid = state.windows[state.window(hwnd)?].1;
+        Ok(state.desktop_at(state.position(Desktop::from(guid))?))
+    }
+
+    fn is_window_on_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<bool> {
+        let state = self.state();
+        let guid = state.windows[state.window(hwnd)?].1;
+        Ok(state.desktops[state.position(desktop)?].0 == guid)
+    }
+
+    fn move_window_to_desktop(&self, hwnd: HWND, desktop: Desktop) -> Result<()> {
+        let mut state = self.state();
+        let window = state.window(hwnd)?;
+        if state.pinned.contains(&hwnd) {
+            return Err(Error::WindowNotMovable);
+        }
+        state.windows[window].1 = state.desktops[state.position(desktop)?].0;
+        state.send(DesktopEvent::WindowChanged(hwnd.into()));
+        Ok(())
+    }
+
+    fn move_desktop(&self, desktop: Desktop, index: u32) -> Result<()> {
+        let mut state = self.state();
+        let position = state.position(desktop)?;
+        if index as usize >= state.desktops.len() {
+            return Err(Error::DesktopNotFound);
+        }
+        let moved = state.desktops.remove(position);
+        let guid = moved.0;
+        state.desktops.insert(index as usize, moved);
+        state.send(DesktopEvent::DesktopMoved {
+            desktop: guid.into(),
+            old_index: position as i64,
+            new_index: index as i64,
+        });
+        Ok(())
+    }
+
+    fn can_move_window(&self, hwnd: HWND) -> Result<bool> {
+        let state = self.state();
+        state.window(hwnd)?;
+        Ok(!state.pinned.contains(&hwnd))
+    }
+
+    fn get_desktop_wallpaper(&self, desktop: Desktop) -> Result<String> {
+        let state = self.state();
+        Ok(state.desktops[state.position(desktop)?].2.clone())
+    }
+
+    fn set_desktop_wallpaper(&self, desktop: Desktop, path: &str) -> Result<()> {
+        let mut state = self.state();
+        let position = state.position(desktop)?;
+        state.desktops[position].2 = path.to_string();
+        let guid = state.desktops[position].0;
+        state.send(DesktopEvent::DesktopWallpaperChanged(
+            guid.into(),
+            path.to_string(),
+        ));
+        Ok(())
+    }
+
+    fn is_pinned_window(&self, hwnd: HWND) -> Result<bool> {
+        let state = self.state();
+        state.window(hwnd)?;
+        Ok(state.pinned.contains(&hwnd))
+    }
+
+    fn pin_window(&self, hwnd: HWND) -> Result<()> {
+        let mut state = self.state();
+        state.window(hwnd)?;
+        if !state.pinned.contains(&hwnd) {
+            state.pinned.push(hwnd);
+            state.send(DesktopEvent::WindowChanged(hwnd.into()));
+        }
+        Ok(())
+    }
+
+    fn unpin_window(&self, hwnd: HWND) -> Result<()> {
+        let mut state = self.state();
+        state.window(hwnd)?;
+        if state.pinned.contains(&hwnd) {
+            state.pinned.retain(|pinned| *pinned != hwnd);
+            state.send(DesktopEvent::WindowChanged(hwnd.into()));
+        }
+        Ok(())
+    }
+
+    fn is_pinned_app(&self, hwnd: HWND) -> Result<bool> {
+        self.state().window(hwnd)?;
+        Ok(false)
+    }
+
+    fn pin_app(&self, _hwnd: HWND) -> Result<()> {
+        Err(Error::ComNotImplemented)
+    }
+
+    fn unpin_app(&self, _hwnd: HWND) -> Result<()> {
+        Err(Error::ComNotImplemented)
+    }
+
+    /// Views of the added windows, the windows added last have the highest
+    /// timestamps
+    fn get_view_infos(&self) -> Result<Vec<ViewInfo>> {
+        let state = self.state();
+        Ok(state
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(i, (hwnd, desktop))| ViewInfo {
+                hwnd: (*hwnd).into(),
+                app_user_model_id: String::new(),
+                desktop: *desktop,
+                show_in_switchers: true,
+                is_pinned: state.pinned.contains(hwnd),
+                last_activation_timestamp: i as u64,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_backend() {
+        let mock = MockBackend::new(3);
+        let events = mock.subscribe();
+        let hwnd = HWND(0x1234);
+        mock.add_window(hwnd, Desktop::from(2)).unwrap();
+        let index = |desktop| mock.get_desktop_index(desktop).unwrap();
+
+        mock.switch_desktop(Desktop::from(2)).unwrap();
+        assert_eq!(index(mock.get_current_desktop().unwrap()), 2);
+        mock.remove_desktop(Desktop::from(2), Desktop::from(0))
+            .unwrap();
+        assert_eq!(mock.get_desktop_count().unwrap(), 2);
+        assert_eq!(index(mock.get_current_desktop().unwrap()), 0);
+        assert_eq!(index(mock.get_desktop_by_window(hwnd).unwrap()), 0);
+        assert!(matches!(
+            mock.remove_desktop(Desktop::from(1), Desktop::from(1)),
+            Err(Error::RemoveDesktopFailed)
+        ));
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(matches!(events[0], DesktopEvent::DesktopChanged { .. }));
+        assert!(matches!(events[1], DesktopEvent::DesktopDestroyed { .. }));
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn test_mock_pins_and_moves() {
+        let mock = MockBackend::new(3);
+        let hwnd = HWND(0x1234);
+        mock.add_window(hwnd, Desktop::from(0)).unwrap();
+        let id = |index: u32| GUID::from_u128(index as u128 + 1);
+
+        mock.pin_window(hwnd).unwrap();
+        assert!(!mock.can_move_window(hwnd).unwrap());
+        assert!(matches!(
+            mock.move_window_to_desktop(hwnd, Desktop::from(1)),
+            Err(Error::WindowNotMovable)
+        ));
+        mock.unpin_window(hwnd).unwrap();
+        mock.move_window_to_desktop(hwnd, Desktop::from(1)).unwrap();
+
+        mock.move_desktop(Desktop::from(2), 0).unwrap();
+        assert_eq!(mock.get_desktop_id(Desktop::from(0)).unwrap(), id(2));
+        assert_eq!(mock.get_desktop_index(Desktop::from(id(1))).unwrap(), 2);
+        let right = mock
+            .get_adjacent_desktop(Desktop::from(0), crate::Direction::Right)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mock.get_desktop_id(right).unwrap(), id(0));
+        assert!(mock
+            .get_adjacent_desktop(Desktop::from(2), crate::Direction::Right)
+            .unwrap()
+            .is_none());
+    }
+}
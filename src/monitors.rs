@@ -0,0 +1,113 @@
+//! Monitors, and switching desktops by monitor.
+//!
+//! ```rust,no_run
+//! use winvd::{monitor_at_point, switch_desktop_on_monitor};
+//!
+//! switch_desktop_on_monitor(monitor_at_point(100, 100), 1).unwrap();
+//! ```
+//!
+//! Desktops are shared by all monitors except on the 22000 build, see
+//! `Desktop::monitor`.
+//!
+//! When monitors are added or removed, the listener sends
+//! `DesktopEvent::MonitorsChanged` with the monitors connected now. It
+//! doesn't tell which monitors were added or removed, or which desktops moved
+//! to other monitors, and resolution changes are not reported. Compare the
+//! list with the previous one, and read `Desktop::monitor` again for the
+//! desktops of the monitors.
+use windows::Win32::Foundation::{BOOL, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow, HDC, HMONITOR,
+    MONITORINFO, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::UI::WindowsAndMessaging::{IsWindow, MONITORINFOF_PRIMARY};
+
+use crate::{Desktop, Error, IntoHwnd, Result};
+
+/// Connected monitor, see `list_monitors`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorInfo {
+    pub hmonitor: HMONITOR,
+
+    /// Bounds in virtual screen coordinates
+    pub rect: RECT,
+
+    /// The monitor with the taskbar's start button, at 0, 0
+    pub primary: bool,
+}
+
+/// Connected monitors, monitors disconnected while listing are left out
+pub fn list_monitors() -> Vec<MonitorInfo> {
+    monitor_handles()
+        .into_iter()
+        .filter_map(|hmonitor| {
+            let mut info = MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                ..Default::default()
+            };
+            unsafe { GetMonitorInfoW(hmonitor, &mut info) }
+                .as_bool()
+                .then_some(MonitorInfo {
+                    hmonitor,
+                    rect: info.rcMonitor,
+                    primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+                })
+        })
+        .collect()
+}
+
+/// Monitor containing the point, or the nearest monitor
+pub fn monitor_at_point(x: i32, y: i32) -> HMONITOR {
+    unsafe { MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST) }
+}
+
+/// Monitor showing the most of the window, or the nearest monitor if the
+/// window is minimized or off screen
+pub fn monitor_of_window<M>(hwnd: impl IntoHwnd<M>) -> Result<HMONITOR> {
+    let hwnd = hwnd.into_hwnd()?;
+    if !unsafe { IsWindow(hwnd) }.as_bool() {
+        return Err(Error::WindowNotFound);
+    }
+    Ok(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) })
+}
+
+/// Switch the desktop shown on the monitor. When desktops are shared by all
+/// monitors this switches the desktop of every monitor.
+///
+/// Fails with `Error::DesktopNotFound` if the desktop belongs to another
+/// monitor.
+pub fn switch_desktop_on_monitor<T>(monitor: HMONITOR, desktop: T) -> Result<()>
+where
+    T: Into<Desktop>,
+{
+    let desktop = desktop.into();
+    match desktop.monitor()? {
+        Some(desktop_monitor) if desktop_monitor != monitor => Err(Error::DesktopNotFound),
+        _ => crate::switch_desktop(desktop),
+    }
+}
+
+/// Handles of the currently connected monitors
+pub(crate) fn monitor_handles() -> Vec<HMONITOR> {
+    unsafe extern "system" fn callback(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let monitors = &mut *(data.0 as *mut Vec<HMONITOR>);
+        monitors.push(monitor);
+        BOOL(1)
+    }
+
+    let mut monitors = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(callback),
+            LPARAM(&mut monitors as *mut Vec<HMONITOR> as isize),
+        );
+    }
+    monitors
+}
@@ -0,0 +1,270 @@
+//! MQTT bridge, enabled with the `mqtt` feature.
+//!
+//! Publishes the desktop events to a broker, and runs the commands published
+//! to the command topic, so home automation, e.g. Home Assistant, can mirror
+//! and drive the virtual desktops.
+//!
+//! - `winvd/events`: each `DesktopEvent` as JSON.
+//! - `winvd/current`: the current desktop as a `DesktopInfo`, retained.
+//! - `winvd/command`: JSON-RPC requests, e.g.
+//!   `{"method":"switch","params":{"desktop":1}}`.
+//! - `winvd/command/response`: responses to the requests that have an `id`.
+//!
+//! The commands are the methods of the JSON-RPC protocol, see the `rpc`
+//! module, e.g. `switch`, `create` and `rename`. Events are dropped while the
+//! broker is not connected.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS};
+
+use crate::rpc;
+use crate::{DesktopEvent, DesktopEventThread, Result, RuntimeChild, VirtualDesktopService};
+
+/// How often the connection thread checks for stopping
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait between the attempts to connect to the broker
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Requests queued for the broker, events are dropped when it is full
+const QUEUE_SIZE: usize = 64;
+
+/// Settings of `MqttBridge`
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    pub host: String,
+
+    /// Defaults to 1883
+    pub port: u16,
+
+    /// Defaults to `winvd`, must be unique on the broker
+    pub client_id: String,
+
+    /// User name and password
+    pub credentials: Option<(String, String)>,
+
+    /// Topic of the events, defaults to `winvd/events`
+    pub event_topic: String,
+
+    /// Topic of the current desktop, defaults to `winvd/current`. `None`
+    /// disables it.
+    pub current_topic: Option<String>,
+
+    /// Topic of the commands, defaults to `winvd/command`. `None` disables the
+    /// commands.
+    pub command_topic: Option<String>,
+}
+
+impl MqttConfig {
+    /// Default settings for the broker on the host
+    pub fn new(host: impl Into<String>) -> Self {
+        MqttConfig {
+            host: host.into(),
+            port: 1883,
+            client_id: "winvd".to_string(),
+            credentials: None,
+            event_topic: "winvd/events".to_string(),
+            current_topic: Some("winvd/current".to_string()),
+            command_topic: Some("winvd/command".to_string()),
+        }
+    }
+}
+
+struct Shared {
+    config: MqttConfig,
+    client: Client,
+    service: VirtualDesktopService,
+    stopping: AtomicBool,
+}
+
+/// Running MQTT bridge, disconnects when dropped, see the module docs
+pub struct MqttBridge {
+    shared: Arc<Shared>,
+    connection: Option<JoinHandle<()>>,
+    events: Option<DesktopEventThread>,
+    broadcast: Option<JoinHandle<()>>,
+}
+
+impl MqttBridge {
+    /// Disconnects and joins the threads, returns the first panic
+    fn stop_threads(&mut self) -> std::thread::Result<()> {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        // Stopping the listener closes the channel of the broadcast thread
+        let events = self
+            .events
+            .take()
+            .map_or(Ok(()), |mut events| events.stop());
+        let broadcast = self.broadcast.take().map_or(Ok(()), JoinHandle::join);
+        let _ = self.shared.client.try_disconnect();
+        let connection = self.connection.take().map_or(Ok(()), JoinHandle::join);
+        events.and(broadcast).and(connection)
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        let _ = self.stop_threads();
+    }
+}
+
+impl RuntimeChild for MqttBridge {
+    fn is_running(&self) -> bool {
+        let alive = |thread: &Option<JoinHandle<()>>| {
+            thread.as_ref().is_some_and(|thread| !thread.is_finished())
+        };
+        alive(&self.connection)
+            && alive(&self.broadcast)
+            && self
+                .events
+                .as_ref()
+                .is_some_and(DesktopEventThread::is_running)
+    }
+
+    fn stop(&mut self) -> std::thread::Result<()> {
+        self.stop_threads()
+    }
+}
+
+impl MqttBridge {
+    /// Connects to the broker and starts publishing the events. The broker
+    /// doesn't have to be up, connecting is retried every few seconds.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// let _bridge = winvd::MqttBridge::start(winvd::MqttConfig::new("homeassistant.local")).unwrap();
+    /// loop {
+    ///     std::thread::park();
+    /// }
+    /// ```
+    pub fn start(config: MqttConfig) -> Result<Self> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((user, password)) = &config.credentials {
+            options.set_credentials(user, password);
+        }
+        let (client, connection) = Client::new(options, QUEUE_SIZE);
+        let shared = Arc::new(Shared {
+            config,
+            client,
+            service: VirtualDesktopService::new()?,
+            stopping: AtomicBool::new(false),
+        });
+
+        let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+        let events = crate::listen_desktop_events(tx)?;
+        let broadcast = {
+            let shared = shared.clone();
+            std::thread::spawn(move || publish_events(rx, &shared))
+        };
+        let connection = {
+            let shared = shared.clone();
+            std::thread::spawn(move || run_connection(connection, &shared))
+        };
+        Ok(MqttBridge {
+            shared,
+            connection: Some(connection),
+            events: Some(events),
+            broadcast: Some(broadcast),
+        })
+    }
+}
+
+/// Drives the connection, subscribes to the commands when connected and runs
+/// them
+fn run_connection(mut connection: Connection, shared: &Shared) {
+    while !shared.stopping.load(Ordering::SeqCst) {
+        let event = match connection.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => event,
+            Ok(Err(_)) => {
+                wait_reconnect(shared);
+                continue;
+            }
+            Err(rumqttc::RecvTimeoutError::Timeout) => continue,
+            Err(rumqttc::RecvTimeoutError::Disconnected) => return,
+        };
+        match event {
+            // Subscriptions are not kept over reconnects
+            Event::Incoming(Packet::ConnAck(_)) => {
+                if let Some(topic) = &shared.config.command_topic {
+                    let _ = shared.client.try_subscribe(topic, QoS::AtLeastOnce);
+                }
+                publish_current(shared);
+            }
+            Event::Incoming(Packet::Publish(publish))
+                if shared.config.command_topic.as_deref() == Some(publish.topic.as_str()) =>
+            {
+                run_command(&publish.topic, &publish.payload, shared);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn wait_reconnect(shared: &Shared) {
+    let mut waited = Duration::ZERO;
+    while waited < RECONNECT_INTERVAL && !shared.stopping.load(Ordering::SeqCst) {
+        std::thread::sleep(POLL_INTERVAL);
+        waited += POLL_INTERVAL;
+    }
+}
+
+fn run_command(topic: &str, payload: &[u8], shared: &Shared) {
+    let request = String::from_utf8_lossy(payload);
+    if let Some(response) = rpc::handle_line(&shared.service, request.trim()) {
+        let _ = shared.client.try_publish(
+            format!("{}/response", topic),
+            QoS::AtLeastOnce,
+            false,
+            response,
+        );
+    }
+}
+
+/// Publishes the events, and the current desktop when it may have changed
+fn publish_events(rx: Receiver<DesktopEvent>, shared: &Shared) {
+    for event in rx {
+        let Ok(payload) = serde_json::to_string(&event) else {
+            continue;
+        };
+        let _ =
+            shared
+                .client
+                .try_publish(&shared.config.event_topic, QoS::AtLeastOnce, false, payload);
+        if changes_current(&event) {
+            publish_current(shared);
+        }
+    }
+}
+
+fn changes_current(event: &DesktopEvent) -> bool {
+    matches!(
+        event,
+        DesktopEvent::DesktopChanged { .. }
+            | DesktopEvent::DesktopNameChanged(..)
+            | DesktopEvent::DesktopWallpaperChanged(..)
+            | DesktopEvent::DesktopMoved { .. }
+            | DesktopEvent::DesktopDestroyed { .. }
+            | DesktopEvent::ConnectionReset { .. }
+            | DesktopEvent::EventsDropped { .. }
+    )
+}
+
+/// Publishes the current desktop as a retained message
+fn publish_current(shared: &Shared) {
+    let Some(topic) = &shared.config.current_topic else {
+        return;
+    };
+    let Ok(info) = shared.service.call(crate::current_desktop_info) else {
+        return;
+    };
+    if let Ok(payload) = serde_json::to_string(&info) {
+        let _ = shared
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload);
+    }
+}
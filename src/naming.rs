@@ -0,0 +1,243 @@
+//! Opt-in manager keeping the desktop names in a naming scheme.
+//!
+//! The names are formatted with a format string, e.g. `"{number}: {name}"`
+//! names the desktops "1: Mail", "2: Code" and so on, and the numbers are
+//! renamed when desktops are created, removed or moved:
+//!
+//! ```rust,no_run
+//! let policy = winvd::NamingPolicy::new("{number}: {name}").except("Scratch");
+//! let _naming = winvd::DesktopNaming::start(policy).unwrap();
+//! ```
+//!
+//! Renaming a desktop keeps the scheme, e.g. renaming "1: Mail" to "Work"
+//! names it "1: Work".
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use windows::core::GUID;
+
+use crate::backend::with_backend;
+use crate::comobjects::parse_guid;
+use crate::listener::DesktopEventHandler;
+use crate::{DesktopEvent, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Number,
+    Index,
+    Name,
+}
+
+/// Naming scheme of `DesktopNaming`
+#[derive(Clone, Debug)]
+pub struct NamingPolicy {
+    parts: Vec<Part>,
+    exceptions: Vec<String>,
+}
+
+impl NamingPolicy {
+    /// Scheme of the format string: `{number}` is the position of the desktop
+    /// in Task View counting from 1, `{index}` from 0, and `{name}` the name
+    /// given to the desktop.
+    ///
+    /// With `{name}` unnamed desktops are left unnamed, without it all
+    /// desktops are named.
+    pub fn new(format: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = format;
+        while !rest.is_empty() {
+            let placeholder = [
+                ("{number}", Part::Number),
+                ("{index}", Part::Index),
+                ("{name}", Part::Name),
+            ]
+            .into_iter()
+            .find(|(text, _)| rest.starts_with(text));
+            if let Some((text, part)) = placeholder {
+                parts.push(part);
+                rest = &rest[text.len()..];
+                continue;
+            }
+            // Braces not starting a placeholder are text
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '{')
+                .map_or(rest.len(), |(end, _)| end);
+            match parts.last_mut() {
+                Some(Part::Text(text)) => text.push_str(&rest[..end]),
+                _ => parts.push(Part::Text(rest[..end].to_string())),
+            }
+            rest = &rest[end..];
+        }
+        NamingPolicy {
+            parts,
+            exceptions: Vec::new(),
+        }
+    }
+
+    /// Leaves the desktop alone, `desktop` is its name without the formatting,
+    /// or its GUID
+    pub fn except(mut self, desktop: impl Into<String>) -> Self {
+        self.exceptions.push(desktop.into());
+        self
+    }
+
+    /// Name of the desktop without the formatting, e.g. "Mail" of "1: Mail".
+    /// Names not in the scheme are returned as is.
+    pub fn base_name<'a>(&self, name: &'a str) -> &'a str {
+        let mut base = None;
+        if matches(&self.parts, name, &mut base) {
+            base.unwrap_or_default()
+        } else {
+            name
+        }
+    }
+
+    /// Formatted name of the desktop at the index, `None` if it is named
+    /// correctly already, unnamed or an exception
+    pub fn rename(&self, index: u32, guid: &GUID, name: &str) -> Option<String> {
+        let base = self.base_name(name);
+        let excepted = self.exceptions.iter().any(|exception| {
+            exception == base || exception == name || parse_guid(exception) == Some(*guid)
+        });
+        if excepted || (base.is_empty() && self.parts.contains(&Part::Name)) {
+            return None;
+        }
+        let formatted = self.format(index, base);
+        (formatted != name).then_some(formatted)
+    }
+
+    fn format(&self, index: u32, base: &str) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Number => (index + 1).to_string(),
+                Part::Index => index.to_string(),
+                Part::Name => base.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// True if the name is in the scheme of the parts, the `{name}` part is
+/// stored in `base`
+fn matches<'a>(parts: &[Part], name: &'a str, base: &mut Option<&'a str>) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return name.is_empty();
+    };
+    match part {
+        Part::Text(text) => name
+            .strip_prefix(text.as_str())
+            .is_some_and(|name| matches(rest, name, base)),
+        Part::Number | Part::Index => {
+            let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+            (1..=digits)
+                .rev()
+                .any(|end| matches(rest, &name[end..], base))
+        }
+        Part::Name => {
+            let ends = name
+                .char_indices()
+                .map(|(end, _)| end)
+                .chain(Some(name.len()));
+            for end in ends {
+                if matches(rest, &name[end..], base) {
+                    *base = Some(&name[..end]);
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+/// Renames the desktops to the naming scheme, see the module docs
+pub struct DesktopNaming {
+    policy: Arc<Mutex<NamingPolicy>>,
+    _events: DesktopEventHandler,
+}
+
+impl DesktopNaming {
+    /// Renames the desktops now, and then whenever they change
+    pub fn start(policy: NamingPolicy) -> Result<Self> {
+        apply(policy.clone())?;
+        let policy = Arc::new(Mutex::new(policy));
+        let thread_policy = policy.clone();
+        let events = DesktopEventHandler::new(move |event| match event {
+            DesktopEvent::DesktopCreated(_)
+            | DesktopEvent::DesktopDestroyed { .. }
+            | DesktopEvent::DesktopMoved { .. }
+            | DesktopEvent::DesktopNameChanged(..)
+            | DesktopEvent::ConnectionReset { .. }
+            | DesktopEvent::EventsDropped { .. } => {
+                // Renaming causes name changes, which are no-ops
+                let _ = apply(lock(&thread_policy).clone());
+            }
+            _ => {}
+        })?;
+
+        Ok(DesktopNaming {
+            policy,
+            _events: events,
+        })
+    }
+
+    /// Replaces the scheme, and renames the desktops to it
+    pub fn set_policy(&self, policy: NamingPolicy) -> Result<()> {
+        *lock(&self.policy) = policy.clone();
+        apply(policy)
+    }
+}
+
+/// Renames the desktops not named by the policy
+fn apply(policy: NamingPolicy) -> Result<()> {
+    with_backend(move |b| {
+        for (index, desktop) in b.get_desktops()?.into_iter().enumerate() {
+            let name = b.get_desktop_name(desktop)?;
+            let guid = b.get_desktop_id(desktop)?;
+            if let Some(name) = policy.rename(index as u32, &guid, &name) {
+                b.set_desktop_name(desktop, &name)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn lock(policy: &Mutex<NamingPolicy>) -> MutexGuard<'_, NamingPolicy> {
+    policy
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_naming_policy() {
+        let guid = GUID::from_u128(0x11223344_5566_7788_99aa_bbccddeeff00);
+        let policy = NamingPolicy::new("{number}: {name}")
+            .except("Scratch")
+            .except("{11223344-5566-7788-99AA-BBCCDDEEFF00}");
+        let other = GUID::from_u128(1);
+
+        assert_eq!(policy.base_name("12: Mail: old"), "Mail: old");
+        assert_eq!(policy.base_name("Mail"), "Mail");
+        assert_eq!(policy.rename(0, &other, "Mail"), Some("1: Mail".into()));
+        assert_eq!(policy.rename(2, &other, "1: Mail"), Some("3: Mail".into()));
+        assert_eq!(policy.rename(0, &other, "1: Mail"), None);
+        assert_eq!(policy.rename(0, &other, ""), None);
+        assert_eq!(policy.rename(0, &other, "2: Scratch"), None);
+        assert_eq!(policy.rename(0, &guid, "Mail"), None);
+
+        // Without the name all desktops are named
+        let numbered = NamingPolicy::new("[{index}]");
+        assert_eq!(numbered.rename(1, &other, ""), Some("[1]".into()));
+        assert_eq!(numbered.rename(1, &other, "[0]"), Some("[1]".into()));
+        assert_eq!(numbered.base_name("{x}"), "{x}");
+        assert_eq!(NamingPolicy::new("{x} {name}").format(0, "a"), "{x} a");
+        assert_eq!(NamingPolicy::new("ö{number}").format(0, "a"), "ö1");
+    }
+}
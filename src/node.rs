@@ -0,0 +1,307 @@
+//! Node.js module, enabled with the `node` feature.
+//!
+//! Build the native module and copy it next to the JS wrapper in `node/`:
+//!
+//! ```text
+//! cargo rustc --release --lib --features node --crate-type cdylib
+//! copy target\release\winvd.dll node\winvd.node
+//! ```
+//!
+//! Calls return promises and run on a `VirtualDesktopService` worker thread.
+//! Desktops are passed as indices, and windows as HWND numbers. The wrapper
+//! turns `listen` into an `EventEmitter`.
+use std::sync::mpsc::channel;
+use std::thread::JoinHandle;
+
+use napi::bindgen_prelude::{AsyncTask, ToNapiValue, TypeName};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, JsFunction, Task};
+use napi_derive::napi;
+use windows::Win32::Foundation::HWND;
+
+use crate::service::shared_service;
+use crate::{Desktop, DesktopEvent, DesktopEventThread, Error};
+
+impl From<Error> for napi::Error {
+    fn from(err: Error) -> Self {
+        napi::Error::from_reason(format!("{:?}", err))
+    }
+}
+
+type Call<T> = Box<dyn FnOnce() -> crate::Result<T> + Send>;
+
+/// Call run from the libuv thread pool on the worker, resolves the promise
+pub struct ServiceCall<T> {
+    call: Option<Call<T>>,
+}
+
+impl<T> Task for ServiceCall<T>
+where
+    T: ToNapiValue + TypeName + Send + 'static,
+{
+    type Output = T;
+    type JsValue = T;
+
+    fn compute(&mut self) -> napi::Result<T> {
+        let call = self
+            .call
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("Call was already run"))?;
+        Ok(shared_service()?.call(call)?)
+    }
+
+    fn resolve(&mut self, _env: Env, output: T) -> napi::Result<T> {
+        Ok(output)
+    }
+}
+
+fn promise<T, F>(call: F) -> AsyncTask<ServiceCall<T>>
+where
+    T: ToNapiValue + TypeName + Send + 'static,
+    F: FnOnce() -> crate::Result<T> + Send + 'static,
+{
+    AsyncTask::new(ServiceCall {
+        call: Some(Box::new(call)),
+    })
+}
+
+#[napi(object, js_name = "Desktop")]
+pub struct JsDesktop {
+    pub index: u32,
+    pub id: String,
+    pub name: String,
+}
+
+/// Event passed to the `listen` callback, the fields not used by the `kind`
+/// are undefined
+#[napi(object, js_name = "DesktopEvent")]
+pub struct JsDesktopEvent {
+    /// E.g. `desktopChanged`
+    pub kind: String,
+
+    /// Index of the created, destroyed, changed to, renamed or moved desktop,
+    /// or the current desktop for `initialState`
+    pub desktop: Option<u32>,
+
+    /// Previous desktop of `desktopChanged`
+    pub old: Option<u32>,
+
+    /// Desktop the windows moved to for `desktopDestroyed`
+    pub fallback: Option<u32>,
+    pub name: Option<String>,
+    pub wallpaper: Option<String>,
+    pub old_index: Option<i64>,
+    pub new_index: Option<i64>,
+    pub hwnd: Option<i64>,
+    pub monitors: Option<Vec<i64>>,
+}
+
+#[napi]
+fn get_desktop_count() -> AsyncTask<ServiceCall<u32>> {
+    promise(crate::get_desktop_count)
+}
+
+/// Index of the current desktop
+#[napi]
+fn get_current_desktop() -> AsyncTask<ServiceCall<u32>> {
+    promise(|| crate::get_current_desktop()?.get_index())
+}
+
+#[napi]
+fn list_desktops() -> AsyncTask<ServiceCall<Vec<JsDesktop>>> {
+    promise(|| {
+        Ok(crate::list_desktops_detailed()?
+            .into_iter()
+            .map(|info| JsDesktop {
+                index: info.index,
+                id: format!("{:?}", info.guid),
+                name: info.name,
+            })
+            .collect())
+    })
+}
+
+#[napi]
+fn switch_desktop(index: u32) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::switch_desktop(index))
+}
+
+/// Creates a new desktop and resolves to its index
+#[napi]
+fn create_desktop() -> AsyncTask<ServiceCall<u32>> {
+    promise(|| crate::create_desktop()?.get_index())
+}
+
+#[napi]
+fn remove_desktop(index: u32, fallback_index: u32) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::remove_desktop(index, fallback_index))
+}
+
+#[napi]
+fn get_desktop_name(index: u32) -> AsyncTask<ServiceCall<String>> {
+    promise(move || crate::get_desktop(index).get_name())
+}
+
+#[napi]
+fn set_desktop_name(index: u32, name: String) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::get_desktop(index).set_name(&name))
+}
+
+/// Index of the desktop the window is on
+#[napi]
+fn get_desktop_by_window(hwnd: i64) -> AsyncTask<ServiceCall<u32>> {
+    promise(move || crate::get_desktop_by_window(HWND(hwnd as isize))?.get_index())
+}
+
+#[napi]
+fn move_window_to_desktop(index: u32, hwnd: i64) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::move_window_to_desktop(index, HWND(hwnd as isize)))
+}
+
+#[napi]
+fn is_window_on_current_desktop(hwnd: i64) -> AsyncTask<ServiceCall<bool>> {
+    promise(move || crate::is_window_on_current_desktop(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn is_pinned_window(hwnd: i64) -> AsyncTask<ServiceCall<bool>> {
+    promise(move || crate::is_pinned_window(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn pin_window(hwnd: i64) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::pin_window(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn unpin_window(hwnd: i64) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::unpin_window(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn is_pinned_app(hwnd: i64) -> AsyncTask<ServiceCall<bool>> {
+    promise(move || crate::is_pinned_app(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn pin_app(hwnd: i64) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::pin_app(HWND(hwnd as isize)))
+}
+
+#[napi]
+fn unpin_app(hwnd: i64) -> AsyncTask<ServiceCall<()>> {
+    promise(move || crate::unpin_app(HWND(hwnd as isize)))
+}
+
+/// Desktop event listener returned by `listen`. Node doesn't exit while it
+/// runs, call `stop` when done.
+#[napi]
+pub struct Listener {
+    thread: Option<DesktopEventThread>,
+    forwarder: Option<JoinHandle<()>>,
+}
+
+#[napi]
+impl Listener {
+    #[napi]
+    pub fn stop(&mut self) {
+        // Forwarder stops when the listener closes the channel
+        drop(self.thread.take());
+        if let Some(forwarder) = self.forwarder.take() {
+            let _ = forwarder.join();
+        }
+    }
+}
+
+/// Calls `callback(event)` for each desktop event
+#[napi]
+fn listen(callback: JsFunction) -> napi::Result<Listener> {
+    let callback: ThreadsafeFunction<JsDesktopEvent, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    let service = shared_service()?;
+    let (tx, rx) = channel::<DesktopEvent>();
+    let thread = crate::listen_desktop_events(tx)?;
+    let forwarder = std::thread::spawn(move || {
+        for event in rx {
+            // Indices of the desktops are read on the worker
+            if let Ok(event) = service.call(move || Ok(event_to_js(event))) {
+                callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        }
+    });
+    Ok(Listener {
+        thread: Some(thread),
+        forwarder: Some(forwarder),
+    })
+}
+
+fn event_to_js(event: DesktopEvent) -> JsDesktopEvent {
+    let index = |desktop: Desktop| desktop.get_index().ok();
+    let mut js = JsDesktopEvent {
+        kind: String::new(),
+        desktop: None,
+        old: None,
+        fallback: None,
+        name: None,
+        wallpaper: None,
+        old_index: None,
+        new_index: None,
+        hwnd: None,
+        monitors: None,
+    };
+    js.kind = match event {
+        DesktopEvent::DesktopCreated(desktop) => {
+            js.desktop = index(desktop);
+            "desktopCreated"
+        }
+        DesktopEvent::DesktopDestroyed {
+            destroyed,
+            fallback,
+        } => {
+            js.desktop = index(destroyed);
+            js.fallback = index(fallback);
+            "desktopDestroyed"
+        }
+        DesktopEvent::DesktopChanged { new, old } => {
+            js.desktop = index(new);
+            js.old = index(old);
+            "desktopChanged"
+        }
+        DesktopEvent::DesktopNameChanged(desktop, name) => {
+            js.desktop = index(desktop);
+            js.name = Some(name);
+            "desktopNameChanged"
+        }
+        DesktopEvent::DesktopWallpaperChanged(desktop, wallpaper) => {
+            js.desktop = index(desktop);
+            js.wallpaper = Some(wallpaper);
+            "desktopWallpaperChanged"
+        }
+        DesktopEvent::DesktopMoved {
+            desktop,
+            old_index,
+            new_index,
+        } => {
+            js.desktop = index(desktop);
+            js.old_index = Some(old_index);
+            js.new_index = Some(new_index);
+            "desktopMoved"
+        }
+        DesktopEvent::WindowChanged(hwnd) => {
+            js.hwnd = Some(hwnd.0 as i64);
+            "windowChanged"
+        }
+        DesktopEvent::MonitorsChanged(monitors) => {
+            js.monitors = Some(monitors.iter().map(|monitor| monitor.0 as i64).collect());
+            "monitorsChanged"
+        }
+        DesktopEvent::InitialState { current, .. } => {
+            js.desktop = index(current);
+            "initialState"
+        }
+        DesktopEvent::ConnectionReset { .. } => "connectionReset",
+        DesktopEvent::EventsDropped { .. } => "eventsDropped",
+    }
+    .to_string();
+    js
+}
@@ -0,0 +1,75 @@
+//! Iterating an `IObjectArray` without index loops.
+//!
+//! ```ignore
+//! for desktop in desktops.iter::<IVirtualDesktop>() {
+//!     let desktop = desktop?;
+//! }
+//! ```
+use std::marker::PhantomData;
+use windows::Win32::UI::Shell::Common::IObjectArray;
+
+use crate::interfaces_multi::ObjectArrayItem;
+
+/// Iterator over the objects of an `IObjectArray`, created by
+/// `ObjectArrayExt::iter`. Yields a single error and stops if the count of the
+/// array can't be read.
+pub struct ObjectArrayIter<'a, T> {
+    array: &'a IObjectArray,
+    index: u32,
+
+    /// Read on the first call of `next`
+    count: Option<u32>,
+    _item: PhantomData<T>,
+}
+
+impl<'a, T> Iterator for ObjectArrayIter<'a, T>
+where
+    T: ObjectArrayItem<'a>,
+{
+    type Item = windows::core::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let count = match self.count {
+            Some(count) => count,
+            None => match unsafe { self.array.GetCount() } {
+                Ok(count) => *self.count.insert(count),
+                Err(err) => {
+                    self.count = Some(0);
+                    return Some(Err(err));
+                }
+            },
+        };
+        if self.index >= count {
+            return None;
+        }
+        let item = unsafe { T::get_at(self.array, self.index) };
+        self.index += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.count {
+            Some(count) => {
+                let remaining = count.saturating_sub(self.index) as usize;
+                (remaining, Some(remaining))
+            }
+            None => (0, None),
+        }
+    }
+}
+
+pub trait ObjectArrayExt {
+    /// Objects of the array, each queried for `T`
+    fn iter<T>(&self) -> ObjectArrayIter<'_, T>;
+}
+
+impl ObjectArrayExt for IObjectArray {
+    fn iter<T>(&self) -> ObjectArrayIter<'_, T> {
+        ObjectArrayIter {
+            array: self,
+            index: 0,
+            count: None,
+            _item: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! JSON-RPC server on a named pipe, enabled with the `server` feature.
+//!
+//! Other processes, e.g. Electron apps or Python scripts, control the desktops
+//! by writing requests to the pipe, one JSON object per line, and receive the
+//! desktop events as notifications. See the `rpc` module for the protocol.
+//!
+//! The pipe only accepts local clients. Calls run on a
+//! `VirtualDesktopService` worker thread.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_IO_PENDING, ERROR_PIPE_CONNECTED, HANDLE, WAIT_OBJECT_0,
+};
+use windows::Win32::Storage::FileSystem::{
+    ReadFile, WriteFile, FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX,
+};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::System::Threading::{CreateEventW, SetEvent, WaitForMultipleObjects, INFINITE};
+use windows::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+
+use crate::log::{log_at, LogLevel};
+use crate::rpc;
+use crate::{DesktopEvent, DesktopEventThread, Result, RuntimeChild, VirtualDesktopService};
+
+/// Pipe used by `vda server` and the examples
+pub const DEFAULT_PIPE_NAME: &str = r"\\.\pipe\winvd";
+
+/// Size of the pipe buffers
+const BUFFER_SIZE: u32 = 4096;
+
+/// Clients sending longer lines are disconnected
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Handle closed on drop
+struct OwnedHandle(HANDLE);
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+struct Client {
+    pipe: OwnedHandle,
+
+    /// Held while writing, so responses and events don't interleave
+    write: Mutex<()>,
+}
+
+struct Shared {
+    service: VirtualDesktopService,
+    clients: Mutex<Vec<Arc<Client>>>,
+
+    /// Set when the server is dropped, cancels the pending pipe operations
+    stop: OwnedHandle,
+    stopping: AtomicBool,
+}
+
+/// Running pipe server, stopped when dropped
+pub struct PipeServer {
+    shared: Arc<Shared>,
+    accept: Option<JoinHandle<()>>,
+    events: Option<DesktopEventThread>,
+    broadcast: Option<JoinHandle<()>>,
+}
+
+impl PipeServer {
+    /// Stops the threads and joins them, returns the first panic
+    fn stop_threads(&mut self) -> std::thread::Result<()> {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        let _ = unsafe { SetEvent(self.shared.stop.0) };
+        let accept = self.accept.take().map_or(Ok(()), JoinHandle::join);
+        // Stopping the listener closes the channel of the broadcast thread
+        let events = self
+            .events
+            .take()
+            .map_or(Ok(()), |mut events| events.stop());
+        let broadcast = self.broadcast.take().map_or(Ok(()), JoinHandle::join);
+        accept.and(events).and(broadcast)
+    }
+}
+
+impl Drop for PipeServer {
+    fn drop(&mut self) {
+        let _ = self.stop_threads();
+    }
+}
+
+impl RuntimeChild for PipeServer {
+    fn is_running(&self) -> bool {
+        let alive = |thread: &Option<JoinHandle<()>>| {
+            thread.as_ref().is_some_and(|thread| !thread.is_finished())
+        };
+        alive(&self.accept)
+            && alive(&self.broadcast)
+            && self
+                .events
+                .as_ref()
+                .is_some_and(DesktopEventThread::is_running)
+    }
+
+    fn stop(&mut self) -> std::thread::Result<()> {
+        self.stop_threads()
+    }
+}
+
+/// Starts serving the JSON-RPC protocol on the named pipe, e.g.
+/// `DEFAULT_PIPE_NAME`.
+///
+/// Fails if another server already has the pipe. The server runs until the
+/// returned `PipeServer` is dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let _server = winvd::serve_pipe(winvd::DEFAULT_PIPE_NAME).unwrap();
+/// loop {
+///     std::thread::park();
+/// }
+/// ```
+pub fn serve_pipe(name: &str) -> Result<PipeServer> {
+    let name = HSTRING::from(name);
+    let pipe = create_pipe(&name, true)?;
+    let shared = Arc::new(Shared {
+        service: VirtualDesktopService::new()?,
+        clients: Mutex::new(Vec::new()),
+        stop: OwnedHandle(unsafe { CreateEventW(None, true, false, None) }?),
+        stopping: AtomicBool::new(false),
+    });
+
+    let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let events = crate::listen_desktop_events(tx)?;
+    let broadcast = {
+        let shared = shared.clone();
+        std::thread::spawn(move || broadcast_events(rx, &shared))
+    };
+    let accept = {
+        let shared = shared.clone();
+        std::thread::spawn(move || accept_clients(&name, pipe, &shared))
+    };
+    Ok(PipeServer {
+        shared,
+        accept: Some(accept),
+        events: Some(events),
+        broadcast: Some(broadcast),
+    })
+}
+
+fn create_pipe(name: &HSTRING, first: bool) -> Result<OwnedHandle> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name,
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if pipe.is_invalid() {
+        return Err(windows::core::Error::from_win32().into());
+    }
+    Ok(OwnedHandle(pipe))
+}
+
+/// Waits for a client on the pipe, and creates a new pipe instance for the
+/// next client, until stopped
+fn accept_clients(name: &HSTRING, mut pipe: OwnedHandle, shared: &Arc<Shared>) {
+    let mut threads: Vec<JoinHandle<()>> = Vec::new();
+    loop {
+        let connected = complete(pipe.0, shared.stop.0, |overlapped| unsafe {
+            ConnectNamedPipe(pipe.0, Some(overlapped))
+        });
+        if shared.stopping.load(Ordering::SeqCst) {
+            break;
+        }
+        let next = match create_pipe(name, false) {
+            Ok(next) => next,
+            Err(err) => {
+                log_at(
+                    LogLevel::Error,
+                    &format!("Creating the pipe failed: {:?}", err),
+                );
+                break;
+            }
+        };
+        let pipe = std::mem::replace(&mut pipe, next);
+        if connected.is_none() {
+            continue;
+        }
+
+        let client = Arc::new(Client {
+            pipe,
+            write: Mutex::new(()),
+        });
+        lock(&shared.clients).push(client.clone());
+        let shared = shared.clone();
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(std::thread::spawn(move || serve_client(&client, &shared)));
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+/// Answers the requests of the client until it disconnects
+fn serve_client(client: &Arc<Client>, shared: &Shared) {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; BUFFER_SIZE as usize];
+    'read: while buffer.len() <= MAX_LINE_LENGTH {
+        let read = complete(client.pipe.0, shared.stop.0, |overlapped| unsafe {
+            ReadFile(client.pipe.0, Some(&mut chunk), None, Some(overlapped))
+        });
+        let Some(read) = read else {
+            break;
+        };
+        buffer.extend_from_slice(&chunk[..read as usize]);
+        while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = rpc::handle_line(&shared.service, line.trim()) {
+                if !send(client, &response, shared) {
+                    break 'read;
+                }
+            }
+        }
+    }
+    lock(&shared.clients).retain(|other| !Arc::ptr_eq(other, client));
+    let _ = unsafe { DisconnectNamedPipe(client.pipe.0) };
+}
+
+/// Sends the events to all clients as notifications
+fn broadcast_events(rx: Receiver<DesktopEvent>, shared: &Shared) {
+    for event in rx {
+        let notification = rpc::event_notification(&event);
+        let clients = lock(&shared.clients).clone();
+        for client in clients {
+            // Failed clients are removed by their own thread
+            send(&client, &notification, shared);
+        }
+    }
+}
+
+/// Writes the line to the client, returns false if the pipe is broken
+fn send(client: &Client, line: &str, shared: &Shared) -> bool {
+    let _write = lock(&client.write);
+    let line = format!("{}\n", line);
+    let written = complete(client.pipe.0, shared.stop.0, |overlapped| unsafe {
+        WriteFile(client.pipe.0, Some(line.as_bytes()), None, Some(overlapped))
+    });
+    written == Some(line.len() as u32)
+}
+
+/// Runs the overlapped operation on the pipe, and waits for it to finish or
+/// for the stop event. Returns the number of bytes transferred, or `None` if
+/// the operation failed or was cancelled.
+fn complete(
+    pipe: HANDLE,
+    stop: HANDLE,
+    start: impl FnOnce(*mut OVERLAPPED) -> windows::core::Result<()>,
+) -> Option<u32> {
+    let event = OwnedHandle(unsafe { CreateEventW(None, true, false, None) }.ok()?);
+    let mut overlapped = OVERLAPPED {
+        hEvent: event.0,
+        ..Default::default()
+    };
+    match start(&mut overlapped) {
+        Ok(()) => {}
+        Err(err) if err.code() == ERROR_IO_PENDING.to_hresult() => {}
+        // Client connected before `ConnectNamedPipe` was called
+        Err(err) if err.code() == ERROR_PIPE_CONNECTED.to_hresult() => return Some(0),
+        Err(_) => return None,
+    }
+
+    let mut transferred = 0;
+    let signaled = unsafe { WaitForMultipleObjects(&[event.0, stop], false, INFINITE) };
+    if signaled != WAIT_OBJECT_0 {
+        // The operation must be finished before `overlapped` is dropped
+        unsafe {
+            let _ = CancelIoEx(pipe, Some(&overlapped));
+            let _ = GetOverlappedResult(pipe, &overlapped, &mut transferred, true);
+        }
+        return None;
+    }
+    unsafe { GetOverlappedResult(pipe, &overlapped, &mut transferred, false) }.ok()?;
+    Some(transferred)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,250 @@
+//! Opt-in guard moving the windows back to their desktops after explorer.exe
+//! restarts.
+//!
+//! When explorer.exe crashes the windows are collapsed onto one desktop. While
+//! a `PlacementGuard` lives, the desktop of each window is recorded from the
+//! desktop events, and after the restart the windows still open are moved
+//! back:
+//!
+//! ```rust,no_run
+//! let _guard = winvd::PlacementGuard::start().unwrap();
+//! // Windows are moved back to their desktops as long as `_guard` lives
+//! ```
+//!
+//! Pinned windows are not recorded, they are pinned again by explorer.exe.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::backend::with_backend;
+use crate::listener::DesktopEventHandler;
+use crate::{Desktop, DesktopBackend, DesktopEvent, Error, Result, WindowId};
+
+/// Times the windows are tried to move after the restart, explorer.exe
+/// creates the views of the windows some time after it is running
+const RESTORE_ATTEMPTS: u32 = 5;
+const RESTORE_DELAY: Duration = Duration::from_secs(1);
+
+/// Desktop GUIDs by window
+type Placements = HashMap<isize, GUID>;
+
+/// Moves the windows back to their desktops, see the module docs
+pub struct PlacementGuard {
+    placements: Arc<Mutex<Placements>>,
+    _events: DesktopEventHandler,
+}
+
+impl PlacementGuard {
+    /// Records the desktops of the open windows, and starts the listener
+    pub fn start() -> Result<Self> {
+        let placements = Arc::new(Mutex::new(snapshot()?));
+        let thread_placements = placements.clone();
+        let events = DesktopEventHandler::new(move |event| match event {
+            DesktopEvent::WindowChanged(hwnd) => {
+                let placements = thread_placements.clone();
+                let _ = with_backend(move |b| {
+                    record(b, &placements, hwnd.into());
+                    Ok(())
+                });
+            }
+            DesktopEvent::ConnectionReset { .. } => restore_windows(thread_placements.clone()),
+            // Moves of the windows were missed
+            DesktopEvent::EventsDropped { .. } => {
+                if let Ok(placements) = snapshot() {
+                    *lock(&thread_placements) = placements;
+                }
+            }
+            _ => {}
+        })?;
+
+        Ok(PlacementGuard {
+            placements,
+            _events: events,
+        })
+    }
+
+    /// Recorded windows and the GUIDs of their desktops, closed windows are
+    /// left out
+    pub fn placements(&self) -> Vec<(WindowId, GUID)> {
+        live_placements(&self.placements, &is_window)
+            .into_iter()
+            .map(|(hwnd, guid)| (WindowId(hwnd), guid))
+            .collect()
+    }
+
+    /// Moves the windows back to their recorded desktops now, e.g. when the
+    /// restart was noticed some other way
+    pub fn restore(&self) {
+        restore_windows(self.placements.clone())
+    }
+}
+
+/// Desktops of the open windows
+fn snapshot() -> Result<Placements> {
+    Ok(crate::list_views()?
+        .into_iter()
+        .filter(|view| view.show_in_switchers && !view.is_pinned)
+        .filter(|view| view.desktop != GUID::zeroed())
+        .map(|view| (view.hwnd.0, view.desktop))
+        .collect())
+}
+
+/// Records the desktop the window is on now
+fn record(b: &dyn DesktopBackend, placements: &Mutex<Placements>, hwnd: HWND) {
+    let desktop = match b.is_pinned_window(hwnd) {
+        Ok(false) => b
+            .get_desktop_by_window(hwnd)
+            .and_then(|desktop| b.get_desktop_id(desktop)),
+        Ok(true) => Err(Error::WindowNotMovable),
+        Err(err) => Err(err),
+    };
+    let mut placements = lock(placements);
+    match desktop {
+        Ok(guid) => {
+            placements.insert(hwnd.0, guid);
+        }
+        // Closed or pinned
+        Err(_) => {
+            placements.remove(&hwnd.0);
+        }
+    }
+}
+
+/// Restores the windows with the backend, see `restore`
+fn restore_windows(placements: Arc<Mutex<Placements>>) {
+    let pending =
+        with_backend(move |b| Ok(restore(b, &placements, &is_window, RESTORE_DELAY))).unwrap_or(0);
+    if pending > 0 {
+        log_format!(Warn: "Restoring the desktops of {} windows failed", pending);
+    }
+}
+
+/// Moves the windows still open back to their desktops, desktops that are gone
+/// are skipped. Returns the number of windows that could not be moved.
+fn restore(
+    b: &dyn DesktopBackend,
+    placements: &Mutex<Placements>,
+    is_open: &dyn Fn(HWND) -> bool,
+    delay: Duration,
+) -> usize {
+    let mut pending = live_placements(placements, is_open);
+    for attempt in 0..RESTORE_ATTEMPTS {
+        if attempt > 0 {
+            std::thread::sleep(delay);
+        }
+        pending.retain(|hwnd, guid| {
+            let hwnd = HWND(*hwnd);
+            if !is_open(hwnd) {
+                return false;
+            }
+            let result = b
+                .get_desktop_by_window(hwnd)
+                .and_then(|desktop| b.get_desktop_id(desktop))
+                .and_then(|current| {
+                    if current == *guid {
+                        Ok(())
+                    } else {
+                        b.move_window_to_desktop(hwnd, Desktop::from(*guid))
+                    }
+                });
+            // Other errors are retried, the view might not be created yet
+            !matches!(
+                result,
+                Ok(()) | Err(Error::DesktopNotFound) | Err(Error::WindowNotMovable)
+            )
+        });
+        if pending.is_empty() {
+            break;
+        }
+    }
+    pending.len()
+}
+
+/// Drops closed windows, and returns the rest
+fn live_placements(placements: &Mutex<Placements>, is_open: &dyn Fn(HWND) -> bool) -> Placements {
+    let mut placements = lock(placements);
+    placements.retain(|hwnd, _| is_open(HWND(*hwnd)));
+    placements.clone()
+}
+
+fn is_window(hwnd: HWND) -> bool {
+    unsafe { IsWindow(hwnd) }.as_bool()
+}
+
+fn lock(placements: &Mutex<Placements>) -> MutexGuard<'_, Placements> {
+    placements
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(all(test, feature = "test-hooks"))]
+mod tests {
+    use super::*;
+    use crate::MockBackend;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_record() {
+        let mock = MockBackend::new(3);
+        let (moved, pinned) = (HWND(1), HWND(2));
+        mock.add_window(moved, Desktop::from(1)).unwrap();
+        mock.add_window(pinned, Desktop::from(1)).unwrap();
+        mock.pin_window(pinned).unwrap();
+        let placements = Mutex::new(Placements::from([(pinned.0, GUID::from_u128(2))]));
+
+        record(&mock, &placements, moved);
+        record(&mock, &placements, pinned);
+        assert_eq!(
+            *lock(&placements),
+            Placements::from([(1, GUID::from_u128(2))])
+        );
+
+        // Closed windows are not known to the shell anymore
+        record(&mock, &placements, HWND(3));
+        let closed = MockBackend::new(1);
+        record(&closed, &placements, moved);
+        assert!(lock(&placements).is_empty());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mock = MockBackend::new(3);
+        let (moved, gone, pinned, closed, pending) = (HWND(1), HWND(2), HWND(3), HWND(4), HWND(5));
+        for hwnd in [moved, gone, pinned, closed] {
+            mock.add_window(hwnd, Desktop::from(0)).unwrap();
+        }
+        mock.pin_window(pinned).unwrap();
+        let desktop = GUID::from_u128(2);
+        let placements = Mutex::new(Placements::from([
+            (moved.0, desktop),
+            (gone.0, GUID::from_u128(99)),
+            (pinned.0, desktop),
+            (closed.0, desktop),
+            (pending.0, desktop),
+        ]));
+
+        // The view of `pending` is never created, it is tried on each attempt
+        let pending_checks = Cell::new(0);
+        let is_open = |hwnd: HWND| {
+            if hwnd == pending {
+                pending_checks.set(pending_checks.get() + 1);
+            }
+            hwnd != closed
+        };
+        assert_eq!(restore(&mock, &placements, &is_open, Duration::ZERO), 1);
+        assert_eq!(pending_checks.get(), RESTORE_ATTEMPTS + 1);
+
+        let on = |hwnd| {
+            mock.get_desktop_id(mock.get_desktop_by_window(hwnd).unwrap())
+                .unwrap()
+        };
+        assert_eq!(on(moved), desktop);
+        assert_eq!(on(gone), GUID::from_u128(1));
+        assert_eq!(on(pinned), GUID::from_u128(1));
+        assert!(!lock(&placements).contains_key(&closed.0));
+    }
+}
@@ -0,0 +1,24 @@
+//! Commonly used types, for glob importing.
+//!
+//! ```rust,no_run
+//! use winvd::prelude::*;
+//!
+//! fn current_name() -> Result<String> {
+//!     get_current_desktop()?.get_name()
+//! }
+//! ```
+//!
+//! There are no window view types, windows are referred to with `HWND`,
+//! which is re-exported here.
+pub use windows::Win32::Foundation::HWND;
+
+pub use crate::{
+    get_current_desktop, get_desktop, get_desktop_count, get_desktops, switch_desktop, Desktop,
+    DesktopInfo, Error, Result, VirtualDesktopService,
+};
+
+#[cfg(feature = "listener")]
+pub use crate::{
+    listen_desktop_events, listen_desktop_events_with_options, DesktopEvent, DesktopEventSender,
+    DesktopEventThread, ListenerOptions,
+};
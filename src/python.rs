@@ -0,0 +1,465 @@
+//! Python module, enabled with the `python` feature.
+//!
+//! Build with maturin, which reads the settings from `pyproject.toml`:
+//!
+//! ```text
+//! maturin build --release
+//! ```
+//!
+//! Calls run on a `VirtualDesktopService` worker thread with the GIL released,
+//! so other Python threads keep running while COM is called. Desktops are
+//! passed as `Desktop` objects, indices, GUID strings or names, and windows as
+//! HWND integers.
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use pyo3::exceptions::PyOSError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use windows::core::GUID;
+use windows::Win32::Foundation::HWND;
+
+use crate::service::shared_service;
+use crate::{Desktop, DesktopEvent, DesktopEventThread, Error, VirtualDesktopService};
+
+/// How often a blocked `Events` iterator checks for Ctrl+C
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        PyOSError::new_err(format!("{:?}", err))
+    }
+}
+
+/// Runs the call on the shared worker with the GIL released
+fn call<T, F>(py: Python<'_>, f: F) -> PyResult<T>
+where
+    F: FnOnce() -> crate::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    Ok(py.allow_threads(move || shared_service()?.call(f))?)
+}
+
+/// Desktop argument, resolved on the worker
+#[derive(FromPyObject)]
+enum DesktopArg {
+    Desktop(PyDesktop),
+    Index(u32),
+
+    /// GUID or name
+    Text(String),
+}
+
+impl DesktopArg {
+    fn resolve(self) -> crate::Result<Desktop> {
+        match self {
+            DesktopArg::Desktop(desktop) => Ok(Desktop::from(desktop.guid)),
+            DesktopArg::Index(index) => Ok(Desktop::from(index)),
+            DesktopArg::Text(text) => text.parse(),
+        }
+    }
+}
+
+/// Virtual desktop, refers to the same desktop when the desktops are
+/// reordered. Created from an index, a GUID string or a name.
+#[pyclass(name = "Desktop", frozen, eq, hash)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PyDesktop {
+    guid: GUID,
+}
+
+impl PyDesktop {
+    fn desktop(&self) -> Desktop {
+        Desktop::from(self.guid)
+    }
+}
+
+#[pymethods]
+impl PyDesktop {
+    #[new]
+    fn new(py: Python<'_>, desktop: DesktopArg) -> PyResult<Self> {
+        let guid = call(py, move || desktop.resolve()?.get_id())?;
+        Ok(PyDesktop { guid })
+    }
+
+    /// GUID of the desktop, as in `list_desktops`
+    #[getter]
+    fn id(&self) -> String {
+        format!("{:?}", self.guid)
+    }
+
+    #[getter]
+    fn index(&self, py: Python<'_>) -> PyResult<u32> {
+        let desktop = self.desktop();
+        call(py, move || desktop.get_index())
+    }
+
+    #[getter]
+    fn name(&self, py: Python<'_>) -> PyResult<String> {
+        let desktop = self.desktop();
+        call(py, move || desktop.get_name())
+    }
+
+    fn rename(&self, py: Python<'_>, name: String) -> PyResult<()> {
+        let desktop = self.desktop();
+        call(py, move || desktop.set_name(&name))
+    }
+
+    fn switch(&self, py: Python<'_>) -> PyResult<()> {
+        let desktop = self.desktop();
+        call(py, move || crate::switch_desktop(desktop))
+    }
+
+    fn move_window(&self, py: Python<'_>, hwnd: isize) -> PyResult<()> {
+        let desktop = self.desktop();
+        call(py, move || {
+            crate::move_window_to_desktop(desktop, HWND(hwnd))
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Desktop('{}')", self.id())
+    }
+}
+
+/// Number of desktops
+#[pyfunction]
+fn get_desktop_count(py: Python<'_>) -> PyResult<u32> {
+    call(py, crate::get_desktop_count)
+}
+
+/// Index of the current desktop
+#[pyfunction]
+fn get_current_desktop(py: Python<'_>) -> PyResult<u32> {
+    call(py, || crate::get_current_desktop()?.get_index())
+}
+
+#[pyfunction]
+fn current_desktop(py: Python<'_>) -> PyResult<PyDesktop> {
+    let guid = call(py, || crate::get_current_desktop()?.get_id())?;
+    Ok(PyDesktop { guid })
+}
+
+/// All desktops as `Desktop` objects, in order
+#[pyfunction]
+fn get_desktops(py: Python<'_>) -> PyResult<Vec<PyDesktop>> {
+    let desktops = call(py, crate::list_desktops_detailed)?;
+    Ok(desktops
+        .into_iter()
+        .map(|info| PyDesktop { guid: info.guid })
+        .collect())
+}
+
+/// List of `(index, id, name)` tuples of all desktops
+#[pyfunction]
+fn list_desktops(py: Python<'_>) -> PyResult<Vec<(u32, String, String)>> {
+    let desktops = call(py, crate::list_desktops_detailed)?;
+    Ok(desktops
+        .into_iter()
+        .map(|info| (info.index, format!("{:?}", info.guid), info.name))
+        .collect())
+}
+
+/// Switches to the desktop, given as a `Desktop`, an index, a GUID or a name
+#[pyfunction]
+fn switch_desktop(py: Python<'_>, index: DesktopArg) -> PyResult<()> {
+    call(py, move || crate::switch_desktop(index.resolve()?))
+}
+
+/// Creates a new desktop and returns its index
+#[pyfunction]
+fn create_desktop(py: Python<'_>) -> PyResult<u32> {
+    call(py, || crate::create_desktop()?.get_index())
+}
+
+#[pyfunction]
+fn remove_desktop(py: Python<'_>, index: DesktopArg, fallback_index: DesktopArg) -> PyResult<()> {
+    call(py, move || {
+        crate::remove_desktop(index.resolve()?, fallback_index.resolve()?)
+    })
+}
+
+#[pyfunction]
+fn get_desktop_name(py: Python<'_>, index: DesktopArg) -> PyResult<String> {
+    call(py, move || index.resolve()?.get_name())
+}
+
+#[pyfunction]
+fn set_desktop_name(py: Python<'_>, index: DesktopArg, name: String) -> PyResult<()> {
+    call(py, move || index.resolve()?.set_name(&name))
+}
+
+/// Index of the desktop the window is on
+#[pyfunction]
+fn get_desktop_by_window(py: Python<'_>, hwnd: isize) -> PyResult<u32> {
+    call(py, move || {
+        crate::get_desktop_by_window(HWND(hwnd))?.get_index()
+    })
+}
+
+#[pyfunction]
+fn move_window_to_desktop(py: Python<'_>, index: DesktopArg, hwnd: isize) -> PyResult<()> {
+    call(py, move || {
+        crate::move_window_to_desktop(index.resolve()?, HWND(hwnd))
+    })
+}
+
+#[pyfunction]
+fn is_window_on_current_desktop(py: Python<'_>, hwnd: isize) -> PyResult<bool> {
+    call(py, move || crate::is_window_on_current_desktop(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn is_pinned_window(py: Python<'_>, hwnd: isize) -> PyResult<bool> {
+    call(py, move || crate::is_pinned_window(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn pin_window(py: Python<'_>, hwnd: isize) -> PyResult<()> {
+    call(py, move || crate::pin_window(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn unpin_window(py: Python<'_>, hwnd: isize) -> PyResult<()> {
+    call(py, move || crate::unpin_window(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn is_pinned_app(py: Python<'_>, hwnd: isize) -> PyResult<bool> {
+    call(py, move || crate::is_pinned_app(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn pin_app(py: Python<'_>, hwnd: isize) -> PyResult<()> {
+    call(py, move || crate::pin_app(HWND(hwnd)))
+}
+
+#[pyfunction]
+fn unpin_app(py: Python<'_>, hwnd: isize) -> PyResult<()> {
+    call(py, move || crate::unpin_app(HWND(hwnd)))
+}
+
+/// Desktop event listener returned by `listen`, events stop when `stop` is
+/// called or the value is garbage collected
+#[pyclass]
+struct Listener {
+    thread: Option<DesktopEventThread>,
+    forwarder: Option<std::thread::JoinHandle<()>>,
+}
+
+#[pymethods]
+impl Listener {
+    /// Stops the listener
+    fn stop(&mut self, py: Python<'_>) {
+        let thread = self.thread.take();
+        let forwarder = self.forwarder.take();
+
+        // Forwarder needs the GIL to finish calling the callback
+        py.allow_threads(move || {
+            drop(thread);
+            if let Some(forwarder) = forwarder {
+                let _ = forwarder.join();
+            }
+        });
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if self.thread.is_some() || self.forwarder.is_some() {
+            Python::with_gil(|py| self.stop(py));
+        }
+    }
+}
+
+/// Calls `callback(kind, data)` for each desktop event, where `kind` is the
+/// name of the event, e.g. `"desktop_changed"`, and `data` is a dict
+#[pyfunction]
+fn listen(py: Python<'_>, callback: PyObject) -> PyResult<Listener> {
+    let service = py.allow_threads(shared_service)?;
+    let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let thread = crate::listen_desktop_events(tx)?;
+    let forwarder = std::thread::spawn(move || {
+        for event in rx {
+            Python::with_gil(|py| {
+                let res = event_to_py(py, &service, event)
+                    .and_then(|(kind, data)| callback.call1(py, (kind, data)));
+                if let Err(err) = res {
+                    err.print(py);
+                }
+            });
+        }
+    });
+    Ok(Listener {
+        thread: Some(thread),
+        forwarder: Some(forwarder),
+    })
+}
+
+/// Iterator of the desktop events as `(kind, data)` tuples, see `listen`.
+/// Waiting for the next event releases the GIL, and the iteration ends when
+/// `stop` is called.
+#[pyclass(frozen)]
+struct Events {
+    service: VirtualDesktopService,
+    rx: Mutex<Receiver<DesktopEvent>>,
+    thread: Mutex<Option<DesktopEventThread>>,
+}
+
+#[pymethods]
+impl Events {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Option<(&'static str, Bound<'py, PyDict>)>> {
+        loop {
+            let event = py.allow_threads(|| lock(&self.rx).recv_timeout(POLL_INTERVAL));
+            match event {
+                Ok(event) => return event_to_py(py, &self.service, event).map(Some),
+                // Lets Ctrl+C interrupt the loop
+                Err(RecvTimeoutError::Timeout) => py.check_signals()?,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+
+    /// Stops the listener, the iteration ends after the queued events
+    fn stop(&self, py: Python<'_>) {
+        let thread = lock(&self.thread).take();
+        py.allow_threads(move || drop(thread));
+    }
+}
+
+/// Desktop events as an iterator, e.g. `for kind, data in winvd.events():`
+#[pyfunction]
+fn events(py: Python<'_>) -> PyResult<Events> {
+    let service = py.allow_threads(shared_service)?;
+    let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let thread = crate::listen_desktop_events(tx)?;
+    Ok(Events {
+        service,
+        rx: Mutex::new(rx),
+        thread: Mutex::new(Some(thread)),
+    })
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn event_to_py<'py>(
+    py: Python<'py>,
+    service: &VirtualDesktopService,
+    event: DesktopEvent,
+) -> PyResult<(&'static str, Bound<'py, PyDict>)> {
+    // Indices of the desktops are read on the worker
+    let index = |desktop: &Desktop| {
+        let desktop = *desktop;
+        py.allow_threads(|| service.call(move || desktop.get_index()).ok())
+    };
+    let data = PyDict::new(py);
+    let kind = match event {
+        DesktopEvent::DesktopCreated(desktop) => {
+            data.set_item("desktop", index(&desktop))?;
+            "desktop_created"
+        }
+        DesktopEvent::DesktopDestroyed {
+            destroyed,
+            fallback,
+        } => {
+            data.set_item("destroyed", index(&destroyed))?;
+            data.set_item("fallback", index(&fallback))?;
+            "desktop_destroyed"
+        }
+        DesktopEvent::DesktopChanged { new, old } => {
+            data.set_item("new", index(&new))?;
+            data.set_item("old", index(&old))?;
+            "desktop_changed"
+        }
+        DesktopEvent::DesktopNameChanged(desktop, name) => {
+            data.set_item("desktop", index(&desktop))?;
+            data.set_item("name", name)?;
+            "desktop_name_changed"
+        }
+        DesktopEvent::DesktopWallpaperChanged(desktop, wallpaper) => {
+            data.set_item("desktop", index(&desktop))?;
+            data.set_item("wallpaper", wallpaper)?;
+            "desktop_wallpaper_changed"
+        }
+        DesktopEvent::DesktopMoved {
+            desktop,
+            old_index,
+            new_index,
+        } => {
+            data.set_item("desktop", index(&desktop))?;
+            data.set_item("old_index", old_index)?;
+            data.set_item("new_index", new_index)?;
+            "desktop_moved"
+        }
+        DesktopEvent::WindowChanged(hwnd) => {
+            data.set_item("hwnd", hwnd.0)?;
+            "window_changed"
+        }
+        DesktopEvent::MonitorsChanged(monitors) => {
+            let monitors: Vec<isize> = monitors.iter().map(|monitor| monitor.0).collect();
+            data.set_item("monitors", monitors)?;
+            "monitors_changed"
+        }
+        DesktopEvent::InitialState { current, desktops } => {
+            let desktops: Vec<(u32, String)> = desktops
+                .into_iter()
+                .map(|info| (info.index, info.name))
+                .collect();
+            data.set_item("current", index(&current))?;
+            data.set_item("desktops", desktops)?;
+            "initial_state"
+        }
+        DesktopEvent::ConnectionReset { desktops } => {
+            let desktops: Vec<Option<u32>> = desktops.iter().map(index).collect();
+            data.set_item("desktops", desktops)?;
+            "connection_reset"
+        }
+        DesktopEvent::EventsDropped { desktops } => {
+            let desktops: Vec<Option<u32>> = desktops.iter().map(index).collect();
+            data.set_item("desktops", desktops)?;
+            "events_dropped"
+        }
+    };
+    Ok((kind, data))
+}
+
+#[pymodule]
+fn winvd(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_desktop_count, m)?)?;
+    m.add_function(wrap_pyfunction!(get_current_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(current_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(get_desktops, m)?)?;
+    m.add_function(wrap_pyfunction!(list_desktops, m)?)?;
+    m.add_function(wrap_pyfunction!(switch_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(create_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(get_desktop_name, m)?)?;
+    m.add_function(wrap_pyfunction!(set_desktop_name, m)?)?;
+    m.add_function(wrap_pyfunction!(get_desktop_by_window, m)?)?;
+    m.add_function(wrap_pyfunction!(move_window_to_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(is_window_on_current_desktop, m)?)?;
+    m.add_function(wrap_pyfunction!(is_pinned_window, m)?)?;
+    m.add_function(wrap_pyfunction!(pin_window, m)?)?;
+    m.add_function(wrap_pyfunction!(unpin_window, m)?)?;
+    m.add_function(wrap_pyfunction!(is_pinned_app, m)?)?;
+    m.add_function(wrap_pyfunction!(pin_app, m)?)?;
+    m.add_function(wrap_pyfunction!(unpin_app, m)?)?;
+    m.add_function(wrap_pyfunction!(listen, m)?)?;
+    m.add_function(wrap_pyfunction!(events, m)?)?;
+    m.add_class::<PyDesktop>()?;
+    m.add_class::<Listener>()?;
+    m.add_class::<Events>()?;
+    Ok(())
+}
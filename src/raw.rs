@@ -0,0 +1,48 @@
+//! Raw COM objects, for calling interface methods the crate doesn't wrap yet.
+//!
+//! The virtual desktop interfaces change between Windows builds, check
+//! `interface_version_in_use` for the build whose definitions apply, e.g. the
+//! bindings in `src/interfaces_multi/build_22631_3155.rs`. Calling a method
+//! with the definitions of another build crashes the caller or explorer.exe.
+use windows::core::IUnknown;
+use windows::Win32::System::Com::IServiceProvider;
+
+use crate::comobjects::with_com_objects;
+use crate::{Desktop, Result};
+
+/// Service provider of the immersive shell, for querying other shell services
+///
+/// # Safety
+///
+/// Use the object only on the calling thread, and not after explorer.exe
+/// restarts.
+pub unsafe fn raw_service_provider() -> Result<IServiceProvider> {
+    with_com_objects(|o| o.raw_service_provider())
+}
+
+/// `IVirtualDesktopManagerInternal` of the current Windows build, see
+/// `interface_version_in_use`
+///
+/// # Safety
+///
+/// Use the object only on the calling thread, and not after explorer.exe
+/// restarts. Its methods must be called with the definitions of the current
+/// build.
+pub unsafe fn raw_manager_internal() -> Result<IUnknown> {
+    with_com_objects(|o| o.raw_manager_internal())
+}
+
+impl Desktop {
+    /// `IVirtualDesktop` of the current Windows build, see
+    /// `interface_version_in_use`
+    ///
+    /// # Safety
+    ///
+    /// Use the object only on the calling thread, and not after explorer.exe
+    /// restarts. Its methods must be called with the definitions of the
+    /// current build.
+    pub unsafe fn as_ivirtualdesktop(&self) -> Result<IUnknown> {
+        let internal = (*self).into();
+        with_com_objects(move |o| o.raw_idesktop(&internal))
+    }
+}
@@ -0,0 +1,325 @@
+//! Reads the virtual desktops from the registry, without using the COM API.
+//!
+//! Explorer stores the desktops in
+//! `HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops`,
+//! this can be used for diagnostics or when the COM interfaces of the current
+//! Windows build are not supported. The values are updated by explorer.exe, so
+//! they may lag behind the actual state a bit.
+//!
+//! The names of the desktops are kept here too on Windows 10 builds without
+//! desktop names, see `read_desktop_name`.
+use windows::core::{w, GUID, HSTRING, PCWSTR};
+use windows::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+use windows::Win32::System::Registry::{
+    RegGetValueW, RegSetKeyValueW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, REG_ROUTINE_FLAGS,
+    REG_SZ, RRF_RT_REG_BINARY, RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
+};
+
+use crate::comobjects::DesktopInternal;
+#[cfg(feature = "listener")]
+use crate::DesktopEvent;
+use crate::{Desktop, Error, Result};
+
+const VIRTUAL_DESKTOPS_KEY: PCWSTR =
+    w!(r#"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops"#);
+
+const CURRENT_VERSION_KEY: PCWSTR = w!(r#"SOFTWARE\Microsoft\Windows NT\CurrentVersion"#);
+
+/// Get all desktops from the registry, in the order they are shown
+pub fn desktops_from_registry() -> Result<Vec<Desktop>> {
+    Ok(read_desktop_ids()?
+        .into_iter()
+        .enumerate()
+        .map(|(index, guid)| DesktopInternal::IndexGuid(index as u32, guid).into())
+        .collect())
+}
+
+/// Get the current desktop from the registry
+///
+/// On Windows 10 the current desktop is stored per session, and this returns
+/// `Error::RegistryError` there.
+pub fn current_desktop_from_registry() -> Result<Desktop> {
+    let guid = read_current_desktop_id()?;
+
+    // Index is known only if the desktop is also in the list
+    let ids = read_desktop_ids().unwrap_or_default();
+    Ok(desktop_from_ids(&ids, guid))
+}
+
+/// Synthesizes events from the changes in the registry, used by the listener
+/// when registering for notifications fails.
+///
+/// Desktop changes are not detected on Windows 10, where the current desktop is
+/// not stored in the same key.
+#[cfg(feature = "listener")]
+pub(crate) struct RegistryPoller {
+    desktops: Vec<GUID>,
+    current: Option<GUID>,
+}
+
+#[cfg(feature = "listener")]
+impl RegistryPoller {
+    pub fn new() -> Self {
+        RegistryPoller {
+            desktops: read_desktop_ids().unwrap_or_default(),
+            current: read_current_desktop_id().ok(),
+        }
+    }
+
+    /// Reads the registry, and returns events for the changes since the last poll
+    pub fn poll(&mut self) -> Vec<DesktopEvent> {
+        match read_desktop_ids() {
+            Ok(desktops) => self.update(desktops, read_current_desktop_id().ok()),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, desktops: Vec<GUID>, current: Option<GUID>) -> Vec<DesktopEvent> {
+        let mut events = Vec::new();
+        for guid in desktops.iter().filter(|guid| !self.desktops.contains(guid)) {
+            let created = desktop_from_ids(&desktops, *guid);
+            events.push(DesktopEvent::DesktopCreated(created));
+        }
+
+        let fallback = current.or_else(|| desktops.first().copied());
+        if let Some(fallback) = fallback {
+            for guid in self.desktops.iter().filter(|guid| !desktops.contains(guid)) {
+                events.push(DesktopEvent::DesktopDestroyed {
+                    destroyed: (*guid).into(),
+                    fallback: desktop_from_ids(&desktops, fallback),
+                });
+            }
+        }
+
+        if let (Some(old), Some(new)) = (self.current, current) {
+            if old != new {
+                events.push(DesktopEvent::DesktopChanged {
+                    new: desktop_from_ids(&desktops, new),
+                    old: desktop_from_ids(&desktops, old),
+                });
+            }
+        }
+
+        self.desktops = desktops;
+        self.current = current.or(self.current);
+        events
+    }
+}
+
+fn desktop_from_ids(ids: &[GUID], guid: GUID) -> Desktop {
+    match ids.iter().position(|id| *id == guid) {
+        Some(index) => DesktopInternal::IndexGuid(index as u32, guid).into(),
+        None => guid.into(),
+    }
+}
+
+fn read_desktop_ids() -> Result<Vec<GUID>> {
+    let ids = read_binary_value(w!("VirtualDesktopIDs"))?;
+    Ok(guids_from_bytes(&ids))
+}
+
+fn read_current_desktop_id() -> Result<GUID> {
+    guids_from_bytes(&read_binary_value(w!("CurrentVirtualDesktop"))?)
+        .into_iter()
+        .next()
+        .ok_or(Error::DesktopNotFound)
+}
+
+/// Windows `(build, patch)` version, e.g. `(22631, 3155)`
+pub(crate) fn read_windows_version() -> Result<(u32, u32)> {
+    let build = read_value(
+        HKEY_LOCAL_MACHINE,
+        CURRENT_VERSION_KEY,
+        w!("CurrentBuildNumber"),
+        RRF_RT_REG_SZ,
+    )?;
+    let build = string_from_bytes(&build)
+        .parse()
+        .map_err(|_| Error::RegistryError(windows::Win32::Foundation::E_UNEXPECTED))?;
+
+    // Patch version is missing on some older builds
+    let patch = read_value(
+        HKEY_LOCAL_MACHINE,
+        CURRENT_VERSION_KEY,
+        w!("UBR"),
+        RRF_RT_REG_DWORD,
+    )
+    .ok()
+    .and_then(|ubr| Some(u32::from_le_bytes(ubr.get(..4)?.try_into().ok()?)))
+    .unwrap_or(0);
+    Ok((build, patch))
+}
+
+/// Name of the desktop kept in the registry, empty if it has none.
+///
+/// Windows 10 builds before 2004 have no desktop names, their names are kept
+/// in `VirtualDesktops\Desktops\{GUID}`, where the later builds keep theirs.
+pub(crate) fn read_desktop_name(guid: &GUID) -> Result<String> {
+    let key = HSTRING::from(desktop_key(guid));
+    match read_value(
+        HKEY_CURRENT_USER,
+        PCWSTR(key.as_ptr()),
+        w!("Name"),
+        RRF_RT_REG_SZ,
+    ) {
+        Ok(name) => Ok(string_from_bytes(&name)),
+        Err(Error::RegistryError(hresult)) if hresult == ERROR_FILE_NOT_FOUND.to_hresult() => {
+            Ok(String::new())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Keeps the name of the desktop in the registry, see `read_desktop_name`
+pub(crate) fn write_desktop_name(guid: &GUID, name: &str) -> Result<()> {
+    let data = HSTRING::from(name);
+    let key = HSTRING::from(desktop_key(guid));
+    let res = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            w!("Name"),
+            REG_SZ.0,
+            Some(data.as_ptr() as _),
+            // Including the null terminator
+            ((data.len() + 1) * 2) as u32,
+        )
+    };
+    if res.is_err() {
+        return Err(Error::RegistryError(res.to_hresult()));
+    }
+    Ok(())
+}
+
+fn desktop_key(guid: &GUID) -> String {
+    format!(
+        r#"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops\Desktops\{{{:?}}}"#,
+        guid
+    )
+}
+
+fn read_binary_value(value: PCWSTR) -> Result<Vec<u8>> {
+    read_value(
+        HKEY_CURRENT_USER,
+        VIRTUAL_DESKTOPS_KEY,
+        value,
+        RRF_RT_REG_BINARY,
+    )
+}
+
+fn read_value(hkey: HKEY, key: PCWSTR, value: PCWSTR, flags: REG_ROUTINE_FLAGS) -> Result<Vec<u8>> {
+    let read = |buffer: Option<&mut [u8]>, cb_data: &mut u32| {
+        let res = unsafe {
+            RegGetValueW(
+                hkey,
+                key,
+                value,
+                flags,
+                None,
+                buffer.map(|buffer| buffer.as_mut_ptr() as _),
+                Some(cb_data as *mut u32),
+            )
+        };
+        if res.is_err() {
+            return Err(Error::RegistryError(res.to_hresult()));
+        }
+        Ok(())
+    };
+
+    // First call gets the size of the value
+    let mut cb_data = 0;
+    read(None, &mut cb_data)?;
+    let mut buffer = vec![0; cb_data as usize];
+    read(Some(&mut buffer), &mut cb_data)?;
+    buffer.truncate(cb_data as usize);
+    Ok(buffer)
+}
+
+/// UTF-16 string value up to the null terminator
+fn string_from_bytes(bytes: &[u8]) -> String {
+    let chars: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&chars)
+}
+
+/// GUIDs are stored one after another in their in-memory layout
+fn guids_from_bytes(bytes: &[u8]) -> Vec<GUID> {
+    bytes
+        .chunks_exact(16)
+        .map(|chunk| {
+            GUID::from_values(
+                u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                u16::from_le_bytes([chunk[4], chunk[5]]),
+                u16::from_le_bytes([chunk[6], chunk[7]]),
+                [
+                    chunk[8], chunk[9], chunk[10], chunk[11], chunk[12], chunk[13], chunk[14],
+                    chunk[15],
+                ],
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guids_from_bytes() {
+        let guid = GUID::from_u128(0x11223344_5566_7788_99aa_bbccddeeff00);
+        let bytes: [u8; 16] = unsafe { std::mem::transmute(guid) };
+        let mut data = bytes.repeat(2);
+
+        // Trailing partial GUID is ignored
+        data.push(0);
+
+        assert_eq!(guids_from_bytes(&data), vec![guid, guid]);
+    }
+
+    #[test]
+    fn test_desktop_name_value() {
+        let guid = GUID::from_u128(0x11223344_5566_7788_99aa_bbccddeeff00);
+        assert_eq!(
+            desktop_key(&guid),
+            r#"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops\Desktops\{11223344-5566-7788-99AA-BBCCDDEEFF00}"#
+        );
+        let bytes: Vec<u8> = "Työ\0"
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        assert_eq!(string_from_bytes(&bytes), "Työ");
+    }
+
+    #[cfg(feature = "listener")]
+    #[test]
+    fn test_registry_poller_events() {
+        let [a, b, c] = [1, 2, 3].map(GUID::from_u128);
+        let mut poller = RegistryPoller {
+            desktops: vec![a, b],
+            current: Some(a),
+        };
+
+        // Desktop equality can query COM, compare the debug output instead
+        assert!(poller.update(vec![a, b], Some(a)).is_empty());
+        assert_eq!(
+            format!("{:?}", poller.update(vec![a, c], Some(c))),
+            format!(
+                "{:?}",
+                vec![
+                    DesktopEvent::DesktopCreated(DesktopInternal::IndexGuid(1, c).into()),
+                    DesktopEvent::DesktopDestroyed {
+                        destroyed: b.into(),
+                        fallback: DesktopInternal::IndexGuid(1, c).into(),
+                    },
+                    DesktopEvent::DesktopChanged {
+                        new: DesktopInternal::IndexGuid(1, c).into(),
+                        old: DesktopInternal::IndexGuid(0, a).into(),
+                    },
+                ]
+            )
+        );
+    }
+}
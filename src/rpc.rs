@@ -0,0 +1,284 @@
+//! JSON-RPC 2.0 protocol of `serve_pipe`, `serve_ws` and the MQTT command
+//! topic. Each line on the pipe, text message on the WebSocket or MQTT
+//! message is one JSON-RPC message.
+//!
+//! | Method              | Params               | Result          |
+//! |---------------------|----------------------|-----------------|
+//! | `list`              |                      | Desktops        |
+//! | `current`           |                      | Desktop         |
+//! | `switch`            | `desktop`            | `null`          |
+//! | `create`            | `name` (optional)    | Desktop         |
+//! | `remove`            | `desktop`            | `null`          |
+//! | `rename`            | `desktop`, `name`    | `null`          |
+//! | `move_window`       | `hwnd`, `desktop`    | `null`          |
+//! | `pin_window`        | `hwnd`               | `null`          |
+//! | `unpin_window`      | `hwnd`               | `null`          |
+//! | `desktop_of_window` | `hwnd`               | Desktop         |
+//!
+//! Params are given by name. A `desktop` is an index, a GUID or a name, and
+//! a `hwnd` is the window handle as a number. Desktops in the results are
+//! `DesktopInfo` objects. `remove` moves the windows to the desktop on the
+//! left.
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":1,"method":"switch","params":{"desktop":"Work"}}
+//! <-- {"jsonrpc":"2.0","id":1,"result":null}
+//! ```
+//!
+//! Failed calls return an error with the code -32000 and the `Error` as the
+//! message. Desktop events are sent to the clients as `event` notifications
+//! with the `DesktopEvent` as the params:
+//!
+//! ```text
+//! <-- {"jsonrpc":"2.0","method":"event","params":{"DesktopChanged":{"new":"...","old":"..."}}}
+//! ```
+use serde_json::{json, Value};
+use windows::core::GUID;
+use windows::Win32::Foundation::HWND;
+
+use crate::{Desktop, DesktopInfo, Error, FallbackPolicy, VirtualDesktopService};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Virtual desktop call failed, the message is the `Error`
+const CALL_FAILED: i64 = -32000;
+
+/// Call of a request, desktops are resolved when the call is run
+#[derive(Debug, PartialEq)]
+enum Call {
+    List,
+    Current,
+    Switch(DesktopParam),
+    Create(Option<String>),
+    Remove(DesktopParam),
+    Rename(DesktopParam, String),
+    MoveWindow(HWND, DesktopParam),
+    PinWindow(HWND),
+    UnpinWindow(HWND),
+    DesktopOfWindow(HWND),
+}
+
+#[derive(Debug, PartialEq)]
+enum DesktopParam {
+    Index(u32),
+
+    /// GUID or name
+    Text(String),
+}
+
+impl DesktopParam {
+    fn resolve(&self) -> crate::Result<Desktop> {
+        match self {
+            DesktopParam::Index(index) => Ok(Desktop::from(*index)),
+            DesktopParam::Text(text) => text.parse(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        RpcError::new(CALL_FAILED, format!("{:?}", err))
+    }
+}
+
+/// Handles a request line, returns the response, or `None` for notifications
+pub(crate) fn handle_line(service: &VirtualDesktopService, line: &str) -> Option<String> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(err) => {
+            return Some(error_response(
+                Value::Null,
+                &RpcError::new(PARSE_ERROR, err.to_string()),
+            ))
+        }
+    };
+    let result = parse_call(&message)
+        .and_then(|call| service.call(move || run_call(call)).map_err(RpcError::from));
+
+    // Requests without an id are notifications, and get no response
+    let id = message.get("id")?.clone();
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+        Err(err) => error_response(id, &err),
+    })
+}
+
+/// Notification of the event sent to the clients
+#[cfg(any(feature = "server", feature = "websocket"))]
+pub(crate) fn event_notification(event: &crate::DesktopEvent) -> String {
+    json!({ "jsonrpc": "2.0", "method": "event", "params": event }).to_string()
+}
+
+fn error_response(id: Value, err: &RpcError) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code, "message": err.message },
+    })
+    .to_string()
+}
+
+fn parse_call(message: &Value) -> Result<Call, RpcError> {
+    let method = message
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_REQUEST, "`method` must be a string"))?;
+    let params = message.get("params").unwrap_or(&Value::Null);
+    Ok(match method {
+        "list" => Call::List,
+        "current" => Call::Current,
+        "switch" => Call::Switch(desktop_param(params)?),
+        "create" => Call::Create(match params.get("name") {
+            None | Some(Value::Null) => None,
+            Some(_) => Some(string_param(params, "name")?),
+        }),
+        "remove" => Call::Remove(desktop_param(params)?),
+        "rename" => Call::Rename(desktop_param(params)?, string_param(params, "name")?),
+        "move_window" => Call::MoveWindow(hwnd_param(params)?, desktop_param(params)?),
+        "pin_window" => Call::PinWindow(hwnd_param(params)?),
+        "unpin_window" => Call::UnpinWindow(hwnd_param(params)?),
+        "desktop_of_window" => Call::DesktopOfWindow(hwnd_param(params)?),
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{}`", method),
+            ))
+        }
+    })
+}
+
+fn desktop_param(params: &Value) -> Result<DesktopParam, RpcError> {
+    match params.get("desktop") {
+        Some(Value::Number(index)) => index
+            .as_u64()
+            .and_then(|index| u32::try_from(index).ok())
+            .map(DesktopParam::Index)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "`desktop` index out of range")),
+        Some(Value::String(text)) => Ok(DesktopParam::Text(text.clone())),
+        _ => Err(RpcError::new(
+            INVALID_PARAMS,
+            "`desktop` must be an index, a GUID or a name",
+        )),
+    }
+}
+
+fn hwnd_param(params: &Value) -> Result<HWND, RpcError> {
+    params
+        .get("hwnd")
+        .and_then(Value::as_i64)
+        .map(|hwnd| HWND(hwnd as isize))
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "`hwnd` must be a number"))
+}
+
+fn string_param(params: &Value, name: &str) -> Result<String, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("`{}` must be a string", name)))
+}
+
+/// Runs the call on the COM worker thread
+fn run_call(call: Call) -> crate::Result<Value> {
+    let null = |_| Value::Null;
+    match call {
+        Call::List => Ok(json!(crate::list_desktops_detailed()?)),
+        Call::Current => Ok(json!(crate::current_desktop_info()?)),
+        Call::Switch(desktop) => crate::switch_desktop(desktop.resolve()?).map(null),
+        Call::Create(name) => {
+            let desktop = match name {
+                Some(name) => crate::create_desktop_with_name(&name)?,
+                None => crate::create_desktop()?,
+            };
+            Ok(json!(desktop_info(desktop.get_id()?)?))
+        }
+        Call::Remove(desktop) => {
+            crate::remove_desktop_with_policy(desktop.resolve()?, FallbackPolicy::Left).map(null)
+        }
+        Call::Rename(desktop, name) => desktop.resolve()?.set_name(&name).map(null),
+        Call::MoveWindow(hwnd, desktop) => {
+            crate::move_window_to_desktop(desktop.resolve()?, hwnd).map(null)
+        }
+        Call::PinWindow(hwnd) => crate::pin_window(hwnd).map(null),
+        Call::UnpinWindow(hwnd) => crate::unpin_window(hwnd).map(null),
+        Call::DesktopOfWindow(hwnd) => {
+            let guid = crate::get_desktop_by_window(hwnd)?.get_id()?;
+            Ok(json!(desktop_info(guid)?))
+        }
+    }
+}
+
+fn desktop_info(guid: GUID) -> crate::Result<DesktopInfo> {
+    crate::list_desktops_detailed()?
+        .into_iter()
+        .find(|info| info.guid == guid)
+        .ok_or(Error::DesktopNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Call, RpcError> {
+        parse_call(&serde_json::from_str(line).unwrap())
+    }
+
+    #[test]
+    fn test_parse_call() {
+        assert_eq!(parse(r#"{"method":"list"}"#), Ok(Call::List));
+        assert_eq!(
+            parse(r#"{"method":"switch","params":{"desktop":2}}"#),
+            Ok(Call::Switch(DesktopParam::Index(2)))
+        );
+        assert_eq!(
+            parse(r#"{"method":"move_window","params":{"hwnd":1234,"desktop":"Work"}}"#),
+            Ok(Call::MoveWindow(
+                HWND(1234),
+                DesktopParam::Text("Work".to_string())
+            ))
+        );
+        assert_eq!(
+            parse(r#"{"method":"create","params":{"name":null}}"#),
+            Ok(Call::Create(None))
+        );
+
+        let code = |line| parse(line).unwrap_err().code;
+        assert_eq!(code(r#"{"id":1}"#), INVALID_REQUEST);
+        assert_eq!(code(r#"{"method":"resize"}"#), METHOD_NOT_FOUND);
+        assert_eq!(code(r#"{"method":"switch"}"#), INVALID_PARAMS);
+        assert_eq!(
+            code(r#"{"method":"switch","params":{"desktop":-1}}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(r#"{"method":"pin_window","params":{"hwnd":"a"}}"#),
+            INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        assert_eq!(
+            error_response(json!(3), &RpcError::from(Error::DesktopNotFound)),
+            r#"{"error":{"code":-32000,"message":"DesktopNotFound"},"id":3,"jsonrpc":"2.0"}"#
+        );
+    }
+}
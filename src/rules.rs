@@ -0,0 +1,319 @@
+//! Window rules, moving new windows to desktops automatically. Enabled with
+//! the `rules` feature.
+//!
+//! ```rust,no_run
+//! use winvd::{WindowMatch, WindowRule, WindowRules};
+//!
+//! let rules = WindowRules::new().unwrap();
+//! rules.add(WindowRule::new(vec![WindowMatch::exe_name("slack.exe")], "Chat").unwrap());
+//! rules.add(
+//!     WindowRule::new(
+//!         vec![WindowMatch::title(r"- Visual Studio Code$").unwrap()],
+//!         1,
+//!     )
+//!     .unwrap(),
+//! );
+//! // Rules are applied as long as `rules` lives
+//! ```
+//!
+//! Windows are matched once, when their view is first seen, e.g. when the
+//! window is opened. The first matching rule wins. Titles are read at that
+//! moment, windows setting their title later may not match a title rule.
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use regex::Regex;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, HWND};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetClassNameW, GetWindowTextW, GetWindowThreadProcessId, IsWindow,
+};
+
+use crate::listener::DesktopEventHandler;
+use crate::{
+    app_id_of_window, get_desktop_by_name, move_window_to_desktop, Desktop, DesktopEvent, Error,
+    NameMatch, Result,
+};
+
+/// Condition on a window, see `WindowRule`
+#[derive(Debug, Clone)]
+pub enum WindowMatch {
+    /// Window class name, compared exactly
+    Class(String),
+
+    /// Regular expression searched in the window title
+    Title(Regex),
+
+    /// AppUserModelID of the app, compared exactly, see `app_id_of_window`
+    AppId(String),
+
+    /// Full path of the executable, or only the file name if it has no
+    /// directory separators, compared case-insensitively
+    ExePath(String),
+}
+
+impl WindowMatch {
+    /// Matches the title with a regular expression
+    pub fn title(pattern: &str) -> std::result::Result<Self, regex::Error> {
+        Ok(WindowMatch::Title(Regex::new(pattern)?))
+    }
+
+    /// Matches the file name of the executable, e.g. "notepad.exe"
+    pub fn exe_name(name: &str) -> Self {
+        WindowMatch::ExePath(name.to_string())
+    }
+
+    fn matches(&self, window: &WindowProperties) -> bool {
+        match self {
+            WindowMatch::Class(class) => window.class == *class,
+            WindowMatch::Title(regex) => regex.is_match(&window.title),
+            WindowMatch::AppId(app_id) => window.app_id == *app_id,
+            WindowMatch::ExePath(path) if path.contains(['\\', '/']) => {
+                window.exe_path.eq_ignore_ascii_case(path)
+            }
+            WindowMatch::ExePath(name) => window
+                .exe_path
+                .rsplit(['\\', '/'])
+                .next()
+                .is_some_and(|file_name| file_name.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+/// Desktop the windows of a rule are moved to
+#[derive(Debug, Clone)]
+pub enum RuleTarget {
+    Desktop(Desktop),
+
+    /// Desktop name, resolved when a window is moved, see
+    /// `get_desktop_by_name`
+    Name(String),
+}
+
+impl<T: Into<Desktop>> From<T> for RuleTarget {
+    fn from(desktop: T) -> Self {
+        RuleTarget::Desktop(desktop.into())
+    }
+}
+
+impl From<&str> for RuleTarget {
+    fn from(name: &str) -> Self {
+        RuleTarget::Name(name.to_string())
+    }
+}
+
+/// Windows matching all of the conditions are moved to the target desktop
+#[derive(Debug, Clone)]
+pub struct WindowRule {
+    pub matches: Vec<WindowMatch>,
+    pub target: RuleTarget,
+}
+
+impl WindowRule {
+    /// Rule matching windows meeting all the conditions, fails with
+    /// `Error::RulesConfigError` without conditions, as such a rule would match
+    /// every window
+    pub fn new(matches: Vec<WindowMatch>, target: impl Into<RuleTarget>) -> Result<Self> {
+        if matches.is_empty() {
+            return Err(Error::RulesConfigError(
+                "rule must have at least one condition".to_string(),
+            ));
+        }
+        Ok(WindowRule {
+            matches,
+            target: target.into(),
+        })
+    }
+
+    fn matches(&self, window: &WindowProperties) -> bool {
+        self.matches.iter().all(|m| m.matches(window))
+    }
+}
+
+/// Identifies a rule added to `WindowRules`, for removing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RuleId(u64);
+
+#[derive(Default)]
+pub(crate) struct RulesState {
+    next_id: u64,
+    pub(crate) rules: Vec<(RuleId, WindowRule)>,
+}
+
+impl RulesState {
+    pub(crate) fn next_rule_id(&mut self) -> RuleId {
+        let id = RuleId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+}
+
+/// Applies the rules to new windows, see the module docs
+pub struct WindowRules {
+    pub(crate) state: Arc<Mutex<RulesState>>,
+    _events: DesktopEventHandler,
+}
+
+impl WindowRules {
+    /// Starts the listener, there are no rules
+    pub fn new() -> Result<Self> {
+        let state = Arc::new(Mutex::new(RulesState::default()));
+        // Windows open when the rules were created are not moved
+        let mut seen: HashSet<isize> = crate::list_views()
+            .map(|views| views.iter().map(|view| view.hwnd.0).collect())
+            .unwrap_or_default();
+
+        let thread_state = state.clone();
+        let events = DesktopEventHandler::new(move |event| {
+            let DesktopEvent::WindowChanged(window) = event else {
+                return;
+            };
+            let hwnd = HWND::from(window);
+            if !seen.insert(hwnd.0) {
+                return;
+            }
+            seen.retain(|hwnd| unsafe { IsWindow(HWND(*hwnd)) }.as_bool());
+
+            let window = WindowProperties::read(hwnd);
+            let target = lock(&thread_state)
+                .rules
+                .iter()
+                .find(|(_, rule)| rule.matches(&window))
+                .map(|(_, rule)| rule.target.clone());
+            if let Some(target) = target {
+                let _ = move_to_target(hwnd, &target);
+            }
+        })?;
+
+        Ok(WindowRules {
+            state,
+            _events: events,
+        })
+    }
+
+    /// Adds a rule after the existing rules
+    pub fn add(&self, rule: WindowRule) -> RuleId {
+        let mut state = lock(&self.state);
+        let id = state.next_rule_id();
+        state.rules.push((id, rule));
+        id
+    }
+
+    /// Removes the rule, returns false if it was already removed
+    pub fn remove(&self, id: RuleId) -> bool {
+        let mut state = lock(&self.state);
+        let count = state.rules.len();
+        state.rules.retain(|(rule_id, _)| *rule_id != id);
+        state.rules.len() != count
+    }
+
+    /// Removes all rules
+    pub fn clear(&self) {
+        lock(&self.state).rules.clear();
+    }
+
+    /// Rules in the order they are tried
+    pub fn rules(&self) -> Vec<(RuleId, WindowRule)> {
+        lock(&self.state).rules.clone()
+    }
+}
+
+fn move_to_target(hwnd: HWND, target: &RuleTarget) -> Result<()> {
+    let desktop = match target {
+        RuleTarget::Desktop(desktop) => *desktop,
+        RuleTarget::Name(name) => get_desktop_by_name(name, NameMatch::default())?,
+    };
+    move_window_to_desktop(desktop, hwnd)
+}
+
+pub(crate) fn lock(state: &Mutex<RulesState>) -> MutexGuard<'_, RulesState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Properties of a window the rules are matched against
+#[derive(Debug, Default)]
+struct WindowProperties {
+    class: String,
+    title: String,
+    app_id: String,
+    exe_path: String,
+}
+
+impl WindowProperties {
+    /// Unreadable properties are left empty
+    fn read(hwnd: HWND) -> Self {
+        let mut buffer = [0u16; 1024];
+        let len = unsafe { GetClassNameW(hwnd, &mut buffer) };
+        let class = String::from_utf16_lossy(&buffer[..len.max(0) as usize]);
+        let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+        let title = String::from_utf16_lossy(&buffer[..len.max(0) as usize]);
+
+        WindowProperties {
+            class,
+            title,
+            app_id: app_id_of_window(hwnd).unwrap_or_default(),
+            exe_path: exe_path(hwnd).unwrap_or_default(),
+        }
+    }
+}
+
+fn exe_path(hwnd: HWND) -> Option<String> {
+    let mut process_id = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
+    if process_id == 0 {
+        return None;
+    }
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let res = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(process);
+        res.ok()?;
+    }
+    Some(String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_rule_matches() {
+        let window = WindowProperties {
+            class: "Notepad".to_string(),
+            title: "notes.txt - Notepad".to_string(),
+            app_id: String::new(),
+            exe_path: r"C:\Windows\System32\notepad.exe".to_string(),
+        };
+        let rule = |matches| WindowRule::new(matches, 1).unwrap().matches(&window);
+
+        assert!(rule(vec![WindowMatch::Class("Notepad".to_string())]));
+        assert!(rule(vec![WindowMatch::title(r"\.txt - Notepad$").unwrap()]));
+        assert!(rule(vec![WindowMatch::exe_name("NOTEPAD.EXE")]));
+        assert!(rule(vec![WindowMatch::ExePath(
+            r"c:\windows\system32\notepad.exe".to_string()
+        )]));
+        assert!(!rule(vec![WindowMatch::exe_name("pad.exe")]));
+
+        // All conditions must match
+        assert!(!rule(vec![
+            WindowMatch::Class("Notepad".to_string()),
+            WindowMatch::AppId("Microsoft.WindowsNotepad".to_string()),
+        ]));
+        assert!(matches!(
+            WindowRule::new(Vec::new(), 1),
+            Err(Error::RulesConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! Loading `WindowRules` from a TOML file, enabled with the `rules-config`
+//! feature.
+//!
+//! ```toml
+//! [[rule]]
+//! match.exe = "slack.exe"
+//! desktop = "Chat"
+//!
+//! [[rule]]
+//! match.class = "CASCADIA_HOSTING_WINDOW_CLASS"
+//! match.title = "^Admin: "
+//! desktop = 2
+//! ```
+//!
+//! Each rule has one or more conditions under `match`: `class`, `title` (a
+//! regular expression), `app_id` and `exe` (a file name or a full path), see
+//! `WindowMatch`. The `desktop` is a name, or an index starting from zero.
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime};
+
+use toml::{Table, Value};
+
+use crate::rules::{lock, RulesState};
+use crate::{Error, Result, RuleId, RuleTarget, WindowMatch, WindowRule, WindowRules};
+
+/// How often the file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Parses rules from the TOML text, see the module docs for the format
+pub fn parse_rules(text: &str) -> Result<Vec<WindowRule>> {
+    let table: Table = text
+        .parse()
+        .map_err(|err: toml::de::Error| Error::RulesConfigError(err.message().to_string()))?;
+    let rules = match table.get("rule") {
+        Some(Value::Array(rules)) => rules,
+        Some(_) => return Err(config_error("`rule` must be an array of tables")),
+        None => return Ok(Vec::new()),
+    };
+    rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            parse_rule(rule).map_err(|err| match err {
+                Error::RulesConfigError(message) => {
+                    Error::RulesConfigError(format!("rule {}: {}", index + 1, message))
+                }
+                err => err,
+            })
+        })
+        .collect()
+}
+
+/// Reads and parses the rules from the file
+pub fn load_rules(path: impl AsRef<Path>) -> Result<Vec<WindowRule>> {
+    let text =
+        std::fs::read_to_string(path.as_ref()).map_err(|err| Error::FileError(err.kind()))?;
+    parse_rules(&text)
+}
+
+fn parse_rule(rule: &Value) -> Result<WindowRule> {
+    let rule = rule
+        .as_table()
+        .ok_or_else(|| config_error("must be a table"))?;
+    let target = match rule.get("desktop") {
+        Some(Value::String(name)) => RuleTarget::Name(name.clone()),
+        Some(Value::Integer(index)) if *index >= 0 => {
+            let index = u32::try_from(*index)
+                .map_err(|_| config_error(&format!("`desktop` index {} is too large", index)))?;
+            RuleTarget::from(index)
+        }
+        _ => return Err(config_error("`desktop` must be a name or an index")),
+    };
+    let conditions = match rule.get("match") {
+        Some(Value::Table(conditions)) if !conditions.is_empty() => conditions,
+        _ => return Err(config_error("`match` must have at least one condition")),
+    };
+    let matches = conditions
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .as_str()
+                .ok_or_else(|| config_error(&format!("`match.{}` must be a string", key)))?;
+            match key.as_str() {
+                "class" => Ok(WindowMatch::Class(value.to_string())),
+                "title" => WindowMatch::title(value)
+                    .map_err(|err| config_error(&format!("`match.title`: {}", err))),
+                "app_id" => Ok(WindowMatch::AppId(value.to_string())),
+                "exe" => Ok(WindowMatch::ExePath(value.to_string())),
+                _ => Err(config_error(&format!("unknown condition `match.{}`", key))),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    WindowRule::new(matches, target)
+}
+
+fn config_error(message: &str) -> Error {
+    Error::RulesConfigError(message.to_string())
+}
+
+impl WindowRules {
+    /// Starts the listener with the rules of the file. The file is checked for
+    /// changes every second, and the rules are replaced when it changes.
+    ///
+    /// If a changed file can't be loaded the previous rules are kept. Rules
+    /// added with `add` are tried after the rules of the file.
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let rules = load_rules(&path)?;
+        let modified = modified(&path);
+
+        let window_rules = WindowRules::new()?;
+        let ids = replace_rules(&window_rules.state, &[], rules);
+        let state = Arc::downgrade(&window_rules.state);
+        std::thread::spawn(move || watch_config(state, path, modified, ids));
+        Ok(window_rules)
+    }
+}
+
+/// Reloads the rules when the file changes, stops when the `WindowRules` is
+/// dropped
+fn watch_config(
+    state: Weak<Mutex<RulesState>>,
+    path: PathBuf,
+    mut modified: Option<SystemTime>,
+    mut ids: Vec<RuleId>,
+) {
+    loop {
+        std::thread::sleep(RELOAD_INTERVAL);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let new_modified = self::modified(&path);
+        if new_modified == modified {
+            continue;
+        }
+        // Editors may save the file in several steps, try again later
+        if let Ok(rules) = load_rules(&path) {
+            modified = new_modified;
+            ids = replace_rules(&state, &ids, rules);
+        }
+    }
+}
+
+/// Replaces the rules by their ids with new rules, tried before the other
+/// rules, and returns the ids of the new rules
+fn replace_rules(state: &Mutex<RulesState>, old: &[RuleId], rules: Vec<WindowRule>) -> Vec<RuleId> {
+    let mut state = lock(state);
+    state.rules.retain(|(id, _)| !old.contains(id));
+    let new: Vec<_> = rules
+        .into_iter()
+        .map(|rule| (state.next_rule_id(), rule))
+        .collect();
+    let ids = new.iter().map(|(id, _)| *id).collect();
+    state.rules.splice(0..0, new);
+    ids
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(
+            r#"
+            [[rule]]
+            match.exe = "slack.exe"
+            desktop = "Chat"
+
+            [[rule]]
+            match = { class = "Notepad", title = "\\.txt" }
+            desktop = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert!(matches!(&rules[0].target, RuleTarget::Name(name) if name == "Chat"));
+        assert!(matches!(&rules[0].matches[..], [WindowMatch::ExePath(exe)] if exe == "slack.exe"));
+        assert_eq!(rules[1].matches.len(), 2);
+        assert_eq!(
+            format!("{:?}", rules[1].target),
+            "Desktop(Desktop(Index(2)))"
+        );
+
+        assert!(parse_rules("").unwrap().is_empty());
+
+        let err = |text| match parse_rules(text) {
+            Err(Error::RulesConfigError(message)) => message,
+            other => panic!("{:?}", other.map(|rules| rules.len())),
+        };
+        assert_eq!(
+            err("[[rule]]\ndesktop = 1"),
+            "rule 1: `match` must have at least one condition"
+        );
+        assert_eq!(
+            err("[[rule]]\nmatch.name = \"a\"\ndesktop = 1"),
+            "rule 1: unknown condition `match.name`"
+        );
+        assert_eq!(
+            err("[[rule]]\nmatch.class = \"a\"\ndesktop = -1"),
+            "rule 1: `desktop` must be a name or an index"
+        );
+        assert_eq!(
+            err("[[rule]]\nmatch.class = \"a\"\ndesktop = 4294967296"),
+            "rule 1: `desktop` index 4294967296 is too large"
+        );
+        assert!(
+            err("[[rule]]\nmatch.title = \"(\"\ndesktop = 1").starts_with("rule 1: `match.title`")
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Single owner for the threads of the crate.
+//!
+//! `Runtime` owns the listener and other long running threads, restarts them
+//! according to their `RestartPolicy`, and stops them all with `shutdown`.
+//! `DesktopEventThread`, `VirtualDesktopService`, `PipeServer`, `WsServer`
+//! and `MqttBridge` implement `RuntimeChild`:
+//!
+//! ```rust,no_run
+//! use winvd::{RestartPolicy, Runtime, VirtualDesktopService};
+//!
+//! let runtime = Runtime::new();
+//! runtime
+//!     .spawn("service", RestartPolicy::Always, VirtualDesktopService::new)
+//!     .unwrap();
+//! ```
+//!
+//! See `Runtime::listen_desktop_events` for the listener.
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::Result;
+#[cfg(feature = "listener")]
+use crate::{DesktopEvent, DesktopEventSender, DesktopEventThread, ListenerOptions};
+
+/// How often the children are checked
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Thread owned by the `Runtime`, see the module docs for the implementors
+pub trait RuntimeChild: Send {
+    /// Returns false once the thread has exited, e.g. after a panic
+    fn is_running(&self) -> bool;
+
+    /// Stops the thread and joins it
+    fn stop(&mut self) -> std::thread::Result<()>;
+}
+
+#[cfg(feature = "listener")]
+impl RuntimeChild for DesktopEventThread {
+    fn is_running(&self) -> bool {
+        DesktopEventThread::is_running(self)
+    }
+
+    fn stop(&mut self) -> std::thread::Result<()> {
+        DesktopEventThread::stop(self)
+    }
+}
+
+/// What to do when a child thread exits on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Leave the child stopped
+    Never,
+
+    /// Restart the child at most this many times
+    Limited(u32),
+
+    /// Always restart the child
+    Always,
+}
+
+/// Current state of a child, see `Runtime::children`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildStatus {
+    pub name: String,
+    pub running: bool,
+    pub restarts: u32,
+
+    /// Panic message or error of the last exit, if the child has exited
+    pub last_error: Option<String>,
+}
+
+/// Result of stopping a child, see `Runtime::shutdown`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildReport {
+    pub name: String,
+    pub restarts: u32,
+
+    /// Panic message if joining the thread failed
+    pub result: std::result::Result<(), String>,
+}
+
+type ChildFactory = Box<dyn FnMut() -> Result<Box<dyn RuntimeChild>> + Send>;
+
+struct Supervised {
+    name: String,
+    policy: RestartPolicy,
+    restarts: u32,
+    child: Option<Box<dyn RuntimeChild>>,
+
+    /// Taken out while the supervisor restarts the child
+    factory: Option<ChildFactory>,
+    last_error: Option<String>,
+}
+
+impl Supervised {
+    /// Stops the child if it has exited, and takes out the factory if the
+    /// policy allows a restart
+    fn take_restart(&mut self) -> Option<ChildFactory> {
+        let child = self.child.as_mut()?;
+        if child.is_running() {
+            return None;
+        }
+
+        self.last_error = Some(match child.stop() {
+            Ok(()) => "Exited".to_string(),
+            Err(panic) => panic_message(&panic),
+        });
+        self.child = None;
+
+        let restart = match self.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::Limited(max) => self.restarts < max,
+            RestartPolicy::Always => true,
+        };
+        if !restart {
+            return None;
+        }
+        self.restarts += 1;
+        self.factory.take()
+    }
+
+    /// Puts back the factory taken by `take_restart` with the new child
+    fn restarted(&mut self, factory: ChildFactory, child: Result<Box<dyn RuntimeChild>>) {
+        self.factory = Some(factory);
+        match child {
+            Ok(child) => self.child = Some(child),
+            Err(err) => self.last_error = Some(format!("Restart failed: {:?}", err)),
+        }
+    }
+}
+
+/// Owns threads of the crate, see the module docs
+pub struct Runtime {
+    children: Arc<Mutex<Vec<Supervised>>>,
+    stop_supervisor: Option<Sender<()>>,
+    supervisor: Option<JoinHandle<()>>,
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        Self::with_interval(SUPERVISE_INTERVAL)
+    }
+
+    fn with_interval(interval: Duration) -> Self {
+        let children = Arc::new(Mutex::new(Vec::<Supervised>::new()));
+        let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+        let supervised = children.clone();
+        let supervisor = std::thread::spawn(move || {
+            // Stops when the sender is dropped
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let restarts: Vec<(usize, ChildFactory)> = match supervised.lock() {
+                    Ok(mut children) => children
+                        .iter_mut()
+                        .enumerate()
+                        .filter_map(|(i, child)| child.take_restart().map(|f| (i, f)))
+                        .collect(),
+                    Err(_) => Vec::new(),
+                };
+
+                // Factories may block, e.g. the listener waits for its thread
+                // to start, so they run without the lock. Children are only
+                // removed after the supervisor is joined, the index stays
+                // valid.
+                for (index, mut factory) in restarts {
+                    let child = factory();
+                    if let Ok(mut children) = supervised.lock() {
+                        if let Some(supervised) = children.get_mut(index) {
+                            supervised.restarted(factory, child);
+                        }
+                    }
+                }
+            }
+        });
+        Runtime {
+            children,
+            stop_supervisor: Some(stop_tx),
+            supervisor: Some(supervisor),
+        }
+    }
+
+    /// Starts a child with the factory, the factory is called again when the
+    /// child is restarted
+    pub fn spawn<C, F>(&self, name: &str, policy: RestartPolicy, mut factory: F) -> Result<()>
+    where
+        C: RuntimeChild + 'static,
+        F: FnMut() -> Result<C> + Send + 'static,
+    {
+        let mut factory: ChildFactory =
+            Box::new(move || factory().map(|child| Box::new(child) as Box<dyn RuntimeChild>));
+        let child = factory()?;
+        self.lock_children().push(Supervised {
+            name: name.to_string(),
+            policy,
+            restarts: 0,
+            child: Some(child),
+            factory: Some(factory),
+            last_error: None,
+        });
+        Ok(())
+    }
+
+    /// Starts a desktop event listener owned by the runtime, see
+    /// `listen_desktop_events_with_options`
+    ///
+    /// ```rust,no_run
+    /// use winvd::{ListenerOptions, RestartPolicy, Runtime};
+    ///
+    /// let (tx, rx) = std::sync::mpsc::channel::<winvd::DesktopEvent>();
+    /// let runtime = Runtime::new();
+    /// runtime
+    ///     .listen_desktop_events(tx, ListenerOptions::default(), RestartPolicy::Always)
+    ///     .unwrap();
+    ///
+    /// // ...
+    ///
+    /// for report in runtime.shutdown() {
+    ///     println!("{:?}", report);
+    /// }
+    /// ```
+    #[cfg(feature = "listener")]
+    pub fn listen_desktop_events<T, S>(
+        &self,
+        sender: S,
+        options: ListenerOptions,
+        policy: RestartPolicy,
+    ) -> Result<()>
+    where
+        T: From<DesktopEvent> + Clone + Send + 'static,
+        S: Into<DesktopEventSender<T>> + Clone + Send + 'static,
+    {
+        self.spawn("listener", policy, move || {
+            DesktopEventThread::new(sender.clone().into(), options.clone())
+        })
+    }
+
+    /// State of all children
+    pub fn children(&self) -> Vec<ChildStatus> {
+        self.lock_children()
+            .iter()
+            .map(|child| ChildStatus {
+                name: child.name.clone(),
+                running: child.child.as_ref().is_some_and(|c| c.is_running()),
+                restarts: child.restarts,
+                last_error: child.last_error.clone(),
+            })
+            .collect()
+    }
+
+    /// Stops the supervisor and all children, in the reverse order they were
+    /// started
+    pub fn shutdown(mut self) -> Vec<ChildReport> {
+        self.stop_all()
+    }
+
+    fn stop_all(&mut self) -> Vec<ChildReport> {
+        // Supervisor must not restart children while they are stopped
+        drop(self.stop_supervisor.take());
+        if let Some(supervisor) = self.supervisor.take() {
+            let _ = supervisor.join();
+        }
+
+        let mut children = self.lock_children();
+        children
+            .drain(..)
+            .rev()
+            .map(|mut supervised| ChildReport {
+                result: match supervised.child.as_mut().map(|child| child.stop()) {
+                    Some(Err(panic)) => Err(panic_message(&panic)),
+                    _ => Ok(()),
+                },
+                name: supervised.name,
+                restarts: supervised.restarts,
+            })
+            .collect()
+    }
+
+    fn lock_children(&self) -> std::sync::MutexGuard<'_, Vec<Supervised>> {
+        self.children
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+pub(crate) fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Child that panics right away
+    struct PanickingChild(Option<JoinHandle<()>>);
+
+    impl RuntimeChild for PanickingChild {
+        fn is_running(&self) -> bool {
+            self.0.as_ref().is_some_and(|t| !t.is_finished())
+        }
+
+        fn stop(&mut self) -> std::thread::Result<()> {
+            self.0.take().map_or(Ok(()), |t| t.join())
+        }
+    }
+
+    #[test]
+    fn test_runtime_restarts_child() {
+        let runtime = Runtime::with_interval(Duration::from_millis(10));
+        runtime
+            .spawn("panics", RestartPolicy::Limited(2), || {
+                Ok(PanickingChild(Some(std::thread::spawn(|| {
+                    panic!("Child failed")
+                }))))
+            })
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        while runtime.children()[0].running || runtime.children()[0].restarts < 2 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let status = &runtime.children()[0];
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.last_error.as_deref(), Some("Child failed"));
+
+        // Last child may be joined by the supervisor or by shutdown
+        let reports = runtime.shutdown();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].name, "panics");
+        assert_eq!(reports[0].restarts, 2);
+    }
+
+    #[test]
+    fn test_children_while_restarting() {
+        let runtime = Runtime::with_interval(Duration::from_millis(10));
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let mut started = false;
+        runtime
+            .spawn("slow", RestartPolicy::Limited(1), move || {
+                // Restart blocks until the test has read the status
+                if started {
+                    let _ = release_rx.recv_timeout(Duration::from_secs(5));
+                }
+                started = true;
+                Ok(PanickingChild(Some(std::thread::spawn(|| {
+                    panic!("Child failed")
+                }))))
+            })
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        while runtime.children()[0].restarts < 1 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        release_tx.send(()).unwrap();
+        runtime.shutdown();
+    }
+}
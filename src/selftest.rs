@@ -0,0 +1,355 @@
+//! Non-destructive check of each capability, for reporting what broke after a
+//! Windows update.
+//!
+//! Run it with `vda doctor`, or from code:
+//!
+//! ```rust,no_run
+//! let report = winvd::run_compatibility_selftest();
+//! print!("{}", report);
+//! ```
+//!
+//! Nothing is changed: the current desktop is switched to itself, and it is
+//! renamed to the name it already has.
+//!
+//! `self_test` (`vda self-test`) additionally exercises the operations that
+//! change the desktops, on a scratch desktop that is removed afterwards.
+use std::fmt;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+use crate::backend::with_backend;
+use crate::desktop::invalidate_cache;
+use crate::diagnostics::windows_version;
+use crate::{
+    interface_version_in_use, is_current_windows_supported, Desktop, DesktopBackend, Error, Result,
+    SupportStatus,
+};
+
+/// Outcome of a single check
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfTestResult {
+    Passed,
+
+    /// Windows version doesn't have the capability, e.g. desktop names
+    NotSupported,
+
+    /// Check could not be run, e.g. there is no window to query
+    Skipped(&'static str),
+
+    Failed(Error),
+}
+
+/// Single capability checked by `run_compatibility_selftest`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub result: SelfTestResult,
+}
+
+/// Result of `run_compatibility_selftest`, displays as a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    /// Windows `(build, patch)`, if it could be read from the registry
+    pub windows: Option<(u32, u32)>,
+
+    /// See `interface_version_in_use`
+    pub interface_version: (&'static str, (u32, u32)),
+
+    pub support: SupportStatus,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// Returns true if no check failed
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| matches!(check.result, SelfTestResult::Failed(_)))
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.windows {
+            Some((build, patch)) => writeln!(f, "Windows build:     {}.{}", build, patch)?,
+            None => writeln!(f, "Windows build:     unknown")?,
+        }
+        writeln!(f, "Interfaces in use: {}", self.interface_version.0)?;
+        writeln!(f, "Support:           {:?}", self.support)?;
+        writeln!(f)?;
+        for check in &self.checks {
+            match &check.result {
+                SelfTestResult::Passed => writeln!(f, "PASS  {}", check.name)?,
+                SelfTestResult::NotSupported => {
+                    writeln!(f, "N/A   {}: not supported by this Windows", check.name)?
+                }
+                SelfTestResult::Skipped(reason) => writeln!(f, "SKIP  {}: {}", check.name, reason)?,
+                SelfTestResult::Failed(err) => match err.hresult() {
+                    Some(hresult) => writeln!(
+                        f,
+                        "FAIL  {}: {:?} (HRESULT 0x{:08X})",
+                        check.name, err, hresult.0 as u32
+                    )?,
+                    None => writeln!(f, "FAIL  {}: {:?}", check.name, err)?,
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs each capability once without changing anything, see the module docs
+pub fn run_compatibility_selftest() -> SelfTestReport {
+    let mut checks = Vec::new();
+    let mut check = |name: &'static str, result: SelfTestResult| {
+        checks.push(SelfTestCheck { name, result });
+    };
+
+    check(
+        "Connect to explorer.exe",
+        on_backend(|o| o.get_desktop_count()),
+    );
+    check("Enumerate desktops", on_backend(|o| o.get_desktop_infos()));
+    check(
+        "Get current desktop",
+        on_backend(|o| o.get_current_desktop()),
+    );
+    check(
+        "Switch to current desktop",
+        on_backend(|o| o.switch_desktop(o.get_current_desktop()?)),
+    );
+    check(
+        "Rename desktop to the same name",
+        on_backend(|o| {
+            let current = o.get_current_desktop()?;
+            o.set_desktop_name(current, &o.get_desktop_name(current)?)
+        }),
+    );
+    check(
+        "Read desktop wallpaper",
+        on_backend(|o| o.get_desktop_wallpaper(o.get_current_desktop()?)),
+    );
+
+    let window = unsafe { GetForegroundWindow() };
+    if window == HWND::default() {
+        let skipped = SelfTestResult::Skipped("no foreground window");
+        check("Query pinned window", skipped.clone());
+        check("Query pinned app", skipped);
+    } else {
+        check(
+            "Query pinned window",
+            on_backend(move |o| o.is_pinned_window(window)),
+        );
+        check(
+            "Query pinned app",
+            on_backend(move |o| o.is_pinned_app(window)),
+        );
+    }
+
+    #[cfg(feature = "listener")]
+    check("Register for notifications", outcome(check_listener()));
+    #[cfg(not(feature = "listener"))]
+    check(
+        "Register for notifications",
+        SelfTestResult::Skipped("listener feature is disabled"),
+    );
+
+    SelfTestReport {
+        windows: windows_version(),
+        interface_version: interface_version_in_use(),
+        support: is_current_windows_supported(),
+        checks,
+    }
+}
+
+/// Name of the scratch desktop of `self_test`
+const SCRATCH_DESKTOP_NAME: &str = "winvd self-test";
+
+/// Checks of `self_test` that need the scratch desktop, in order
+const SCRATCH_CHECKS: [&str; 6] = [
+    "Rename desktop",
+    "Set desktop wallpaper",
+    "Move window to desktop",
+    "Pin and unpin window",
+    "Switch desktop",
+    "Remove desktop",
+];
+
+/// Runs the checks of `run_compatibility_selftest`, then creates a scratch
+/// desktop and renames it, sets its wallpaper, moves the foreground window to
+/// it and back, pins and unpins the window, switches to it and back, and
+/// removes it.
+///
+/// The wallpaper is set to the one the desktop already has, and a window that
+/// is pinned already is not unpinned. If the scratch desktop can't be
+/// created, its checks are skipped.
+pub fn self_test() -> SelfTestReport {
+    let mut report = run_compatibility_selftest();
+    report.checks.extend(scratch_checks());
+    report
+}
+
+fn scratch_checks() -> Vec<SelfTestCheck> {
+    let mut checks = Vec::new();
+    let mut check = |name: &'static str, result: SelfTestResult| {
+        checks.push(SelfTestCheck { name, result });
+    };
+
+    let desktops = with_backend(|b| Ok((b.get_current_desktop()?, b.create_desktop()?)));
+    invalidate_cache();
+    let (current, scratch) = match desktops {
+        Ok(desktops) => {
+            check("Create desktop", SelfTestResult::Passed);
+            desktops
+        }
+        Err(err) => {
+            check("Create desktop", outcome::<()>(Err(err)));
+            for name in SCRATCH_CHECKS {
+                check(name, SelfTestResult::Skipped("no scratch desktop"));
+            }
+            return checks;
+        }
+    };
+
+    check(
+        "Rename desktop",
+        on_backend(move |o| o.set_desktop_name(scratch, SCRATCH_DESKTOP_NAME)),
+    );
+    check(
+        "Set desktop wallpaper",
+        on_backend(move |o| o.set_desktop_wallpaper(scratch, &o.get_desktop_wallpaper(scratch)?)),
+    );
+
+    let window = unsafe { GetForegroundWindow() };
+    if window == HWND::default() {
+        let skipped = SelfTestResult::Skipped("no foreground window");
+        check("Move window to desktop", skipped.clone());
+        check("Pin and unpin window", skipped);
+    } else {
+        check(
+            "Move window to desktop",
+            on_backend(move |o| {
+                o.move_window_to_desktop(window, scratch)?;
+                o.move_window_to_desktop(window, current)
+            }),
+        );
+        check(
+            "Pin and unpin window",
+            on_backend(move |o| pin_and_unpin(o, window)),
+        );
+    }
+
+    check(
+        "Switch desktop",
+        on_backend(move |o| {
+            o.switch_desktop(scratch)?;
+            o.switch_desktop(current)
+        }),
+    );
+    check("Remove desktop", remove_scratch(scratch, current));
+    checks
+}
+
+fn pin_and_unpin(o: &dyn DesktopBackend, window: HWND) -> Result<()> {
+    if o.is_pinned_window(window)? {
+        return Ok(());
+    }
+    o.pin_window(window)?;
+    o.unpin_window(window)
+}
+
+fn remove_scratch(scratch: Desktop, fallback: Desktop) -> SelfTestResult {
+    let result = on_backend(move |o| o.remove_desktop(scratch, fallback));
+    invalidate_cache();
+    result
+}
+
+fn outcome<T>(result: Result<T>) -> SelfTestResult {
+    match result {
+        Ok(_) => SelfTestResult::Passed,
+        Err(Error::ComNotImplemented) => SelfTestResult::NotSupported,
+        Err(err) => SelfTestResult::Failed(err),
+    }
+}
+
+/// Runs the call on the backend, ignoring the returned value
+fn on_backend<F, T>(f: F) -> SelfTestResult
+where
+    F: Fn(&dyn DesktopBackend) -> Result<T> + 'static,
+    T: 'static,
+{
+    outcome(with_backend(move |b| f(b).map(|_| ())))
+}
+
+/// Starts a listener and waits until it registers or fails to register
+#[cfg(feature = "listener")]
+fn check_listener() -> Result<()> {
+    use crate::{DesktopEvent, DesktopEventThread, ListenerOptions};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// How long to wait for the listener to register
+    const LISTENER_TIMEOUT: Duration = Duration::from_secs(3);
+
+    let error = Arc::new(Mutex::new(None));
+    let hook_error = error.clone();
+    let options = ListenerOptions {
+        registry_poll_interval: None,
+        ..ListenerOptions::default()
+    }
+    .on_error(move |err| {
+        if let Ok(mut error) = hook_error.lock() {
+            error.get_or_insert(err);
+        }
+    });
+
+    let (tx, _rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let listener = DesktopEventThread::new(tx.into(), options)?;
+    let start = Instant::now();
+    while start.elapsed() < LISTENER_TIMEOUT {
+        if listener.is_registered() {
+            return Ok(());
+        }
+        if let Some(err) = error.lock().ok().and_then(|mut error| error.take()) {
+            return Err(err);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    Err(Error::ListenerThreadFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::HRESULT;
+
+    #[test]
+    fn test_report_display() {
+        let report = SelfTestReport {
+            windows: Some((26100, 1)),
+            interface_version: ("build_22631_3155", (22631, 3155)),
+            support: SupportStatus::Supported,
+            checks: vec![
+                SelfTestCheck {
+                    name: "Enumerate desktops",
+                    result: SelfTestResult::Passed,
+                },
+                SelfTestCheck {
+                    name: "Read desktop wallpaper",
+                    result: SelfTestResult::Failed(Error::ComCallFailed {
+                        interface: "IVirtualDesktop",
+                        method: "get_wallpaper",
+                        hresult: HRESULT(0x80004005_u32 as i32),
+                    }),
+                },
+            ],
+        };
+        let text = report.to_string();
+        assert!(text.contains("PASS  Enumerate desktops\n"));
+        assert!(text.contains("FAIL  Read desktop wallpaper: ComCallFailed"));
+        assert!(text.contains("(HRESULT 0x80004005)"));
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,119 @@
+//! Serde support, enabled with the `serde` feature.
+//!
+//! `Desktop` is written as its index or its GUID string, depending on how it
+//! was created. Window and monitor handles are written as integers.
+use serde::de::{Error as _, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use windows::core::GUID;
+
+use crate::comobjects::{parse_guid, DesktopInternal};
+use crate::Desktop;
+
+impl Serialize for Desktop {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match DesktopInternal::from(*self) {
+            DesktopInternal::Index(index) => serializer.serialize_u32(index),
+            DesktopInternal::Guid(guid) | DesktopInternal::IndexGuid(_, guid) => {
+                guid::serialize(&guid, serializer)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Desktop {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DesktopVisitor;
+
+        impl<'de> Visitor<'de> for DesktopVisitor {
+            type Value = Desktop;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a desktop index or GUID")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, index: u64) -> Result<Desktop, E> {
+                u32::try_from(index)
+                    .map(Desktop::from)
+                    .map_err(|_| E::custom("desktop index out of range"))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, index: i64) -> Result<Desktop, E> {
+                u32::try_from(index)
+                    .map(Desktop::from)
+                    .map_err(|_| E::custom("desktop index out of range"))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<Desktop, E> {
+                parse_guid(text)
+                    .map(Desktop::from)
+                    .ok_or_else(|| E::custom("invalid desktop GUID"))
+            }
+        }
+
+        deserializer.deserialize_any(DesktopVisitor)
+    }
+}
+
+/// GUID as a `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX` string
+pub(crate) mod guid {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(guid: &GUID, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:?}", guid))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GUID, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_guid(&text).ok_or_else(|| D::Error::custom("invalid GUID"))
+    }
+}
+
+/// `HMONITOR`s as integers
+#[cfg(feature = "listener")]
+pub(crate) mod hmonitors {
+    use super::*;
+    use windows::Win32::Graphics::Gdi::HMONITOR;
+
+    pub fn serialize<S: Serializer>(
+        monitors: &[HMONITOR],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(monitors.iter().map(|monitor| monitor.0 as i64))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<HMONITOR>, D::Error> {
+        let monitors = Vec::<i64>::deserialize(deserializer)?;
+        Ok(monitors
+            .into_iter()
+            .map(|monitor| HMONITOR(monitor as isize))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, StrDeserializer, U32Deserializer};
+    use serde::de::IntoDeserializer;
+
+    #[test]
+    fn test_deserialize_desktop() {
+        let index: U32Deserializer<Error> = 2u32.into_deserializer();
+        assert!(matches!(
+            Desktop::deserialize(index).map(DesktopInternal::from),
+            Ok(DesktopInternal::Index(2))
+        ));
+
+        let guid: StrDeserializer<Error> =
+            "1E1B0D56-0B3A-4D1C-8C8E-5A2F3B4C6D7E".into_deserializer();
+        assert!(matches!(
+            Desktop::deserialize(guid).map(DesktopInternal::from),
+            Ok(DesktopInternal::Guid(g)) if g == GUID::from_u128(0x1e1b0d56_0b3a_4d1c_8c8e_5a2f3b4c6d7e)
+        ));
+
+        let invalid: StrDeserializer<Error> = "Work".into_deserializer();
+        assert!(Desktop::deserialize(invalid).is_err());
+    }
+}
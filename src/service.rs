@@ -0,0 +1,351 @@
+//! COM worker thread usable from any thread.
+//!
+//! The virtual desktop COM objects belong to the thread that created them.
+//! `VirtualDesktopService` owns a dedicated STA thread with a message pump,
+//! and runs all calls on it. The handle is cheap to clone and can be shared
+//! between threads, the worker stops when the last handle is dropped.
+//!
+//! ```rust,no_run
+//! let service = winvd::VirtualDesktopService::new().unwrap();
+//! let handle = service.clone();
+//! std::thread::spawn(move || {
+//!     handle.switch_desktop(1).unwrap();
+//! })
+//! .join()
+//! .unwrap();
+//! println!("{}", service.get_desktop_count().unwrap());
+//! ```
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, PeekMessageW, PostThreadMessageW, TranslateMessage, MSG,
+    PM_NOREMOVE, WM_APP, WM_USER,
+};
+
+use crate::comobjects::with_com_objects;
+use crate::{Desktop, DesktopInfo, Error, IntoHwnd, Result, RuntimeChild};
+
+/// Posted to the worker when calls are queued
+const WM_SERVICE_CALL: u32 = WM_APP + 4;
+
+/// Posted to the worker when the last handle is dropped
+const WM_SERVICE_QUIT: u32 = WM_APP + 5;
+
+pub(crate) type Job = Box<dyn FnOnce() + Send>;
+
+struct Worker {
+    jobs: Sender<Job>,
+    thread_id: u32,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Worker {
+    /// Stops the worker thread and joins it, unless called on the worker
+    fn stop(&self) -> std::thread::Result<()> {
+        let _ =
+            unsafe { PostThreadMessageW(self.thread_id, WM_SERVICE_QUIT, WPARAM(0), LPARAM(0)) };
+        // Last handle may be dropped by a call running on the worker itself
+        if unsafe { GetCurrentThreadId() } == self.thread_id {
+            return Ok(());
+        }
+        let thread = self
+            .thread
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        thread.map_or(Ok(()), JoinHandle::join)
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Handle to the COM worker thread, see the module docs
+#[derive(Clone)]
+pub struct VirtualDesktopService {
+    worker: Arc<Worker>,
+}
+
+impl VirtualDesktopService {
+    /// Starts the worker thread
+    pub fn new() -> Result<Self> {
+        let (jobs_tx, jobs_rx) = std::sync::mpsc::channel::<Job>();
+
+        // Channel for receiving the thread id of the worker thread
+        let (tx, rx) = std::sync::mpsc::channel::<u32>();
+
+        let thread = std::thread::spawn(move || {
+            let _ = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) };
+
+            // Ensure the thread has a message queue before anyone posts to it
+            let mut msg = MSG::default();
+            unsafe {
+                let _ = PeekMessageW(&mut msg, None, WM_USER, WM_USER, PM_NOREMOVE);
+            }
+            let _ = tx.send(unsafe { GetCurrentThreadId() });
+
+            run_worker(&jobs_rx);
+
+            // Release the COM objects before COM is uninitialized
+            drop(jobs_rx);
+            let _ = with_com_objects(|o| {
+                o.drop_services();
+                Ok(())
+            });
+            unsafe { CoUninitialize() };
+        });
+
+        let thread_id = rx.recv().map_err(|_| Error::ServiceStopped)?;
+        Ok(VirtualDesktopService {
+            worker: Arc::new(Worker {
+                jobs: jobs_tx,
+                thread_id,
+                thread: Mutex::new(Some(thread)),
+            }),
+        })
+    }
+
+    /// Runs the function on the worker thread and waits for the result.
+    ///
+    /// The free functions of the crate, e.g. `winvd::get_desktops`, can be
+    /// called inside the function. Calling this from the worker thread runs
+    /// the function right away.
+    pub fn call<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        if unsafe { GetCurrentThreadId() } == self.worker.thread_id {
+            return f();
+        }
+
+        let (tx, rx) = std::sync::mpsc::sync_channel::<Result<T>>(1);
+        self.enqueue(Box::new(move || {
+            let _ = tx.send(f());
+        }))?;
+
+        // Sender is dropped without a result if the function panics
+        rx.recv().map_err(|_| Error::ServiceStopped)?
+    }
+
+    /// Queues the job to run on the worker thread. If the worker can't be
+    /// woken up, e.g. its message queue is full, the job is dropped without
+    /// running and `Error::ServiceStopped` is returned.
+    pub(crate) fn enqueue(&self, job: Job) -> Result<()> {
+        // Taken by the worker, or back out if posting fails
+        let queued = Arc::new(Mutex::new(Some(job)));
+        let worker_job = queued.clone();
+        self.worker
+            .jobs
+            .send(Box::new(move || {
+                let job = lock(&worker_job).take();
+                if let Some(job) = job {
+                    job();
+                }
+            }))
+            .map_err(|_| Error::ServiceStopped)?;
+
+        let posted = unsafe {
+            PostThreadMessageW(self.worker.thread_id, WM_SERVICE_CALL, WPARAM(0), LPARAM(0))
+        };
+        if let Err(_err) = posted {
+            // The message of an earlier call may have run the job meanwhile
+            let job = lock(&queued).take();
+            if job.is_some() {
+                log_format!(Warn: "Could not post to the service worker {:?}", _err);
+                return Err(Error::ServiceStopped);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_desktop_count(&self) -> Result<u32> {
+        self.call(crate::get_desktop_count)
+    }
+
+    pub fn get_desktops(&self) -> Result<Vec<Desktop>> {
+        self.call(crate::get_desktops)
+    }
+
+    pub fn list_desktops_detailed(&self) -> Result<Vec<DesktopInfo>> {
+        self.call(crate::list_desktops_detailed)
+    }
+
+    pub fn get_current_desktop(&self) -> Result<Desktop> {
+        self.call(crate::get_current_desktop)
+    }
+
+    pub fn current_desktop_info(&self) -> Result<DesktopInfo> {
+        self.call(crate::current_desktop_info)
+    }
+
+    pub fn create_desktop(&self) -> Result<Desktop> {
+        self.call(crate::create_desktop)
+    }
+
+    pub fn create_desktop_with_name(&self, name: &str) -> Result<Desktop> {
+        let name = name.to_string();
+        self.call(move || crate::create_desktop_with_name(&name))
+    }
+
+    pub fn switch_desktop<T>(&self, desktop: T) -> Result<()>
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into();
+        self.call(move || crate::switch_desktop(desktop))
+    }
+
+    pub fn remove_desktop<T>(&self, desktop: T, fallback_desktop: T) -> Result<()>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, fallback_desktop) = (desktop.into(), fallback_desktop.into());
+        self.call(move || crate::remove_desktop(desktop, fallback_desktop))
+    }
+
+    pub fn get_desktop_name<T>(&self, desktop: T) -> Result<String>
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into();
+        self.call(move || desktop.get_name())
+    }
+
+    pub fn set_desktop_name<T>(&self, desktop: T, name: &str) -> Result<()>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, name) = (desktop.into(), name.to_string());
+        self.call(move || desktop.set_name(&name))
+    }
+
+    pub fn get_desktop_by_window<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<Desktop> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::get_desktop_by_window(hwnd))
+    }
+
+    pub fn is_window_on_desktop<T, M>(&self, desktop: T, hwnd: impl IntoHwnd<M>) -> Result<bool>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, hwnd) = (desktop.into(), hwnd.into_hwnd()?);
+        self.call(move || crate::is_window_on_desktop(desktop, hwnd))
+    }
+
+    pub fn is_window_on_current_desktop<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<bool> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::is_window_on_current_desktop(hwnd))
+    }
+
+    pub fn move_window_to_desktop<T, M>(&self, desktop: T, hwnd: impl IntoHwnd<M>) -> Result<()>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, hwnd) = (desktop.into(), hwnd.into_hwnd()?);
+        self.call(move || crate::move_window_to_desktop(desktop, hwnd))
+    }
+
+    pub fn is_pinned_window<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<bool> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::is_pinned_window(hwnd))
+    }
+
+    pub fn pin_window<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::pin_window(hwnd))
+    }
+
+    pub fn unpin_window<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::unpin_window(hwnd))
+    }
+
+    pub fn is_pinned_app<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<bool> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::is_pinned_app(hwnd))
+    }
+
+    pub fn pin_app<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::pin_app(hwnd))
+    }
+
+    pub fn unpin_app<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        self.call(move || crate::unpin_app(hwnd))
+    }
+}
+
+/// The worker is shared by all handles, stopping it makes the calls of the
+/// other handles fail with `Error::ServiceStopped`
+impl RuntimeChild for VirtualDesktopService {
+    fn is_running(&self) -> bool {
+        self.worker
+            .thread
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    fn stop(&mut self) -> std::thread::Result<()> {
+        self.worker.stop()
+    }
+}
+
+/// Worker shared by the language bindings, started on the first call
+#[cfg(any(feature = "python", all(feature = "node", not(test))))]
+pub(crate) fn shared_service() -> Result<VirtualDesktopService> {
+    static SHARED: Mutex<Option<VirtualDesktopService>> = Mutex::new(None);
+
+    let mut shared = SHARED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(service) = shared.as_ref() {
+        return Ok(service.clone());
+    }
+    let service = VirtualDesktopService::new()?;
+    *shared = Some(service.clone());
+    Ok(service)
+}
+
+fn lock(job: &Mutex<Option<Job>>) -> MutexGuard<'_, Option<Job>> {
+    job.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Message loop of the worker, returns when the quit message is received
+fn run_worker(jobs: &Receiver<Job>) {
+    let mut msg = MSG::default();
+    loop {
+        let res = unsafe { GetMessageW(&mut msg, None, 0, 0) };
+
+        // WM_QUIT or an error
+        if res.0 == 0 || res.0 == -1 {
+            break;
+        }
+
+        match msg.message {
+            WM_SERVICE_QUIT => break,
+            WM_SERVICE_CALL => {
+                while let Ok(job) = jobs.try_recv() {
+                    // Panicking job must not stop the worker
+                    let _ = std::panic::catch_unwind(AssertUnwindSafe(job));
+                }
+            }
+            _ => unsafe {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            },
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Async variants of the `VirtualDesktopService` methods, enabled with the
+//! `async` feature.
+//!
+//! Calls are queued to the worker thread, and the returned future resolves
+//! when the worker has run them, so the executor is never blocked. The futures
+//! work with any executor.
+//!
+//! ```rust,no_run
+//! # async fn example() -> winvd::Result<()> {
+//! let service = winvd::VirtualDesktopService::new()?;
+//! let desktop = service.create_desktop_async().await?;
+//! service.switch_desktop_async(desktop).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::{Desktop, Error, IntoHwnd, Result, VirtualDesktopService};
+
+struct FutureState<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Result of a call queued to the worker thread
+pub struct ServiceFuture<T> {
+    state: Arc<Mutex<FutureState<T>>>,
+}
+
+/// Completes the future, or fails it if dropped without a result, e.g. when
+/// the call panics or the worker stops
+struct Completer<T> {
+    state: Arc<Mutex<FutureState<T>>>,
+}
+
+impl<T> Completer<T> {
+    fn complete(&self, result: Result<T>) {
+        let waker = {
+            let mut state = lock(&self.state);
+            if state.result.is_some() {
+                return;
+            }
+            state.result = Some(result);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(Error::ServiceStopped));
+    }
+}
+
+fn channel<T>() -> (ServiceFuture<T>, Completer<T>) {
+    let state = Arc::new(Mutex::new(FutureState {
+        result: None,
+        waker: None,
+    }));
+    (
+        ServiceFuture {
+            state: state.clone(),
+        },
+        Completer { state },
+    )
+}
+
+fn lock<T>(state: &Mutex<FutureState<T>>) -> std::sync::MutexGuard<'_, FutureState<T>> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<T> Future for ServiceFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.state);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl VirtualDesktopService {
+    /// Queues the function to the worker thread, see `call`.
+    ///
+    /// Unlike `call`, this always queues the function, so blocking on the
+    /// future from the worker thread never completes.
+    pub fn call_async<F, T>(&self, f: F) -> ServiceFuture<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (future, completer) = channel();
+        // If queueing fails, the job is dropped and fails the future
+        let _ = self.enqueue(Box::new(move || completer.complete(f())));
+        future
+    }
+
+    pub fn get_desktop_count_async(&self) -> ServiceFuture<u32> {
+        self.call_async(crate::get_desktop_count)
+    }
+
+    pub fn get_desktops_async(&self) -> ServiceFuture<Vec<Desktop>> {
+        self.call_async(crate::get_desktops)
+    }
+
+    pub fn get_current_desktop_async(&self) -> ServiceFuture<Desktop> {
+        self.call_async(crate::get_current_desktop)
+    }
+
+    pub fn create_desktop_async(&self) -> ServiceFuture<Desktop> {
+        self.call_async(crate::create_desktop)
+    }
+
+    pub fn switch_desktop_async<T>(&self, desktop: T) -> ServiceFuture<()>
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into();
+        self.call_async(move || crate::switch_desktop(desktop))
+    }
+
+    pub fn remove_desktop_async<T>(&self, desktop: T, fallback_desktop: T) -> ServiceFuture<()>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, fallback_desktop) = (desktop.into(), fallback_desktop.into());
+        self.call_async(move || crate::remove_desktop(desktop, fallback_desktop))
+    }
+
+    pub fn set_desktop_name_async<T>(&self, desktop: T, name: &str) -> ServiceFuture<()>
+    where
+        T: Into<Desktop>,
+    {
+        let (desktop, name) = (desktop.into(), name.to_string());
+        self.call_async(move || desktop.set_name(&name))
+    }
+
+    pub fn move_window_to_desktop_async<T, M>(
+        &self,
+        desktop: T,
+        hwnd: impl IntoHwnd<M>,
+    ) -> ServiceFuture<()>
+    where
+        T: Into<Desktop>,
+    {
+        // Window without a handle fails the future
+        let (desktop, hwnd) = (desktop.into(), hwnd.into_hwnd());
+        self.call_async(move || crate::move_window_to_desktop(desktop, hwnd?))
+    }
+
+    pub fn pin_window_async<M>(&self, hwnd: impl IntoHwnd<M>) -> ServiceFuture<()> {
+        let hwnd = hwnd.into_hwnd();
+        self.call_async(move || crate::pin_window(hwnd?))
+    }
+
+    pub fn unpin_window_async<M>(&self, hwnd: impl IntoHwnd<M>) -> ServiceFuture<()> {
+        let hwnd = hwnd.into_hwnd();
+        self.call_async(move || crate::unpin_window(hwnd?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_future() {
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let (mut future, completer) = channel::<u32>();
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        completer.complete(Ok(3));
+        assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(Ok(3)));
+
+        // Dropped without a result, e.g. the call panicked
+        let (mut future, completer) = channel::<u32>();
+        drop(completer);
+        assert_eq!(
+            Pin::new(&mut future).poll(&mut cx),
+            Poll::Ready(Err(Error::ServiceStopped))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Opt-in manager keeping a set of windows visible on every desktop.
+//!
+//! ```rust,no_run
+//! # let hwnd = winvd::WindowId(0);
+//! let sticky = winvd::StickyWindows::new(winvd::StickyMode::Follow).unwrap();
+//! sticky.add(hwnd).unwrap();
+//! // The window follows to the desktop switched to, as long as `sticky` lives
+//! ```
+//!
+//! Closed windows are removed from the set automatically.
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::listener::DesktopEventHandler;
+use crate::{DesktopEvent, IntoHwnd, Result, WindowId};
+
+/// How `StickyWindows` keeps the windows visible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickyMode {
+    /// Pin the windows, pinned again after explorer.exe restarts
+    Pin,
+
+    /// Move the windows to the desktop switched to. Unlike pinning, the
+    /// windows stay on one desktop at a time, e.g. in Task View.
+    Follow,
+}
+
+/// Keeps the windows visible on every desktop, see the module docs
+pub struct StickyWindows {
+    mode: StickyMode,
+    windows: Arc<Mutex<Vec<HWND>>>,
+    events: DesktopEventHandler,
+}
+
+impl StickyWindows {
+    /// Starts the listener, the set of windows is empty
+    pub fn new(mode: StickyMode) -> Result<Self> {
+        let windows = Arc::new(Mutex::new(Vec::new()));
+        let thread_windows = windows.clone();
+        let events = DesktopEventHandler::new(move |event| {
+            let windows = live_windows(&thread_windows);
+            match (mode, event) {
+                (StickyMode::Follow, DesktopEvent::DesktopChanged { new, .. }) => {
+                    for hwnd in windows {
+                        let _ = crate::move_window_to_desktop(new, hwnd);
+                    }
+                }
+                // Pins are lost when explorer.exe restarts
+                (StickyMode::Pin, DesktopEvent::ConnectionReset { .. }) => {
+                    for hwnd in windows {
+                        let _ = crate::pin_window(hwnd);
+                    }
+                }
+                _ => {}
+            }
+        })?;
+
+        Ok(StickyWindows {
+            mode,
+            windows,
+            events,
+        })
+    }
+
+    /// Adds the window, and shows it on the current desktop
+    pub fn add<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        match self.mode {
+            StickyMode::Pin => crate::pin_window(hwnd)?,
+            StickyMode::Follow => {
+                crate::move_window_to_desktop(crate::get_current_desktop()?, hwnd)?
+            }
+        }
+        let mut windows = lock(&self.windows);
+        if !windows.contains(&hwnd) {
+            windows.push(hwnd);
+        }
+        Ok(())
+    }
+
+    /// Removes the window, pinned windows are unpinned. The window stays on
+    /// the current desktop.
+    pub fn remove<M>(&self, hwnd: impl IntoHwnd<M>) -> Result<()> {
+        let hwnd = hwnd.into_hwnd()?;
+        lock(&self.windows).retain(|window| *window != hwnd);
+        if self.mode == StickyMode::Pin && unsafe { IsWindow(hwnd) }.as_bool() {
+            crate::unpin_window(hwnd)?;
+        }
+        Ok(())
+    }
+
+    /// Windows in the set, closed windows are left out
+    pub fn list(&self) -> Vec<WindowId> {
+        live_windows(&self.windows)
+            .into_iter()
+            .map(WindowId::from)
+            .collect()
+    }
+}
+
+impl Drop for StickyWindows {
+    /// Unpins the windows pinned by the manager
+    fn drop(&mut self) {
+        // The windows are not pinned again meanwhile
+        self.events.stop();
+        if self.mode == StickyMode::Pin {
+            for hwnd in live_windows(&self.windows) {
+                let _ = crate::unpin_window(hwnd);
+            }
+        }
+    }
+}
+
+/// Drops closed windows from the set, and returns the rest
+fn live_windows(windows: &Mutex<Vec<HWND>>) -> Vec<HWND> {
+    let mut windows = lock(windows);
+    windows.retain(|hwnd| unsafe { IsWindow(*hwnd) }.as_bool());
+    windows.clone()
+}
+
+fn lock(windows: &Mutex<Vec<HWND>>) -> MutexGuard<'_, Vec<HWND>> {
+    windows
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,173 @@
+//! Helpers for tests that change the desktops of the session, enabled with
+//! the `test-hooks` feature.
+//!
+//! ```rust,no_run
+//! use winvd::testing::DesktopStateGuard;
+//!
+//! let _guard = DesktopStateGuard::new().unwrap();
+//! let desktop = winvd::create_desktop().unwrap();
+//! desktop.set_name("Scratch").unwrap();
+//! winvd::switch_desktop(desktop).unwrap();
+//! // The desktop is removed and the previous desktop is current again when
+//! // the guard is dropped, also if the test panics
+//! ```
+use windows::core::GUID;
+
+use crate::backend::with_backend;
+use crate::{Desktop, DesktopBackend, Error, Result};
+
+/// Snapshot of the desktops, restored when dropped.
+///
+/// Desktops created since the snapshot are removed, their windows move to the
+/// first remaining desktop. Removed desktops are created again, they get new
+/// GUIDs. The names are restored and the desktop that was current is switched
+/// to. The order of the desktops is not restored.
+#[derive(Debug)]
+pub struct DesktopStateGuard {
+    snapshot: Snapshot,
+}
+
+impl DesktopStateGuard {
+    /// Takes the snapshot
+    pub fn new() -> Result<Self> {
+        let snapshot = with_backend(Snapshot::take)?;
+        Ok(DesktopStateGuard { snapshot })
+    }
+
+    /// Number of desktops in the snapshot
+    pub fn desktop_count(&self) -> u32 {
+        self.snapshot.desktops.len() as u32
+    }
+
+    /// Restores the snapshot now, the guard restores it again when dropped
+    pub fn restore(&self) -> Result<()> {
+        let snapshot = self.snapshot.clone();
+        with_backend(move |b| snapshot.restore(b))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    /// GUIDs and names set by the user, in order
+    desktops: Vec<(GUID, String)>,
+    current: usize,
+}
+
+impl Snapshot {
+    fn take(b: &dyn DesktopBackend) -> Result<Self> {
+        let current = b.get_desktop_index(b.get_current_desktop()?)? as usize;
+        let desktops = b
+            .get_desktops()?
+            .into_iter()
+            .map(|desktop| Ok((b.get_desktop_id(desktop)?, b.get_desktop_name(desktop)?)))
+            .collect::<Result<_>>()?;
+        Ok(Snapshot { desktops, current })
+    }
+
+    fn restore(&self, b: &dyn DesktopBackend) -> Result<()> {
+        let ids = b
+            .get_desktops()?
+            .into_iter()
+            .map(|desktop| b.get_desktop_id(desktop))
+            .collect::<Result<Vec<_>>>()?;
+        let missing = self
+            .desktops
+            .iter()
+            .filter(|(guid, _)| !ids.contains(guid))
+            .count();
+
+        // New desktops take the place of the removed ones first
+        let mut replacements = Vec::new();
+        let mut extra = Vec::new();
+        for id in &ids {
+            if self.desktops.iter().any(|(guid, _)| guid == id) {
+                continue;
+            }
+            if replacements.len() < missing {
+                replacements.push(*id);
+            } else {
+                extra.push(*id);
+            }
+        }
+        if let Some(fallback) = ids.iter().find(|id| !extra.contains(id)) {
+            for id in &extra {
+                b.remove_desktop(Desktop::from(*id), Desktop::from(*fallback))?;
+            }
+        }
+        while replacements.len() < missing {
+            let desktop = b.create_desktop()?;
+            replacements.push(b.get_desktop_id(desktop)?);
+        }
+
+        // Desktops that still exist keep their GUID, the removed ones are
+        // replaced in order
+        let mut replacements = replacements.into_iter();
+        let targets = self
+            .desktops
+            .iter()
+            .map(|(guid, _)| {
+                if ids.contains(guid) {
+                    Some(*guid)
+                } else {
+                    replacements.next()
+                }
+            })
+            .collect::<Vec<_>>();
+        for (target, (_, name)) in targets.iter().zip(&self.desktops) {
+            let desktop = Desktop::from(target.ok_or(Error::DesktopNotFound)?);
+            if b.get_desktop_name(desktop)? != *name {
+                b.set_desktop_name(desktop, name)?;
+            }
+        }
+        let current = targets[self.current].ok_or(Error::DesktopNotFound)?;
+        b.switch_desktop(Desktop::from(current))
+    }
+}
+
+impl Drop for DesktopStateGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.restore() {
+            log_format!("Restoring the desktops failed: {:?}", err);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "listener"))]
+mod tests {
+    use super::*;
+    use crate::MockBackend;
+
+    #[test]
+    fn test_restore_replaced_desktop() {
+        let mock = MockBackend::new(3);
+        let [a, b, c] = [1, 2, 3].map(GUID::from_u128);
+        mock.set_desktop_name(Desktop::from(a), "a").unwrap();
+        mock.set_desktop_name(Desktop::from(b), "b").unwrap();
+        let snapshot = Snapshot::take(&mock).unwrap();
+
+        // A is replaced by D, E is extra
+        mock.remove_desktop(Desktop::from(a), Desktop::from(b))
+            .unwrap();
+        mock.create_desktop().unwrap();
+        mock.create_desktop().unwrap();
+        mock.set_desktop_name(Desktop::from(b), "renamed").unwrap();
+        snapshot.restore(&mock).unwrap();
+
+        let d = GUID::from_u128(4);
+        let names = mock
+            .get_desktops()
+            .unwrap()
+            .into_iter()
+            .map(|desktop| {
+                let id = mock.get_desktop_id(desktop).unwrap();
+                (id, mock.get_desktop_name(desktop).unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![(b, "b".into()), (c, "".into()), (d, "a".into())]
+        );
+        let current = mock.get_current_desktop().unwrap();
+        assert_eq!(mock.get_desktop_id(current).unwrap(), d);
+    }
+}
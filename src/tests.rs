@@ -1,3 +1,4 @@
+use super::comobjects::{with_com_objects, DesktopInternal};
 use super::*;
 use once_cell::sync::Lazy;
 use std::sync::{Arc, Mutex, PoisonError};
@@ -112,7 +113,7 @@ fn test_move_notepad_between_desktops() {
         );
 
         // Move notepad current desktop -> 0 -> 1 -> current desktop
-        move_window_to_desktop(0, &notepad_hwnd).unwrap();
+        move_window_to_desktop(0, notepad_hwnd).unwrap();
         let notepad_desktop = get_desktop_by_window(notepad_hwnd)
             .unwrap()
             .get_index()
@@ -120,7 +121,7 @@ fn test_move_notepad_between_desktops() {
         assert_eq!(notepad_desktop, 0, "Notepad should have moved to desktop 0");
         std::thread::sleep(Duration::from_millis(300));
 
-        move_window_to_desktop(1, &notepad_hwnd).unwrap();
+        move_window_to_desktop(1, notepad_hwnd).unwrap();
         let notepad_desktop = get_desktop_by_window(notepad_hwnd)
             .unwrap()
             .get_index()
@@ -128,7 +129,7 @@ fn test_move_notepad_between_desktops() {
         assert_eq!(notepad_desktop, 1, "Notepad should have moved to desktop 1");
         std::thread::sleep(Duration::from_millis(300));
 
-        move_window_to_desktop(current_desktop, &notepad_hwnd).unwrap();
+        move_window_to_desktop(current_desktop, notepad_hwnd).unwrap();
         let notepad_desktop = get_desktop_by_window(notepad_hwnd).unwrap();
         assert!(
             notepad_desktop == current_desktop,
@@ -231,7 +232,10 @@ fn test_rename_desktop() {
     sync_test(|| {
         let desktops = get_desktops().unwrap();
         let first_desktop = desktops.get(0).take().unwrap();
-        let first_desktop_name_before = first_desktop.get_name().unwrap();
+        // Raw name, so an unnamed desktop stays unnamed
+        let first_internal = DesktopInternal::from(*first_desktop);
+        let first_desktop_name_before =
+            with_com_objects(move |o| o.get_desktop_name(&first_internal)).unwrap();
 
         // Pre-condition
         assert_ne!(
@@ -276,10 +280,10 @@ fn test_errors() {
         let err = get_desktop_by_window(HWND(9999999)).unwrap_err();
         assert_eq!(err, Error::WindowNotFound);
 
-        let err = move_window_to_desktop(99999, &notepad_hwnd).unwrap_err();
+        let err = move_window_to_desktop(99999, notepad_hwnd).unwrap_err();
         assert_eq!(err, Error::DesktopNotFound);
 
-        let err = move_window_to_desktop(0, &HWND(999999)).unwrap_err();
+        let err = move_window_to_desktop(0, HWND(999999)).unwrap_err();
         assert_eq!(err, Error::WindowNotFound);
     });
 }
@@ -444,3 +448,412 @@ fn test_desktop_count() {
         assert!(count > 1);
     })
 }
+
+/// Call the service from several threads
+#[test]
+fn test_service_from_threads() {
+    sync_test(|| {
+        let service = VirtualDesktopService::new().unwrap();
+        let count = get_desktop_count().unwrap();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let service = service.clone();
+                thread::spawn(move || service.get_desktop_count().unwrap())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), count);
+        }
+    })
+}
+
+#[test]
+fn test_list_desktops_detailed() {
+    sync_test(|| {
+        let desktops = list_desktops_detailed().unwrap();
+        assert_eq!(desktops.len() as u32, get_desktop_count().unwrap());
+        for (index, info) in desktops.iter().enumerate() {
+            assert_eq!(info.index, index as u32);
+            assert_eq!(info.guid, get_desktop(info.index).get_id().unwrap());
+        }
+    })
+}
+
+/// Create a named desktop, and remove it again
+#[test]
+fn test_create_desktop_with_name() {
+    sync_test(|| {
+        let count = get_desktop_count().unwrap();
+        let desktop = create_desktop_with_name("Example Desktop").unwrap();
+        assert_eq!(desktop.get_name().unwrap(), "Example Desktop");
+        assert_eq!(get_desktop_count().unwrap(), count + 1);
+
+        remove_desktop(desktop, get_current_desktop().unwrap()).unwrap();
+        assert_eq!(get_desktop_count().unwrap(), count);
+    })
+}
+
+/// Calls are retried with new services after a simulated explorer.exe restart
+#[test]
+fn test_simulated_shell_restart() {
+    sync_test(|| {
+        let count = get_desktop_count().unwrap();
+        simulate_shell_restart();
+        assert_eq!(get_desktop_count().unwrap(), count);
+    })
+}
+
+/// Listener registers again after a simulated explorer.exe restart
+#[test]
+fn test_listener_simulated_shell_restart() {
+    sync_test(|| {
+        let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+        let listener = listen_desktop_events(tx).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(listener.is_registered());
+
+        simulate_shell_restart();
+
+        // Reconnect is checked every few seconds
+        let reset = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(10)).ok())
+            .find(|event| matches!(event, DesktopEvent::ConnectionReset { .. }));
+        let Some(DesktopEvent::ConnectionReset { desktops }) = reset else {
+            panic!("Listener did not reconnect");
+        };
+        assert_eq!(desktops.len() as u32, get_desktop_count().unwrap());
+        assert!(listener.is_registered());
+    })
+}
+
+/// Add two desktops at the end, and remove them again
+#[test]
+fn test_ensure_desktop_count() {
+    sync_test(|| {
+        let count = get_desktop_count().unwrap();
+        ensure_desktop_count(count + 2, 0).unwrap();
+        assert_eq!(get_desktop_count().unwrap(), count + 2);
+
+        let err = ensure_desktop_count(count, count + 1).unwrap_err();
+        assert_eq!(err, Error::RemoveDesktopFailed);
+        assert_eq!(get_desktop_count().unwrap(), count + 2);
+
+        ensure_desktop_count(count, 0).unwrap();
+        assert_eq!(get_desktop_count().unwrap(), count);
+    })
+}
+
+#[test]
+fn test_adjacent_desktop() {
+    sync_test(|| {
+        let count = get_desktop_count().unwrap();
+        let first = get_desktop(0).get_id().unwrap();
+        let last = get_desktop(count - 1).get_id().unwrap();
+
+        let right = get_adjacent_desktop(0, Direction::Right, false).unwrap();
+        assert_eq!(right.get_index().unwrap(), 1);
+
+        let err = get_adjacent_desktop(count - 1, Direction::Right, false).unwrap_err();
+        assert_eq!(err, Error::DesktopNotFound);
+
+        let wrapped = get_adjacent_desktop(count - 1, Direction::Right, true).unwrap();
+        assert_eq!(wrapped.get_id().unwrap(), first);
+        let wrapped = get_adjacent_desktop(0, Direction::Left, true).unwrap();
+        assert_eq!(wrapped.get_id().unwrap(), last);
+    })
+}
+
+/// Move notepad to the left, and back to the right
+#[test]
+fn test_move_notepad_to_adjacent_desktop() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert!(
+            notepad_hwnd != HWND::default(),
+            "Notepad requires to be running for this test"
+        );
+
+        let current_desktop = get_current_desktop().unwrap().get_index().unwrap();
+        assert!(current_desktop != 0, "Current desktop must not be 0");
+        assert!(can_move_window_between_desktops(notepad_hwnd).unwrap());
+        assert!(get_desktop(current_desktop)
+            .has_window(notepad_hwnd)
+            .unwrap());
+
+        let left = move_window_to_adjacent_desktop(notepad_hwnd, Direction::Left, false).unwrap();
+        assert_eq!(left.get_index().unwrap(), current_desktop - 1);
+        assert_eq!(
+            get_desktop_by_window(notepad_hwnd)
+                .unwrap()
+                .get_index()
+                .unwrap(),
+            current_desktop - 1
+        );
+        std::thread::sleep(Duration::from_millis(300));
+
+        assert!(!get_desktop(current_desktop)
+            .has_window(notepad_hwnd)
+            .unwrap());
+        move_window_to_adjacent_desktop(notepad_hwnd, Direction::Right, false).unwrap();
+        assert!(is_window_on_current_desktop(notepad_hwnd).unwrap());
+    })
+}
+
+#[test]
+fn test_list_views() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert_ne!(notepad_hwnd.0, 0, "Notepad must be running for this test");
+
+        let views = list_views().unwrap();
+        let notepad = views
+            .iter()
+            .find(|view| view.hwnd == WindowId::from(notepad_hwnd))
+            .expect("Notepad view not listed");
+        assert!(notepad.show_in_switchers);
+        let recent = get_desktop(notepad.desktop)
+            .windows_by_recent_use()
+            .unwrap();
+        assert!(recent.contains(&(notepad_hwnd.into(), notepad.last_activation_timestamp)));
+        assert!(recent.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert_eq!(
+            notepad.app_user_model_id,
+            app_id_of_window(notepad_hwnd).unwrap()
+        );
+        assert!(windows_with_app_id(&notepad.app_user_model_id)
+            .unwrap()
+            .contains(&notepad_hwnd.into()));
+        assert_eq!(
+            notepad.desktop,
+            get_desktop_by_window(notepad_hwnd)
+                .unwrap()
+                .get_id()
+                .unwrap()
+        );
+    })
+}
+
+/// Move all notepad windows to the current desktop
+#[test]
+fn test_move_app_to_desktop() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert_ne!(notepad_hwnd.0, 0, "Notepad must be running for this test");
+
+        let app_id = app_id_of_window(notepad_hwnd).unwrap();
+        let current_desktop = get_current_desktop().unwrap();
+        let results = move_app_to_desktop(current_desktop, &app_id).unwrap();
+        assert!(results
+            .iter()
+            .any(|(hwnd, _)| *hwnd == WindowId::from(notepad_hwnd)));
+        for (hwnd, result) in results {
+            result.unwrap();
+            assert!(is_window_on_current_desktop(hwnd).unwrap());
+        }
+    })
+}
+
+/// Hide notepad from Alt+Tab, and show it again
+#[test]
+fn test_notepad_visible_in_switchers() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert_ne!(notepad_hwnd.0, 0, "Notepad must be running for this test");
+
+        assert!(is_window_visible_in_switchers(notepad_hwnd).unwrap());
+        set_window_visible_in_switchers(notepad_hwnd, false).unwrap();
+        assert!(!is_window_visible_in_switchers(notepad_hwnd).unwrap());
+        set_window_visible_in_switchers(notepad_hwnd, true).unwrap();
+        assert!(is_window_visible_in_switchers(notepad_hwnd).unwrap());
+    })
+}
+
+/// Notepad follows to the next desktop
+#[test]
+fn test_sticky_notepad() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert_ne!(notepad_hwnd.0, 0, "Notepad must be running for this test");
+
+        let current_desktop = get_current_desktop().unwrap();
+        let sticky = StickyWindows::new(StickyMode::Follow).unwrap();
+        sticky.add(notepad_hwnd).unwrap();
+        assert_eq!(sticky.list(), vec![WindowId::from(notepad_hwnd)]);
+
+        switch_desktop(next_desktop(true).unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(is_window_on_current_desktop(notepad_hwnd).unwrap());
+
+        sticky.remove(notepad_hwnd).unwrap();
+        switch_desktop(current_desktop).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        move_window_to_desktop(current_desktop, notepad_hwnd).unwrap();
+    })
+}
+
+/// Switching back returns to the desktop switched from
+#[test]
+fn test_desktop_history_switch_back() {
+    sync_test(|| {
+        let current_desktop = get_current_desktop().unwrap();
+        let history = DesktopHistory::new(5).unwrap();
+
+        let next = next_desktop(true).unwrap();
+        switch_desktop(next).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(history.history(), vec![current_desktop]);
+
+        assert_eq!(history.switch_back().unwrap(), current_desktop);
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(get_current_desktop().unwrap(), current_desktop);
+        assert_eq!(history.history(), vec![next]);
+    })
+}
+
+/// Toggling twice returns to the starting desktop
+#[test]
+fn test_desktop_history_toggle() {
+    sync_test(|| {
+        let current_desktop = get_current_desktop().unwrap();
+        let history = DesktopHistory::new(5).unwrap();
+        let next = next_desktop(true).unwrap();
+
+        assert_eq!(history.toggle_desktops(next, None).unwrap(), next);
+        assert_eq!(
+            history.toggle_desktops(next, None).unwrap(),
+            current_desktop
+        );
+        assert_eq!(
+            history
+                .toggle_desktops(current_desktop, Some(next))
+                .unwrap(),
+            next
+        );
+        assert_eq!(
+            history
+                .toggle_desktops(current_desktop, Some(next))
+                .unwrap(),
+            current_desktop
+        );
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(get_current_desktop().unwrap(), current_desktop);
+    })
+}
+
+/// Notepad is on a monitor, desktops are shared by all monitors
+#[test]
+fn test_monitor_of_window() {
+    sync_test(|| {
+        let notepad_hwnd = unsafe {
+            let notepad = "notepad\0".encode_utf16().collect::<Vec<_>>();
+            let pw = PCWSTR::from_raw(notepad.as_ptr());
+            FindWindowW(pw, PCWSTR::null())
+        };
+        assert_ne!(notepad_hwnd.0, 0, "Notepad must be running for this test");
+
+        assert_ne!(monitor_of_window(notepad_hwnd).unwrap().0, 0);
+        assert_eq!(monitor_of_window(HWND(0)), Err(Error::WindowNotFound));
+        assert_eq!(get_current_desktop().unwrap().monitor().unwrap(), None);
+    })
+}
+
+/// The primary monitor is at 0, 0
+#[test]
+fn test_list_monitors() {
+    sync_test(|| {
+        let monitors = list_monitors();
+        let primary: Vec<_> = monitors.iter().filter(|m| m.primary).collect();
+        assert_eq!(primary.len(), 1);
+        assert_eq!(monitor_at_point(0, 0), primary[0].hmonitor);
+
+        let current_desktop = get_current_desktop().unwrap();
+        switch_desktop_on_monitor(primary[0].hmonitor, current_desktop).unwrap();
+    })
+}
+
+/// Raw objects can be queried
+#[test]
+fn test_raw_com_objects() {
+    sync_test(|| unsafe {
+        raw_service_provider().unwrap();
+        raw_manager_internal().unwrap();
+        get_current_desktop().unwrap().as_ivirtualdesktop().unwrap();
+    })
+}
+
+/// The desktop functions go through an installed backend
+#[test]
+fn test_mock_backend() {
+    sync_test(|| {
+        let mock = Arc::new(MockBackend::new(2));
+        set_backend(Some(mock.clone()));
+        let desktop = create_desktop().unwrap();
+        desktop.set_name("Mocked").unwrap();
+        switch_desktop(desktop).unwrap();
+        assert_eq!(get_desktop_count().unwrap(), 3);
+        assert_eq!(get_current_desktop().unwrap().get_name().unwrap(), "Mocked");
+        assert_eq!(mock.get_desktop_name(Desktop::from(2)).unwrap(), "Mocked");
+
+        // Batches too
+        batch(|ops| {
+            ops.set_name(0, "Batched").switch(0);
+        })
+        .unwrap();
+        assert_eq!(mock.get_desktop_name(Desktop::from(0)).unwrap(), "Batched");
+        assert_eq!(get_current_desktop().unwrap().get_index().unwrap(), 0);
+
+        // Other threads use the backend too
+        let hwnd = HWND(0x1234);
+        mock.add_window(hwnd, Desktop::from(0)).unwrap();
+        std::thread::spawn(move || pin_window(hwnd))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert!(mock.is_pinned_window(hwnd).unwrap());
+        set_backend(None);
+    })
+}
+
+/// Desktops created and renamed by a test are reverted by the guard
+#[test]
+fn test_desktop_state_guard() {
+    sync_test(|| {
+        let count = get_desktop_count().unwrap();
+        let current = get_current_desktop().unwrap().get_id().unwrap();
+        let first_name = get_desktop(0).get_name().unwrap();
+        let guard = testing::DesktopStateGuard::new().unwrap();
+        assert_eq!(guard.desktop_count(), count);
+
+        let result = std::panic::catch_unwind(|| {
+            let _guard = testing::DesktopStateGuard::new().unwrap();
+            let desktop = create_desktop().unwrap();
+            get_desktop(0).set_name("Renamed by test").unwrap();
+            switch_desktop(desktop).unwrap();
+            panic!("test failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(get_desktop_count().unwrap(), count);
+        assert_eq!(get_current_desktop().unwrap().get_id().unwrap(), current);
+        assert_eq!(get_desktop(0).get_name().unwrap(), first_name);
+        drop(guard);
+    })
+}
@@ -0,0 +1,168 @@
+//! Desktop handle that stays valid when desktops are moved or removed.
+//!
+//! ```rust,no_run
+//! use winvd::{DesktopEvent, TrackedDesktop};
+//!
+//! let mut work = TrackedDesktop::new(1).unwrap();
+//! let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+//! let _listener = winvd::listen_desktop_events(tx).unwrap();
+//! for event in rx {
+//!     work.handle_event(&event);
+//!     println!("Work is at {:?}", work.index());
+//! }
+//! ```
+use windows::core::GUID;
+
+use crate::comobjects::DesktopInternal;
+use crate::{Desktop, DesktopEvent, Error, Result};
+
+/// Desktop bound to its GUID, caching its index, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedDesktop {
+    guid: GUID,
+
+    /// `None` when the index has to be read again
+    index: Option<u32>,
+    removed: bool,
+}
+
+impl TrackedDesktop {
+    /// Tracks the desktop, e.g. given by index, from now on
+    pub fn new<T>(desktop: T) -> Result<Self>
+    where
+        T: Into<Desktop>,
+    {
+        let desktop = desktop.into();
+        Ok(TrackedDesktop {
+            guid: desktop.get_id()?,
+            index: Some(desktop.get_index()?),
+            removed: false,
+        })
+    }
+
+    pub fn guid(&self) -> GUID {
+        self.guid
+    }
+
+    /// The desktop by its GUID
+    pub fn desktop(&self) -> Desktop {
+        Desktop::from(self.guid)
+    }
+
+    /// Current index of the desktop, read again only if an event made the
+    /// cached index unreliable. Fails with `Error::DesktopNotFound` after the
+    /// desktop was removed.
+    pub fn index(&mut self) -> Result<u32> {
+        if self.removed {
+            return Err(Error::DesktopNotFound);
+        }
+        if let Some(index) = self.index {
+            return Ok(index);
+        }
+        let index = self.desktop().get_index()?;
+        self.index = Some(index);
+        Ok(index)
+    }
+
+    /// True if a `DesktopDestroyed` event for the desktop was handled
+    pub fn is_removed(&self) -> bool {
+        self.removed
+    }
+
+    /// Updates the cached index from an event of the listener
+    pub fn handle_event(&mut self, event: &DesktopEvent) {
+        match event {
+            DesktopEvent::DesktopMoved {
+                desktop,
+                old_index,
+                new_index,
+            } => {
+                self.index = if guid_of(*desktop) == Some(self.guid) {
+                    u32::try_from(*new_index).ok()
+                } else {
+                    self.index
+                        .and_then(|index| shifted_index(index, *old_index, *new_index))
+                };
+            }
+            DesktopEvent::DesktopDestroyed { destroyed, .. }
+                if guid_of(*destroyed) == Some(self.guid) =>
+            {
+                self.removed = true;
+                self.index = None;
+            }
+            // Indexes of the other desktops may change
+            DesktopEvent::DesktopCreated(_)
+            | DesktopEvent::DesktopDestroyed { .. }
+            | DesktopEvent::InitialState { .. }
+            | DesktopEvent::ConnectionReset { .. }
+            | DesktopEvent::EventsDropped { .. } => self.index = None,
+            _ => {}
+        }
+    }
+}
+
+impl From<TrackedDesktop> for Desktop {
+    fn from(desktop: TrackedDesktop) -> Self {
+        desktop.desktop()
+    }
+}
+
+/// GUID of a desktop of an event, without calling COM
+fn guid_of(desktop: Desktop) -> Option<GUID> {
+    match DesktopInternal::from(desktop) {
+        DesktopInternal::Guid(guid) | DesktopInternal::IndexGuid(_, guid) => Some(guid),
+        DesktopInternal::Index(_) => None,
+    }
+}
+
+/// Index of another desktop after a desktop was moved from `old` to `new`,
+/// `None` if the indexes are out of range
+fn shifted_index(index: u32, old: i64, new: i64) -> Option<u32> {
+    let index = i64::from(index);
+    let shifted = if old < index && index <= new {
+        index - 1
+    } else if new <= index && index < old {
+        index + 1
+    } else {
+        index
+    };
+    u32::try_from(shifted).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_event() {
+        let guid = |n| GUID::from_u128(n);
+        let mut tracked = TrackedDesktop {
+            guid: guid(1),
+            index: Some(2),
+            removed: false,
+        };
+        let moved = |desktop, old_index, new_index| DesktopEvent::DesktopMoved {
+            desktop: Desktop::from(guid(desktop)),
+            old_index,
+            new_index,
+        };
+
+        // Other desktops moving past it shift the index
+        tracked.handle_event(&moved(2, 0, 3));
+        assert_eq!(tracked.index, Some(1));
+        tracked.handle_event(&moved(2, 3, 0));
+        assert_eq!(tracked.index, Some(2));
+        tracked.handle_event(&moved(2, 3, 4));
+        assert_eq!(tracked.index, Some(2));
+
+        tracked.handle_event(&moved(1, 2, 0));
+        assert_eq!(tracked.index, Some(0));
+
+        tracked.handle_event(&DesktopEvent::DesktopDestroyed {
+            destroyed: Desktop::from(guid(1)),
+            fallback: Desktop::from(guid(2)),
+        });
+        assert!(tracked.is_removed());
+        assert_eq!(tracked.index, None);
+    }
+}
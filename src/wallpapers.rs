@@ -0,0 +1,102 @@
+//! Opt-in emulation of per desktop wallpapers, for Windows versions where all
+//! desktops share one wallpaper, see `wallpaper_support`.
+//!
+//! While a `WallpaperEmulation` lives, `Desktop::set_wallpaper` remembers the
+//! wallpaper of each desktop, and the shared wallpaper is changed to it when
+//! the desktop is switched to:
+//!
+//! ```rust,no_run
+//! let _emulation = winvd::WallpaperEmulation::start().unwrap();
+//! winvd::get_desktop(1).set_wallpaper(r"C:\Wallpapers\work.jpg").unwrap();
+//! ```
+//!
+//! `Desktop::get_wallpaper` then returns the remembered wallpaper, or an empty
+//! string. On versions with per desktop wallpapers the emulation does nothing.
+//! The wallpapers are remembered until the process exits.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use windows::core::GUID;
+
+use crate::listener::DesktopEventHandler;
+use crate::{Desktop, DesktopEvent, Result};
+
+/// Wallpapers by desktop GUID
+static WALLPAPERS: Mutex<Option<HashMap<GUID, String>>> = Mutex::new(None);
+
+/// Number of `WallpaperEmulation`s alive
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Switches the shared wallpaper with the desktop, see the module docs
+pub struct WallpaperEmulation {
+    _events: DesktopEventHandler,
+}
+
+impl WallpaperEmulation {
+    /// Starts the listener, `Desktop::set_wallpaper` remembers the wallpapers
+    /// from now on
+    pub fn start() -> Result<Self> {
+        let events = DesktopEventHandler::new(|event| {
+            if let DesktopEvent::DesktopChanged { new, .. } = event {
+                let _ = apply(new);
+            }
+        })?;
+
+        ACTIVE.fetch_add(1, Ordering::SeqCst);
+        Ok(WallpaperEmulation { _events: events })
+    }
+}
+
+impl Drop for WallpaperEmulation {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub(crate) fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst) > 0
+}
+
+/// Remembered wallpaper of the desktop, empty if none
+pub(crate) fn get(guid: &GUID) -> String {
+    lock()
+        .as_ref()
+        .and_then(|wallpapers| wallpapers.get(guid).cloned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn set(guid: GUID, path: &str) {
+    lock()
+        .get_or_insert_with(HashMap::new)
+        .insert(guid, path.to_string());
+}
+
+/// Shows the wallpaper of the desktop, if it has one
+fn apply(desktop: Desktop) -> Result<()> {
+    let path = get(&desktop.get_id()?);
+    if path.is_empty() {
+        return Ok(());
+    }
+    crate::desktop::set_shared_wallpaper(&path)
+}
+
+fn lock() -> MutexGuard<'static, Option<HashMap<GUID, String>>> {
+    WALLPAPERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remembered_wallpapers() {
+        let [a, b] = [0xa11, 0xb11].map(GUID::from_u128);
+        set(a, r"C:\a.jpg");
+        set(a, r"C:\b.jpg");
+        assert_eq!(get(&a), r"C:\b.jpg");
+        assert_eq!(get(&b), "");
+    }
+}
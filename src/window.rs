@@ -0,0 +1,175 @@
+//! Window arguments of the window functions
+use std::ffi::c_void;
+use std::num::NonZeroIsize;
+
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use windows::Win32::Foundation::HWND;
+
+#[cfg(feature = "raw-window-handle")]
+use crate::Error;
+use crate::Result;
+
+/// Window argument of the window functions, e.g. `pin_window`.
+///
+/// Implemented for `HWND` and `&HWND` of the `windows` crate version used by
+/// winvd, for raw handles, i.e. `WindowId`, `isize`, `NonZeroIsize` and the
+/// `*mut c_void` of newer `windows` crates, and with the `raw-window-handle`
+/// feature for references to windows implementing `HasWindowHandle`, e.g.
+/// winit, tao and egui windows:
+///
+/// ```rust,ignore
+/// winvd::pin_window(&window)?;
+/// winvd::move_window_to_desktop(1, &window)?;
+/// ```
+///
+/// `Marker` only keeps the implementations apart, and is always inferred.
+pub trait IntoHwnd<Marker = ()> {
+    /// Fails with `Error::WindowNotFound` if the window has no Win32 handle
+    fn into_hwnd(self) -> Result<HWND>;
+}
+
+impl IntoHwnd for HWND {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(self)
+    }
+}
+
+impl IntoHwnd for &HWND {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(*self)
+    }
+}
+
+/// Raw window handle, independent of the `windows` crate version. Converts
+/// from and to `HWND`, `isize` and `*mut c_void`.
+///
+/// ```rust
+/// let window = winvd::WindowId::from(0x1234_isize);
+/// assert_eq!(isize::from(window), 0x1234);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct WindowId(pub isize);
+
+impl From<isize> for WindowId {
+    fn from(hwnd: isize) -> Self {
+        WindowId(hwnd)
+    }
+}
+
+impl From<NonZeroIsize> for WindowId {
+    fn from(hwnd: NonZeroIsize) -> Self {
+        WindowId(hwnd.get())
+    }
+}
+
+impl From<*mut c_void> for WindowId {
+    fn from(hwnd: *mut c_void) -> Self {
+        WindowId(hwnd as isize)
+    }
+}
+
+impl From<HWND> for WindowId {
+    fn from(hwnd: HWND) -> Self {
+        WindowId(hwnd.0)
+    }
+}
+
+impl From<WindowId> for isize {
+    fn from(window: WindowId) -> Self {
+        window.0
+    }
+}
+
+impl From<WindowId> for *mut c_void {
+    fn from(window: WindowId) -> Self {
+        window.0 as *mut c_void
+    }
+}
+
+impl From<WindowId> for HWND {
+    fn from(window: WindowId) -> Self {
+        HWND(window.0)
+    }
+}
+
+impl IntoHwnd for WindowId {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(HWND(self.0))
+    }
+}
+
+impl IntoHwnd for isize {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(HWND(self))
+    }
+}
+
+impl IntoHwnd for NonZeroIsize {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(HWND(self.get()))
+    }
+}
+
+impl IntoHwnd for *mut c_void {
+    fn into_hwnd(self) -> Result<HWND> {
+        Ok(HWND(self as isize))
+    }
+}
+
+/// `IntoHwnd` marker of the `HasWindowHandle` windows
+#[cfg(feature = "raw-window-handle")]
+#[doc(hidden)]
+pub enum RawWindowHandleMarker {}
+
+#[cfg(feature = "raw-window-handle")]
+impl<W> IntoHwnd<RawWindowHandleMarker> for &W
+where
+    W: HasWindowHandle + ?Sized,
+{
+    fn into_hwnd(self) -> Result<HWND> {
+        let handle = self.window_handle().map_err(|_| Error::WindowNotFound)?;
+        match handle.as_raw() {
+            RawWindowHandle::Win32(handle) => Ok(HWND(handle.hwnd.get())),
+            _ => Err(Error::WindowNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_hwnd() {
+        assert_eq!(HWND(0x1234).into_hwnd().unwrap(), HWND(0x1234));
+        assert_eq!((&HWND(0x1234)).into_hwnd().unwrap(), HWND(0x1234));
+        assert_eq!(WindowId(0x1234).into_hwnd().unwrap(), HWND(0x1234));
+        assert_eq!(0x1234_isize.into_hwnd().unwrap(), HWND(0x1234));
+        let pointer = 0x1234 as *mut c_void;
+        assert_eq!(pointer.into_hwnd().unwrap(), HWND(0x1234));
+        assert_eq!(WindowId::from(pointer), WindowId::from(HWND(0x1234)));
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    #[test]
+    fn test_raw_window_handle() {
+        use raw_window_handle::{HandleError, Win32WindowHandle, WindowHandle};
+
+        struct TestWindow(isize);
+
+        impl HasWindowHandle for TestWindow {
+            fn window_handle(&self) -> std::result::Result<WindowHandle<'_>, HandleError> {
+                let handle = Win32WindowHandle::new(NonZeroIsize::new(self.0).unwrap());
+                Ok(unsafe { WindowHandle::borrow_raw(handle.into()) })
+            }
+        }
+
+        assert_eq!((&TestWindow(0x1234)).into_hwnd().unwrap(), HWND(0x1234));
+    }
+}
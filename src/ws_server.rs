@@ -0,0 +1,277 @@
+//! WebSocket server, enabled with the `websocket` feature.
+//!
+//! Browser dashboards and stream deck plugins receive the desktop events as
+//! text messages, and control the desktops by sending requests. The messages
+//! are the same JSON-RPC messages as on the named pipe, see the `rpc` module.
+//!
+//! Browsers let any web page connect to local servers, so only pages served
+//! from localhost, and clients sending no `Origin` header, are accepted.
+//! Pages opened from files send the origin `null` and are rejected.
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use crate::rpc;
+use crate::{DesktopEvent, DesktopEventThread, Error, Result, RuntimeChild, VirtualDesktopService};
+
+/// How often the threads check for queued events and for stopping
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Clients not finishing the handshake in time are disconnected
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Shared {
+    service: VirtualDesktopService,
+
+    /// Queues of the events for each client
+    clients: Mutex<Vec<Sender<String>>>,
+    stopping: AtomicBool,
+}
+
+/// Running WebSocket server, stopped when dropped
+pub struct WsServer {
+    shared: Arc<Shared>,
+    accept: Option<JoinHandle<()>>,
+    events: Option<DesktopEventThread>,
+    broadcast: Option<JoinHandle<()>>,
+}
+
+impl WsServer {
+    /// Stops the threads and joins them, returns the first panic
+    fn stop_threads(&mut self) -> std::thread::Result<()> {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        let accept = self.accept.take().map_or(Ok(()), JoinHandle::join);
+        // Stopping the listener closes the channel of the broadcast thread
+        let events = self
+            .events
+            .take()
+            .map_or(Ok(()), |mut events| events.stop());
+        let broadcast = self.broadcast.take().map_or(Ok(()), JoinHandle::join);
+        accept.and(events).and(broadcast)
+    }
+}
+
+impl Drop for WsServer {
+    fn drop(&mut self) {
+        let _ = self.stop_threads();
+    }
+}
+
+impl RuntimeChild for WsServer {
+    fn is_running(&self) -> bool {
+        let alive = |thread: &Option<JoinHandle<()>>| {
+            thread.as_ref().is_some_and(|thread| !thread.is_finished())
+        };
+        alive(&self.accept)
+            && alive(&self.broadcast)
+            && self
+                .events
+                .as_ref()
+                .is_some_and(DesktopEventThread::is_running)
+    }
+
+    fn stop(&mut self) -> std::thread::Result<()> {
+        self.stop_threads()
+    }
+}
+
+/// Starts a WebSocket server on the address, e.g. `"127.0.0.1:8765"`.
+///
+/// Each desktop event is sent to all clients as a JSON-RPC `event`
+/// notification, and the clients can send JSON-RPC requests, e.g. `switch`.
+/// The server runs until the returned `WsServer` is dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let _server = winvd::serve_ws("127.0.0.1:8765").unwrap();
+/// loop {
+///     std::thread::park();
+/// }
+/// ```
+///
+/// In the browser:
+///
+/// ```js
+/// const socket = new WebSocket("ws://127.0.0.1:8765");
+/// socket.onmessage = (message) => console.log(JSON.parse(message.data));
+/// socket.onopen = () =>
+///     socket.send(JSON.stringify({ jsonrpc: "2.0", id: 1, method: "switch", params: { desktop: 0 } }));
+/// ```
+pub fn serve_ws(addr: impl ToSocketAddrs) -> Result<WsServer> {
+    let listener = TcpListener::bind(addr).map_err(io_error)?;
+    listener.set_nonblocking(true).map_err(io_error)?;
+    let shared = Arc::new(Shared {
+        service: VirtualDesktopService::new()?,
+        clients: Mutex::new(Vec::new()),
+        stopping: AtomicBool::new(false),
+    });
+
+    let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let events = crate::listen_desktop_events(tx)?;
+    let broadcast = {
+        let shared = shared.clone();
+        std::thread::spawn(move || broadcast_events(rx, &shared))
+    };
+    let accept = {
+        let shared = shared.clone();
+        std::thread::spawn(move || accept_clients(listener, &shared))
+    };
+    Ok(WsServer {
+        shared,
+        accept: Some(accept),
+        events: Some(events),
+        broadcast: Some(broadcast),
+    })
+}
+
+fn io_error(err: std::io::Error) -> Error {
+    Error::FileError(err.kind())
+}
+
+fn accept_clients(listener: TcpListener, shared: &Arc<Shared>) {
+    let mut threads: Vec<JoinHandle<()>> = Vec::new();
+    while !shared.stopping.load(Ordering::SeqCst) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(_) => {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        let shared = shared.clone();
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(std::thread::spawn(move || serve_client(stream, &shared)));
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+/// Rejects the connections from web pages not served from this machine
+#[allow(clippy::result_large_err)] // Callback of tungstenite
+fn check_origin(
+    request: &Request,
+    response: Response,
+) -> std::result::Result<Response, ErrorResponse> {
+    let origin = match request.headers().get("Origin") {
+        None => return Ok(response),
+        Some(origin) => origin.to_str().unwrap_or_default(),
+    };
+    if is_local_origin(origin) {
+        return Ok(response);
+    }
+    let mut rejected = ErrorResponse::new(Some(format!("Origin {} not allowed", origin)));
+    *rejected.status_mut() = StatusCode::FORBIDDEN;
+    Err(rejected)
+}
+
+/// True for pages of localhost.
+///
+/// Origin `null` is rejected: browsers send it for files, but also for
+/// sandboxed iframes and `data:` URLs, which any web page can create.
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Answers the requests of the client and sends it the events, until it
+/// disconnects
+fn serve_client(stream: TcpStream, shared: &Shared) {
+    // Handshake blocks, reading after it polls
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+    let Ok(mut socket) = tungstenite::accept_hdr(stream, check_origin) else {
+        return;
+    };
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    lock(&shared.clients).push(tx);
+
+    while !shared.stopping.load(Ordering::SeqCst) {
+        if !send_queued(&mut socket, &rx) {
+            return;
+        }
+        let request = match socket.read() {
+            Ok(Message::Text(request)) => request,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(_) => return,
+        };
+        if let Some(response) = rpc::handle_line(&shared.service, request.trim()) {
+            if socket.send(Message::Text(response)).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = socket.close(None);
+    let _ = socket.flush();
+}
+
+/// Sends the queued events, returns false if the connection is broken
+fn send_queued(socket: &mut WebSocket<TcpStream>, events: &Receiver<String>) -> bool {
+    for event in events.try_iter() {
+        if socket.send(Message::Text(event)).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Queues the events to all clients as notifications
+fn broadcast_events(rx: Receiver<DesktopEvent>, shared: &Shared) {
+    for event in rx {
+        let notification = rpc::event_notification(&event);
+        // Clients that are gone have dropped their receiver
+        lock(&shared.clients).retain(|client| client.send(notification.clone()).is_ok());
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_origin() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("null"));
+        assert!(!is_local_origin("https://example.com"));
+        assert!(!is_local_origin("http://localhost.example.com"));
+        assert!(!is_local_origin("localhost"));
+    }
+}
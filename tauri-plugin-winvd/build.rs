@@ -0,0 +1,5 @@
+const COMMANDS: &[&str] = &["list", "switch", "move_window"];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS).build();
+}
@@ -0,0 +1,131 @@
+//! Tauri plugin for the Windows 11 virtual desktops.
+//!
+//! Register the plugin and allow `winvd:default` in the capabilities:
+//!
+//! ```rust,ignore
+//! tauri::Builder::default()
+//!     .plugin(tauri_plugin_winvd::init())
+//!     .run(tauri::generate_context!())
+//!     .unwrap();
+//! ```
+//!
+//! The frontend calls the commands, and receives each `DesktopEvent` as the
+//! `winvd://event` event. Desktops are given by index, GUID or name:
+//!
+//! ```js
+//! import { invoke } from "@tauri-apps/api/core";
+//! import { listen } from "@tauri-apps/api/event";
+//!
+//! const desktops = await invoke("plugin:winvd|list");
+//! await invoke("plugin:winvd|switch", { desktop: "Work" });
+//! // Moves the calling window, or the window given by `hwnd`
+//! await invoke("plugin:winvd|move_window", { desktop: 1 });
+//! await listen("winvd://event", (event) => console.log(event.payload));
+//! ```
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewWindow};
+use winvd::{
+    Desktop, DesktopEvent, DesktopEventThread, DesktopInfo, IntoHwnd, VirtualDesktopService,
+    WindowId,
+};
+
+/// Event carrying each `DesktopEvent`, serialized with the `serde` feature of
+/// winvd
+pub const EVENT: &str = "winvd://event";
+
+/// Managed state of the plugin, the events stop when the app exits
+struct Winvd {
+    service: VirtualDesktopService,
+    _events: Mutex<DesktopEventThread>,
+}
+
+/// Desktop by index, or by GUID or name
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DesktopArg {
+    Index(u32),
+    Text(String),
+}
+
+impl DesktopArg {
+    /// Names are resolved with COM, so this runs on the worker
+    fn resolve(self) -> winvd::Result<Desktop> {
+        match self {
+            DesktopArg::Index(index) => Ok(Desktop::from(index)),
+            DesktopArg::Text(text) => text.parse(),
+        }
+    }
+}
+
+fn error(err: winvd::Error) -> String {
+    format!("{:?}", err)
+}
+
+#[tauri::command]
+async fn list(state: State<'_, Winvd>) -> Result<Vec<DesktopInfo>, String> {
+    state
+        .service
+        .call_async(winvd::list_desktops_detailed)
+        .await
+        .map_err(error)
+}
+
+#[tauri::command]
+async fn switch(state: State<'_, Winvd>, desktop: DesktopArg) -> Result<(), String> {
+    state
+        .service
+        .call_async(move || winvd::switch_desktop(desktop.resolve()?))
+        .await
+        .map_err(error)
+}
+
+/// Moves the window to the desktop, the calling window if `hwnd` is not given
+#[tauri::command]
+async fn move_window<R: Runtime>(
+    window: WebviewWindow<R>,
+    state: State<'_, Winvd>,
+    desktop: DesktopArg,
+    hwnd: Option<isize>,
+) -> Result<(), String> {
+    let hwnd = match hwnd {
+        Some(hwnd) => WindowId(hwnd),
+        None => WindowId::from((&window).into_hwnd().map_err(error)?),
+    };
+    state
+        .service
+        .call_async(move || winvd::move_window_to_desktop(desktop.resolve()?, hwnd))
+        .await
+        .map_err(error)
+}
+
+/// Plugin registering the commands and emitting the desktop events, see the
+/// crate docs
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("winvd")
+        .invoke_handler(tauri::generate_handler![list, switch, move_window])
+        .setup(|app, _api| {
+            let state = start(app.clone()).map_err(error)?;
+            app.manage(state);
+            Ok(())
+        })
+        .build()
+}
+
+fn start<R: Runtime>(app: AppHandle<R>) -> winvd::Result<Winvd> {
+    let service = VirtualDesktopService::new()?;
+    let (tx, rx) = std::sync::mpsc::channel::<DesktopEvent>();
+    let events = winvd::listen_desktop_events(tx)?;
+    // Ends when the listener is dropped with the state
+    std::thread::spawn(move || {
+        for event in rx {
+            let _ = app.emit(EVENT, &event);
+        }
+    });
+    Ok(Winvd {
+        service,
+        _events: Mutex::new(events),
+    })
+}